@@ -0,0 +1,38 @@
+// The gRPC service is described in Rust (see proto/minning.proto for the
+// same API as a .proto file), so building it needs no protoc
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let unary = |name: &str, route: &str, input: &str, output: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::{}", input))
+                .output_type(format!("crate::grpc::{}", output))
+                .codec_path("tonic_prost::ProstCodec")
+                .build()
+        };
+        let supervisor = Service::builder()
+            .name("Supervisor")
+            .package("minning")
+            .method(unary("get_state", "GetState", "Empty", "SupervisorState"))
+            .method(unary("pause", "Pause", "Empty", "Reply"))
+            .method(unary("resume", "Resume", "Empty", "Reply"))
+            .method(unary("restart", "Restart", "Empty", "Reply"))
+            .method(unary("stop", "Stop", "Empty", "Reply"))
+            .method(
+                Method::builder()
+                    .name("watch_state")
+                    .route_name("WatchState")
+                    .input_type("crate::grpc::WatchRequest")
+                    .output_type("crate::grpc::SupervisorState")
+                    .codec_path("tonic_prost::ProstCodec")
+                    .server_streaming()
+                    .build(),
+            )
+            .build();
+        Builder::new().build_client(false).compile(&[supervisor]);
+    }
+}
@@ -0,0 +1,148 @@
+use sha3::{Digest, Keccak256};
+
+use crate::run::XmrError;
+
+// Monero's base58 alphabet, the same as Bitcoin's
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+// Monero encodes 8 byte blocks as 11 characters each; the last, shorter
+// block of n bytes takes ENCODED_SIZES[n] characters
+const FULL_ENCODED: usize = 11;
+const ENCODED_SIZES: [usize; 9] = [0, 2, 3, 5, 6, 7, 9, 10, 11];
+// Public spend key, public view key, payment ID and Keccak checksum
+const KEY_LEN: usize = 32;
+const PAYMENT_ID_LEN: usize = 8;
+const CHECKSUM_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Stagenet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Standard,
+    Integrated,
+    Subaddress,
+}
+
+// Network byte -> what the address is
+const PREFIXES: [(u8, Network, Kind); 9] = [
+    (18, Network::Mainnet, Kind::Standard),
+    (19, Network::Mainnet, Kind::Integrated),
+    (42, Network::Mainnet, Kind::Subaddress),
+    (53, Network::Testnet, Kind::Standard),
+    (54, Network::Testnet, Kind::Integrated),
+    (63, Network::Testnet, Kind::Subaddress),
+    (24, Network::Stagenet, Kind::Standard),
+    (25, Network::Stagenet, Kind::Integrated),
+    (36, Network::Stagenet, Kind::Subaddress),
+];
+
+impl Network {
+    pub fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Stagenet => "stagenet",
+        }
+    }
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Standard => "standard",
+            Kind::Integrated => "integrated",
+            Kind::Subaddress => "subaddress",
+        }
+    }
+}
+
+fn invalid(address: &str, reason: &str) -> XmrError {
+    XmrError::ConfigError(format!("{} is not a valid Monero address: {}", address, reason))
+}
+
+fn decode_block(block: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut value: u128 = 0;
+    for &c in block {
+        let digit = ALPHABET.iter().position(|&a| a == c)?;
+        value = value * 58 + digit as u128;
+    }
+    // An 11 character block can hold more than 8 bytes' worth
+    if size < 16 && value >> (8 * size) != 0 {
+        return None;
+    }
+    Some(value.to_be_bytes()[16 - size..].to_vec())
+}
+
+// Monero's block-wise base58, unlike Bitcoin's whole-number one
+fn decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for block in encoded.as_bytes().chunks(FULL_ENCODED) {
+        let size = ENCODED_SIZES.iter().position(|&len| len == block.len())?;
+        bytes.extend(decode_block(block, size)?);
+    }
+    Some(bytes)
+}
+
+// Check a payout address character by character: the base58 encoding, the
+// network byte, the length for its kind and the Keccak checksum. A typo
+// fails the checksum; the pool would otherwise take the shares and never
+// pay them out.
+pub fn validate(address: &str) -> Result<(Network, Kind), XmrError> {
+    let bytes = decode(address).ok_or_else(|| invalid(address, "not Monero base58"))?;
+    let Some(&prefix) = bytes.first() else {
+        return Err(invalid(address, "empty"));
+    };
+    let Some(&(_, network, kind)) = PREFIXES.iter().find(|(byte, _, _)| *byte == prefix) else {
+        return Err(invalid(address, &format!("unknown network byte {}", prefix)));
+    };
+
+    let payload = match kind {
+        Kind::Integrated => 2 * KEY_LEN + PAYMENT_ID_LEN,
+        Kind::Standard | Kind::Subaddress => 2 * KEY_LEN,
+    };
+    if bytes.len() != 1 + payload + CHECKSUM_LEN {
+        return Err(invalid(address, "wrong length"));
+    }
+    let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    if &Keccak256::digest(data)[..CHECKSUM_LEN] != checksum {
+        return Err(invalid(address, "checksum mismatch, check for a typo"));
+    }
+    Ok((network, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DONATION: &str = "44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A";
+
+    #[test]
+    fn test_validate_standard() {
+        assert_eq!(validate(DONATION).unwrap(), (Network::Mainnet, Kind::Standard));
+    }
+
+    #[test]
+    fn test_validate_rejects_typos() {
+        // One character changed
+        assert!(validate(&DONATION.replacen("44AFF", "44AFG", 1)).is_err());
+        // Truncated
+        assert!(validate(&DONATION[..94]).is_err());
+        // Not in the alphabet
+        assert!(validate(&DONATION.replacen('q', "0", 1)).is_err());
+        assert!(validate("").is_err());
+        assert!(validate("4abc").is_err());
+    }
+
+    #[test]
+    fn test_decode_block_bounds() {
+        assert_eq!(decode("11").unwrap(), vec![0]);
+        // "zz" is 3363, more than one byte holds
+        assert!(decode("zz").is_none());
+        // Four characters is no block size
+        assert!(decode("1111").is_none());
+    }
+}
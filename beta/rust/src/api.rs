@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::events::{self, Event, Sink};
+use crate::pause;
+use crate::redact;
+use crate::stats;
+use crate::status;
+
+// Single-page dashboard served at /; it polls the endpoints below
+const DASHBOARD: &str = include_str!("dashboard.html");
+// Miner exits kept for /api/restarts
+const RECENT_EXITS: usize = 20;
+
+static EXITS: Mutex<VecDeque<Value>> = Mutex::new(VecDeque::new());
+
+struct ExitLog;
+
+impl Sink for ExitLog {
+    fn handle(&self, event: &Event) {
+        if let Event::ChildExited { pid, code, .. } = event
+            && let Ok(mut exits) = EXITS.lock()
+        {
+            if exits.len() == RECENT_EXITS {
+                exits.pop_front();
+            }
+            exits.push_back(json!({ "time": Utc::now().timestamp(), "pid": pid, "exit_code": code }));
+        }
+    }
+}
+
+fn restarts() -> Value {
+    let session = stats::session_restarts();
+    let previous = stats::load().map_or(0, |stats| stats.previous.restarts);
+    let recent: Vec<Value> = EXITS.lock().map(|exits| exits.iter().rev().cloned().collect()).unwrap_or_default();
+    json!({
+        "session": session,
+        "lifetime": previous + session,
+        "recent_exits": recent,
+    })
+}
+
+fn route(path: &str, config: &Config) -> (&'static str, Value) {
+    match path {
+        "/api/status" => match status::snapshot(config) {
+            Ok(mut snapshot) => {
+                snapshot["paused"] = json!(pause::reasons());
+                ("200 OK", snapshot)
+            },
+            Err(e) => ("503 Service Unavailable", json!({ "error": e.to_string() })),
+        },
+        "/api/restarts" => ("200 OK", restarts()),
+        "/api/stats" => match stats::load() {
+            Some(stats) => ("200 OK", json!({ "session": stats.session, "lifetime": stats.lifetime() })),
+            None => ("503 Service Unavailable", json!({ "error": "no statistics recorded yet" })),
+        },
+        "/api/config" => ("200 OK", redact::config(config)),
+        _ => (
+            "404 Not Found",
+            json!({ "endpoints": ["/api/status", "/api/restarts", "/api/stats", "/api/config"] }),
+        ),
+    }
+}
+
+fn respond(stream: TcpStream, config: &Config) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = path.split('?').next().unwrap_or(path).to_string();
+
+    let mut authorization = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("authorization")
+        {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+
+    // The page holds no data, so it is served without the token
+    if path == "/" || path == "/index.html" {
+        return send(stream, "200 OK", "text/html; charset=utf-8", DASHBOARD);
+    }
+    let authorized = match &config.api.token {
+        Some(token) => authorization.as_deref().and_then(|a| a.strip_prefix("Bearer ")) == Some(token.as_str()),
+        None => true,
+    };
+    let (status, body) = if authorized {
+        route(&path, config)
+    } else {
+        ("401 Unauthorized", json!({ "error": "missing or wrong bearer token" }))
+    };
+    send(stream, status, "application/json", &body.to_string())
+}
+
+fn send(mut stream: TcpStream, status: &str, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+// Supervised modes: serve the dashboard and status API on [api] listen
+pub fn start(config: &Config) {
+    if !config.api.enabled {
+        return;
+    }
+
+    let listener = match TcpListener::bind(&config.api.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Could not serve the status API on {}: {}", config.api.listen, e);
+            return;
+        },
+    };
+    info!("Serving the dashboard and status API on http://{}/", config.api.listen);
+    events::subscribe(Box::new(ExitLog));
+
+    let config = config.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            if let Err(e) = respond(stream, &config) {
+                debug!("Status API request failed: {}", e);
+            }
+        }
+    });
+}
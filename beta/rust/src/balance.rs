@@ -0,0 +1,53 @@
+use crate::config::Config;
+use crate::market;
+use crate::pool_api;
+use crate::run::XmrError;
+use crate::telemetry;
+
+fn format_xmr(amount: f64, price: Option<f64>, currency: &str) -> String {
+    match price {
+        Some(price) => format!("{:.12} XMR ({:.2} {})", amount, amount * price, currency.to_uppercase()),
+        None => format!("{:.12} XMR", amount),
+    }
+}
+
+// `balance`: unpaid balance, pending payout and pool-side hashrate for the
+// configured wallet, straight from the pool's public API
+pub fn show(config: &Config, fiat: Option<&str>) -> Result<(), XmrError> {
+    let wallet = config.wallet.address.as_deref().ok_or_else(|| {
+        XmrError::ConfigError("No wallet configured; set [wallet] address in the config file".to_string())
+    })?;
+    let pool = pool_api::adapter(&config.pool.api)?;
+    let stats = pool.wallet_stats(wallet)?;
+
+    // Fiat is best effort: a price API outage should not hide the balance
+    let currency = fiat.or(config.market.fiat.as_deref());
+    let price = match currency {
+        Some(currency) => match market::xmr_price(&config.market, currency) {
+            Ok(price) => Some(price),
+            Err(e) => {
+                eprintln!("Warning: could not fetch XMR price: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    let currency = currency.unwrap_or_default();
+
+    println!("Pool:            {}", pool.name());
+    println!("Wallet:          {}", wallet);
+    println!("Unpaid balance:  {}", format_xmr(stats.unpaid, price, currency));
+    match stats.pending {
+        Some(pending) => println!("Pending payout:  {}", format_xmr(pending, price, currency)),
+        None => println!("Pending payout:  -"),
+    }
+    if let Some(paid) = stats.total_paid {
+        println!("Total paid:      {}", format_xmr(paid, price, currency));
+    }
+    match stats.hashrate {
+        Some(hashrate) => println!("Pool hashrate:   {}", telemetry::format_hashrate(hashrate)),
+        None => println!("Pool hashrate:   -"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::config;
+use crate::events::{self, Event, Sink};
+use crate::run::XmrError;
+use crate::telemetry;
+
+pub const BANDWIDTH_FILE: &str = "bandwidth.json";
+// Bytes a socket moves after its last sample are lost when it closes, so
+// sample often enough to keep that small
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Cumulative miner traffic in bytes since `since` (unix seconds)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Totals {
+    pub sent: u64,
+    pub received: u64,
+    pub since: i64,
+}
+
+// Miner processes whose sockets are counted
+static MINERS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+struct MinerSink;
+
+impl Sink for MinerSink {
+    fn handle(&self, event: &Event) {
+        let Ok(mut miners) = MINERS.lock() else {
+            return;
+        };
+        match event {
+            Event::ChildStarted { pid } => {
+                miners.insert(*pid);
+            },
+            Event::ChildExited { pid, .. } => {
+                miners.remove(pid);
+            },
+            _ => {},
+        }
+    }
+}
+
+fn owned_by(process: &str, pids: &BTreeSet<u32>) -> bool {
+    process
+        .split("pid=")
+        .skip(1)
+        .filter_map(|rest| rest.split(|c: char| !c.is_ascii_digit()).next()?.parse::<u32>().ok())
+        .any(|pid| pids.contains(&pid))
+}
+
+// Byte counters per TCP socket of `pids` from `ss -tinpH` output, keyed by
+// local and peer address. Each socket line is followed by an indented line
+// with its tcp_info counters.
+fn parse_sockets(output: &str, pids: &BTreeSet<u32>) -> HashMap<String, (u64, u64)> {
+    let mut sockets = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            current = (fields.len() >= 6 && owned_by(&fields[5..].join(" "), pids))
+                .then(|| format!("{} {}", fields[3], fields[4]));
+            continue;
+        }
+
+        let Some(key) = current.take() else {
+            continue;
+        };
+        let counter = |name: &str| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix(name)?.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        sockets.insert(key, (counter("bytes_sent:"), counter("bytes_received:")));
+    }
+    sockets
+}
+
+fn sample(pids: &BTreeSet<u32>) -> Option<HashMap<String, (u64, u64)>> {
+    let output = Command::new("ss").arg("-tinpH").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_sockets(&String::from_utf8_lossy(&output.stdout), pids))
+}
+
+fn totals_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(BANDWIDTH_FILE))
+}
+
+pub fn load() -> Totals {
+    totals_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_else(|| Totals { since: Utc::now().timestamp(), ..Totals::default() })
+}
+
+fn save(totals: &Totals) -> Result<(), XmrError> {
+    let path = totals_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(totals)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode bandwidth totals: {}", e)))?;
+    config::write_atomic(&path, &contents)?;
+    Ok(())
+}
+
+// Supervised modes: add the traffic of the miner's TCP sockets to the
+// running totals. Counters are per socket, so only the growth since the
+// previous sample is added.
+pub fn start_recorder(running: Arc<AtomicBool>) {
+    events::subscribe(Box::new(MinerSink));
+
+    thread::spawn(move || {
+        let mut previous: HashMap<String, (u64, u64)> = HashMap::new();
+        while running.load(Ordering::SeqCst) {
+            let pids = MINERS.lock().map(|m| m.clone()).unwrap_or_default();
+            if let Some(current) = sample(&pids) {
+                let (mut sent, mut received) = (0, 0);
+                for (key, &(now_sent, now_received)) in &current {
+                    let (was_sent, was_received) = previous.get(key).copied().unwrap_or((0, 0));
+                    // A smaller counter means the address pair belongs to a new socket
+                    sent += if now_sent >= was_sent { now_sent - was_sent } else { now_sent };
+                    received += if now_received >= was_received { now_received - was_received } else { now_received };
+                }
+                previous = current;
+
+                if sent > 0 || received > 0 {
+                    debug!("Miner traffic: {} bytes sent, {} bytes received", sent, received);
+                    let mut totals = load();
+                    totals.sent += sent;
+                    totals.received += received;
+                    if let Err(e) = save(&totals) {
+                        warn!("Could not save bandwidth totals: {}", e);
+                    }
+                }
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < SAMPLE_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+// `bandwidth`: what mining has consumed so far, optionally starting over
+pub fn report(reset: bool) -> Result<(), XmrError> {
+    if reset {
+        save(&Totals { since: Utc::now().timestamp(), ..Totals::default() })?;
+        println!("Bandwidth totals reset.");
+        return Ok(());
+    }
+
+    let totals = load();
+    let since = Local
+        .timestamp_opt(totals.since, 0)
+        .single()
+        .map_or_else(|| "?".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string());
+    println!("Miner traffic since {}:", since);
+    println!("  Upload:   {}", telemetry::format_bytes(totals.sent));
+    println!("  Download: {}", telemetry::format_bytes(totals.received));
+    println!("  Total:    {}", telemetry::format_bytes(totals.sent + totals.received));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sockets() {
+        let output = "ESTAB 0 0 10.0.0.2:51234 203.0.113.7:3333 users:((\"xmr\",pid=4242,fd=12))\n\
+                      \t cubic wscale:7,7 rto:204 bytes_sent:1200 bytes_acked:1201 bytes_received:56000 segs_out:40\n\
+                      ESTAB 0 0 10.0.0.2:40000 198.51.100.1:443 users:((\"firefox\",pid=77,fd=90))\n\
+                      \t cubic bytes_sent:999 bytes_received:999\n";
+        let pids = BTreeSet::from([4242]);
+        let sockets = parse_sockets(output, &pids);
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets["10.0.0.2:51234 203.0.113.7:3333"], (1200, 56000));
+    }
+}
@@ -0,0 +1,175 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::cpu::{self, Topology};
+use crate::output;
+use crate::pidfile;
+use crate::procgroup;
+use crate::run::{self, XmrError};
+use crate::telemetry;
+
+// Thread counts worth comparing: one, half the cores, what `auto` picks,
+// every core and every logical CPU
+fn candidates(topology: &Topology) -> Vec<usize> {
+    let mut candidates = vec![
+        1,
+        topology.physical_cores / 2,
+        cpu::auto_threads(topology),
+        topology.physical_cores,
+        topology.logical,
+    ];
+    candidates.retain(|&threads| threads > 0);
+    candidates.sort_unstable();
+    candidates.dedup();
+    candidates
+}
+
+// The longest average on a "speed 10s/60s/15m 1234.5 1230.1 n/a H/s" line
+fn parse_speed(line: &str) -> Option<f64> {
+    let (_, rest) = line.split_once("speed 10s/60s/15m")?;
+    rest.split_whitespace().take(3).filter_map(|value| value.parse().ok()).last()
+}
+
+// The result of a completed run, "benchmark finished in 25.1 seconds (3980.2 h/s)"
+fn parse_finished(line: &str) -> Option<f64> {
+    let (_, rest) = line.split_once("benchmark finished in")?;
+    let (_, rate) = rest.split_once('(')?;
+    rate.split_whitespace().next()?.parse().ok()
+}
+
+// Run the miner's own benchmark with `threads` threads for up to `duration`
+// and report the last hashrate it printed. The first figures only appear
+// once the dataset is built, so short durations can end with none.
+fn measure(xmr_path: &str, config: &Config, threads: usize, duration: Duration) -> Result<Option<f64>, XmrError> {
+    let mut command = Command::new(xmr_path);
+    command.args(["--bench=10M", "--no-color", &format!("--threads={}", threads)]);
+    if !config.miner.huge_pages {
+        command.arg("--no-huge-pages");
+    }
+    let mut child = procgroup::isolate(&mut command).stdout(Stdio::piped()).stderr(Stdio::null()).spawn()?;
+    cpu::pin_miner(child.id(), &config.miner);
+
+    let (lines, received) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if lines.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let deadline = Instant::now() + duration;
+    let mut hashrate = None;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match received.recv_timeout(left) {
+            Ok(line) => {
+                if let Some(finished) = parse_finished(&line) {
+                    hashrate = Some(finished);
+                    break;
+                }
+                if let Some(speed) = parse_speed(&line) {
+                    hashrate = Some(speed);
+                }
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => break,
+            // The miner exited
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    if let Err(e) = procgroup::kill(&mut child) {
+        debug!("Stopping the benchmark miner: {}", e);
+    }
+    let _ = child.wait();
+    Ok(hashrate)
+}
+
+// `bench`: measure each thread count in turn and print the hashrate of each,
+// so [miner] threads can be set to the best one
+pub fn run(config: &Config, threads: Vec<usize>, duration: Duration) -> Result<(), XmrError> {
+    if let Some(pid) = pidfile::running() {
+        warn!("A supervisor is mining (PID {}); the results will be low while it runs", pid);
+    }
+    let xmr_path = run::get_xmr_path()?;
+    run::set_executable_permissions(&xmr_path)?;
+
+    let candidates = if threads.is_empty() { candidates(&cpu::detect(config.miner.cpus.as_ref())) } else { threads };
+    let human = !output::json();
+    let mut results = Vec::new();
+    for &threads in &candidates {
+        if human {
+            eprintln!("Benchmarking {} thread{} for {}s...", threads, if threads == 1 { "" } else { "s" }, duration.as_secs());
+        }
+        let hashrate = measure(&xmr_path, config, threads, duration)?;
+        if human {
+            match hashrate {
+                Some(hashrate) => println!(
+                    "{:>4} thread{}: {:>12}  ({} per thread)",
+                    threads,
+                    if threads == 1 { " " } else { "s" },
+                    telemetry::format_hashrate(hashrate),
+                    telemetry::format_hashrate(hashrate / threads as f64)
+                ),
+                None => println!("{:>4} thread{}: no result; try a longer --duration", threads, if threads == 1 { " " } else { "s" }),
+            }
+        }
+        results.push((threads, hashrate));
+    }
+
+    let best = results
+        .iter()
+        .filter_map(|&(threads, hashrate)| Some((threads, hashrate?)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(threads, _)| threads);
+    if output::json() {
+        let results: Vec<_> = results
+            .iter()
+            .map(|&(threads, hashrate)| {
+                json!({ "threads": threads, "hashrate": hashrate, "per_thread": hashrate.map(|h| h / threads as f64) })
+            })
+            .collect();
+        output::ok(json!({ "duration_secs": duration.as_secs(), "results": results, "best_threads": best }));
+    } else if let Some(best) = best {
+        println!("Fastest with {} threads; set [miner] threads = {} to use it.", best, best);
+    }
+    if best.is_none() {
+        return Err(XmrError::ExecutionError("The miner reported no hashrate for any thread count".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_speed() {
+        let line = "[2024-01-01 12:00:00.000]  miner    speed 10s/60s/15m 2345.6 2301.2 n/a H/s max 2400.1 H/s";
+        assert_eq!(parse_speed(line), Some(2301.2));
+        assert_eq!(parse_speed(" miner    speed 10s/60s/15m n/a n/a n/a H/s max n/a H/s"), None);
+        assert_eq!(parse_speed("net      use pool"), None);
+    }
+
+    #[test]
+    fn test_parse_finished() {
+        let line = "[2024-01-01 12:00:00.000]  bench    benchmark finished in 25.123 seconds (39803.2 h/s) hash sum = 0x1234";
+        assert_eq!(parse_finished(line), Some(39803.2));
+        assert_eq!(parse_finished("bench    press Ctrl+C to stop"), None);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let topology = Topology { logical: 16, physical_cores: 8, l3_bytes: Some(8 * 1024 * 1024) };
+        assert_eq!(candidates(&topology), vec![1, 4, 8, 16]);
+        let topology = Topology { logical: 1, physical_cores: 1, l3_bytes: None };
+        assert_eq!(candidates(&topology), vec![1]);
+    }
+}
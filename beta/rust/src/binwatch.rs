@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use tracing::{debug, info, warn};
+
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
+
+use crate::download;
+use crate::events::{self, Event};
+use crate::init;
+
+// Bumped each time a new, verified miner binary is in place; watchdogs
+// compare it with what they last saw and restart their child
+static REPLACEMENTS: AtomicU64 = AtomicU64::new(0);
+// How long to wait for an inotify event before we look at `running` again,
+// and how often the binary is checked without inotify
+const WAIT: Duration = Duration::from_secs(5);
+// A binary still being written keeps changing; wait until it has not for
+// this long before checking it
+const SETTLE: Duration = Duration::from_secs(2);
+
+pub fn replacements() -> u64 {
+    REPLACEMENTS.load(Ordering::SeqCst)
+}
+
+// Size, modification time and inode: enough to notice a rewrite in place as
+// well as a new file (or folder) renamed over the old one
+#[derive(Debug, Clone, PartialEq)]
+struct Fingerprint {
+    len: u64,
+    modified: Option<SystemTime>,
+    inode: u64,
+}
+
+fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    #[cfg(unix)]
+    let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+    #[cfg(not(unix))]
+    let inode = 0;
+    Some(Fingerprint { len: metadata.len(), modified: metadata.modified().ok(), inode })
+}
+
+// Block until something changes in `dir` or `timeout` passes: whether
+// anything did, or None when `dir` cannot be watched, so the caller falls
+// back to sleeping
+#[cfg(target_os = "linux")]
+fn wait_for_event(dir: &Path, timeout: Duration) -> Option<bool> {
+    let path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    // SAFETY: inotify_init1 takes flags and returns a new file descriptor,
+    // which OwnedFd takes ownership of
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return None;
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+    // SAFETY: `path` is NUL-terminated and outlives the call
+    if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) } < 0 {
+        return None;
+    }
+    let mut pollfd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    // SAFETY: poll reads and writes the one pollfd it is given
+    let ready = unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis().min(i32::MAX as u128) as libc::c_int) };
+    // The events themselves go unread: the fingerprint tells what changed,
+    // and closing the descriptor drops them
+    Some(ready > 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wait_for_event(_dir: &Path, _timeout: Duration) -> Option<bool> {
+    None
+}
+
+// Whether the binary is the one release.json in its folder was installed
+// with; anything else is not run
+fn verify(binary: &Path) -> Result<String, String> {
+    let dir = binary.parent().unwrap_or(Path::new("."));
+    let release = init::installed_release(dir).ok_or_else(|| format!("no {} next to it", init::RELEASE_FILE))?;
+    let expected = release
+        .binary_sha256
+        .ok_or_else(|| format!("{} has no binary checksum; reinstall with `update` or `init`", init::RELEASE_FILE))?;
+    let actual = download::sha256_file(binary).map_err(|e| e.to_string())?;
+    if actual != expected {
+        return Err(format!("SHA-256 {} does not match {} from {}", actual, expected, init::RELEASE_FILE));
+    }
+    Ok(release.tag)
+}
+
+fn changed(binary: &Path) {
+    match verify(binary) {
+        Ok(tag) => {
+            info!("Miner binary {} replaced by release {}; restarting XMR", binary.display(), tag);
+            REPLACEMENTS.fetch_add(1, Ordering::SeqCst);
+        },
+        Err(reason) => {
+            warn!("Miner binary {} changed but was not verified ({}); keeping the running miner", binary.display(), reason);
+            events::emit(Event::Alert { message: format!("Unverified miner binary at {}: {}", binary.display(), reason) });
+        },
+    }
+}
+
+// Supervised modes: watch the install folder and have the watchdogs restart
+// the miner once `update` (or anything else) puts a verified new binary in
+// place
+pub fn start_watch(xmr_path: &str, running: Arc<AtomicBool>) {
+    let binary = PathBuf::from(xmr_path);
+    let dir = binary.parent().map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+
+    thread::spawn(move || {
+        let mut last = fingerprint(&binary);
+        let mut inotify = true;
+        while running.load(Ordering::SeqCst) {
+            // The folder itself may have been swapped out, so wait on
+            // whichever folder is there now
+            if !(inotify && dir.is_dir() && wait_for_event(&dir, WAIT).is_some()) {
+                if inotify && dir.is_dir() {
+                    debug!("inotify unavailable; checking the miner binary every {} seconds", WAIT.as_secs());
+                    inotify = false;
+                }
+                thread::sleep(WAIT);
+            }
+
+            let current = fingerprint(&binary);
+            if current.is_none() || current == last {
+                continue;
+            }
+            // Let a copy or unzip finish before hashing
+            thread::sleep(SETTLE);
+            let settled = fingerprint(&binary);
+            if settled != current {
+                continue;
+            }
+            last = settled;
+            changed(&binary);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_sees_replacement() {
+        let dir = std::env::temp_dir().join(format!("minning-binwatch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("xmr");
+        fs::write(&binary, "old").unwrap();
+        let before = fingerprint(&binary);
+
+        let staged = dir.join("xmr.new");
+        fs::write(&staged, "new").unwrap();
+        fs::rename(&staged, &binary).unwrap();
+        assert_ne!(fingerprint(&binary), before);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_wait_for_event() {
+        let dir = std::env::temp_dir().join(format!("minning-binwatch-event-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert_eq!(wait_for_event(&dir, Duration::from_millis(50)), Some(false));
+
+        // A new binary renamed into place wakes the wait well before the timeout
+        let staged = std::env::temp_dir().join(format!("minning-binwatch-staged-{}", std::process::id()));
+        fs::write(&staged, "new").unwrap();
+        let binary = dir.join("xmr");
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            fs::rename(&staged, &binary).unwrap();
+        });
+        let started = std::time::Instant::now();
+        assert_eq!(wait_for_event(&dir, Duration::from_secs(10)), Some(true));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        writer.join().unwrap();
+
+        assert_eq!(wait_for_event(&dir.join("missing"), Duration::from_millis(50)), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::cpu;
+use crate::run::XmrError;
+use crate::service;
+
+// cpu.max quotas are given per this period, in microseconds
+const CPU_PERIOD_US: u64 = 100_000;
+
+// The cgroup miners are moved into once started
+static GROUP: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// Where the cgroup v2 hierarchy is mounted, from /proc/self/mounts
+fn parse_mounts(mounts: &str) -> Option<PathBuf> {
+    mounts.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        (fields.get(2) == Some(&"cgroup2")).then(|| PathBuf::from(fields[1]))
+    })
+}
+
+// Our own cgroup v2 path, the "0::" line of /proc/self/cgroup
+fn parse_own(cgroup: &str) -> Option<String> {
+    cgroup.lines().find_map(|line| line.strip_prefix("0::")).map(str::to_string)
+}
+
+// "4G", "512M", "1024K" or bytes
+fn parse_memory(memory: &str) -> Result<u64, XmrError> {
+    let invalid = || XmrError::ConfigError(format!("Expected a memory size like 4G or 512M, got \"{}\"", memory));
+    let memory = memory.trim();
+    let (number, unit) = match memory.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((at, _)) => memory.split_at(at),
+        None => (memory, ""),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
+// cpu.max for `percent` of `cpus` CPUs: "<quota> <period>"
+fn cpu_max(percent: u8, cpus: usize) -> String {
+    let quota = CPU_PERIOD_US * u64::from(percent) * cpus.max(1) as u64 / 100;
+    format!("{} {}", quota.max(1000), CPU_PERIOD_US)
+}
+
+// Check [cgroup] cpu_max and memory_max
+pub fn validate(config: &Config) -> Result<(), XmrError> {
+    if let Some(cap) = config.cgroup.cpu_max
+        && !(1..=100).contains(&cap)
+    {
+        return Err(XmrError::ConfigError(format!("[cgroup] cpu_max must be a percentage from 1 to 100, got {}", cap)));
+    }
+    if let Some(memory) = &config.cgroup.memory_max {
+        parse_memory(memory)?;
+    }
+    Ok(())
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), XmrError> {
+    fs::write(path, contents).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied => XmrError::PermissionError(format!(
+            "Could not write {}; [cgroup] needs root or a delegated cgroup (Delegate=yes in the service)",
+            path.display()
+        )),
+        _ => XmrError::IoError(io::Error::new(e.kind(), format!("Could not write {}: {}", path.display(), e))),
+    })
+}
+
+// Turn on `controllers` for the groups below `base`. The kernel refuses
+// while `base` itself holds processes, so the supervisor first moves into
+// a leaf group next to the miner's.
+fn enable(base: &Path, controllers: &[&str], supervisor: &str) -> Result<(), XmrError> {
+    let line = controllers.iter().map(|c| format!("+{}", c)).collect::<Vec<_>>().join(" ");
+    let subtree_control = base.join("cgroup.subtree_control");
+    match fs::write(&subtree_control, &line) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.raw_os_error() != Some(libc::EBUSY) => return write(&subtree_control, &line),
+        Err(_) => {},
+    }
+    let leaf = base.join(supervisor);
+    fs::create_dir_all(&leaf)?;
+    write(&leaf.join("cgroup.procs"), &std::process::id().to_string())?;
+    debug!("Moved the supervisor into {}", leaf.display());
+    write(&subtree_control, &line)
+}
+
+// Before the first miner start: create its cgroup with the [cgroup] limits.
+// Started miners join it through `enter`.
+pub fn prepare(config: &Config) -> Result<(), XmrError> {
+    let cgroup = &config.cgroup;
+    if cgroup.cpu_max.is_none() && cgroup.memory_max.is_none() {
+        return Ok(());
+    }
+    validate(config)?;
+    let root = fs::read_to_string("/proc/self/mounts").ok().and_then(|mounts| parse_mounts(&mounts)).ok_or_else(|| {
+        XmrError::ConfigError("[cgroup] needs the cgroup v2 hierarchy, which is not mounted here".to_string())
+    })?;
+    let own = fs::read_to_string("/proc/self/cgroup")
+        .ok()
+        .and_then(|cgroup| parse_own(&cgroup))
+        .ok_or_else(|| XmrError::ConfigError("[cgroup] needs the cgroup v2 hierarchy; this process is not in it".to_string()))?;
+
+    let supervisor = format!("{}-supervisor", service::name());
+    let own = own.trim_start_matches('/');
+    let mut base = if own.is_empty() { root } else { root.join(own) };
+    // A restarted supervisor may already sit in its leaf
+    if base.file_name().is_some_and(|name| *name == *supervisor)
+        && let Some(parent) = base.parent()
+    {
+        base = parent.to_path_buf();
+    }
+
+    let mut controllers = Vec::new();
+    if cgroup.cpu_max.is_some() {
+        controllers.push("cpu");
+    }
+    if cgroup.memory_max.is_some() {
+        controllers.push("memory");
+    }
+    let available = fs::read_to_string(base.join("cgroup.controllers")).unwrap_or_default();
+    if let Some(missing) = controllers.iter().find(|c| !available.split_whitespace().any(|a| a == **c)) {
+        return Err(XmrError::ConfigError(format!(
+            "The {} cgroup controller is not available in {}; it must be enabled in the parent group",
+            missing,
+            base.display()
+        )));
+    }
+    enable(&base, &controllers, &supervisor)?;
+
+    let group = base.join(format!("{}-miner", service::name()));
+    fs::create_dir_all(&group)?;
+    // Lift limits left from an earlier run with a different [cgroup]
+    let cpu = cgroup.cpu_max.map(|percent| cpu_max(percent, cpu::detect(config.miner.cpus.as_ref()).logical));
+    if cpu.is_some() || group.join("cpu.max").exists() {
+        write(&group.join("cpu.max"), cpu.as_deref().unwrap_or("max"))?;
+    }
+    let memory = cgroup.memory_max.as_deref().map(parse_memory).transpose()?.map(|bytes| bytes.to_string());
+    if memory.is_some() || group.join("memory.max").exists() {
+        write(&group.join("memory.max"), memory.as_deref().unwrap_or("max"))?;
+    }
+    info!(
+        "Limiting the miner to {}{}{} through cgroup {}",
+        cgroup.cpu_max.map_or(String::new(), |percent| format!("{}% CPU", percent)),
+        if cgroup.cpu_max.is_some() && cgroup.memory_max.is_some() { " and " } else { "" },
+        cgroup.memory_max.as_deref().map_or(String::new(), |memory| format!("{} of memory", memory)),
+        group.display()
+    );
+    if let Ok(mut current) = GROUP.lock() {
+        *current = Some(group);
+    }
+    Ok(())
+}
+
+// Move a started miner into the cgroup from `prepare`. Threads it starts
+// from then on are created inside it.
+pub fn enter(pid: u32) {
+    let Some(group) = GROUP.lock().ok().and_then(|group| group.clone()) else {
+        return;
+    };
+    match write(&group.join("cgroup.procs"), &pid.to_string()) {
+        Ok(()) => debug!("Moved the miner ({}) into {}", pid, group.display()),
+        Err(e) => warn!("The miner runs without its [cgroup] limits: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = "proc /proc proc rw 0 0\ncgroup /sys/fs/cgroup/cpu cgroup rw,cpu 0 0\ncgroup2 /sys/fs/cgroup cgroup2 rw,nsdelegate 0 0\n";
+        assert_eq!(parse_mounts(mounts), Some(PathBuf::from("/sys/fs/cgroup")));
+        assert_eq!(parse_mounts("proc /proc proc rw 0 0\n"), None);
+    }
+
+    #[test]
+    fn test_parse_own() {
+        assert_eq!(parse_own("0::/system.slice/minning.service\n").as_deref(), Some("/system.slice/minning.service"));
+        assert_eq!(parse_own("4:memory:/user\n1:cpu:/\n0::/\n").as_deref(), Some("/"));
+        assert_eq!(parse_own("4:memory:/user\n"), None);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("4G").unwrap(), 4 << 30);
+        assert_eq!(parse_memory("512m").unwrap(), 512 << 20);
+        assert_eq!(parse_memory("1048576").unwrap(), 1 << 20);
+        assert!(parse_memory("lots").is_err());
+        assert!(parse_memory("4GB").is_err());
+    }
+
+    #[test]
+    fn test_cpu_max() {
+        assert_eq!(cpu_max(50, 8), "400000 100000");
+        assert_eq!(cpu_max(25, 1), "25000 100000");
+        // The kernel's smallest quota is 1ms
+        assert_eq!(cpu_max(1, 0), "1000 100000");
+    }
+}
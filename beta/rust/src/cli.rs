@@ -0,0 +1,411 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+
+use crate::config::{CpuList, Threads};
+use crate::service::Manager;
+
+// Command line of the launcher. Global flags may appear before or after
+// the subcommand.
+#[derive(Debug, Parser)]
+#[command(name = "minning", version, about = "Install, run and supervise the XMR miner", arg_required_else_help = true)]
+pub struct Cli {
+    #[arg(long, global = true, value_name = "INSTANCE", help = "Run against a separately configured named instance")]
+    pub name: Option<String>,
+    #[arg(long, global = true, value_name = "PATH", help = "Read the configuration from PATH instead of the default location")]
+    pub config: Option<PathBuf>,
+    #[arg(short, long, global = true, action = ArgAction::Count, help = "Log more detail from the launcher (-v debug, -vv trace)")]
+    pub verbose: u8,
+    #[arg(short, long, global = true, conflicts_with = "verbose", help = "Log only warnings and errors; no progress bars or chatter")]
+    pub quiet: bool,
+    #[arg(
+        long,
+        global = true,
+        alias = "events-json",
+        help = "Machine-readable output: events and a final result or error object as JSON lines on stdout"
+    )]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+// Completion script for `shell`, registered under the name this launcher was
+// invoked as so it completes whatever the binary is installed as
+pub fn completions(shell: Shell) -> String {
+    let bin_name = std::env::args_os()
+        .next()
+        .and_then(|arg0| PathBuf::from(arg0).file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "minning".to_string());
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), bin_name, &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+#[derive(Debug, Args)]
+pub struct DownloadArgs {
+    #[arg(long, help = "Download even on a metered connection")]
+    pub allow_metered: bool,
+    #[arg(long, help = "Skip the checksum and signature checks (locally built archives)")]
+    pub no_verify: bool,
+    #[arg(long, value_name = "PATH", help = "Also write the install_completed / install_failed result to PATH")]
+    pub result_file: Option<String>,
+}
+
+// Miner settings of the run modes, held across restarts
+#[derive(Debug, Args)]
+pub struct MinerArgs {
+    #[arg(long, value_name = "N|auto", help = "Mining threads, overriding [miner] threads")]
+    pub threads: Option<Threads>,
+    #[arg(long, value_name = "LIST", help = "Pin the miner to these CPUs, e.g. 0-3,8, overriding [miner] cpus")]
+    pub cpus: Option<CpuList>,
+    #[arg(long, help = "Refuse to start when this machine cannot run RandomX in fast mode")]
+    pub strict: bool,
+    #[arg(long, value_name = "NAME", help = "Mine with this profile (eco, balanced, max or one of [profiles]), overriding [miner] profile")]
+    pub profile: Option<String>,
+    #[arg(long, conflicts_with = "replace", help = "Keep a miner left running by a crashed supervisor, taking over once it exits")]
+    pub adopt: bool,
+    #[arg(long, help = "Stop the supervisor and miner this instance already runs, then take over from them")]
+    pub replace: bool,
+    #[arg(last = true, value_name = "MINER_ARGS", help = "Passed on to the miner as they are, e.g. `-- --randomx-mode fast`")]
+    pub miner_args: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "Install the miner from a release tag (checked against the release checksum and signature)")]
+    Init {
+        #[arg(long = "version", value_name = "TAG", help = "Release tag to install instead of the default")]
+        release: Option<String>,
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+    #[command(about = "Install the latest miner release; a running supervisor switches to it")]
+    Update {
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+    #[command(about = "Run the miner in the foreground")]
+    Run {
+        #[arg(long, help = "Detach from the terminal; detached runs are supervised as with run-resilient")]
+        daemon: bool,
+        #[command(flatten)]
+        miner: MinerArgs,
+    },
+    #[command(about = "Run the miner under a watchdog (stops only on Ctrl+C or `stop`)")]
+    RunResilient {
+        #[arg(long, help = "Detach from the terminal and log to minning.log; manage it with `status` and `stop`")]
+        daemon: bool,
+        #[command(flatten)]
+        miner: MinerArgs,
+    },
+    #[command(about = "Run the miner under several redundant watchdogs (maximum resistance)")]
+    RunSuperResilient {
+        #[arg(long, help = "Detach from the terminal and log to minning.log; manage it with `status` and `stop`")]
+        daemon: bool,
+        #[command(flatten)]
+        miner: MinerArgs,
+    },
+    #[command(about = "Run the [[scheduler.jobs]] in the foreground; supervisors run them too")]
+    Scheduler,
+    #[command(about = "Install and enable a service that keeps the supervisor running across reboots")]
+    InstallService {
+        #[arg(long, conflicts_with = "system", help = "Install for the current user (the default unless run as root)")]
+        user: bool,
+        #[arg(long, help = "Install system-wide (the default when run as root)")]
+        system: bool,
+        #[arg(long, value_enum, help = "Init system to install into (detected by default)")]
+        manager: Option<Manager>,
+        #[arg(long, help = "Supervise with run-super-resilient instead of run-resilient")]
+        super_resilient: bool,
+        #[arg(long, help = "Enable it without starting it now")]
+        no_start: bool,
+        #[arg(long, help = "Only print the service definition")]
+        print: bool,
+    },
+    #[command(about = "Show the miner output captured during the current or last supervised run")]
+    Logs {
+        #[arg(short, long, help = "Keep printing new output as the miner writes it")]
+        follow: bool,
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 100, help = "How many lines from the end to show")]
+        lines: usize,
+    },
+    #[command(about = "Pause mining until `resume`, keeping the supervisor running (or send it SIGUSR1)")]
+    Pause,
+    #[command(about = "Resume mining after `pause` (or send the supervisor SIGUSR2)")]
+    Resume,
+    #[command(about = "Restart the supervised miner with the reloaded config, keeping the supervisor running")]
+    Restart,
+    #[command(about = "Cleanly shut down a running supervisor")]
+    Stop {
+        #[arg(long, value_name = "SECS", default_value_t = 60, help = "How long to wait for it to exit")]
+        timeout: u64,
+    },
+    #[command(about = "Show hashrate, uptime, pool connection and shares of the running miner")]
+    Status {
+        #[arg(long, help = "Show the launcher's own timings, CPU and memory instead")]
+        internal: bool,
+    },
+    #[command(about = "Show the pool balance of the configured wallet")]
+    Balance {
+        #[arg(long, value_name = "CURRENCY", help = "Also show the value in this fiat currency")]
+        fiat: Option<String>,
+    },
+    #[command(about = "Show recorded earnings")]
+    Earnings {
+        #[arg(long, value_name = "PERIOD", default_value = "day", value_parser = ["day", "week", "month"])]
+        by: String,
+        #[arg(long, value_name = "CURRENCY", help = "Also show the value in this fiat currency")]
+        fiat: Option<String>,
+    },
+    #[command(about = "Estimate daily profit after electricity")]
+    Profit {
+        #[arg(long, value_name = "H/S", help = "Hashrate to assume instead of the running miner's")]
+        hashrate: Option<f64>,
+        #[arg(long, value_name = "W", help = "Power draw to assume")]
+        watts: Option<f64>,
+    },
+    #[command(about = "List pool payouts to the configured wallet")]
+    Payouts,
+    #[command(about = "Manage the configured wallet address(es)")]
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+    #[command(about = "Show configured vs actual wallet split")]
+    Split,
+    #[command(about = "Show uptime, restarts, average hashrate and shares for the current session and all time")]
+    Stats,
+    #[command(about = "Show upload/download used by the miner")]
+    Bandwidth {
+        #[arg(long, help = "Start counting from zero")]
+        reset: bool,
+    },
+    #[command(about = "Export integration files")]
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    #[command(about = "Write a support bundle (config, state, recent logs) for bug reports")]
+    Report {
+        #[arg(long, help = "Hide wallets, host names and IP addresses")]
+        anonymize: bool,
+        #[arg(long, value_name = "FILE", default_value = "minning-report.txt")]
+        output: PathBuf,
+    },
+    #[command(about = "Test, compare and discover pools")]
+    Pool {
+        #[command(subcommand)]
+        command: PoolCommand,
+    },
+    #[command(about = "Share one pool connection between several rigs on the LAN")]
+    Proxy {
+        #[arg(long, value_name = "ADDR:PORT", help = "Address to accept miners on")]
+        listen: Option<String>,
+    },
+    #[command(about = "Install, run alongside the miner, or check a local p2pool")]
+    P2pool {
+        #[command(subcommand)]
+        command: Option<ServiceCommand>,
+    },
+    #[command(about = "Show height and connections of the solo mining node")]
+    Solo,
+    #[command(about = "Measure the miner's hashrate at several thread counts")]
+    Bench {
+        #[arg(long, value_name = "N,...", value_delimiter = ',', help = "Thread counts to try instead of ones picked from the CPU")]
+        threads: Vec<usize>,
+        #[arg(long, value_name = "SECS", default_value_t = 60, help = "How long to run each thread count")]
+        duration: u64,
+    },
+    #[command(about = "List and edit mining profiles")]
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    #[command(about = "Check AES, huge pages, L3 cache and RAM for RandomX fast mode")]
+    Preflight {
+        #[arg(long, help = "Exit with an error when a check fails")]
+        strict: bool,
+    },
+    #[command(about = "Tune the system for RandomX")]
+    Tune {
+        #[command(subcommand)]
+        command: TuneCommand,
+    },
+    #[command(about = "Install, run or check a managed monerod node")]
+    Node {
+        #[command(subcommand)]
+        command: Option<ServiceCommand>,
+    },
+    #[command(about = "Print the shell completion script, e.g. `minning completions bash > /etc/bash_completion.d/minning`")]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    #[command(about = "Save the Monero payout address in the config")]
+    Set {
+        address: String,
+    },
+    #[command(about = "Show the configured payout address(es)")]
+    Show,
+    #[command(about = "Confirm the configured address(es) before mining to them")]
+    Confirm,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportCommand {
+    #[command(about = "Print a Grafana dashboard for the [metrics] Prometheus exporter")]
+    GrafanaDashboard,
+    #[command(about = "Print the miner config.json that [miner] generate_config starts the miner with")]
+    MinerConfig,
+    #[command(about = "Print a systemd unit that passes the wallet and passwords as systemd credentials")]
+    SystemdCredentials {
+        #[arg(long, value_name = "DIR", help = "Load the credential files from DIR instead of the system credential store")]
+        from: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    #[command(about = "Show the built-in and configured profiles")]
+    List,
+    #[command(about = "Create or change a profile in the config file")]
+    Set {
+        // Not "name", which is the global --name
+        #[arg(id = "profile", value_name = "NAME")]
+        name: String,
+        #[arg(long, value_name = "N|auto", help = "Mining threads")]
+        threads: Option<Threads>,
+        #[arg(long, value_name = "-20..19", allow_negative_numbers = true, help = "Niceness of the miner")]
+        nice: Option<i32>,
+        #[arg(long, value_name = "PERCENT", help = "Share of the CPUs the miner may use")]
+        cpu_cap: Option<u8>,
+        #[arg(long, value_name = "HH:MM-HH:MM", help = "Only mine between these local times")]
+        hours: Option<String>,
+    },
+    #[command(about = "Remove a profile from the config file")]
+    Remove {
+        #[arg(id = "profile", value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TuneCommand {
+    #[command(about = "Reserve the huge pages RandomX needs for the configured miners")]
+    Hugepages {
+        #[arg(long, help = "Only report what is reserved and what is needed")]
+        check: bool,
+        #[arg(long, conflicts_with = "check", help = "Keep the setting across reboots in /etc/sysctl.d")]
+        persist: bool,
+        #[arg(short, long, help = "Apply without asking")]
+        yes: bool,
+    },
+    #[command(about = "Apply the RandomX MSR tweaks for Ryzen and Intel CPUs (needs root)")]
+    Msr {
+        #[arg(long, help = "Show the registers and values without writing them")]
+        dry_run: bool,
+        #[arg(long, conflicts_with = "dry_run", help = "Restore the values saved before the last change")]
+        revert: bool,
+        #[arg(short, long, help = "Apply without asking")]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PoolCommand {
+    #[command(about = "Log in to the pool and report latency")]
+    Test {
+        #[arg(long, value_name = "HOST:PORT", help = "Pool to test instead of the configured one")]
+        pool: Option<String>,
+        #[arg(long, help = "Connect with TLS")]
+        tls: bool,
+    },
+    #[command(about = "Measure DNS, connect, TLS and login latency of all configured pools")]
+    Ping {
+        #[arg(long, help = "Also ping the regional endpoints of known pools")]
+        regional: bool,
+    },
+    #[command(about = "Suggest pool endpoints near you")]
+    Suggest {
+        #[arg(long, help = "Look up the region by IP address")]
+        geoip: bool,
+    },
+    #[command(about = "Save a pool under a name")]
+    Add {
+        // Not "name", which is the global --name
+        #[arg(id = "pool", value_name = "NAME")]
+        name: String,
+        #[arg(help = "Pool address, e.g. pool.supportxmr.com:443 or stratum+ssl://pool.supportxmr.com:443")]
+        url: String,
+        #[arg(long, help = "Port, when the address has none")]
+        port: Option<u16>,
+        #[arg(long, help = "Connect with TLS")]
+        tls: bool,
+        #[arg(long, help = "Login to send instead of the wallet address")]
+        user: Option<String>,
+        #[arg(long, help = "Password to send instead of [pool] password")]
+        password: Option<String>,
+        #[arg(long = "default", help = "Mine on this pool from now on")]
+        make_default: bool,
+    },
+    #[command(about = "List the saved pools")]
+    List,
+    #[command(about = "Forget a saved pool")]
+    Remove {
+        #[arg(id = "pool", value_name = "NAME")]
+        name: String,
+    },
+    #[command(about = "Mine on a saved pool from now on")]
+    SetDefault {
+        #[arg(id = "pool", value_name = "NAME")]
+        name: String,
+    },
+}
+
+// p2pool and node share their lifecycle commands; without one they report
+// their status
+#[derive(Debug, Subcommand)]
+pub enum ServiceCommand {
+    #[command(about = "Download and install it")]
+    Init {
+        #[arg(long, help = "Download even on a metered connection")]
+        allow_metered: bool,
+    },
+    #[command(about = "Run it in the foreground")]
+    Run,
+    #[command(about = "Check whether it is running and in sync")]
+    Status,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["minning", "init", "--version", "v2", "--name", "rig2", "-vv"]).unwrap();
+        assert_eq!(cli.name.as_deref(), Some("rig2"));
+        assert_eq!(cli.verbose, 2);
+        assert!(matches!(cli.command, Command::Init { release: Some(ref tag), .. } if tag == "v2"));
+
+        let cli = Cli::try_parse_from(["minning", "run-resilient", "--events-json", "--daemon"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::RunResilient { daemon: true, .. }));
+        let cli = Cli::try_parse_from(["minning", "run", "--", "--threads", "8", "--randomx-mode", "fast"]).unwrap();
+        assert!(matches!(cli.command, Command::Run { daemon: false, ref miner } if miner.miner_args.len() == 4));
+        let cli = Cli::try_parse_from(["minning", "run-super-resilient", "--threads", "auto"]).unwrap();
+        assert!(matches!(cli.command, Command::RunSuperResilient { ref miner, .. } if miner.threads == Some(Threads::Auto)));
+        assert!(Cli::try_parse_from(["minning", "run", "--threads", "0"]).is_err());
+        assert!(Cli::try_parse_from(["minning", "earnings", "--by", "year"]).is_err());
+        assert!(Cli::try_parse_from(["minning", "update", "--quiet"]).unwrap().quiet);
+        assert!(Cli::try_parse_from(["minning", "status", "-q", "-v"]).is_err());
+        assert!(completions(Shell::Bash).contains("run-resilient"));
+    }
+}
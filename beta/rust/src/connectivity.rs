@@ -0,0 +1,109 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::pause;
+use crate::pool;
+use crate::proxy;
+use crate::stratum;
+
+const PAUSE_REASON: &str = "network offline";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+// Failed checks in a row before calling it an outage, so a single dropped
+// probe does not stop the miner
+const FAILED_CHECKS: u32 = 2;
+
+// Whether a /proc/net/route or /proc/net/ipv6_route table has a default route
+fn has_default_route(table: &str, ipv6: bool) -> bool {
+    table.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if ipv6 {
+            // Destination and prefix length come first; skip the loopback device
+            fields.len() >= 10 && fields[0].trim_matches('0').is_empty() && fields[1] == "00" && fields[9] != "lo"
+        } else {
+            fields.len() >= 2 && fields[1] == "00000000"
+        }
+    })
+}
+
+// Without any default route nothing outside the LAN can be reached. Systems
+// without /proc are given the benefit of the doubt.
+fn default_route() -> bool {
+    let v4 = fs::read_to_string("/proc/net/route");
+    let v6 = fs::read_to_string("/proc/net/ipv6_route");
+    if v4.is_err() && v6.is_err() {
+        return true;
+    }
+    v4.is_ok_and(|t| has_default_route(&t, false)) || v6.is_ok_and(|t| has_default_route(&t, true))
+}
+
+fn online(endpoints: &[String]) -> bool {
+    if !default_route() {
+        debug!("No default route");
+        return false;
+    }
+    endpoints.iter().any(|endpoint| stratum::reachable(endpoint, PROBE_TIMEOUT))
+}
+
+// Supervised modes: while neither the default route nor any pool endpoint
+// answers, keep mining paused so the watchdog does not burn through restarts
+// and crash alerts; mining resumes on its own once a pool is reachable again
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    let interval = Duration::from_secs(config.network.outage_check_secs);
+    // p2pool and solo mining talk to local processes the sidecars look after
+    let endpoints: Vec<String> = pool::endpoints(config).into_iter().filter(|e| !proxy::is_loopback(e)).collect();
+    if interval.is_zero() || config.solo.enabled || endpoints.is_empty() {
+        return;
+    }
+
+    thread::spawn(move || {
+        let mut failures = 0;
+        let mut offline_since: Option<Instant> = None;
+        while running.load(Ordering::SeqCst) {
+            if online(&endpoints) {
+                failures = 0;
+                if let Some(since) = offline_since.take() {
+                    info!("Network is back after {} seconds, resuming mining", since.elapsed().as_secs());
+                    pause::resume(PAUSE_REASON);
+                }
+            } else {
+                failures += 1;
+                if failures >= FAILED_CHECKS && offline_since.is_none() {
+                    warn!("No pool is reachable, pausing mining until the network returns");
+                    offline_since = Some(Instant::now());
+                    pause::pause(PAUSE_REASON);
+                }
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < interval && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_route() {
+        let route = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                     eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n\
+                     eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\n";
+        assert!(has_default_route(route, false));
+        assert!(!has_default_route(&route.replace("\t00000000\t0101A8C0", "\t0002A8C0\t0101A8C0"), false));
+
+        let v6 = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003 wlan0\n\
+                  00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo\n";
+        assert!(has_default_route(v6, true));
+        assert!(!has_default_route(v6.lines().nth(1).unwrap(), true));
+    }
+}
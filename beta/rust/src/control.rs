@@ -0,0 +1,351 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tracing::{debug, error, info, warn};
+
+use crate::config::{self, Config};
+use crate::output;
+use crate::pause;
+use crate::pidfile;
+use crate::run::XmrError;
+use crate::status;
+use crate::wallet;
+
+#[cfg(unix)]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+// Control channel of a running supervisor. Clients write one request name
+// per line to its Unix socket and get one JSON object back, e.g.
+// "restart" -> {"ok":true}. Where there are no Unix sockets, stop and
+// restart leave a request file in the state directory instead, which the
+// supervisor picks up within a second and removes once it has acted on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Request {
+    // The supervisor's and the miner's state, as `status --json` shows it
+    Status,
+    // Stop mining until `resume`, keeping the supervisor running
+    Pause,
+    Resume,
+    // Reload the config and replace the miner, keeping the session
+    Restart,
+    // Shut down as on Ctrl+C
+    Stop,
+}
+
+// Requests that also work through request files
+const FILE_REQUESTS: [Request; 2] = [Request::Stop, Request::Restart];
+// Pause reason owned by the control channel
+pub const PAUSE_REASON: &str = "user";
+// How long `send` waits for the supervisor to pick a request up
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl Request {
+    pub fn name(self) -> &'static str {
+        match self {
+            Request::Status => "status",
+            Request::Pause => "pause",
+            Request::Resume => "resume",
+            Request::Restart => "restart",
+            Request::Stop => "stop",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Request> {
+        [Request::Status, Request::Pause, Request::Resume, Request::Restart, Request::Stop]
+            .into_iter()
+            .find(|request| request.name() == name)
+    }
+
+    fn path(self) -> Result<PathBuf, XmrError> {
+        Ok(config::state_dir()?.join(format!("{}.request", self.name())))
+    }
+}
+
+// $XDG_RUNTIME_DIR/minning.sock (minning-<instance>.sock for named
+// instances), or control.sock in the state directory without a runtime dir
+pub fn socket_path() -> Result<PathBuf, XmrError> {
+    let name = if config::instance() == config::DEFAULT_INSTANCE {
+        "minning.sock".to_string()
+    } else {
+        format!("minning-{}.sock", config::instance())
+    };
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join(name)),
+        None => Ok(config::state_dir()?.join("control.sock")),
+    }
+}
+
+// Bumped for every accepted restart; watchdogs compare it with what they
+// last saw and replace their child
+static RESTARTS: AtomicU64 = AtomicU64::new(0);
+// Config reloaded for the latest restart
+static RELOADED: Mutex<Option<Config>> = Mutex::new(None);
+
+pub fn restarts() -> u64 {
+    RESTARTS.load(Ordering::SeqCst)
+}
+
+pub fn reloaded_config() -> Option<Config> {
+    RELOADED.lock().ok().and_then(|config| config.clone())
+}
+
+// Drop requests and pause reasons left over from a supervisor that died
+// before seeing them
+fn clear() {
+    for request in FILE_REQUESTS {
+        if let Ok(path) = request.path() {
+            let _ = fs::remove_file(path);
+        }
+    }
+    pause::clear_saved();
+}
+
+// The latest SIGUSR1 (pause) or SIGUSR2 (resume) not yet acted on, 0 for none
+#[cfg(unix)]
+static SIGNALLED: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    SIGNALLED.store(signal, Ordering::SeqCst);
+}
+
+// `kill -USR1` pauses mining and `kill -USR2` resumes it, as `pause` and
+// `resume` do
+#[cfg(unix)]
+fn catch_signals() {
+    for signal in [libc::SIGUSR1, libc::SIGUSR2] {
+        // SAFETY: the handler only stores to an atomic
+        unsafe { libc::signal(signal, on_signal as *const () as libc::sighandler_t) };
+    }
+}
+
+#[cfg(unix)]
+fn signalled_request() -> Option<Request> {
+    match SIGNALLED.swap(0, Ordering::SeqCst) {
+        libc::SIGUSR1 => Some(Request::Pause),
+        libc::SIGUSR2 => Some(Request::Resume),
+        _ => None,
+    }
+}
+
+fn restart() -> Result<(), XmrError> {
+    // A config that no longer loads must not take the running miner down
+    let config = config::load().inspect_err(|e| error!("Ignoring the restart request, the configuration does not load: {}", e))?;
+    // Nor may it mine to an address startup would have refused
+    wallet::check_confirmed(&config).inspect_err(|e| error!("Ignoring the restart request: {}", e))?;
+    if let Ok(mut reloaded) = RELOADED.lock() {
+        *reloaded = Some(config);
+    }
+    info!("Received a restart request, restarting XMR with the reloaded configuration");
+    RESTARTS.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+// Replace the miner with the config it runs on, for a setting the
+// supervisor changed itself
+pub fn restart_miner(reason: &str) {
+    info!("Restarting XMR: {}", reason);
+    RESTARTS.fetch_add(1, Ordering::SeqCst);
+}
+
+// Carry out `request` in the supervisor; the reply for the client
+pub fn handle(request: Request, config: &Config, running: &AtomicBool) -> Value {
+    let result = match request {
+        Request::Status => status::snapshot(config).map(|mut snapshot| {
+            snapshot["paused"] = json!(pause::reasons());
+            snapshot
+        }),
+        Request::Pause => {
+            info!("Received a pause request");
+            pause::pause(PAUSE_REASON);
+            Ok(json!({ "paused": pause::reasons() }))
+        },
+        Request::Resume => {
+            info!("Received a resume request");
+            pause::resume(PAUSE_REASON);
+            Ok(json!({ "paused": pause::reasons() }))
+        },
+        Request::Restart => restart().map(|_| json!({})),
+        Request::Stop => {
+            info!("Received a stop request, preparing for graceful shutdown...");
+            running.store(false, Ordering::SeqCst);
+            Ok(json!({}))
+        },
+    };
+    match result {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert("ok".to_string(), json!(true));
+            Value::Object(fields)
+        },
+        Ok(value) => json!({ "ok": true, "result": value }),
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    }
+}
+
+#[cfg(unix)]
+fn serve(stream: UnixStream, config: &Config, running: &AtomicBool) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let reply = match Request::parse(line.trim()) {
+            Some(request) => handle(request, config, running),
+            None => json!({ "ok": false, "error": format!("unknown request {:?}", line.trim()) }),
+        };
+        writeln!(writer, "{}", reply)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn listen(config: &Config, running: &Arc<AtomicBool>) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = socket_path().ok()?;
+    // Only one supervisor holds the PID file, so a socket left here is stale
+    let _ = fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Could not open the control socket {}: {}", path.display(), e);
+            return None;
+        },
+    };
+    let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o600));
+    debug!("Listening for control requests on {}", path.display());
+
+    let (config, running) = (config.clone(), running.clone());
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            if let Err(e) = serve(stream, &config, &running) {
+                debug!("Control request failed: {}", e);
+            }
+        }
+    });
+    Some(path)
+}
+
+// Supervised modes: take requests on the socket, as request files and as
+// signals
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    clear();
+    #[cfg(unix)]
+    let socket = listen(config, &running);
+    #[cfg(unix)]
+    catch_signals();
+    let config = config.clone();
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            #[cfg(unix)]
+            if let Some(request) = signalled_request() {
+                handle(request, &config, &running);
+            }
+            for request in FILE_REQUESTS {
+                let Ok(path) = request.path() else { continue };
+                if path.exists() {
+                    handle(request, &config, &running);
+                    let _ = fs::remove_file(&path);
+                }
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+        #[cfg(unix)]
+        if let Some(socket) = socket {
+            let _ = fs::remove_file(socket);
+        }
+    });
+}
+
+#[cfg(unix)]
+fn send_socket(request: Request) -> Option<Result<Value, XmrError>> {
+    let path = socket_path().ok()?;
+    let mut stream = UnixStream::connect(&path).ok()?;
+    let mut exchange = || -> std::io::Result<Value> {
+        stream.set_read_timeout(Some(ACCEPT_TIMEOUT))?;
+        writeln!(stream, "{}", request.name())?;
+        let mut reply = String::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        serde_json::from_str(&reply).map_err(std::io::Error::other)
+    };
+    Some(match exchange() {
+        Ok(reply) if reply["ok"] == json!(true) => Ok(reply),
+        Ok(reply) => Err(XmrError::ExecutionError(reply["error"].as_str().unwrap_or("request failed").to_string())),
+        Err(e) => Err(XmrError::ExecutionError(format!("No answer on the control socket {}: {}", path.display(), e))),
+    })
+}
+
+fn send_file(request: Request, pid: u32) -> Result<Value, XmrError> {
+    if !FILE_REQUESTS.contains(&request) {
+        return Err(XmrError::ExecutionError(format!(
+            "Supervisor {} has no control socket to send {} to",
+            pid,
+            request.name()
+        )));
+    }
+    let path = request.path()?;
+    fs::write(&path, pid.to_string())
+        .map_err(|e| XmrError::PermissionError(format!("Could not send a request to supervisor {}: {}", pid, e)))?;
+
+    let started = Instant::now();
+    while path.exists() {
+        if started.elapsed() >= ACCEPT_TIMEOUT || pidfile::running() != Some(pid) {
+            let _ = fs::remove_file(&path);
+            return Err(XmrError::ExecutionError(format!(
+                "Supervisor {} did not accept the request within {} seconds",
+                pid,
+                ACCEPT_TIMEOUT.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    Ok(json!({ "ok": true }))
+}
+
+// Hand `request` to the running supervisor; its PID and reply
+pub fn send(request: Request) -> Result<(u32, Value), XmrError> {
+    let Some(pid) = pidfile::running() else {
+        return Err(XmrError::ExecutionError("No supervised miner is running".to_string()));
+    };
+    #[cfg(unix)]
+    if let Some(reply) = send_socket(request) {
+        return reply.map(|reply| (pid, reply));
+    }
+    send_file(request, pid).map(|reply| (pid, reply))
+}
+
+// `pause` and `resume`: hold or release the running supervisor's miner.
+// Other reasons to pause, such as [miner] hours, keep it paused after a
+// resume.
+pub fn set_paused(paused: bool) -> Result<(), XmrError> {
+    let (pid, reply) = send(if paused { Request::Pause } else { Request::Resume })?;
+    let reasons: Vec<String> = serde_json::from_value(reply["paused"].clone()).unwrap_or_default();
+    if output::json() {
+        output::ok(json!({ "supervisor_pid": pid, "paused": reasons }));
+    } else if paused {
+        println!("Supervisor {} paused mining; `resume` picks it up again.", pid);
+    } else if reasons.is_empty() {
+        println!("Supervisor {} resumed mining.", pid);
+    } else {
+        println!("Supervisor {} is still paused: {}.", pid, reasons.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Request::parse("restart"), Some(Request::Restart));
+        assert_eq!(Request::parse("status"), Some(Request::Status));
+        assert_eq!(Request::parse("reboot"), None);
+    }
+}
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use std::thread;
+
+use tracing::{debug, info, warn};
+
+use crate::config::{CpuList, MinerConfig, Threads};
+use crate::run::XmrError;
+
+// RandomX wants 2 MiB of L3 cache per mining thread
+const SCRATCHPAD_BYTES: u64 = 2 * 1024 * 1024;
+
+// Caps from set_cap, by reason
+static CAPS: Mutex<BTreeMap<&'static str, u8>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Topology {
+    pub logical: usize,
+    pub physical_cores: usize,
+    // Total L3 across all caches; None where it cannot be read
+    pub l3_bytes: Option<u64>,
+}
+
+// Cache sizes as sysfs writes them: "32768K", "32M" or bytes
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, unit) = match size.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((at, _)) => size.split_at(at),
+        None => (size, ""),
+    };
+    let number: u64 = number.parse().ok()?;
+    match unit {
+        "" => Some(number),
+        "K" => Some(number * 1024),
+        "M" => Some(number * 1024 * 1024),
+        _ => None,
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+// Logical CPUs, physical cores and L3 size from sysfs, of all CPUs or the
+// ones in `within`. Each L3 is counted once, however many cores share it.
+#[cfg(target_os = "linux")]
+fn detect_sysfs(within: Option<&CpuList>) -> Option<Topology> {
+    let mut cores = BTreeSet::new();
+    let mut caches = BTreeSet::new();
+    let mut logical = 0;
+    let mut l3_bytes = 0;
+    for entry in fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+        let name = entry.file_name();
+        let Some(index) = name.to_str().and_then(|n| n.strip_prefix("cpu")) else { continue };
+        let Ok(index) = index.parse::<usize>() else { continue };
+        if within.is_some_and(|cpus| !cpus.cpus().contains(&index)) {
+            continue;
+        }
+        let dir = entry.path();
+        // Offline CPUs have no topology
+        let Some(core) = read(&dir.join("topology/core_id")) else { continue };
+        let package = read(&dir.join("topology/physical_package_id")).unwrap_or_default();
+        logical += 1;
+        cores.insert((package, core));
+
+        for cache in fs::read_dir(dir.join("cache")).into_iter().flatten().flatten() {
+            let cache = cache.path();
+            if read(&cache.join("level")).as_deref() != Some("3") {
+                continue;
+            }
+            let shared = read(&cache.join("shared_cpu_list")).unwrap_or_default();
+            if caches.insert(shared) {
+                l3_bytes += read(&cache.join("size")).as_deref().and_then(parse_size).unwrap_or(0);
+            }
+        }
+    }
+    (logical > 0).then(|| Topology { logical, physical_cores: cores.len(), l3_bytes: (l3_bytes > 0).then_some(l3_bytes) })
+}
+
+pub fn detect(within: Option<&CpuList>) -> Topology {
+    #[cfg(target_os = "linux")]
+    if let Some(topology) = detect_sysfs(within) {
+        return topology;
+    }
+    let logical = match within {
+        Some(cpus) => cpus.cpus().len(),
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    Topology { logical, physical_cores: logical, l3_bytes: None }
+}
+
+// "auto": one thread per physical core, no more than the L3 cache has room
+// for, since threads beyond that only thrash it
+pub fn auto_threads(topology: &Topology) -> usize {
+    let by_cache = topology.l3_bytes.map_or(usize::MAX, |bytes| (bytes / SCRATCHPAD_BYTES) as usize);
+    topology.physical_cores.min(by_cache).max(1)
+}
+
+// Thread count to start the miner with, if [miner] sets one
+pub fn threads(miner: &MinerConfig) -> Option<usize> {
+    let threads = match miner.threads {
+        None => None,
+        Some(Threads::Count(count)) => Some(count),
+        // Only the cores and caches the miner is pinned to count
+        Some(Threads::Auto) => {
+            let topology = detect(miner.cpus.as_ref());
+            let threads = auto_threads(&topology);
+            info!(
+                "Mining with {} threads: {} physical cores ({} logical), {} MiB of L3 cache",
+                threads,
+                topology.physical_cores,
+                topology.logical,
+                topology.l3_bytes.map_or("unknown".to_string(), |bytes| (bytes / 1024 / 1024).to_string())
+            );
+            Some(threads)
+        },
+    };
+    match cpu_cap(miner) {
+        Some(cap) => {
+            let logical = detect(miner.cpus.as_ref()).logical;
+            Some(threads.unwrap_or(logical).min(capped(logical, cap)))
+        },
+        None => threads,
+    }
+}
+
+// Hold the miner to `cap` percent of the CPUs for `reason`, such as running
+// on battery, or lift that cap with None. True when the lowest cap changed
+// and the miner needs a restart to pick it up.
+pub fn set_cap(reason: &'static str, cap: Option<u8>) -> bool {
+    let Ok(mut caps) = CAPS.lock() else {
+        return false;
+    };
+    let before = caps.values().min().copied();
+    match cap {
+        Some(cap) => caps.insert(reason, cap),
+        None => caps.remove(reason),
+    };
+    caps.values().min().copied() != before
+}
+
+// [miner] cpu_cap, lowered by any cap the supervisor holds
+pub fn cpu_cap(miner: &MinerConfig) -> Option<u8> {
+    let held = CAPS.lock().ok().and_then(|caps| caps.values().min().copied());
+    match (miner.cpu_cap, held) {
+        (Some(cap), Some(held)) => Some(cap.min(held)),
+        (cap, held) => cap.or(held),
+    }
+}
+
+// Threads that fit in `cap` percent of `logical` CPUs, at least one
+fn capped(logical: usize, cap: u8) -> usize {
+    (logical * cap as usize / 100).max(1)
+}
+
+// Start the command at niceness `nice`; every miner thread inherits it
+#[cfg(unix)]
+pub fn nice(command: &mut Command, nice: i32) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: the closure only makes one syscall. A failure, as when raising
+    // priority without the right, leaves the inherited niceness.
+    unsafe {
+        command.pre_exec(move || {
+            libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn nice(_command: &mut Command, _nice: i32) {
+    warn!("[miner] nice is only supported on Unix");
+}
+
+// Every thread of process `pid`
+#[cfg(target_os = "linux")]
+fn tasks(pid: u32) -> Vec<libc::pid_t> {
+    fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|tasks| tasks.flatten().filter_map(|task| task.file_name().to_str()?.parse().ok()).collect())
+        .unwrap_or_else(|_| vec![pid as libc::pid_t])
+}
+
+// Restrict process `pid` to `cpus`, as `taskset -a -p` does. Every thread
+// it already has is moved; threads it starts later inherit the mask.
+#[cfg(target_os = "linux")]
+pub fn pin(pid: u32, cpus: &CpuList) -> Result<(), XmrError> {
+    // SAFETY: cpu_set_t is a plain bit mask; all zeroes is the empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus.cpus() {
+        // SAFETY: CpuList only holds CPU numbers below CPU_SETSIZE
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+
+    for task in tasks(pid) {
+        // SAFETY: the mask outlives the call and its size is passed along
+        if unsafe { libc::sched_setaffinity(task, std::mem::size_of::<libc::cpu_set_t>(), &set) } != 0 {
+            return Err(XmrError::ExecutionError(format!(
+                "Could not pin process {} to CPUs {}: {}",
+                pid,
+                cpus,
+                std::io::Error::last_os_error()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin(_pid: u32, _cpus: &CpuList) -> Result<(), XmrError> {
+    Err(XmrError::ExecutionError("CPU pinning is only supported on Linux".to_string()))
+}
+
+// Set the niceness of every thread of process `pid`; on Linux each thread
+// has its own
+#[cfg(target_os = "linux")]
+pub fn renice(pid: u32, nice: i32) -> Result<(), XmrError> {
+    for task in tasks(pid) {
+        // SAFETY: setpriority only takes plain integers
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, task as libc::id_t, nice) } != 0 {
+            return Err(XmrError::ExecutionError(format!(
+                "Could not set the niceness of process {} to {}: {}",
+                pid,
+                nice,
+                std::io::Error::last_os_error()
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn renice(_pid: u32, _nice: i32) -> Result<(), XmrError> {
+    Err(XmrError::ExecutionError("Changing the miner's niceness is only supported on Linux".to_string()))
+}
+
+// After every miner start: apply [miner] cpus. A failure leaves the miner
+// running unpinned rather than not mining at all.
+pub fn pin_miner(pid: u32, miner: &MinerConfig) {
+    let Some(cpus) = &miner.cpus else {
+        return;
+    };
+    match pin(pid, cpus) {
+        Ok(()) => debug!("Pinned the miner ({}) to CPUs {}", pid, cpus),
+        Err(e) => warn!("{}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("32768K"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_size("8M\n"), Some(8 * 1024 * 1024));
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn test_auto_threads() {
+        // 8 cores with 16 MiB of L3: room for all of them
+        let topology = Topology { logical: 16, physical_cores: 8, l3_bytes: Some(16 * 1024 * 1024) };
+        assert_eq!(auto_threads(&topology), 8);
+        let topology = Topology { l3_bytes: Some(6 * 1024 * 1024), ..topology };
+        assert_eq!(auto_threads(&topology), 3);
+        let topology = Topology { l3_bytes: None, ..topology };
+        assert_eq!(auto_threads(&topology), 8);
+        let topology = Topology { l3_bytes: Some(1024 * 1024), ..topology };
+        assert_eq!(auto_threads(&topology), 1);
+    }
+
+    #[test]
+    fn test_set_cap() {
+        let miner = MinerConfig { cpu_cap: Some(80), ..MinerConfig::default() };
+        assert!(set_cap("test battery", Some(50)));
+        assert!(set_cap("test heat", Some(25)));
+        assert_eq!(cpu_cap(&miner), Some(25));
+        // Lifting a cap that is not the lowest changes nothing
+        assert!(!set_cap("test battery", None));
+        assert!(set_cap("test heat", None));
+        assert_eq!(cpu_cap(&miner), Some(80));
+    }
+
+    #[test]
+    fn test_capped() {
+        assert_eq!(capped(16, 25), 4);
+        assert_eq!(capped(6, 50), 3);
+        assert_eq!(capped(2, 10), 1);
+        assert_eq!(capped(8, 100), 8);
+    }
+}
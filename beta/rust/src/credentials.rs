@@ -0,0 +1,120 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::service::{self, Scope, Service};
+
+// Directory systemd places LoadCredential= files in for the running unit
+const CREDENTIALS_ENV: &str = "CREDENTIALS_DIRECTORY";
+// Where ImportCredential= finds credentials, plain or `systemd-creds encrypt`ed
+const CREDSTORE: &str = "/etc/credstore";
+
+// Secrets that can come from a systemd credential instead of the config file
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Secret {
+    Wallet,
+    PoolPassword,
+    MqttPassword,
+}
+
+const SECRETS: [Secret; 3] = [Secret::Wallet, Secret::PoolPassword, Secret::MqttPassword];
+
+impl Secret {
+    // Credential ID, as in LoadCredential=<id>
+    fn id(self) -> &'static str {
+        match self {
+            Secret::Wallet => "minning.wallet",
+            Secret::PoolPassword => "minning.pool-password",
+            Secret::MqttPassword => "minning.mqtt-password",
+        }
+    }
+
+    fn apply(self, config: &mut Config, value: String) {
+        match self {
+            Secret::Wallet => config.wallet.address = Some(value),
+            Secret::PoolPassword => config.pool.password = value,
+            Secret::MqttPassword => config.mqtt.password = Some(value),
+        }
+    }
+}
+
+fn read(dir: &Path, secret: Secret) -> Option<String> {
+    let path = dir.join(secret.id());
+    let contents = fs::read_to_string(&path).ok()?;
+    let value = contents.trim();
+    if value.is_empty() {
+        warn!("Credential {} is empty; ignoring it", path.display());
+        return None;
+    }
+    Some(value.to_string())
+}
+
+// Started by systemd with LoadCredential=: take the secrets it passed over
+// whatever the config file says, so they never need to be on disk in clear
+pub fn apply(config: &mut Config) {
+    let Some(dir) = env::var_os(CREDENTIALS_ENV).map(PathBuf::from) else {
+        return;
+    };
+    for secret in SECRETS {
+        if let Some(value) = read(&dir, secret) {
+            debug!("Using {} from the systemd credentials", secret.id());
+            secret.apply(config, value);
+        }
+    }
+}
+
+// `export systemd-credentials`: a service unit that runs the supervisor with
+// its secrets handed over by systemd, from the files in `from` or else from
+// the system credential store
+pub fn unit(from: Option<&Path>) -> String {
+    let mut unit = String::new();
+    unit.push_str("# Store each secret in its own file, readable by root only:\n");
+    for secret in SECRETS {
+        unit.push_str(&format!("#   {}\n", from.unwrap_or(Path::new(CREDSTORE)).join(secret.id()).display()));
+    }
+    let load = match from {
+        Some(dir) => {
+            unit.push_str("# systemd refuses to start the unit while a listed file is missing;\n");
+            unit.push_str("# drop the lines for secrets kept in the config file.\n");
+            SECRETS
+                .iter()
+                .map(|secret| format!("LoadCredential={}:{}", secret.id(), dir.join(secret.id()).display()))
+                .collect()
+        },
+        None => {
+            unit.push_str("# Secrets without a file keep their config file value.\n");
+            // Unlike LoadCredential=, imports whichever of them exist (systemd 254+)
+            vec!["ImportCredential=minning.*".to_string()]
+        },
+    };
+    let service = Service { scope: Scope::System, mode: "run-resilient" };
+    unit.push_str(&service::systemd_unit(&service, &load));
+    unit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_credentials() {
+        let dir = env::temp_dir().join(format!("minning-credentials-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("minning.pool-password"), "s3cret\n").unwrap();
+        fs::write(dir.join("minning.wallet"), "  \n").unwrap();
+
+        let mut config = Config::default();
+        for secret in SECRETS {
+            if let Some(value) = read(&dir, secret) {
+                secret.apply(&mut config, value);
+            }
+        }
+        assert_eq!(config.pool.password, "s3cret");
+        assert_eq!(config.wallet.address, None);
+        assert_eq!(config.mqtt.password, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs::{self, File, OpenOptions};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config;
+use crate::pidfd::ProcessHandle;
+use crate::pidfile;
+use crate::run::XmrError;
+
+// Whatever the detached supervisor writes outside the log (panics, miner
+// output before logging starts) ends up here, in the state directory
+pub const OUTPUT_FILE: &str = "daemon.out";
+// How long the terminal side waits for the supervisor to take the PID file
+const START_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub enum Detached {
+    // In the terminal process: the supervisor runs in the background as `pid`
+    Parent { pid: u32, output: PathBuf },
+    // In the background process, which carries on with the command
+    Daemon,
+}
+
+// `--daemon`: fork twice and leave the terminal behind (new session, stdin
+// from /dev/null, stdout and stderr to OUTPUT_FILE). Must run before any
+// thread is started, since only the forking thread survives a fork.
+pub fn detach() -> Result<Detached, XmrError> {
+    if let Some(pid) = pidfile::running() {
+        return Err(XmrError::ExecutionError(format!(
+            "A supervisor is already running (PID {}); stop it with `stop` first",
+            pid
+        )));
+    }
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    let output_path = dir.join(OUTPUT_FILE);
+    let output = OpenOptions::new().create(true).append(true).open(&output_path)?;
+    let null = File::open("/dev/null")?;
+
+    #[cfg(unix)]
+    {
+        match fork::detach(&output, &null)? {
+            Some(pid) => wait_for_start(pid, output_path),
+            None => Ok(Detached::Daemon),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (output, null);
+        Err(XmrError::ExecutionError("--daemon is only supported on Unix; run it as a service instead".to_string()))
+    }
+}
+
+// The supervisor is up once it holds the PID file; give up early if it died
+#[cfg(unix)]
+fn wait_for_start(pid: u32, output: PathBuf) -> Result<Detached, XmrError> {
+    let daemon = ProcessHandle::open(pid);
+    let started = Instant::now();
+    while started.elapsed() < START_TIMEOUT {
+        if pidfile::running() == Some(pid) {
+            return Ok(Detached::Parent { pid, output });
+        }
+        if !daemon.alive() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    Err(XmrError::ExecutionError(format!(
+        "The background supervisor did not start; see {} and the log",
+        output.display()
+    )))
+}
+
+#[cfg(unix)]
+mod fork {
+    use std::fs::File;
+    use std::io;
+    use std::os::fd::AsRawFd;
+
+    fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+        if result < 0 { Err(io::Error::last_os_error()) } else { Ok(result) }
+    }
+
+    // Some(daemon PID) in the caller, None in the daemon
+    pub fn detach(output: &File, null: &File) -> io::Result<Option<u32>> {
+        let mut fds = [0; 2];
+        // SAFETY: pipe fills both descriptors on success
+        check(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+        let (read_end, write_end) = (fds[0], fds[1]);
+
+        // SAFETY: no other threads exist yet, so the child gets a consistent copy
+        let first = check(unsafe { libc::fork() })?;
+        if first > 0 {
+            // The intermediate child exits straight away; reap it, then learn
+            // the daemon's PID from the pipe
+            unsafe {
+                libc::close(write_end);
+                libc::waitpid(first, std::ptr::null_mut(), 0);
+            }
+            let mut pid = [0u8; 4];
+            let read = unsafe { libc::read(read_end, pid.as_mut_ptr().cast(), pid.len()) };
+            unsafe { libc::close(read_end) };
+            if read != pid.len() as isize {
+                return Err(io::Error::other("the background process did not report its PID"));
+            }
+            return Ok(Some(u32::from_ne_bytes(pid)));
+        }
+
+        // SAFETY: plain syscalls on descriptors this process owns. The second
+        // fork keeps the daemon from ever reacquiring a controlling terminal.
+        unsafe {
+            libc::close(read_end);
+            libc::setsid();
+            if libc::fork() != 0 {
+                libc::_exit(0);
+            }
+            let pid = std::process::id().to_ne_bytes();
+            libc::write(write_end, pid.as_ptr().cast(), pid.len());
+            libc::close(write_end);
+            libc::dup2(null.as_raw_fd(), 0);
+            libc::dup2(output.as_raw_fd(), 1);
+            libc::dup2(output.as_raw_fd(), 2);
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+use ureq::unversioned::resolver::{ResolvedSocketAddrs, Resolver};
+use ureq::unversioned::transport::NextTimeout;
+
+use crate::http;
+use crate::run::XmrError;
+use crate::split::Target;
+
+const DOH_TIMEOUT: Duration = Duration::from_secs(5);
+// Never cache answers for less than this, whatever their TTL
+const MIN_TTL_SECS: u64 = 60;
+// DNS record types in the JSON API
+const TYPE_A: u64 = 1;
+const TYPE_AAAA: u64 = 28;
+
+// Hostname -> addresses and the moment they expire
+type Answers = HashMap<String, (Vec<IpAddr>, Instant)>;
+
+static CACHE: Mutex<Option<Answers>> = Mutex::new(None);
+
+// Addresses from a DoH JSON answer (application/dns-json), with the
+// smallest TTL among them
+fn parse_answer(answer: &serde_json::Value, record_type: u64) -> (Vec<IpAddr>, u64) {
+    let records: Vec<&serde_json::Value> = answer["Answer"]
+        .as_array()
+        .map(|records| records.iter().filter(|r| r["type"].as_u64() == Some(record_type)).collect())
+        .unwrap_or_default();
+
+    let ttl = records.iter().filter_map(|r| r["TTL"].as_u64()).min().unwrap_or(MIN_TTL_SECS);
+    let ips = records.iter().filter_map(|r| r["data"].as_str()?.parse().ok()).collect();
+    (ips, ttl)
+}
+
+fn query(name: &str, record_type: &str) -> Result<serde_json::Value, XmrError> {
+    let url = format!("{}?name={}&type={}", http::network().doh_url, name, record_type);
+    http::direct_agent(DOH_TIMEOUT)
+        .get(&url)
+        .header("accept", "application/dns-json")
+        .call()
+        .map_err(|e| XmrError::NetworkError(format!("DoH lookup of {}: {}", name, e)))?
+        .body_mut()
+        .read_json()
+        .map_err(|e| XmrError::NetworkError(format!("Invalid DoH answer for {}: {}", name, e)))
+}
+
+// Look a hostname up over DNS-over-HTTPS, IPv4 first
+pub fn resolve_doh(name: &str) -> Result<Vec<IpAddr>, XmrError> {
+    if let Ok(ip) = name.trim_matches(['[', ']']).parse::<IpAddr>() {
+        return Ok(vec![ip]);
+    }
+
+    if let Ok(cache) = CACHE.lock()
+        && let Some((ips, expires)) = cache.as_ref().and_then(|c| c.get(name))
+        && *expires > Instant::now()
+    {
+        return Ok(ips.clone());
+    }
+
+    let (mut ips, mut ttl) = parse_answer(&query(name, "A")?, TYPE_A);
+    if ips.is_empty() {
+        (ips, ttl) = parse_answer(&query(name, "AAAA")?, TYPE_AAAA);
+    }
+    if ips.is_empty() {
+        return Err(XmrError::NetworkError(format!("DoH found no address for {}", name)));
+    }
+    debug!("DoH resolved {} to {:?}", name, ips);
+
+    if let Ok(mut cache) = CACHE.lock() {
+        let expires = Instant::now() + Duration::from_secs(ttl.max(MIN_TTL_SECS));
+        cache.get_or_insert_with(HashMap::new).insert(name.to_string(), (ips.clone(), expires));
+    }
+    Ok(ips)
+}
+
+// Socket addresses for "host:port", through DoH when enabled
+pub fn socket_addrs(address: &str) -> Result<Vec<SocketAddr>, XmrError> {
+    if !http::network().doh {
+        let addrs = address
+            .to_socket_addrs()
+            .map_err(|e| XmrError::NetworkError(format!("{}: {}", address, e)))?;
+        return Ok(addrs.collect());
+    }
+
+    let (host, port) = address
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| XmrError::NetworkError(format!("{}: missing port", address)))?;
+    Ok(resolve_doh(host)?.into_iter().map(|ip| SocketAddr::new(ip, port)).collect())
+}
+
+// "stratum+tcp://host:port" with the host replaced by its DoH address.
+// Falls back to the name so the miner can still try its own resolver.
+fn resolve_endpoint(endpoint: &str) -> String {
+    let (scheme, address) = match endpoint.split_once("://") {
+        Some((scheme, address)) => (format!("{}://", scheme), address),
+        None => (String::new(), endpoint),
+    };
+    let Some((host, port)) = address.rsplit_once(':') else {
+        return endpoint.to_string();
+    };
+
+    match resolve_doh(host) {
+        Ok(ips) => match ips[0] {
+            IpAddr::V4(ip) => format!("{}{}:{}", scheme, ip, port),
+            IpAddr::V6(ip) => format!("{}[{}]:{}", scheme, ip, port),
+        },
+        Err(e) => {
+            warn!("{}", e);
+            endpoint.to_string()
+        },
+    }
+}
+
+// The miner resolves hostnames itself; with DoH on it gets IPs instead
+pub fn resolve_target(target: &Target) -> Target {
+    if !http::network().doh {
+        return target.clone();
+    }
+
+    Target {
+        pool: resolve_endpoint(&target.pool),
+        backups: target.backups.iter().map(|b| resolve_endpoint(b)).collect(),
+        ..target.clone()
+    }
+}
+
+// ureq resolver that sends every lookup through DoH
+#[derive(Debug)]
+pub struct DohResolver;
+
+impl Resolver for DohResolver {
+    fn resolve(
+        &self,
+        uri: &ureq::http::Uri,
+        _config: &ureq::config::Config,
+        _timeout: NextTimeout,
+    ) -> Result<ResolvedSocketAddrs, ureq::Error> {
+        let authority = uri.authority().ok_or_else(|| ureq::Error::BadUri(uri.to_string()))?;
+        let port = authority
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("https") { 443 } else { 80 });
+
+        let ips = resolve_doh(authority.host()).map_err(|e| {
+            warn!("{}", e);
+            ureq::Error::HostNotFound
+        })?;
+
+        let mut addrs = self.empty();
+        for ip in ips.into_iter().take(16) {
+            addrs.push(SocketAddr::new(ip, port));
+        }
+        Ok(addrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_answer() {
+        let answer = json!({
+            "Status": 0,
+            "Answer": [
+                { "name": "pool.example.com", "type": 5, "TTL": 30, "data": "edge.example.net." },
+                { "name": "edge.example.net", "type": 1, "TTL": 300, "data": "203.0.113.7" },
+                { "name": "edge.example.net", "type": 1, "TTL": 120, "data": "203.0.113.8" },
+            ]
+        });
+        let (ips, ttl) = parse_answer(&answer, TYPE_A);
+        assert_eq!(ips, vec!["203.0.113.7".parse::<IpAddr>().unwrap(), "203.0.113.8".parse().unwrap()]);
+        assert_eq!(ttl, 120);
+    }
+}
@@ -0,0 +1,363 @@
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::http;
+use crate::retry;
+use crate::selfmetrics;
+use crate::run::XmrError;
+
+// Release archives are tens of megabytes; allow slow links to finish
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+// How often the progress bar is redrawn
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const PROGRESS_WIDTH: usize = 30;
+
+// Set by `--quiet`: no progress bar, for scripts
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// "12.3 MB" style sizes
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+// One line of progress: bar and percentage when the size is known, bytes,
+// speed and time left
+fn progress_line(done: u64, total: Option<u64>, elapsed: Duration) -> String {
+    let rate = done as f64 / elapsed.as_secs_f64().max(0.001);
+    let speed = format!("{}/s", human_bytes(rate as u64));
+    match total.filter(|&t| t > 0) {
+        Some(total) => {
+            let fraction = (done as f64 / total as f64).min(1.0);
+            let filled = (fraction * PROGRESS_WIDTH as f64) as usize;
+            let eta = if rate > 0.0 { (total.saturating_sub(done) as f64 / rate) as u64 } else { 0 };
+            format!(
+                "[{}{}] {:>3}%  {} / {}  {}  ETA {}:{:02}",
+                "#".repeat(filled),
+                "-".repeat(PROGRESS_WIDTH - filled),
+                (fraction * 100.0) as u32,
+                human_bytes(done),
+                human_bytes(total),
+                speed,
+                eta / 60,
+                eta % 60
+            )
+        },
+        None => format!("{}  {}", human_bytes(done), speed),
+    }
+}
+
+// Wraps a download body and draws a progress bar on stderr as it is read
+struct Progress<R> {
+    inner: R,
+    total: Option<u64>,
+    done: u64,
+    started: Instant,
+    drawn: Option<Instant>,
+}
+
+impl<R: Read> Progress<R> {
+    fn new(inner: R, total: Option<u64>) -> Self {
+        Progress { inner, total, done: 0, started: Instant::now(), drawn: None }
+    }
+
+    fn draw(&mut self) {
+        let line = progress_line(self.done, self.total, self.started.elapsed());
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}\x1b[K", line);
+        let _ = stderr.flush();
+        self.drawn = Some(Instant::now());
+    }
+
+    fn finish(&mut self) {
+        if self.drawn.is_some() {
+            self.draw();
+            eprintln!();
+        }
+    }
+}
+
+impl<R: Read> Read for Progress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.done += n as u64;
+        if self.drawn.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL) {
+            self.draw();
+        }
+        Ok(n)
+    }
+}
+
+// A published release: tag plus (file name, download URL) for each asset
+pub struct Release {
+    pub tag: String,
+    pub assets: Vec<(String, String)>,
+}
+
+impl Release {
+    pub fn asset(&self, name: &str) -> Option<&str> {
+        self.assets.iter().find(|(n, _)| n == name).map(|(_, url)| url.as_str())
+    }
+}
+
+// Latest release of a GitHub repository such as "SChernykh/p2pool"
+pub fn latest_github_release(repo: &str) -> Result<Release, XmrError> {
+    release_from(repo, &format!("https://api.github.com/repos/{}/releases/latest", repo))
+}
+
+// The release published under `tag`
+pub fn github_release(repo: &str, tag: &str) -> Result<Release, XmrError> {
+    release_from(repo, &format!("https://api.github.com/repos/{}/releases/tags/{}", repo, tag))
+}
+
+fn release_from(repo: &str, url: &str) -> Result<Release, XmrError> {
+    let release = http::get_json(url)?;
+
+    let tag = release["tag_name"]
+        .as_str()
+        .ok_or_else(|| XmrError::NetworkError(format!("No release tag for {}", repo)))?
+        .to_string();
+    let assets = release["assets"]
+        .as_array()
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|a| Some((a["name"].as_str()?.to_string(), a["browser_download_url"].as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Release { tag, assets })
+}
+
+// Stream a URL into `dest`, returning the number of bytes written. A
+// download cut off midway starts over on the next attempt.
+pub fn fetch(url: &str, dest: &Path) -> Result<u64, XmrError> {
+    info!("Downloading {}", url);
+    let started = std::time::Instant::now();
+    let result = retry::run(url, || {
+        let mut response = http::agent(DOWNLOAD_TIMEOUT)
+            .get(url)
+            .call()
+            .map_err(|e| XmrError::NetworkError(format!("GET {}: {}", url, e)))?;
+
+        let total = response
+            .headers()
+            .get("content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let mut file = File::create(dest)?;
+        let mut body = response.body_mut().as_reader();
+        let copied = if quiet() || !io::stderr().is_terminal() {
+            io::copy(&mut body, &mut file)
+        } else {
+            let mut progress = Progress::new(body, total);
+            let copied = io::copy(&mut progress, &mut file);
+            progress.finish();
+            copied
+        };
+        let written = copied.map_err(|e| XmrError::NetworkError(format!("Reading {}: {}", url, e)))?;
+        debug!("Wrote {} bytes to {}", written, dest.display());
+        Ok(written)
+    });
+    selfmetrics::record("download", started.elapsed());
+    result
+}
+
+// Like `fetch_text`, but None when the server has no such file, e.g. a
+// checksum or signature a release does not publish
+pub fn fetch_published(url: &str) -> Result<Option<String>, XmrError> {
+    retry::run(url, || {
+        let mut response = match http::agent(DOWNLOAD_TIMEOUT).get(url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::StatusCode(404)) => return Ok(None),
+            Err(e) => return Err(XmrError::NetworkError(format!("GET {}: {}", url, e))),
+        };
+        response
+            .body_mut()
+            .read_to_string()
+            .map(Some)
+            .map_err(|e| XmrError::NetworkError(format!("Reading {}: {}", url, e)))
+    })
+}
+
+pub fn fetch_text(url: &str) -> Result<String, XmrError> {
+    retry::run(url, || {
+        http::agent(DOWNLOAD_TIMEOUT)
+            .get(url)
+            .call()
+            .map_err(|e| XmrError::NetworkError(format!("GET {}: {}", url, e)))?
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| XmrError::NetworkError(format!("Reading {}: {}", url, e)))
+    })
+}
+
+// Lowercase hex SHA-256 of a file
+pub fn sha256_file(path: &Path) -> Result<String, XmrError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn is_sha256(token: &str) -> bool {
+    token.len() == 64 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Checksum published for `file_name` in a checksum listing. Handles the
+// `<hash>  <name>` sha256sum layout, `<name>, <hash>` lines and `Name:` /
+// `SHA256:` blocks.
+pub fn checksum_for(listing: &str, file_name: &str) -> Option<String> {
+    let lines: Vec<&str> = listing.lines().collect();
+    let position = lines
+        .iter()
+        .position(|line| line.split_whitespace().any(|t| t.trim_start_matches('*').trim_end_matches(',') == file_name))?;
+
+    lines[position..lines.len().min(position + 4)]
+        .iter()
+        .flat_map(|line| line.split_whitespace())
+        .find(|token| is_sha256(token))
+        .map(|hash| hash.to_lowercase())
+}
+
+// Compare a downloaded file against the expected checksum
+pub fn verify(path: &Path, expected: &str) -> Result<(), XmrError> {
+    let actual = sha256_file(path)?;
+    if actual != expected.to_lowercase() {
+        let _ = fs::remove_file(path);
+        return Err(XmrError::ChecksumError(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            path.display(),
+            expected,
+            actual
+        )));
+    }
+    info!("Verified SHA-256 of {}", path.display());
+    Ok(())
+}
+
+// Check a minisign signature over a downloaded file against a base64
+// public key ("RW...")
+pub fn verify_signature(path: &Path, signature: &str, public_key: &str) -> Result<(), XmrError> {
+    let key = minisign_verify::PublicKey::from_base64(public_key)
+        .map_err(|e| XmrError::SignatureError(format!("Invalid public key: {}", e)))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| XmrError::SignatureError(format!("Invalid signature file: {}", e)))?;
+    let contents = fs::read(path)?;
+    if let Err(e) = key.verify(&contents, &signature, false) {
+        let _ = fs::remove_file(path);
+        return Err(XmrError::SignatureError(format!("Bad signature on {}: {}", path.display(), e)));
+    }
+    info!("Verified signature of {} ({})", path.display(), signature.trusted_comment());
+    Ok(())
+}
+
+// An executable called `name` at the top of `dir` or one directory below,
+// which is where release archives put it
+pub fn find_binary(dir: &Path, name: &str) -> Option<PathBuf> {
+    let direct = dir.join(name);
+    if direct.is_file() {
+        return Some(direct);
+    }
+
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join(name))
+        .find(|path| path.is_file())
+}
+
+// Unpack a .tar.gz / .tar.bz2 archive into `dest` with the system tar
+pub fn unpack(archive: &Path, dest: &Path) -> Result<(), XmrError> {
+    fs::create_dir_all(dest)?;
+    let status = selfmetrics::time("extract", || Command::new("tar").arg("-xf").arg(archive).arg("-C").arg(dest).status())
+        .map_err(|e| XmrError::ExecutionError(format!("Failed to execute tar: {}", e)))?;
+
+    if !status.success() {
+        return Err(XmrError::ExecutionError(format!("tar failed with exit code: {}", status)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_line() {
+        let line = progress_line(5 * 1024 * 1024, Some(10 * 1024 * 1024), Duration::from_secs(5));
+        assert_eq!(line, format!("[{}{}]  50%  5.0 MB / 10.0 MB  1.0 MB/s  ETA 0:05", "#".repeat(15), "-".repeat(15)));
+        assert_eq!(progress_line(512, None, Duration::from_secs(1)), "512 B  512 B/s");
+    }
+
+    const HASH: &str = "9d6b0a7b7e3c1f0e0a9e52b2d8fb2a3c4e6f1a2b3c4d5e6f7a8b9c0d1e2f3a4b";
+
+    #[test]
+    fn test_checksum_sha256sum_layout() {
+        let listing = format!("{}  p2pool-v4.9-linux-x64.tar.gz\n{}  other.zip\n", HASH, "0".repeat(64));
+        assert_eq!(checksum_for(&listing, "p2pool-v4.9-linux-x64.tar.gz").as_deref(), Some(HASH));
+    }
+
+    #[test]
+    fn test_checksum_name_blocks() {
+        let listing = format!(
+            "Name: a.tar.gz\nSize: 1 bytes\nSHA256: {}\n\nName: b.tar.gz\nSize: 2 bytes\nSHA256: {}\n",
+            "1".repeat(64),
+            HASH.to_uppercase()
+        );
+        assert_eq!(checksum_for(&listing, "b.tar.gz").as_deref(), Some(HASH));
+        assert_eq!(checksum_for(&listing, "c.tar.gz"), None);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let public_key = "RWSdExteMDNQolG3ijJVTfu4hZ4GFOBj91lbS80iHmK4CYmrylsO+jFS";
+        let signature = "untrusted comment: signature from minisign secret key\n\
+                         RUSdExteMDNQom25+alyTjXK4G4QmAIHWUr8QbQz/L82stXdD3MYaOr56tO+AETheHibRTIyTF/7JZFPN/1ZhXMN3GOnJQQMPg8=\n\
+                         trusted comment: timestamp:1760000000\tfile:xmr.zip\n\
+                         npJDRAcPlBiqXnR6nrAHJvbe3TsURgjQpEfdnaDbrGdjanc9qtQ+n3+LiXRBUBQUyIHdG2lTZD6BOcSwSq44Dg==\n";
+        let path = std::env::temp_dir().join(format!("minning-signature-{}", std::process::id()));
+        fs::write(&path, "minning test archive\n").unwrap();
+        assert!(verify_signature(&path, signature, public_key).is_ok());
+
+        fs::write(&path, "minning tampered archive\n").unwrap();
+        assert!(matches!(verify_signature(&path, signature, public_key), Err(XmrError::SignatureError(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_checksum_comma_layout() {
+        let listing = format!("monero-linux-x64-v0.18.3.4.tar.bz2, {}\n", HASH);
+        assert_eq!(checksum_for(&listing, "monero-linux-x64-v0.18.3.4.tar.bz2").as_deref(), Some(HASH));
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, Config};
+use crate::market;
+use crate::payouts;
+use crate::pool_api;
+use crate::run::XmrError;
+
+pub const HISTORY_FILE: &str = "earnings.jsonl";
+
+// One snapshot of what the pool has credited to the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: i64,
+    pub unpaid: f64,
+    pub total_paid: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn parse(name: &str) -> Result<Period, XmrError> {
+        match name {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            other => Err(XmrError::ConfigError(format!(
+                "Unknown period '{}' (expected day, week or month)",
+                other
+            ))),
+        }
+    }
+
+    // Bucket label in local time, sortable as a string
+    fn label(&self, timestamp: i64) -> String {
+        let time = Local.timestamp_opt(timestamp, 0).single().unwrap_or_else(Local::now);
+        match self {
+            Period::Day => time.format("%Y-%m-%d").to_string(),
+            Period::Week => time.format("%G-W%V").to_string(),
+            Period::Month => time.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+fn history_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(HISTORY_FILE))
+}
+
+pub fn load_history() -> Result<Vec<Sample>, XmrError> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    // Skip torn lines (e.g. from a power loss mid-append) instead of failing
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append(sample: &Sample) -> Result<(), XmrError> {
+    let path = history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let line = serde_json::to_string(sample)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode earnings sample: {}", e)))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+// Ask the pool for the current figures and append them to the history
+pub fn record_sample(config: &Config) -> Result<Sample, XmrError> {
+    let wallet = config.wallet.address.as_deref().ok_or_else(|| {
+        XmrError::ConfigError("No wallet configured; set [wallet] address in the config file".to_string())
+    })?;
+    let stats = pool_api::adapter(&config.pool.api)?.wallet_stats(wallet)?;
+
+    let sample = Sample {
+        timestamp: Utc::now().timestamp(),
+        unpaid: stats.unpaid,
+        total_paid: stats.total_paid,
+    };
+    append(&sample)?;
+    Ok(sample)
+}
+
+// Background recorder for the supervised run modes
+pub fn start_recorder(config: &Config, running: Arc<AtomicBool>) {
+    // Solo blocks are not credited by any pool API
+    if !config.earnings.enabled || config.wallet.address.is_none() || config.solo.enabled {
+        return;
+    }
+
+    let config = config.clone();
+    let interval = Duration::from_secs(config.earnings.interval_mins.max(1) * 60);
+    info!("Recording pool earnings every {} minutes", config.earnings.interval_mins.max(1));
+
+    thread::spawn(move || {
+        let mut last = load_history().ok().and_then(|history| history.last().cloned());
+        while running.load(Ordering::SeqCst) {
+            match record_sample(&config) {
+                Ok(sample) => {
+                    debug!("Recorded earnings sample: {:.12} XMR unpaid", sample.unpaid);
+                    payouts::check(&config, last.as_ref(), &sample);
+                    last = Some(sample);
+                },
+                Err(e) => warn!("Could not record earnings sample: {}", e),
+            }
+
+            // Sleep in short steps so Ctrl+C is not held up by a long interval
+            let mut waited = Duration::ZERO;
+            while waited < interval && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+// XMR credited between two consecutive samples. Without a paid total from
+// the pool, a drop in `unpaid` means a payout happened in between and only
+// what accrued since then is counted.
+fn credited_between(prev: &Sample, next: &Sample) -> f64 {
+    match (prev.total_paid, next.total_paid) {
+        (Some(prev_paid), Some(next_paid)) => ((next.unpaid + next_paid) - (prev.unpaid + prev_paid)).max(0.0),
+        _ if next.unpaid >= prev.unpaid => next.unpaid - prev.unpaid,
+        _ => next.unpaid,
+    }
+}
+
+pub fn earned_by_period(history: &[Sample], period: Period) -> BTreeMap<String, f64> {
+    let mut buckets = BTreeMap::new();
+    for pair in history.windows(2) {
+        *buckets.entry(period.label(pair[1].timestamp)).or_insert(0.0) += credited_between(&pair[0], &pair[1]);
+    }
+    buckets
+}
+
+// `earnings`: XMR earned per day/week/month from the local history
+pub fn report(config: &Config, period: Period, fiat: Option<&str>) -> Result<(), XmrError> {
+    if let Err(e) = record_sample(config) {
+        eprintln!("Warning: could not record a fresh sample: {}", e);
+    }
+
+    let history = load_history()?;
+    if history.len() < 2 {
+        println!("Not enough history yet; earnings are recorded while run-resilient is active.");
+        return Ok(());
+    }
+
+    let currency = fiat.or(config.market.fiat.as_deref());
+    let price = match currency {
+        Some(currency) => match market::xmr_price(&config.market, currency) {
+            Ok(price) => Some(price),
+            Err(e) => {
+                eprintln!("Warning: could not fetch XMR price: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let buckets = earned_by_period(&history, period);
+    let total: f64 = buckets.values().sum();
+
+    match (price, currency) {
+        (Some(_), Some(currency)) => println!("{:<10}  {:>16}  {:>12}", "Period", "Earned XMR", currency.to_uppercase()),
+        _ => println!("{:<10}  {:>16}", "Period", "Earned XMR"),
+    }
+    for (label, earned) in buckets.iter().chain([(&"Total".to_string(), &total)]) {
+        match price {
+            Some(price) => println!("{:<10}  {:>16.12}  {:>12.2}", label, earned, earned * price),
+            None => println!("{:<10}  {:>16.12}", label, earned),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: i64, unpaid: f64, total_paid: Option<f64>) -> Sample {
+        Sample { timestamp, unpaid, total_paid }
+    }
+
+    #[test]
+    fn test_credited_with_paid_total() {
+        // A payout moves 0.3 XMR from unpaid to paid; only 0.05 is new
+        let prev = sample(0, 0.30, Some(1.0));
+        let next = sample(3600, 0.05, Some(1.3));
+        assert!((credited_between(&prev, &next) - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_credited_without_paid_total() {
+        assert!((credited_between(&sample(0, 0.1, None), &sample(1, 0.15, None)) - 0.05).abs() < 1e-12);
+        assert!((credited_between(&sample(0, 0.3, None), &sample(1, 0.02, None)) - 0.02).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,123 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::json;
+
+// Lifecycle events raised by the supervisor. Sinks (desktop notifications and
+// friends) subscribe once at startup and receive every event in order.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ChildStarted { pid: u32 },
+    // `stopped` is set when the supervisor stopped the child itself (a
+    // pause, restart or failed health check) rather than it exiting
+    ChildExited { pid: u32, code: Option<i32>, stopped: bool },
+    // A restart is pending and will happen after `delay_secs`
+    Restarting { consecutive_failures: usize, delay_secs: u64 },
+    HealthCheckFailed { pid: u32, detail: String },
+    Alert { message: String },
+    PayoutReceived { amount: f64, tx_hash: Option<String> },
+    PriceAlert { price: f64, currency: String, threshold: f64, direction: String },
+    // The supervisor gave up on a pool and moved the miner to the next one
+    PoolFailover { from: String, to: String, reason: String },
+    // Mining was stopped / restarted without tearing down the supervisor
+    Paused { reason: String },
+    Resumed { reason: String },
+    Shutdown,
+    // `init` / `update` finished; `changed` is false when the release was
+    // already in place, `hook_exit_code` is set when a post-install hook ran
+    InstallCompleted {
+        command: String,
+        tag: Option<String>,
+        xmr_dir: String,
+        binary: String,
+        archive_sha256: Option<String>,
+        binary_sha256: Option<String>,
+        changed: bool,
+        hook_exit_code: Option<i32>,
+    },
+    InstallFailed { command: String, error: String },
+}
+
+impl Event {
+    // Stable snake_case identifier used in structured log fields
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::ChildStarted { .. } => "child_started",
+            Event::ChildExited { .. } => "child_exited",
+            Event::Restarting { .. } => "restarting",
+            Event::HealthCheckFailed { .. } => "health_check_failed",
+            Event::Alert { .. } => "alert",
+            Event::PayoutReceived { .. } => "payout_received",
+            Event::PriceAlert { .. } => "price_alert",
+            Event::PoolFailover { .. } => "pool_failover",
+            Event::Paused { .. } => "paused",
+            Event::Resumed { .. } => "resumed",
+            Event::Shutdown => "shutdown",
+            Event::InstallCompleted { .. } => "install_completed",
+            Event::InstallFailed { .. } => "install_failed",
+        }
+    }
+
+    // True for events the user should hear about even when not watching logs
+    pub fn is_failure(&self) -> bool {
+        match self {
+            Event::ChildExited { code, stopped, .. } => !stopped && *code != Some(0),
+            Event::HealthCheckFailed { .. } | Event::Alert { .. } | Event::PoolFailover { .. } | Event::InstallFailed { .. } => {
+                true
+            },
+            Event::InstallCompleted { hook_exit_code, .. } => hook_exit_code.is_some_and(|code| code != 0),
+            _ => false,
+        }
+    }
+}
+
+pub trait Sink: Send {
+    fn handle(&self, event: &Event);
+}
+
+static SINKS: Mutex<Vec<Box<dyn Sink>>> = Mutex::new(Vec::new());
+
+pub fn subscribe(sink: Box<dyn Sink>) {
+    if let Ok(mut sinks) = SINKS.lock() {
+        sinks.push(sink);
+    }
+}
+
+pub fn emit(event: Event) {
+    if let Ok(sinks) = SINKS.lock() {
+        for sink in sinks.iter() {
+            sink.handle(&event);
+        }
+    }
+}
+
+// An event as a flat JSON object with a timestamp, e.g.
+// {"timestamp":1700000000,"event":"child_started","pid":42}
+pub fn record(event: &Event) -> serde_json::Value {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut record = json!({ "timestamp": timestamp });
+    if let (Some(record), Ok(serde_json::Value::Object(fields))) =
+        (record.as_object_mut(), serde_json::to_value(event))
+    {
+        record.extend(fields);
+    }
+    record
+}
+
+// `--events-json`: one JSON object per line on stdout for orchestration tools
+pub struct JsonLinesSink;
+
+impl Sink for JsonLinesSink {
+    fn handle(&self, event: &Event) {
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", record(event));
+        let _ = stdout.flush();
+    }
+}
@@ -0,0 +1,125 @@
+use serde_json::{json, Value};
+
+use crate::metrics;
+
+// Label filter every panel query carries, driven by the dashboard variables
+const FILTER: &str = "{host=~\"$host\",instance_name=~\"$instance_name\"}";
+
+fn panel(id: u64, title: &str, unit: &str, targets: &[(String, &str)], x: u64, y: u64) -> Value {
+    json!({
+        "id": id,
+        "type": "timeseries",
+        "title": title,
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "gridPos": { "h": 8, "w": 12, "x": x, "y": y },
+        "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+        "targets": targets
+            .iter()
+            .enumerate()
+            .map(|(i, (expr, legend))| json!({
+                "refId": ((b'A' + i as u8) as char).to_string(),
+                "expr": expr,
+                "legendFormat": legend,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn variable(name: &str, label: &str) -> Value {
+    json!({
+        "name": name,
+        "label": label,
+        "type": "query",
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "query": format!("label_values({}, {})", metrics::PAUSED, name),
+        "refresh": 2,
+        "includeAll": true,
+        "multi": true,
+        "current": { "text": "All", "value": "$__all" },
+    })
+}
+
+// Dashboard for the metrics served by the [metrics] exporter
+pub fn dashboard() -> Value {
+    let legend = "{{host}} {{instance_name}}";
+    let panels = vec![
+        panel(1, "Hashrate", "H/s", &[(format!("{}{}", metrics::HASHRATE, FILTER), legend)], 0, 0),
+        panel(
+            2,
+            "Shares per minute",
+            "short",
+            &[
+                (format!("rate({}{}[5m]) * 60", metrics::SHARES_ACCEPTED, FILTER), "accepted {{host}} {{instance_name}}"),
+                (format!("rate({}{}[5m]) * 60", metrics::SHARES_REJECTED, FILTER), "rejected {{host}} {{instance_name}}"),
+            ],
+            12,
+            0,
+        ),
+        panel(3, "CPU temperature", "celsius", &[(format!("{}{}", metrics::TEMPERATURE, FILTER), legend)], 0, 8),
+        panel(
+            4,
+            "Miner restarts (per hour)",
+            "short",
+            &[(format!("increase({}{}[1h])", metrics::RESTARTS, FILTER), legend)],
+            12,
+            8,
+        ),
+        panel(5, "Paused", "bool_on_off", &[(format!("{}{}", metrics::PAUSED, FILTER), legend)], 0, 16),
+        panel(
+            6,
+            "Miner traffic",
+            "Bps",
+            &[
+                (format!("rate({}{}[5m])", metrics::TRAFFIC_SENT, FILTER), "up {{host}} {{instance_name}}"),
+                (format!("rate({}{}[5m])", metrics::TRAFFIC_RECEIVED, FILTER), "down {{host}} {{instance_name}}"),
+            ],
+            12,
+            16,
+        ),
+        panel(
+            7,
+            "Launcher CPU",
+            "percentunit",
+            &[(format!("rate({}{}[5m])", metrics::LAUNCHER_CPU, FILTER), legend)],
+            0,
+            24,
+        ),
+        panel(8, "Launcher memory", "bytes", &[(format!("{}{}", metrics::LAUNCHER_RSS, FILTER), legend)], 12, 24),
+    ];
+
+    json!({
+        "title": "Minning",
+        "uid": "minning",
+        "tags": ["minning", "monero"],
+        "timezone": "browser",
+        "schemaVersion": 39,
+        "refresh": "30s",
+        "time": { "from": "now-24h", "to": "now" },
+        "templating": {
+            "list": [
+                {
+                    "name": "datasource",
+                    "label": "Prometheus",
+                    "type": "datasource",
+                    "query": "prometheus",
+                },
+                variable("host", "Host"),
+                variable("instance_name", "Instance"),
+            ],
+        },
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panels_use_exported_metrics() {
+        let dashboard = dashboard();
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert!(panels.iter().all(|p| p["targets"][0]["expr"].as_str().unwrap().contains("minning_")));
+        assert_eq!(panels[1]["targets"][1]["refId"], "B");
+    }
+}
@@ -0,0 +1,196 @@
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::control;
+use crate::stats;
+use crate::telemetry;
+
+// Messages of proto/minning.proto
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Empty {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchRequest {
+    #[prost(uint32, tag = "1")]
+    pub interval_secs: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Reply {
+    #[prost(bool, tag = "1")]
+    pub ok: bool,
+    #[prost(string, tag = "2")]
+    pub error: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SupervisorState {
+    #[prost(string, tag = "1")]
+    pub instance: String,
+    #[prost(uint32, tag = "2")]
+    pub supervisor_pid: u32,
+    #[prost(string, repeated, tag = "3")]
+    pub paused: Vec<String>,
+    #[prost(bool, tag = "4")]
+    pub miner_running: bool,
+    #[prost(double, optional, tag = "5")]
+    pub hashrate: Option<f64>,
+    #[prost(uint64, tag = "6")]
+    pub miner_uptime_secs: u64,
+    #[prost(string, optional, tag = "7")]
+    pub pool: Option<String>,
+    #[prost(uint64, tag = "8")]
+    pub accepted: u64,
+    #[prost(uint64, tag = "9")]
+    pub rejected: u64,
+    #[prost(uint64, tag = "10")]
+    pub restarts: u64,
+    #[prost(double, optional, tag = "11")]
+    pub temperature: Option<f64>,
+}
+
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/minning.Supervisor.rs"));
+}
+
+use generated::supervisor_server::{Supervisor, SupervisorServer};
+
+const DEFAULT_WATCH_SECS: u64 = 5;
+
+struct Service {
+    config: Config,
+    running: Arc<AtomicBool>,
+}
+
+impl Service {
+    fn request(&self, request: control::Request) -> Value {
+        control::handle(request, &self.config, &self.running)
+    }
+
+    fn state(&self) -> SupervisorState {
+        let reply = self.request(control::Request::Status);
+        let miner = &reply["miner"];
+        let hashrate = &miner["hashrate"];
+        SupervisorState {
+            instance: reply["instance"].as_str().unwrap_or_default().to_string(),
+            supervisor_pid: std::process::id(),
+            paused: reply["paused"]
+                .as_array()
+                .map(|reasons| reasons.iter().filter_map(|r| r.as_str().map(str::to_string)).collect())
+                .unwrap_or_default(),
+            miner_running: !miner.is_null(),
+            hashrate: hashrate[0].as_f64().or(hashrate[1].as_f64()),
+            miner_uptime_secs: miner["uptime_secs"].as_u64().unwrap_or_default(),
+            pool: miner["pool"].as_str().map(str::to_string),
+            accepted: miner["accepted"].as_u64().unwrap_or_default(),
+            rejected: miner["rejected"].as_u64().unwrap_or_default(),
+            restarts: stats::session_restarts(),
+            temperature: telemetry::cpu_temperature(),
+        }
+    }
+
+    fn reply(&self, request: control::Request) -> Response<Reply> {
+        let reply = self.request(request);
+        Response::new(Reply {
+            ok: reply["ok"].as_bool().unwrap_or(false),
+            error: reply["error"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Supervisor for Arc<Service> {
+    async fn get_state(&self, _: Request<Empty>) -> Result<Response<SupervisorState>, Status> {
+        let service = self.clone();
+        let state = tokio::task::spawn_blocking(move || service.state())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        Ok(Response::new(state))
+    }
+
+    async fn pause(&self, _: Request<Empty>) -> Result<Response<Reply>, Status> {
+        Ok(self.reply(control::Request::Pause))
+    }
+
+    async fn resume(&self, _: Request<Empty>) -> Result<Response<Reply>, Status> {
+        Ok(self.reply(control::Request::Resume))
+    }
+
+    async fn restart(&self, _: Request<Empty>) -> Result<Response<Reply>, Status> {
+        Ok(self.reply(control::Request::Restart))
+    }
+
+    async fn stop(&self, _: Request<Empty>) -> Result<Response<Reply>, Status> {
+        Ok(self.reply(control::Request::Stop))
+    }
+
+    type WatchStateStream = Pin<Box<dyn Stream<Item = Result<SupervisorState, Status>> + Send>>;
+
+    async fn watch_state(&self, request: Request<WatchRequest>) -> Result<Response<Self::WatchStateStream>, Status> {
+        let interval = match request.into_inner().interval_secs {
+            0 => DEFAULT_WATCH_SECS,
+            secs => secs as u64,
+        };
+        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        let service = self.clone();
+        // The state is gathered with blocking HTTP calls, so off the runtime
+        thread::spawn(move || {
+            while service.running.load(Ordering::SeqCst) {
+                if sender.blocking_send(Ok(service.state())).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(interval));
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+// Supervised modes: serve the gRPC API on [grpc] listen, on its own runtime
+pub fn start(config: &Config, running: Arc<AtomicBool>) {
+    if !config.grpc.enabled {
+        return;
+    }
+    let address = match config.grpc.listen.parse() {
+        Ok(address) => address,
+        Err(e) => {
+            warn!("Invalid [grpc] listen address {}: {}", config.grpc.listen, e);
+            return;
+        },
+    };
+    let token = config.grpc.token.clone().map(|token| format!("Bearer {}", token));
+    let service = Arc::new(Service { config: config.clone(), running });
+
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                warn!("Could not start the gRPC runtime: {}", e);
+                return;
+            },
+        };
+        info!("Serving the gRPC API on {}", address);
+        let authorize = move |request: Request<()>| match &token {
+            Some(token) if request.metadata().get("authorization").and_then(|v| v.to_str().ok()) != Some(token) => {
+                Err(Status::unauthenticated("missing or wrong bearer token"))
+            },
+            _ => Ok(request),
+        };
+        let server = tonic::transport::Server::builder()
+            .add_service(SupervisorServer::with_interceptor(service, authorize))
+            .serve(address);
+        if let Err(e) = runtime.block_on(server) {
+            warn!("gRPC API stopped: {}", e);
+        }
+    });
+}
@@ -0,0 +1,348 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::{Config, HealthConfig, HealthStrategy};
+use crate::pidfd::ProcessHandle;
+use crate::retry;
+use crate::suspend;
+use crate::telemetry;
+
+// Missed API polls in a row before the miner counts as hung
+const API_MISSES: u32 = 3;
+// How long a miner has to stay up before its start counts as a recovery
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+// Unix time of the most recent line of output from one watchdog's miner,
+// shared between its output readers and its Monitor. Each watchdog has its
+// own, so one NUMA miner's output never covers for another's silence.
+#[derive(Debug, Clone, Default)]
+pub struct OutputClock(Arc<AtomicU64>);
+
+impl OutputClock {
+    // Called for every line the miner prints
+    pub fn note(&self) {
+        self.0.store(now_secs(), Ordering::Relaxed);
+    }
+
+    fn silent_secs(&self) -> u64 {
+        now_secs().saturating_sub(self.0.load(Ordering::Relaxed))
+    }
+}
+
+// One way of telling whether a running miner is still doing its job
+pub trait HealthCheck: Send {
+    // Why the miner should be restarted, or None while it looks healthy
+    fn check(&mut self, process: &ProcessHandle) -> Option<String>;
+
+    // Forget what was learned about the previous process
+    fn reset(&mut self) {}
+}
+
+// The process is not stopped, dead or a zombie
+struct ProcessAlive;
+
+impl HealthCheck for ProcessAlive {
+    fn check(&mut self, process: &ProcessHandle) -> Option<String> {
+        // Through the handle, so a recycled PID is never mistaken for the
+        // miner; if the state cannot be read, assume the process is fine
+        match process.state() {
+            Some(code @ ('T' | 't' | 'X' | 'Z')) => Some(format!("process state {}", code)),
+            _ => None,
+        }
+    }
+}
+
+// The miner's HTTP API answers summary requests
+struct ApiSummary {
+    api_url: String,
+    misses: u32,
+}
+
+impl HealthCheck for ApiSummary {
+    fn check(&mut self, _process: &ProcessHandle) -> Option<String> {
+        if telemetry::miner_connection(&self.api_url).is_some() {
+            self.misses = 0;
+            return None;
+        }
+        self.misses += 1;
+        (self.misses >= API_MISSES).then(|| format!("miner API unresponsive for {} checks", self.misses))
+    }
+
+    fn reset(&mut self) {
+        self.misses = 0;
+    }
+}
+
+// The miner keeps printing; xmrig reports its speed every minute
+struct LogActivity {
+    stall: Duration,
+    output: OutputClock,
+}
+
+impl HealthCheck for LogActivity {
+    fn check(&mut self, _process: &ProcessHandle) -> Option<String> {
+        let silent = self.output.silent_secs();
+        (silent > self.stall.as_secs()).then(|| format!("no output for {} seconds", silent))
+    }
+}
+
+// The reported hashrate stays above a floor, or above zero without one;
+// brief dips are tolerated
+struct HashrateThreshold {
+    api_url: String,
+    min: f64,
+    tolerance: Duration,
+    below_since: Option<Instant>,
+}
+
+// A miner whose API does not answer or reports nothing is not hashing
+fn hashing(hashrate: Option<f64>, min: f64) -> bool {
+    hashrate.is_some_and(|hashrate| hashrate > 0.0 && hashrate >= min)
+}
+
+impl HealthCheck for HashrateThreshold {
+    fn check(&mut self, _process: &ProcessHandle) -> Option<String> {
+        let hashrate = telemetry::miner_hashrate(&self.api_url);
+        if hashing(hashrate, self.min) {
+            self.below_since = None;
+            return None;
+        }
+        let since = *self.below_since.get_or_insert_with(Instant::now);
+        (since.elapsed() >= self.tolerance).then(|| match hashrate.filter(|&hashrate| hashrate > 0.0) {
+            Some(hashrate) => format!(
+                "hashrate {} below {} for {} seconds",
+                telemetry::format_hashrate(hashrate),
+                telemetry::format_hashrate(self.min),
+                since.elapsed().as_secs()
+            ),
+            None => format!("alive but not hashing for {} seconds", since.elapsed().as_secs()),
+        })
+    }
+
+    fn reset(&mut self) {
+        self.below_since = None;
+    }
+}
+
+fn from_config(config: &Config, output: &OutputClock) -> Box<dyn HealthCheck> {
+    let health = &config.health;
+    let api_url = config.miner.api_url.clone();
+    match health.check {
+        HealthStrategy::Process => Box::new(ProcessAlive),
+        HealthStrategy::Api => Box::new(ApiSummary { api_url, misses: 0 }),
+        HealthStrategy::Log => Box::new(LogActivity { stall: Duration::from_secs(health.stall_secs), output: output.clone() }),
+        HealthStrategy::Hashrate => Box::new(HashrateThreshold {
+            api_url,
+            min: health.min_hashrate,
+            tolerance: Duration::from_secs(health.stall_secs),
+            below_since: None,
+        }),
+    }
+}
+
+// Delay before restarting a miner: this host's fixed slot in the fleet
+// stagger window (from its host name, so rigs spread out without talking
+// to each other) plus random jitter
+pub fn restart_delay(health: &HealthConfig) -> Duration {
+    let stagger = if health.restart_stagger_secs > 0 {
+        let slot = Sha256::digest(telemetry::hostname().as_bytes());
+        let slot = u64::from_be_bytes(slot[..8].try_into().unwrap_or_default());
+        Duration::from_secs(slot % health.restart_stagger_secs)
+    } else {
+        Duration::ZERO
+    };
+    stagger + retry::jitter(Duration::from_secs(health.restart_jitter_secs))
+}
+
+// Consecutive failures of a watchdog's miner: failed starts and crashes. A
+// start alone does not clear them, or a miner crashing every few seconds
+// would never count past one; a clean exit or a crash after STABLE_UPTIME
+// does.
+#[derive(Debug, Default)]
+pub struct Failures {
+    count: usize,
+    started: Option<Instant>,
+}
+
+impl Failures {
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn started(&mut self, now: Instant) {
+        self.started = Some(now);
+    }
+
+    pub fn start_failed(&mut self) -> usize {
+        self.started = None;
+        self.count += 1;
+        self.count
+    }
+
+    // The miner exited; returns the failures in a row, this one included
+    pub fn exited(&mut self, success: bool, now: Instant) -> usize {
+        let stable = self.started.take().is_some_and(|started| now.duration_since(started) >= STABLE_UPTIME);
+        self.count = match (success, stable) {
+            (true, _) => 0,
+            (false, true) => 1,
+            (false, false) => self.count + 1,
+        };
+        self.count
+    }
+}
+
+// Runs the configured check on a watchdog's child at the configured
+// interval, leaving a freshly started miner alone during its grace period
+pub struct Monitor {
+    check: Box<dyn HealthCheck>,
+    // The child being watched
+    process: Option<ProcessHandle>,
+    output: OutputClock,
+    interval: Duration,
+    grace: Duration,
+    started: Instant,
+    last_check: Instant,
+    restart_on_resume: bool,
+    // suspend::resumes() as of the last poll
+    resumes: u64,
+}
+
+impl Monitor {
+    pub fn new(config: &Config) -> Monitor {
+        let output = OutputClock::default();
+        Monitor {
+            check: from_config(config, &output),
+            process: None,
+            output,
+            interval: Duration::from_secs(config.health.interval_secs.max(1)),
+            grace: Duration::from_secs(config.health.grace_secs),
+            started: Instant::now(),
+            last_check: Instant::now(),
+            restart_on_resume: config.health.restart_on_resume,
+            resumes: suspend::resumes(),
+        }
+    }
+
+    // For the readers of the watched child's output
+    pub fn output(&self) -> OutputClock {
+        self.output.clone()
+    }
+
+    // Watch the newly spawned child `pid`; call before it can be reaped
+    pub fn child_started(&mut self, pid: u32) {
+        self.process = Some(ProcessHandle::open(pid));
+        self.restart_clock();
+    }
+
+    // The child was frozen and runs again: give it a fresh grace period
+    pub fn resumed(&mut self) {
+        self.restart_clock();
+    }
+
+    fn restart_clock(&mut self) {
+        self.started = Instant::now();
+        self.last_check = Instant::now();
+        // A new process gets a clean slate for the output-based check
+        self.output.note();
+        self.check.reset();
+    }
+
+    // Why the child should be restarted, when a check is due and fails. Time
+    // spent suspended is not held against the miner: after a resume it gets
+    // a fresh grace period, or a restart when so configured.
+    pub fn poll(&mut self) -> Option<String> {
+        if self.process.is_none() || suspend::suspended() {
+            return None;
+        }
+        let resumes = suspend::resumes();
+        if resumes != self.resumes {
+            self.resumes = resumes;
+            self.restart_clock();
+            return self.restart_on_resume.then(|| "resumed from suspend".to_string());
+        }
+        if self.started.elapsed() < self.grace || self.last_check.elapsed() < self.interval {
+            return None;
+        }
+        self.last_check = Instant::now();
+        self.check.check(self.process.as_ref()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Event;
+
+    #[test]
+    fn test_hashing() {
+        assert!(hashing(Some(2400.0), 0.0));
+        assert!(hashing(Some(2400.0), 2000.0));
+        assert!(!hashing(Some(1500.0), 2000.0));
+        // Without a floor, a miner stuck at zero or silent still counts
+        assert!(!hashing(Some(0.0), 0.0));
+        assert!(!hashing(None, 0.0));
+    }
+
+    #[test]
+    fn test_log_activity_per_miner() {
+        let process = ProcessHandle::open(std::process::id());
+        let talking = OutputClock::default();
+        let silent = OutputClock(Arc::new(AtomicU64::new(now_secs() - 120)));
+        let stall = Duration::from_secs(60);
+        let mut checks = [
+            LogActivity { stall, output: talking.clone() },
+            LogActivity { stall, output: silent.clone() },
+        ];
+        // The first miner printing says nothing about the second
+        talking.note();
+        assert_eq!(checks[0].check(&process), None);
+        assert!(checks[1].check(&process).is_some());
+        silent.note();
+        assert_eq!(checks[1].check(&process), None);
+    }
+
+    #[test]
+    fn test_failures_crash_loop() {
+        let threshold = crate::config::EmailConfig::default().failure_threshold;
+        let start = Instant::now();
+        let mut failures = Failures::default();
+        // A miner that starts fine and crashes a few seconds later, over and
+        // over, reaches the alert threshold the way the watchdog reports it
+        let events: Vec<Event> = (0..5u64)
+            .map(|cycle| {
+                failures.started(start + Duration::from_secs(cycle * 10));
+                let consecutive_failures = failures.exited(false, start + Duration::from_secs(cycle * 10 + 3));
+                Event::Restarting { consecutive_failures, delay_secs: 0 }
+            })
+            .collect();
+        assert!(events.iter().any(|event| matches!(
+            event,
+            Event::Restarting { consecutive_failures, .. } if *consecutive_failures == threshold
+        )));
+        assert_eq!(failures.count(), 5);
+        assert_eq!(failures.start_failed(), 6);
+    }
+
+    #[test]
+    fn test_failures_recovery() {
+        let start = Instant::now();
+        let mut failures = Failures::default();
+        failures.started(start);
+        failures.exited(false, start + Duration::from_secs(2));
+        failures.started(start + Duration::from_secs(5));
+        assert_eq!(failures.exited(false, start + Duration::from_secs(7)), 2);
+        // A crash after a long healthy run starts the count over
+        failures.started(start + Duration::from_secs(10));
+        assert_eq!(failures.exited(false, start + Duration::from_secs(10) + STABLE_UPTIME), 1);
+        // So does a clean exit
+        failures.started(start + Duration::from_secs(200));
+        assert_eq!(failures.exited(true, start + Duration::from_secs(201)), 0);
+    }
+}
@@ -0,0 +1,79 @@
+use std::process::{Command, ExitStatus};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use crate::config::{self, HooksConfig};
+
+// [hooks] of the running supervisor, replaced when `restart` reloads the config
+static HOOKS: Mutex<Option<HooksConfig>> = Mutex::new(None);
+
+pub fn configure(hooks: &HooksConfig) {
+    if let Ok(mut current) = HOOKS.lock() {
+        *current = Some(hooks.clone());
+    }
+}
+
+fn current() -> HooksConfig {
+    HOOKS.lock().ok().and_then(|hooks| hooks.clone()).unwrap_or_default()
+}
+
+// Run `script` with the event in MINNING_* variables, giving it
+// `timeout_secs` before it is killed so a stuck hook cannot keep the miner
+// down
+fn run(script: &str, event: &str, timeout_secs: u64, vars: &[(&str, String)]) {
+    info!("Running {} hook {}", event, script);
+    let mut command = Command::new(script);
+    command.env("MINNING_EVENT", event).env("MINNING_INSTANCE", config::instance());
+    for (name, value) in vars {
+        command.env(name, value);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Could not run {} hook {}: {}", event, script, e);
+            return;
+        },
+    };
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return debug!("{} hook {} finished", event, script),
+            Ok(Some(status)) => return warn!("{} hook {} failed: {}", event, script, status),
+            Ok(None) if started.elapsed() < Duration::from_secs(timeout_secs) => thread::sleep(Duration::from_millis(100)),
+            Ok(None) => {
+                warn!("{} hook {} still running after {} seconds, killing it", event, script, timeout_secs);
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            },
+            Err(e) => return warn!("Lost track of {} hook {}: {}", event, script, e),
+        }
+    }
+}
+
+// Before every miner start
+pub fn pre_start() {
+    let hooks = current();
+    if let Some(script) = &hooks.pre_start {
+        run(script, "pre_start", hooks.timeout_secs, &[]);
+    }
+}
+
+// After every miner exit, whether it crashed or was stopped. The exit code
+// is empty when the miner was ended by a signal.
+pub fn post_stop(pid: u32, status: Option<ExitStatus>) {
+    let hooks = current();
+    if let Some(script) = &hooks.post_stop {
+        let code = status.and_then(|status| status.code()).map(|code| code.to_string()).unwrap_or_default();
+        run(
+            script,
+            "post_stop",
+            hooks.timeout_secs,
+            &[("MINNING_PID", pid.to_string()), ("MINNING_EXIT_CODE", code)],
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::pause;
+use crate::run::XmrError;
+
+const PAUSE_REASON: &str = "mining hours";
+// How often the clock is checked against [miner] hours
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// A daily mining window; one ending before it starts runs past midnight,
+// one ending as it starts lasts all day
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Window {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start == self.end {
+            true
+        } else if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+// [miner] hours in [miner] timezone, or the machine's local time without one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hours {
+    windows: Vec<Window>,
+    timezone: Option<Tz>,
+}
+
+impl Hours {
+    // "22:00-07:00" or several windows, "00:00-07:00, 12:00-14:00"
+    pub fn parse(hours: &str, timezone: Option<&str>) -> Result<Hours, XmrError> {
+        let invalid = || XmrError::ConfigError(format!("Expected hours like 22:00-07:00, got \"{}\"", hours));
+        let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| invalid());
+        let windows = hours
+            .split(',')
+            .map(|window| {
+                let (start, end) = window.split_once('-').ok_or_else(invalid)?;
+                Ok(Window { start: time(start)?, end: time(end)? })
+            })
+            .collect::<Result<Vec<_>, XmrError>>()?;
+        let timezone = timezone
+            .map(|name| {
+                name.parse::<Tz>()
+                    .map_err(|_| XmrError::ConfigError(format!("Unknown time zone \"{}\"; use a name like Europe/Berlin", name)))
+            })
+            .transpose()?;
+        Ok(Hours { windows, timezone })
+    }
+
+    fn now(&self) -> NaiveTime {
+        match self.timezone {
+            Some(timezone) => Utc::now().with_timezone(&timezone).time(),
+            None => Local::now().time(),
+        }
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        self.windows.iter().any(|window| window.contains(time))
+    }
+
+    // When mining next starts or stops after `time`; overlapping windows
+    // only change anything at their outer edges
+    fn next_change(&self, time: NaiveTime) -> Option<NaiveTime> {
+        let minute = |t: NaiveTime| t.hour() * 60 + t.minute();
+        let now = minute(time);
+        let mining = self.contains(time);
+        self.windows
+            .iter()
+            .flat_map(|window| [window.start, window.end])
+            .filter(|&edge| self.contains(edge) != mining)
+            .min_by_key(|&edge| (minute(edge) + MINUTES_PER_DAY - now - 1) % MINUTES_PER_DAY)
+    }
+
+    fn zone(&self) -> String {
+        self.timezone.map_or("local time".to_string(), |timezone| timezone.name().to_string())
+    }
+}
+
+fn until(hours: &Hours, time: NaiveTime) -> String {
+    match hours.next_change(time) {
+        Some(change) => format!(" until {} {}", change.format("%H:%M"), hours.zone()),
+        None => String::new(),
+    }
+}
+
+// Check [miner] hours and [miner] timezone
+pub fn validate(config: &Config) -> Result<(), XmrError> {
+    if let Some(hours) = &config.miner.hours {
+        Hours::parse(hours, config.miner.timezone.as_deref())?;
+    }
+    Ok(())
+}
+
+// Stop the miner outside [miner] hours and start it again inside them,
+// with the supervisor running throughout
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    let Some(hours) = &config.miner.hours else {
+        return;
+    };
+    let hours = match Hours::parse(hours, config.miner.timezone.as_deref()) {
+        Ok(hours) => hours,
+        Err(e) => {
+            warn!("Ignoring [miner] hours: {}", e);
+            return;
+        },
+    };
+    info!("Mining only during {} ({})", config.miner.hours.as_deref().unwrap_or_default(), hours.zone());
+
+    thread::spawn(move || {
+        let mut mining = None;
+        while running.load(Ordering::SeqCst) {
+            let now = hours.now();
+            let inside = hours.contains(now);
+            if mining != Some(inside) {
+                if inside {
+                    info!("Inside mining hours; mining{}", until(&hours, now));
+                } else {
+                    info!("Outside mining hours; paused{}", until(&hours, now));
+                }
+                pause::set(PAUSE_REASON, !inside);
+                mining = Some(inside);
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(t: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(t, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_hours() {
+        let night = Hours::parse("22:00-07:00", None).unwrap();
+        assert!(night.contains(time("23:30")));
+        assert!(night.contains(time("03:00")));
+        assert!(!night.contains(time("07:00")));
+        assert!(!night.contains(time("12:00")));
+        let day = Hours::parse("09:00 - 17:30", None).unwrap();
+        assert!(day.contains(time("09:00")));
+        assert!(!day.contains(time("17:30")));
+        assert!(Hours::parse("late", None).is_err());
+    }
+
+    #[test]
+    fn test_several_windows() {
+        let hours = Hours::parse("00:00-07:00, 12:00-14:00", None).unwrap();
+        assert!(hours.contains(time("06:59")));
+        assert!(hours.contains(time("13:00")));
+        assert!(!hours.contains(time("10:00")));
+        assert!(Hours::parse("00:00-07:00,", None).is_err());
+    }
+
+    #[test]
+    fn test_next_change() {
+        let hours = Hours::parse("00:00-07:00, 12:00-14:00", None).unwrap();
+        assert_eq!(hours.next_change(time("03:00")), Some(time("07:00")));
+        assert_eq!(hours.next_change(time("08:00")), Some(time("12:00")));
+        assert_eq!(hours.next_change(time("15:00")), Some(time("00:00")));
+        // Back to back windows keep mining across the shared edge
+        let hours = Hours::parse("22:00-00:00, 00:00-06:00", None).unwrap();
+        assert_eq!(hours.next_change(time("23:00")), Some(time("06:00")));
+        // Always mining: nothing changes
+        assert_eq!(Hours::parse("00:00-00:00", None).unwrap().next_change(time("01:00")), None);
+    }
+
+    #[test]
+    fn test_timezone() {
+        assert!(Hours::parse("22:00-07:00", Some("Europe/Berlin")).is_ok());
+        assert!(Hours::parse("22:00-07:00", Some("Mars/Olympus")).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde_json::Value;
+use tracing::{info, warn};
+use ureq::Proxy;
+use ureq::unversioned::transport::DefaultConnector;
+
+use crate::config::NetworkConfig;
+use crate::dns;
+use crate::retry;
+use crate::run::XmrError;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// [network] settings, installed once at startup before any request is made
+static NETWORK: OnceLock<NetworkConfig> = OnceLock::new();
+
+pub fn configure(network: &NetworkConfig) {
+    let _ = NETWORK.set(network.clone());
+    match (&network.http_proxy, http_proxy()) {
+        (Some(url), None) => warn!("Ignoring invalid [network] http_proxy {}", url),
+        (Some(_), Some(proxy)) => info!("Using HTTP proxy {}:{}", proxy.host(), proxy.port()),
+        _ => {},
+    }
+}
+
+pub fn network() -> NetworkConfig {
+    NETWORK.get().cloned().unwrap_or_default()
+}
+
+// Proxy environment variables in the order they are looked at
+const PROXY_VARS: [&str; 6] = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"];
+
+fn env_list(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+}
+
+// [network] http_proxy, else the proxy from the environment, with the
+// NO_PROXY and [network] no_proxy exceptions
+fn http_proxy() -> Option<Proxy> {
+    let network = network();
+    let url = network.http_proxy.clone().or_else(|| env_list(&PROXY_VARS))?;
+    let proxy = Proxy::new(&url).ok()?;
+
+    let mut builder = Proxy::builder(proxy.protocol()).host(proxy.host()).port(proxy.port());
+    if let Some(username) = proxy.username() {
+        builder = builder.username(username);
+    }
+    if let Some(password) = proxy.password() {
+        builder = builder.password(password);
+    }
+    let env_exceptions = env_list(&["NO_PROXY", "no_proxy"]).unwrap_or_default();
+    for exception in env_exceptions.split(',').map(str::to_string).chain(network.no_proxy) {
+        builder = builder.no_proxy(exception.trim());
+    }
+    builder.build().ok()
+}
+
+fn agent_config(timeout: Duration) -> ureq::config::Config {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(timeout))
+        .proxy(http_proxy())
+        .user_agent(concat!("minning/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+// Shared HTTP client settings for every outbound request the launcher makes
+pub fn agent(timeout: Duration) -> ureq::Agent {
+    if network().doh {
+        ureq::Agent::with_parts(agent_config(timeout), DefaultConnector::default(), dns::DohResolver)
+    } else {
+        agent_config(timeout).new_agent()
+    }
+}
+
+// Agent that always uses the system resolver, for the DoH queries themselves
+pub fn direct_agent(timeout: Duration) -> ureq::Agent {
+    agent_config(timeout).new_agent()
+}
+
+// GET a remote API and decode the response body as JSON, retrying
+// transient failures
+pub fn get_json(url: &str) -> Result<Value, XmrError> {
+    retry::run(url, || get_json_with_timeout(url, DEFAULT_TIMEOUT))
+}
+
+// A single attempt, for polling local services where a miss is an answer
+pub fn get_json_with_timeout(url: &str, timeout: Duration) -> Result<Value, XmrError> {
+    agent(timeout)
+        .get(url)
+        .call()
+        .map_err(|e| XmrError::NetworkError(format!("GET {}: {}", url, e)))?
+        .body_mut()
+        .read_json()
+        .map_err(|e| XmrError::NetworkError(format!("Invalid JSON from {}: {}", url, e)))
+}
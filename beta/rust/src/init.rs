@@ -3,77 +3,335 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub fn initialize() -> Result<(), String> {
-    // Get the home directory path
-    let home_dir = match env::var("HOME") {
-        Ok(path) => path,
-        Err(_) => return Err("Could not determine home directory".to_string()),
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::{self, MinerConfig};
+use crate::download;
+use crate::run::XmrError;
+use crate::selfmetrics;
+
+const RELEASE_BASE_URL: &str = "https://github.com/cazzano/Minning/releases/download";
+// GitHub repository whose releases `update` follows
+pub const RELEASE_REPO: &str = "cazzano/Minning";
+// Release tag installed when no --version is given
+pub const DEFAULT_TAG: &str = "minning";
+// Written into the xmr folder so a setup can be reproduced later
+pub const RELEASE_FILE: &str = "release.json";
+
+fn home_dir() -> Result<PathBuf, XmrError> {
+    env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|_| XmrError::EnvError("Could not determine home directory".to_string()))
+}
+
+// Where `init` installs the miner: ~/xmr
+pub fn xmr_dir() -> Result<PathBuf, XmrError> {
+    Ok(home_dir()?.join("xmr"))
+}
+
+// Which release is installed in the xmr folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledRelease {
+    pub tag: String,
+    pub url: String,
+    pub sha256: String,
+    pub installed: i64,
+    // SHA-256 of the unpacked miner binary, checked before the supervisor
+    // switches to a replaced one
+    #[serde(default)]
+    pub binary_sha256: Option<String>,
+}
+
+// What `init` or `update` left in place
+pub struct Installation {
+    pub xmr_dir: PathBuf,
+    // None for installs that predate release.json
+    pub release: Option<InstalledRelease>,
+    // False when the wanted release was already installed
+    pub changed: bool,
+}
+
+// Release archive built for this machine's architecture
+pub fn asset_name() -> Result<&'static str, XmrError> {
+    match env::consts::ARCH {
+        "x86_64" => Ok("xmr.zip"),
+        "aarch64" => Ok("xmr-aarch64.zip"),
+        other => Err(XmrError::EnvError(format!("No miner build is published for {} machines", other))),
+    }
+}
+
+fn zip_url(tag: &str) -> Result<String, XmrError> {
+    Ok(format!("{}/{}/{}", RELEASE_BASE_URL, tag, asset_name()?))
+}
+
+// Where a release archive can be downloaded from: GitHub first, then each
+// [miner] mirrors entry. A mirror is either a template with {tag} and
+// {asset} or a base URL laid out like GitHub's (<base>/<tag>/<asset>).
+pub fn download_urls(miner: &MinerConfig, tag: &str) -> Result<Vec<String>, XmrError> {
+    let asset = asset_name()?;
+    let mut urls = vec![zip_url(tag)?];
+    for mirror in &miner.mirrors {
+        let url = if mirror.contains("{tag}") || mirror.contains("{asset}") {
+            mirror.replace("{tag}", tag).replace("{asset}", asset)
+        } else {
+            format!("{}/{}/{}", mirror.trim_end_matches('/'), tag, asset)
+        };
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    Ok(urls)
+}
+
+pub fn installed_release(xmr_dir: &Path) -> Option<InstalledRelease> {
+    let contents = fs::read_to_string(xmr_dir.join(RELEASE_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// The configured checksum, or the one published as <archive>.sha256 (either
+// a sha256sum line or a bare hash) on the GitHub release. Never a mirror's:
+// a mirror serving a doctored archive would serve a matching hash with it.
+pub fn expected_checksum(miner: &MinerConfig, tag: &str) -> Result<String, XmrError> {
+    if let Some(sha256) = &miner.sha256 {
+        return Ok(sha256.clone());
+    }
+    let zip_url = zip_url(tag)?;
+    let file_name = asset_name()?;
+    let listing = download::fetch_published(&format!("{}.sha256", zip_url))
+        .map_err(|e| {
+            XmrError::ChecksumError(format!(
+                "Could not fetch the published checksum ({}); set [miner] sha256 to verify against",
+                e
+            ))
+        })?
+        .ok_or_else(|| {
+            XmrError::ChecksumError(format!(
+                "Release {} publishes no checksum at {}.sha256; set [miner] sha256 to verify against",
+                tag, zip_url
+            ))
+        })?;
+    download::checksum_for(&listing, file_name)
+        .or_else(|| listing.split_whitespace().find(|t| download::is_sha256(t)).map(str::to_lowercase))
+        .ok_or_else(|| XmrError::ChecksumError(format!("No SHA-256 for {} at {}.sha256", file_name, zip_url)))
+}
+
+// An archive is only trusted with a signature from the configured key;
+// without either there is nothing to check it against
+fn check_signature(zip_path: &Path, signature: Option<&str>, key: Option<&str>) -> Result<(), XmrError> {
+    let refuse = |reason: &str| {
+        let _ = fs::remove_file(zip_path);
+        Err(XmrError::SignatureError(format!("{}; pass --no-verify to install it unchecked", reason)))
     };
-    
-    println!("Home directory: {}", home_dir);
-    
-    // Check if the XMR folder exists
-    let xmr_path = Path::new(&home_dir).join("xmr");
-    
-    if xmr_path.exists() && xmr_path.is_dir() {
-        println!("XMR folder already exists at {}", xmr_path.display());
-        return Ok(());
+    let Some(key) = key else {
+        return refuse("No release signing key is configured in [miner] signing_key");
+    };
+    let Some(signature) = signature else {
+        return refuse("The release publishes no signature for the archive");
+    };
+    download::verify_signature(zip_path, signature, key)
+}
+
+// Check the archive against its published checksum and the release
+// signature (<archive>.minisig), removing it when either does not match
+fn verify_archive(miner: &MinerConfig, tag: &str, zip_url: &str, zip_path: &Path) -> Result<(), XmrError> {
+    let expected = match expected_checksum(miner, tag) {
+        Ok(expected) => expected,
+        Err(e) => {
+            let _ = fs::remove_file(zip_path);
+            return Err(e);
+        },
+    };
+    download::verify(zip_path, &expected)?;
+
+    // The key, not the source, vouches for the archive, so a mirror's
+    // signature is as good as GitHub's
+    let signature = match download::fetch_published(&format!("{}.minisig", zip_url)) {
+        Ok(signature) => signature,
+        Err(e) => {
+            let _ = fs::remove_file(zip_path);
+            return Err(XmrError::SignatureError(format!("Could not fetch the release signature: {}", e)));
+        },
+    };
+    check_signature(zip_path, signature.as_deref(), miner.signing_key.as_deref())
+}
+
+// Download, verify and unpack release `tag` into `parent`, which ends up
+// holding the xmr folder with its release.json. `verify: false` skips the
+// checksum and signature checks, for testing with locally built archives.
+pub fn install(miner: &MinerConfig, verify: bool, tag: &str, parent: &Path) -> Result<InstalledRelease, XmrError> {
+    let xmr_path = parent.join("xmr");
+    // Fail before downloading anything when this architecture has no build
+    let asset = asset_name()?;
+    if let Ok(release) = download::github_release(RELEASE_REPO, tag)
+        && release.asset(asset).is_none()
+    {
+        return Err(XmrError::EnvError(format!(
+            "Release {} has no {} build ({})",
+            tag,
+            env::consts::ARCH,
+            asset
+        )));
     }
+    let zip_path = parent.join(asset);
     
-    // XMR folder doesn't exist, so download the zip file
-    println!("XMR folder not found. Downloading XMR zip file...");
-    
-    let xmr_zip_url = "https://github.com/cazzano/Minning/releases/download/minning/xmr.zip";
-    let zip_path = PathBuf::from(&home_dir).join("xmr.zip");
-    
-    // Download the zip file using wget
-    let wget_status = Command::new("wget")
-        .arg("-O")
-        .arg(&zip_path)
-        .arg(xmr_zip_url)
-        .status()
-        .map_err(|e| format!("Failed to execute wget: {}", e))?;
-    
-    if !wget_status.success() {
-        return Err(format!("wget failed with exit code: {}", wget_status));
+    // Try each source in turn; a download that fails or does not verify
+    // moves on to the next one. The built-in client needs no wget, and a
+    // partial file from a failed download is not left behind.
+    let mut failures = Vec::new();
+    let mut downloaded = None;
+    for url in download_urls(miner, tag)? {
+        let result = download::fetch(&url, &zip_path).and_then(|_| {
+            // Never extract (and later run) a binary that is not the published one
+            if verify {
+                verify_archive(miner, tag, &url, &zip_path)
+            } else {
+                warn!("Skipping checksum and signature verification of {}", zip_path.display());
+                Ok(())
+            }
+        });
+        match result {
+            Ok(()) => {
+                downloaded = Some(url);
+                break;
+            },
+            Err(e) => {
+                warn!("Download from {} failed: {}", url, e);
+                let _ = fs::remove_file(&zip_path);
+                failures.push((url, e));
+            },
+        }
     }
+    let Some(xmr_zip_url) = downloaded else {
+        // With a single source its own error says it all
+        if failures.len() == 1 {
+            return Err(failures.remove(0).1);
+        }
+        let report: Vec<String> = failures.iter().map(|(url, e)| format!("{}: {}", url, e)).collect();
+        return Err(XmrError::NetworkError(format!("Every download source failed:\n  {}", report.join("\n  "))));
+    };
+    let xmr_zip_url = xmr_zip_url.as_str();
     
-    println!("Download completed. Extracting zip file...");
+    info!("Download completed. Extracting zip file...");
     
     // Unzip the file
-    let unzip_status = Command::new("unzip")
-        .arg("-o") // Overwrite files without prompting
-        .arg(&zip_path)
-        .arg("-d")
-        .arg(&home_dir)
-        .status()
-        .map_err(|e| format!("Failed to execute unzip: {}", e))?;
+    let unzip_status = selfmetrics::time("extract", || {
+        Command::new("unzip")
+            .arg("-o") // Overwrite files without prompting
+            .arg(&zip_path)
+            .arg("-d")
+            .arg(parent)
+            .status()
+    })
+        .map_err(|e| XmrError::ExecutionError(format!("Failed to execute unzip: {}", e)))?;
     
     if !unzip_status.success() {
-        return Err(format!("unzip failed with exit code: {}", unzip_status));
+        return Err(XmrError::ExecutionError(format!("unzip failed with exit code: {}", unzip_status)));
     }
     
-    println!("Extraction completed successfully.");
+    info!("Extraction completed successfully.");
     
     // Verify the XMR folder now exists
+    if !xmr_path.is_dir() {
+        return Err(XmrError::ExecutionError("XMR folder was not created properly after extraction".to_string()));
+    }
+    
+    let release = InstalledRelease {
+        tag: tag.to_string(),
+        url: xmr_zip_url.to_string(),
+        sha256: download::sha256_file(&zip_path)?,
+        installed: Utc::now().timestamp(),
+        binary_sha256: Some(download::sha256_file(&xmr_path.join("xmr"))?),
+    };
+    let contents = serde_json::to_string_pretty(&release)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode release metadata: {}", e)))?;
+    config::write_atomic(&xmr_path.join(RELEASE_FILE), &contents)?;
+    
+    // Optionally, remove the zip file to clean up
+    if let Err(e) = fs::remove_file(&zip_path) {
+        warn!("Could not remove zip file: {}", e);
+    }
+    
+    Ok(release)
+}
+
+// `init`: install the miner unless it is already there. `version` picks a
+// release tag (`init --version <tag>`); asking for a tag other than the
+// installed one replaces the install.
+pub fn initialize(miner: &MinerConfig, verify: bool, version: Option<&str>) -> Result<Installation, XmrError> {
+    let home_dir = home_dir()?;
+    let xmr_path = home_dir.join("xmr");
+    
+    let tag = version.unwrap_or(DEFAULT_TAG);
     if xmr_path.exists() && xmr_path.is_dir() {
-        println!("XMR folder successfully created at {}", xmr_path.display());
-        
-        // Optionally, remove the zip file to clean up
-        if let Err(e) = fs::remove_file(&zip_path) {
-            println!("Warning: Could not remove zip file: {}", e);
+        let installed = installed_release(&xmr_path);
+        match (version, &installed) {
+            (Some(wanted), Some(installed)) if installed.tag != wanted => {
+                info!("Replacing release {} with {}", installed.tag, wanted);
+            },
+            (Some(wanted), None) => {
+                info!("Installed release unknown; installing {}", wanted);
+            },
+            _ => {
+                let tag = installed.as_ref().map_or_else(|| "unknown release".to_string(), |r| format!("release {}", r.tag));
+                info!("XMR folder already exists at {} ({})", xmr_path.display(), tag);
+                return Ok(Installation { xmr_dir: xmr_path, release: installed, changed: false });
+            },
         }
-        
-        Ok(())
     } else {
-        Err("XMR folder was not created properly after extraction".to_string())
+        // XMR folder doesn't exist, so download the zip file
+        info!("XMR folder not found. Downloading XMR zip file ({})...", tag);
     }
+    
+    let release = install(miner, verify, tag, &home_dir)?;
+    info!("XMR folder successfully created at {}", xmr_path.display());
+    Ok(Installation { xmr_dir: xmr_path, release: Some(release), changed: true })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    #[test]
+    fn test_download_urls() {
+        let miner = MinerConfig {
+            mirrors: vec![
+                "https://mirror.example/minning/".to_string(),
+                "https://cdn.example/{tag}-{asset}".to_string(),
+            ],
+            ..MinerConfig::default()
+        };
+        let asset = asset_name().unwrap();
+        assert_eq!(
+            download_urls(&miner, "v2").unwrap(),
+            vec![
+                format!("{}/v2/{}", RELEASE_BASE_URL, asset),
+                format!("https://mirror.example/minning/v2/{}", asset),
+                format!("https://cdn.example/v2-{}", asset),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_signature() {
+        let key = "RWSdExteMDNQolG3ijJVTfu4hZ4GFOBj91lbS80iHmK4CYmrylsO+jFS";
+        let signature = "untrusted comment: signature from minisign secret key\n\
+                         RUSdExteMDNQom25+alyTjXK4G4QmAIHWUr8QbQz/L82stXdD3MYaOr56tO+AETheHibRTIyTF/7JZFPN/1ZhXMN3GOnJQQMPg8=\n\
+                         trusted comment: timestamp:1760000000\tfile:xmr.zip\n\
+                         npJDRAcPlBiqXnR6nrAHJvbe3TsURgjQpEfdnaDbrGdjanc9qtQ+n3+LiXRBUBQUyIHdG2lTZD6BOcSwSq44Dg==\n";
+        let path = env::temp_dir().join(format!("minning-init-signature-{}", std::process::id()));
+        fs::write(&path, "minning test archive\n").unwrap();
+        assert!(check_signature(&path, Some(signature), Some(key)).is_ok());
+
+        // Unsigned, or no key to check with: refused and removed
+        assert!(matches!(check_signature(&path, None, Some(key)), Err(XmrError::SignatureError(_))));
+        assert!(!path.exists());
+        fs::write(&path, "minning test archive\n").unwrap();
+        assert!(matches!(check_signature(&path, Some(signature), None), Err(XmrError::SignatureError(_))));
+        assert!(!path.exists());
+    }
+
     #[test]
     fn test_initialize() {
         // This is just a placeholder test
@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::config::{Config, LoadAction, LoadConfig};
+use crate::cpu;
+use crate::events::{self, Event, Sink};
+use crate::pause;
+
+const PAUSE_REASON: &str = "busy machine";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// The niceness the miner drops to while yielding
+const YIELD_NICE: i32 = 19;
+
+// Miner processes whose CPU time is not counted as other load
+static MINERS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+struct MinerSink;
+
+impl Sink for MinerSink {
+    fn handle(&self, event: &Event) {
+        let Ok(mut miners) = MINERS.lock() else {
+            return;
+        };
+        match event {
+            Event::ChildStarted { pid } => {
+                miners.insert(*pid);
+            },
+            Event::ChildExited { pid, .. } => {
+                miners.remove(pid);
+            },
+            _ => {},
+        }
+    }
+}
+
+// (busy, total) clock ticks of all CPUs from the "cpu" line of /proc/stat;
+// time waiting for I/O counts as idle
+fn parse_stat(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let ticks: Vec<u64> = line.split_whitespace().skip(1).filter_map(|value| value.parse().ok()).collect();
+    // user nice system idle iowait irq softirq steal; guest time is already
+    // included in user and nice
+    let total: u64 = ticks.iter().take(8).sum();
+    let idle = ticks.get(3)? + ticks.get(4).unwrap_or(&0);
+    Some((total - idle, total))
+}
+
+// User plus system clock ticks of a process from /proc/<pid>/stat. The
+// command name can hold spaces and parentheses, so fields are counted from
+// the last ')'.
+fn parse_process_stat(stat: &str) -> Option<u64> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    busy: u64,
+    total: u64,
+    miner: u64,
+}
+
+fn sample(pids: &BTreeSet<u32>) -> Option<Sample> {
+    let (busy, total) = parse_stat(&fs::read_to_string("/proc/stat").ok()?)?;
+    let miner = pids
+        .iter()
+        .filter_map(|pid| parse_process_stat(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?))
+        .sum();
+    Some(Sample { busy, total, miner })
+}
+
+// Percent of all CPU time between two samples spent outside the miner. A
+// miner that started or exited in between makes its share uncertain, so
+// that interval is skipped.
+fn other_percent(before: Sample, after: Sample) -> Option<f64> {
+    let total = after.total.checked_sub(before.total).filter(|&total| total > 0)?;
+    let busy = after.busy.checked_sub(before.busy)?;
+    let miner = after.miner.checked_sub(before.miner)?;
+    Some(busy.saturating_sub(miner) as f64 * 100.0 / total as f64)
+}
+
+// Whether the miner yields: from other load reaching max_other_percent until
+// it has stayed below it by the hysteresis for resume_secs
+#[derive(Debug)]
+struct Yielder {
+    yielding: bool,
+    calm_since: Option<Instant>,
+}
+
+impl Yielder {
+    fn update(&mut self, config: &LoadConfig, max: u8, other: f64, now: Instant) -> bool {
+        if other >= f64::from(max) {
+            self.yielding = true;
+            self.calm_since = None;
+        } else if self.yielding && other <= f64::from(max.saturating_sub(config.hysteresis)) {
+            let since = *self.calm_since.get_or_insert(now);
+            if now.duration_since(since) >= Duration::from_secs(config.resume_secs) {
+                self.yielding = false;
+                self.calm_since = None;
+            }
+        } else {
+            self.calm_since = None;
+        }
+        self.yielding
+    }
+}
+
+fn renice_miners(pids: &BTreeSet<u32>, nice: i32) -> bool {
+    let mut ok = true;
+    for &pid in pids {
+        if let Err(e) = cpu::renice(pid, nice) {
+            warn!("{}", e);
+            ok = false;
+        }
+    }
+    ok
+}
+
+// The niceness the miner runs with when not yielding
+fn normal_nice(config: &Config) -> i32 {
+    config.miner.nice.unwrap_or_else(|| {
+        // SAFETY: getpriority only takes plain integers
+        unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) }
+    })
+}
+
+// Make way for other work: pause the miner, or drop it to the lowest CPU
+// priority, while processes other than the miner keep the CPUs busy
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    let load = config.load.clone();
+    let Some(max) = load.max_other_percent else {
+        return;
+    };
+    let max = max.clamp(1, 100);
+    let normal = normal_nice(config);
+    info!("Yielding to other work above {}% CPU load", max);
+    events::subscribe(Box::new(MinerSink));
+
+    thread::spawn(move || {
+        let mut yielder = Yielder { yielding: false, calm_since: None };
+        let mut previous = None;
+        let mut previous_pids = BTreeSet::new();
+        while running.load(Ordering::SeqCst) {
+            let pids = MINERS.lock().map(|m| m.clone()).unwrap_or_default();
+            let current = sample(&pids);
+            let other = match (previous, current) {
+                (Some(before), Some(after)) if pids == previous_pids => other_percent(before, after),
+                _ => None,
+            };
+            previous = current;
+            previous_pids = pids.clone();
+
+            if let Some(other) = other {
+                let was_yielding = yielder.yielding;
+                let yielding = yielder.update(&load, max, other, Instant::now());
+                if yielding != was_yielding {
+                    match (yielding, load.action) {
+                        (true, LoadAction::Pause) => info!("Other processes use {:.0}% of the CPU, pausing mining", other),
+                        (true, LoadAction::Nice) => {
+                            info!("Other processes use {:.0}% of the CPU, mining at the lowest priority", other)
+                        },
+                        (false, _) => info!("Other load is down to {:.0}%, mining normally again", other),
+                    }
+                    if load.action == LoadAction::Nice
+                        && !yielding
+                        && !renice_miners(&pids, normal)
+                        && normal < YIELD_NICE
+                    {
+                        warn!("Raising the miner's priority back needs root; it keeps niceness {} until restarted", YIELD_NICE);
+                    }
+                }
+                if load.action == LoadAction::Pause {
+                    pause::set(PAUSE_REASON, yielding);
+                }
+            }
+            // Restarted miners come up at their normal priority; push them
+            // down again
+            if load.action == LoadAction::Nice && yielder.yielding {
+                renice_miners(&pids, YIELD_NICE);
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "cpu  100 20 30 800 50 5 5 0 40 0\ncpu0 50 10 15 400 25 2 3 0 20 0\nintr 12345\n";
+        assert_eq!(parse_stat(stat), Some((160, 1010)));
+        assert_eq!(parse_stat("intr 12345\n"), None);
+    }
+
+    #[test]
+    fn test_parse_process_stat() {
+        let stat = "4242 (xmr (worker)) S 1 4242 4242 0 -1 4194560 1000 0 0 0 1500 250 0 0 20 0 8 0 100 0 0";
+        assert_eq!(parse_process_stat(stat), Some(1750));
+        assert_eq!(parse_process_stat("4242 (xmr) S 1"), None);
+    }
+
+    #[test]
+    fn test_other_percent() {
+        let before = Sample { busy: 1000, total: 2000, miner: 600 };
+        let after = Sample { busy: 1800, total: 3000, miner: 1100 };
+        assert_eq!(other_percent(before, after), Some(30.0));
+        assert_eq!(other_percent(before, before), None);
+    }
+
+    #[test]
+    fn test_yielder() {
+        let config = LoadConfig { max_other_percent: Some(50), hysteresis: 10, resume_secs: 60, ..LoadConfig::default() };
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut yielder = Yielder { yielding: false, calm_since: None };
+        assert!(!yielder.update(&config, 50, 45.0, at(0)));
+        assert!(yielder.update(&config, 50, 70.0, at(5)));
+        // Below the threshold, but not by the hysteresis
+        assert!(yielder.update(&config, 50, 45.0, at(10)));
+        assert!(yielder.update(&config, 50, 30.0, at(15)));
+        // Calm, but not for long enough
+        assert!(yielder.update(&config, 50, 30.0, at(60)));
+        assert!(!yielder.update(&config, 50, 30.0, at(75)));
+        // A burst in between starts the wait over
+        let mut yielder = Yielder { yielding: true, calm_since: Some(at(0)) };
+        assert!(yielder.update(&config, 50, 45.0, at(30)));
+        assert!(yielder.update(&config, 50, 30.0, at(70)));
+    }
+}
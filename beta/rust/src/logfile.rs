@@ -0,0 +1,137 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{LogRotation, LoggingConfig};
+
+// Log file that rotates itself: once it grows past the size limit or the
+// hour/day it was started in is over, minning.log becomes minning.log.1,
+// older files shift up by one and the oldest beyond `keep` is dropped
+pub struct RotatingFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    // Rotation period the open file belongs to
+    period: Option<u64>,
+    max_bytes: u64,
+    rotate: LogRotation,
+    keep: usize,
+}
+
+// Index of the UTC hour or day `time` falls in
+fn period(rotate: LogRotation, time: SystemTime) -> Option<u64> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    match rotate {
+        LogRotation::Never => None,
+        LogRotation::Hourly => Some(secs / 3600),
+        LogRotation::Daily => Some(secs / 86_400),
+    }
+}
+
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+impl RotatingFile {
+    pub fn new(path: PathBuf, config: &LoggingConfig) -> RotatingFile {
+        RotatingFile {
+            path,
+            file: None,
+            size: 0,
+            period: None,
+            max_bytes: config.max_size_mb.saturating_mul(1024 * 1024),
+            rotate: config.rotate,
+            keep: config.keep,
+        }
+    }
+
+    // Continue the existing file; its age decides which period it is in
+    fn open(&mut self) -> io::Result<&mut File> {
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+                let metadata = file.metadata()?;
+                self.size = metadata.len();
+                let started = if self.size > 0 { metadata.modified().ok() } else { None };
+                self.period = period(self.rotate, started.unwrap_or_else(SystemTime::now));
+                file
+            },
+        };
+        Ok(self.file.insert(file))
+    }
+
+    fn due(&self, incoming: usize) -> bool {
+        if self.file.is_none() || self.size == 0 {
+            return false;
+        }
+        let too_big = self.max_bytes > 0 && self.size + incoming as u64 > self.max_bytes;
+        too_big || period(self.rotate, SystemTime::now()) != self.period
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        if self.keep == 0 {
+            return fs::remove_file(&self.path);
+        }
+        for n in (1..self.keep).rev() {
+            let from = numbered(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, numbered(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, numbered(&self.path, 1))
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Open first so a file left from an earlier run is rotated as well
+        self.open()?;
+        if self.due(buf.len()) {
+            // Keep logging into the old file rather than losing records
+            if let Err(e) = self.rotate() {
+                eprintln!("Could not rotate {}: {}; no longer rotating it", self.path.display(), e);
+                self.max_bytes = 0;
+                self.rotate = LogRotation::Never;
+            }
+        }
+        let written = self.open()?.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("minning-logfile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("minning.log");
+        let config = LoggingConfig { rotate: LogRotation::Never, keep: 2, ..LoggingConfig::default() };
+        let mut log = RotatingFile::new(path.clone(), &config);
+        log.max_bytes = 10;
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(numbered(&path, 2)).unwrap(), "second\n");
+        assert!(!numbered(&path, 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,344 @@
+use std::cell::Cell;
+use std::env;
+use std::fmt::Debug;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::process;
+use std::sync::OnceLock;
+
+use tracing::field::{Field, Visit};
+use tracing::{info, warn, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{self, LogBackend, LogFormat, LoggingConfig};
+use crate::events::{self, Event, Sink};
+use crate::logfile::RotatingFile;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+const IDENTIFIER: &str = "minning";
+pub const LOG_FILE_NAME: &str = "minning.log";
+// Commands that log to LOG_FILE_NAME unless [logging] file says otherwise
+const SUPERVISOR_MODES: [&str; 3] = ["run", "run-resilient", "run-super-resilient"];
+
+// Keeps the non-blocking file writer flushing until the process exits
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+thread_local! {
+    // Watchdog running on this thread, exported as MINNING_WATCHDOG_ID
+    static WATCHDOG_ID: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Tag every record logged from the calling thread with watchdog `id`
+pub fn set_watchdog(id: usize) {
+    WATCHDOG_ID.with(|watchdog| watchdog.set(Some(id)));
+}
+
+// journald/syslog layer: every tracing event becomes one structured record.
+// Event fields are exported as MINNING_<FIELD> next to mode, instance and,
+// from watchdog threads, the watchdog ID.
+struct StructuredLayer {
+    backend: LogBackend,
+    mode: String,
+    instance: String,
+    #[cfg(unix)]
+    socket: UnixDatagram,
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = value;
+        } else {
+            self.fields.push((format!("MINNING_{}", field.name().to_uppercase()), value));
+        }
+    }
+}
+
+// syslog(3) priority for a tracing level, shared by journald's PRIORITY field
+fn priority(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+// Append one field in journald's native protocol; values containing
+// newlines need the length-prefixed binary form
+fn push_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+impl StructuredLayer {
+    #[cfg(unix)]
+    fn connect(backend: LogBackend, mode: &str, instance: &str) -> Option<StructuredLayer> {
+        let connect = |path: &str| UnixDatagram::unbound().and_then(|s| s.connect(path).map(|_| s));
+
+        // Fall back from journald to syslog when the journal socket is missing
+        let (backend, socket) = match backend {
+            LogBackend::Journald => match connect(JOURNALD_SOCKET) {
+                Ok(socket) => (LogBackend::Journald, socket),
+                Err(_) => (LogBackend::Syslog, connect(SYSLOG_SOCKET).ok()?),
+            },
+            _ => (LogBackend::Syslog, connect(SYSLOG_SOCKET).ok()?),
+        };
+
+        Some(StructuredLayer {
+            backend,
+            mode: mode.to_string(),
+            instance: instance.to_string(),
+            socket,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn connect(_backend: LogBackend, _mode: &str, _instance: &str) -> Option<StructuredLayer> {
+        None
+    }
+
+    fn encode(&self, priority: u8, msg: &str, extra: &[(String, String)]) -> Vec<u8> {
+        match self.backend {
+            LogBackend::Journald => {
+                let mut buf = Vec::new();
+                push_field(&mut buf, "MESSAGE", msg);
+                push_field(&mut buf, "PRIORITY", &priority.to_string());
+                push_field(&mut buf, "SYSLOG_IDENTIFIER", IDENTIFIER);
+                push_field(&mut buf, "MINNING_MODE", &self.mode);
+                push_field(&mut buf, "MINNING_INSTANCE", &self.instance);
+                for (key, value) in extra {
+                    push_field(&mut buf, key, value);
+                }
+                buf
+            },
+            // RFC 3164 line with facility "daemon"; fields become key=value pairs
+            _ => {
+                let mut line = format!(
+                    "<{}>{}[{}]: {} mode={} instance={}",
+                    (3 << 3) | priority,
+                    IDENTIFIER,
+                    process::id(),
+                    msg,
+                    self.mode,
+                    self.instance
+                );
+                for (key, value) in extra {
+                    let key = key.trim_start_matches("MINNING_").to_lowercase();
+                    line.push_str(&format!(" {}={}", key, value));
+                }
+                line.into_bytes()
+            },
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for StructuredLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        if let Some(id) = WATCHDOG_ID.with(Cell::get) {
+            visitor.fields.push(("MINNING_WATCHDOG_ID".to_string(), id.to_string()));
+        }
+        let payload = self.encode(priority(event.metadata().level()), &visitor.message, &visitor.fields);
+
+        #[cfg(unix)]
+        if self.socket.send(&payload).is_err() {
+            eprintln!("{}", visitor.message);
+        }
+        #[cfg(not(unix))]
+        let _ = payload;
+    }
+}
+
+// Lifecycle events as dedicated records carrying the child PID and event type
+struct EventLogSink;
+
+impl Sink for EventLogSink {
+    fn handle(&self, event: &Event) {
+        let name = event.name();
+        match event {
+            Event::ChildStarted { pid } => info!(event = name, child_pid = pid, "Miner child {} started", pid),
+            Event::ChildExited { pid, code, .. } if event.is_failure() => {
+                warn!(event = name, child_pid = pid, exit_code = ?code, "Miner child {} exited", pid)
+            },
+            Event::ChildExited { pid, code, .. } => {
+                info!(event = name, child_pid = pid, exit_code = ?code, "Miner child {} exited", pid)
+            },
+            Event::Restarting { consecutive_failures, delay_secs } => info!(
+                event = name,
+                consecutive_failures,
+                delay_secs,
+                "Restarting miner in {} seconds",
+                delay_secs
+            ),
+            Event::HealthCheckFailed { pid, detail } => {
+                warn!(event = name, child_pid = pid, "Health check failed: {}", detail)
+            },
+            Event::Alert { message } => warn!(event = name, "{}", message),
+            Event::PayoutReceived { amount, tx_hash } => {
+                info!(event = name, amount, tx_hash = ?tx_hash, "Payout of {:.12} XMR received", amount)
+            },
+            Event::PriceAlert { price, currency, threshold, direction } => warn!(
+                event = name,
+                price,
+                threshold,
+                "XMR price {:.2} {} is {} {:.2}",
+                price,
+                currency.to_uppercase(),
+                direction,
+                threshold
+            ),
+            Event::PoolFailover { from, to, reason } => warn!(
+                event = name,
+                from = from.as_str(),
+                to = to.as_str(),
+                "Pool failover from {} to {}: {}",
+                from,
+                to,
+                reason
+            ),
+            Event::Paused { reason } => info!(event = name, reason = reason.as_str(), "Mining paused: {}", reason),
+            Event::Resumed { reason } => info!(event = name, reason = reason.as_str(), "Mining resumed: {}", reason),
+            Event::Shutdown => info!(event = name, "Supervisor shutting down"),
+            Event::InstallCompleted { command, tag, changed, .. } => info!(
+                event = name,
+                tag = ?tag,
+                changed,
+                "{} finished ({})",
+                command,
+                tag.as_deref().unwrap_or("unknown release")
+            ),
+            Event::InstallFailed { command, error } => warn!(event = name, "{} failed: {}", command, error),
+        }
+    }
+}
+
+// Filter for the -v / -vv / --quiet flags on top of the configured one:
+// verbosity raises only the launcher's own modules, so -v does not bury
+// the output under debug records from the HTTP and MQTT libraries
+pub fn verbosity(level: &str, verbose: u8, quiet: bool) -> String {
+    let crate_name = env!("CARGO_CRATE_NAME");
+    match (quiet, verbose) {
+        (true, _) => "warn".to_string(),
+        (false, 0) => level.to_string(),
+        (false, 1) => format!("{},{}=debug", level, crate_name),
+        (false, _) => format!("{},{}=trace", level, crate_name),
+    }
+}
+
+// Install the global tracing subscriber once per process. RUST_LOG overrides
+// the configured level; `mode` ends up in structured records as MINNING_MODE
+// so journalctl can filter resilient vs super-resilient runs.
+pub fn init(config: &LoggingConfig, mode: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.level));
+
+    let backend = match config.backend {
+        LogBackend::Auto if env::var_os("JOURNAL_STREAM").is_some() => LogBackend::Journald,
+        LogBackend::Auto => LogBackend::Console,
+        backend => backend,
+    };
+    let structured = match backend {
+        LogBackend::Console | LogBackend::File => None,
+        backend => StructuredLayer::connect(backend, mode, &config.instance),
+    };
+    let structured_failed = !matches!(backend, LogBackend::Console | LogBackend::File) && structured.is_none();
+
+    // Console output goes to stderr so stdout stays free for machine output
+    let console = (structured.is_none() && backend != LogBackend::File).then(|| match config.format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .with_ansi(io::stderr().is_terminal())
+            .with_target(false)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_writer(io::stderr)
+            .boxed(),
+    });
+
+    let mut file_error = None;
+    let file = if backend == LogBackend::File || config.file.unwrap_or(SUPERVISOR_MODES.contains(&mode)) {
+        match config::state_dir().and_then(|dir| fs::create_dir_all(&dir).map(|_| dir).map_err(Into::into)) {
+            Ok(dir) => {
+                let (writer, guard) = tracing_appender::non_blocking(RotatingFile::new(dir.join(LOG_FILE_NAME), config));
+                let _ = FILE_GUARD.set(guard);
+                Some(match config.format {
+                    LogFormat::Text => tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false).boxed(),
+                    LogFormat::Json => tracing_subscriber::fmt::layer().json().with_writer(writer).boxed(),
+                })
+            },
+            Err(e) => {
+                file_error = Some(e);
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    let has_structured = structured.is_some();
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(structured)
+        .with(console)
+        .with(file)
+        .try_init()
+        .is_ok();
+
+    if !installed {
+        return;
+    }
+    if has_structured {
+        events::subscribe(Box::new(EventLogSink));
+    }
+    if structured_failed {
+        warn!("No journald or syslog socket available, logging to the console");
+    }
+    if let Some(e) = file_error {
+        warn!("Could not open the log file in the state directory: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(verbosity("info", 0, false), "info");
+        assert_eq!(verbosity("info", 1, false), "info,xmr=debug");
+        assert_eq!(verbosity("warn,xmr::pool=debug", 2, false), "warn,xmr::pool=debug,xmr=trace");
+        assert_eq!(verbosity("debug", 0, true), "warn");
+    }
+}
@@ -1,77 +1,437 @@
-use std::env;
+use clap::{CommandFactory, FromArgMatches};
 
+use cli::{Cli, Command, ExportCommand, PoolCommand, ProfileCommand, ServiceCommand, TuneCommand, WalletCommand};
+use daemon::Detached;
+
+mod address;
+mod api;
+mod balance;
+mod bandwidth;
+mod bench;
+mod cgroup;
+mod binwatch;
+mod cli;
+mod config;
+mod connectivity;
+mod control;
+mod cpu;
+mod credentials;
+mod daemon;
+mod dns;
+mod download;
+mod earnings;
+mod events;
+mod grafana;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod health;
+mod hooks;
+mod hours;
+mod http;
 // Import the initialize function from init.rs
 mod init;
+mod load;
+mod logfile;
+mod logging;
+mod market;
+mod metered;
+mod metrics;
+mod miner_config;
+mod minerlog;
+mod mqtt;
+mod node;
+mod notify;
+mod numa;
+mod output;
+mod p2pool;
+mod pause;
+mod pidfd;
+mod pidfile;
+mod payouts;
+mod pool;
+mod power;
+mod preflight;
+mod profile;
+mod pool_api;
+mod profit;
+mod procgroup;
+mod provision;
+mod registry;
+mod proxy;
+mod redact;
+mod region;
+mod report;
+mod retry;
+mod scheduler;
 // Import the run module
 mod run;
+mod selfmetrics;
+mod service;
+mod sidecar;
+mod solo;
+mod split;
+mod stats;
+mod statecheck;
+mod status;
+mod stratum;
+mod stratum_proxy;
+mod suspend;
+mod telemetry;
+mod thermal;
+mod tune;
+mod update;
+mod wallet;
+
+// Report a failed command and exit non-zero
+fn exit_on_error(context: &str, result: Result<(), run::XmrError>) {
+    if let Err(e) = result {
+        if output::json() {
+            output::error(&e);
+        } else {
+            eprintln!("{}: {}", context, e);
+        }
+        std::process::exit(1);
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let matches = Cli::command().get_matches();
+    let command = matches.subcommand_name().unwrap_or_default().to_string();
+    let cli = match Cli::from_arg_matches(&matches) {
+        Ok(cli) => cli,
+        Err(e) => e.exit(),
+    };
+    // Needs no config, so it works before the launcher is set up
+    if let Command::Completions { shell } = &cli.command {
+        print!("{}", cli::completions(*shell));
+        return;
+    }
     
-    if args.len() > 1 {
-        let command = &args[1];
-        
-        match command.as_str() {
-            "init" => {
-                println!("Starting XMR initialization...");
-                
-                match init::initialize() {
-                    Ok(()) => println!("Initialization completed successfully."),
-                    Err(e) => eprintln!("Error during initialization: {}", e),
+    output::set_json(&command, cli.json);
+    
+    // `--name <instance>` and `--config <path>` apply to every command
+    if let Some(name) = &cli.name {
+        exit_on_error("Error", config::set_instance(name));
+    }
+    if let Some(path) = &cli.config {
+        config::set_config_file(path);
+    }
+    
+    selfmetrics::begin(&command);
+    
+    let mut config = match config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            exit_on_error("Error loading configuration", Err(e));
+            return;
+        }
+    };
+    config.logging.level = logging::verbosity(&config.logging.level, cli.verbose, cli.quiet);
+    
+    // `--daemon` forks, so it must happen before logging or anything else
+    // starts a thread
+    if let Command::Run { daemon: true, .. }
+    | Command::RunResilient { daemon: true, .. }
+    | Command::RunSuperResilient { daemon: true, .. } = cli.command
+    {
+        match daemon::detach() {
+            Ok(Detached::Parent { pid, output }) => {
+                let log = config::state_dir().map(|dir| dir.join(logging::LOG_FILE_NAME)).unwrap_or_default();
+                if cli.json {
+                    output::ok(serde_json::json!({ "supervisor_pid": pid, "log": log, "output": output }));
+                } else if !cli.quiet {
+                    println!("Supervisor running in the background (PID {}); logging to {}", pid, log.display());
+                    println!("Check on it with `status`, shut it down with `stop`.");
                 }
+                return;
             },
+            Ok(Detached::Daemon) => config.logging.backend = config::LogBackend::File,
+            Err(e) => exit_on_error("Error starting in the background", Err(e)),
+        }
+    }
+    logging::init(&config.logging, &command);
+    http::configure(&config.network);
+    retry::configure(&config.retry);
+    statecheck::run();
+    
+    // Supervisor events as JSON Lines on stdout; human chatter is suppressed
+    let events_json = cli.json;
+    if events_json {
+        events::subscribe(Box::new(events::JsonLinesSink));
+    }
+    // Progress bars and chatter on stdout, off for --json and --quiet
+    let quiet = events_json || cli.quiet;
+    download::set_quiet(quiet);
+    
+    match cli.command {
+        Command::Init { release, download: flags } => {
+            notify::install(&config);
+            if !quiet {
+                println!("Starting XMR initialization...");
+            }
+            
+            let outcome = metered::check_download(&config.network, flags.allow_metered)
+                .and_then(|_| init::initialize(&config.miner, !flags.no_verify, release.as_deref()));
+            let result = provision::finish("init", &config.miner, outcome, flags.result_file.as_deref());
+            exit_on_error("Error during initialization", result);
+            if !quiet {
+                println!("Initialization completed successfully.");
+                // What the miner will run into on this machine, before it tries
+                if let Err(e) = preflight::report(&config, false) {
+                    eprintln!("Error checking this machine: {}", e);
+                }
+            }
+            // Point newcomers at nearby pools before their first run
+            if config.pool.url.is_none()
+                && !quiet
+                && let Err(e) = pool::suggest(false)
+            {
+                eprintln!("Error suggesting pools: {}", e);
+            }
+        },
 
-            "run" => {
+        Command::Update { download: flags } => {
+            notify::install(&config);
+            let outcome = metered::check_download(&config.network, flags.allow_metered)
+                .and_then(|_| update::run(&config, !flags.no_verify));
+            exit_on_error("Error updating", provision::finish("update", &config.miner, outcome, flags.result_file.as_deref()));
+        },
+        
+        Command::Scheduler => exit_on_error("Error running the scheduler", scheduler::run(&config)),
+        
+        Command::Run { daemon, miner } => {
+            let flags = run::RunFlags {
+                miner_args: miner.miner_args,
+                threads: miner.threads,
+                cpus: miner.cpus,
+                strict: miner.strict,
+                profile: miner.profile,
+                existing: registry::Existing::from_flags(miner.adopt, miner.replace),
+            };
+            exit_on_error("Error", run::set_flags(flags, &mut config));
+            if !quiet {
                 println!("Running XMR...");
-                
-                match run::run_xmr() {
-                    Ok(_) => println!("XMR executed successfully."),
-                    Err(e) => {
-                        eprintln!("Error running XMR: {}", e);
-                        std::process::exit(1);
-                    }
-                }
-            },
-            
-            "run-resilient" => {
+            }
+            // Only a supervisor can be reached by `status` and `stop` once detached
+            let result = if daemon { run::run_xmr_resilient(&config) } else { run::run_xmr(&config) };
+            exit_on_error("Error running XMR", result);
+            if !quiet {
+                println!("XMR executed successfully.");
+            }
+        },
+        
+        Command::RunResilient { miner, .. } => {
+            let flags = run::RunFlags {
+                miner_args: miner.miner_args,
+                threads: miner.threads,
+                cpus: miner.cpus,
+                strict: miner.strict,
+                profile: miner.profile,
+                existing: registry::Existing::from_flags(miner.adopt, miner.replace),
+            };
+            exit_on_error("Error", run::set_flags(flags, &mut config));
+            if !quiet {
                 println!("Running XMR in resilient mode (can only be killed with Ctrl+C)...");
-                
-                match run::run_xmr_resilient() {
-                    Ok(_) => println!("XMR resilient mode terminated successfully."),
-                    Err(e) => {
-                        eprintln!("Error running XMR in resilient mode: {}", e);
-                        std::process::exit(1);
-                    }
-                }
-            },
-            
-            "run-super-resilient" => {
+            }
+            exit_on_error("Error running XMR in resilient mode", run::run_xmr_resilient(&config));
+            if !quiet {
+                println!("XMR resilient mode terminated successfully.");
+            }
+        },
+        
+        Command::RunSuperResilient { miner, .. } => {
+            let flags = run::RunFlags {
+                miner_args: miner.miner_args,
+                threads: miner.threads,
+                cpus: miner.cpus,
+                strict: miner.strict,
+                profile: miner.profile,
+                existing: registry::Existing::from_flags(miner.adopt, miner.replace),
+            };
+            exit_on_error("Error", run::set_flags(flags, &mut config));
+            if !quiet {
                 println!("Running XMR in super-resilient mode (maximum resistance)...");
-                
-                match run::run_xmr_super_resilient() {
-                    Ok(_) => println!("XMR super-resilient mode terminated successfully."),
-                    Err(e) => {
-                        eprintln!("Error running XMR in super-resilient mode: {}", e);
-                        std::process::exit(1);
-                    }
+            }
+            exit_on_error("Error running XMR in super-resilient mode", run::run_xmr_super_resilient(&config));
+            if !quiet {
+                println!("XMR super-resilient mode terminated successfully.");
+            }
+        },
+        
+        Command::InstallService { user, system, manager, super_resilient, no_start, print } => {
+            let scope = match (user, system) {
+                (true, _) => service::Scope::User,
+                (_, true) => service::Scope::System,
+                _ => service::Scope::detect(),
+            };
+            let mode = if super_resilient { "run-super-resilient" } else { "run-resilient" };
+            let service = service::Service { scope, mode };
+            let Some(manager) = manager.or_else(service::Manager::detect) else {
+                exit_on_error(
+                    "Error installing the service",
+                    Err(run::XmrError::EnvError("No supported init system found; pick one with --manager".to_string())),
+                );
+                return;
+            };
+            if print {
+                print!("{}", service::definition(manager, &service));
+            } else {
+                exit_on_error("Error installing the service", service::install(manager, &service, !no_start));
+            }
+        },
+        
+        Command::Logs { follow, lines } => {
+            exit_on_error("Error showing the miner output", minerlog::show(lines, follow));
+        },
+        
+        Command::Pause => {
+            exit_on_error("Error pausing the miner", control::set_paused(true));
+        },
+        
+        Command::Resume => {
+            exit_on_error("Error resuming the miner", control::set_paused(false));
+        },
+        
+        Command::Restart => {
+            let result = control::send(control::Request::Restart).map(|(pid, _)| {
+                if events_json {
+                    output::ok(serde_json::json!({ "supervisor_pid": pid }));
+                } else {
+                    println!("Supervisor {} is restarting the miner with the reloaded configuration.", pid);
                 }
+            });
+            exit_on_error("Error restarting the miner", result);
+        },
+        
+        Command::Stop { timeout } => {
+            exit_on_error("Error stopping the miner", pidfile::stop(std::time::Duration::from_secs(timeout)));
+        },
+        
+        Command::Status { internal } => {
+            let result = match (internal, events_json) {
+                (true, _) => selfmetrics::show(),
+                (false, true) => status::snapshot(&config).map(output::ok),
+                (false, false) => status::show(&config),
+            };
+            exit_on_error("Error", result);
+        },
+        
+        Command::Balance { fiat } => exit_on_error("Error fetching balance", balance::show(&config, fiat.as_deref())),
+        
+        Command::Earnings { by, fiat } => {
+            let result = earnings::Period::parse(&by).and_then(|period| earnings::report(&config, period, fiat.as_deref()));
+            exit_on_error("Error reporting earnings", result);
+        },
+        
+        Command::Profit { hashrate, watts } => {
+            exit_on_error("Error estimating profitability", profit::report(&config, hashrate, watts));
+        },
+        
+        Command::Payouts => exit_on_error("Error listing payouts", payouts::list(&config)),
+        
+        Command::Stats => exit_on_error("Error showing mining statistics", stats::show()),
+        
+        Command::Bandwidth { reset } => exit_on_error("Error reporting bandwidth", bandwidth::report(reset)),
+        
+        Command::Split => exit_on_error("Error showing wallet split", split::status(&config)),
+        
+        Command::Pool { command } => {
+            let result = match command {
+                PoolCommand::Test { pool, tls } => pool::test(&config, pool.as_deref(), tls),
+                PoolCommand::Ping { regional } => pool::ping(&config, regional),
+                PoolCommand::Suggest { geoip } => pool::suggest(geoip),
+                PoolCommand::Add { name, url, port, tls, user, password, make_default } => {
+                    pool::add(&name, &url, port, tls, user, password, make_default)
+                },
+                PoolCommand::List => pool::list(&config),
+                PoolCommand::Remove { name } => pool::remove(&name),
+                PoolCommand::SetDefault { name } => pool::set_default(&name),
+            };
+            exit_on_error("Error", result);
+        },
+        
+        Command::Proxy { listen } => {
+            exit_on_error("Error running the stratum proxy", stratum_proxy::run(&config, listen.as_deref()));
+        },
+        
+        Command::Report { anonymize, output } => {
+            exit_on_error("Error writing the report", report::write(&config, &output, anonymize));
+        },
+        
+        Command::Export { command: ExportCommand::GrafanaDashboard } => {
+            println!("{}", serde_json::to_string_pretty(&grafana::dashboard()).unwrap_or_default());
+        },
+        
+        Command::Export { command: ExportCommand::MinerConfig } => match split::current_target(&config) {
+            Some(target) => {
+                let rendered = miner_config::render(&config, &proxy::route_target(&target));
+                println!("{}", serde_json::to_string_pretty(&rendered).unwrap_or_default());
             },
-            
-            _ => {
-                println!("Unknown command: {}", command);
-                println!("Available commands:");
-                println!("  ./main init - Initialize XMR");
-                println!("  ./main run - Run XMR");
-                println!("  ./main run-resilient - Run XMR in resilient mode (can only be terminated with Ctrl+C)");
-                println!("  ./main run-super-resilient - Run XMR in super-resilient mode (maximum resistance)");
-            }
-        }
-    } else {
-        println!("Hello, world!");
-        println!("Available commands:");
-        println!("  ./main init - Initialize XMR");
-        println!("  ./main run - Run XMR");
-        println!("  ./main run-resilient - Run XMR in resilient mode (can only be terminated with Ctrl+C)");
-        println!("  ./main run-super-resilient - Run XMR in super-resilient mode (maximum resistance)");
+            None => exit_on_error(
+                "Error",
+                Err(run::XmrError::ConfigError("No [pool] url and [wallet] address to render a miner config for".to_string())),
+            ),
+        },
+        
+        Command::Export { command: ExportCommand::SystemdCredentials { from } } => {
+            print!("{}", credentials::unit(from.as_deref()));
+        },
+        
+        Command::Wallet { command: WalletCommand::Set { address } } => {
+            exit_on_error("Error saving the wallet", wallet::set(&address))
+        },
+        Command::Wallet { command: WalletCommand::Show } => exit_on_error("Error", wallet::show(&config)),
+        Command::Wallet { command: WalletCommand::Confirm } => exit_on_error("Error", wallet::confirm_all(&config)),
+        
+        Command::Bench { threads, duration } => {
+            exit_on_error("Error benchmarking", bench::run(&config, threads, std::time::Duration::from_secs(duration)))
+        },
+        
+        Command::Profile { command: ProfileCommand::List } => exit_on_error("Error", profile::list(&config)),
+        Command::Profile { command: ProfileCommand::Set { name, threads, nice, cpu_cap, hours } } => {
+            let changes = config::Profile { threads, nice, cpu_cap, hours };
+            exit_on_error("Error saving the profile", profile::set(&config, &name, changes))
+        },
+        Command::Profile { command: ProfileCommand::Remove { name } } => {
+            exit_on_error("Error removing the profile", profile::remove(&name))
+        },
+        
+        Command::Preflight { strict } => exit_on_error("Error", preflight::report(&config, strict)),
+        
+        Command::Solo => exit_on_error("Error checking the solo mining node", solo::status(&config)),
+        
+        Command::Tune { command: TuneCommand::Hugepages { check, persist, yes } } => {
+            exit_on_error("Error tuning huge pages", tune::hugepages(&config, check, persist, yes))
+        },
+        Command::Tune { command: TuneCommand::Msr { dry_run, revert, yes } } => {
+            exit_on_error("Error tuning MSRs", tune::msr(dry_run, revert, yes))
+        },
+        
+        // Printed before the config is loaded
+        Command::Completions { .. } => {},
+        
+        Command::Node { command } => {
+            let result = match command {
+                Some(ServiceCommand::Init { allow_metered }) => {
+                    metered::check_download(&config.network, allow_metered).and_then(|_| node::init())
+                },
+                Some(ServiceCommand::Run) => node::run(&config),
+                Some(ServiceCommand::Status) | None => node::status(&config),
+            };
+            exit_on_error("Error managing the node", result);
+        },
+        
+        Command::P2pool { command } => {
+            let result = match command {
+                Some(ServiceCommand::Init { allow_metered }) => {
+                    metered::check_download(&config.network, allow_metered).and_then(|_| p2pool::init())
+                },
+                Some(ServiceCommand::Run) => p2pool::run(&config),
+                Some(ServiceCommand::Status) | None => p2pool::status(&config),
+            };
+            exit_on_error("Error running p2pool", result);
+        },
     }
+    selfmetrics::finish();
+    output::finish();
 }
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, Config, MarketConfig};
+use crate::events::{self, Event};
+use crate::pause;
+use crate::http;
+use crate::run::XmrError;
+
+const COINGECKO_PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price?ids=monero";
+pub const PRICE_CACHE_FILE: &str = "prices.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedPrice {
+    price: f64,
+    fetched_at: i64,
+}
+
+fn cache_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(PRICE_CACHE_FILE))
+}
+
+fn load_cache() -> HashMap<String, CachedPrice> {
+    cache_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(cache: &HashMap<String, CachedPrice>) -> Result<(), XmrError> {
+    let path = cache_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(cache)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode price cache: {}", e)))?;
+    config::write_atomic(&path, &contents)?;
+    Ok(())
+}
+
+fn fetch_price(currency: &str) -> Result<f64, XmrError> {
+    let url = format!("{}&vs_currencies={}", COINGECKO_PRICE_URL, currency);
+    let response = http::get_json(&url)?;
+
+    response["monero"][currency]
+        .as_f64()
+        .ok_or_else(|| XmrError::NetworkError(format!("No XMR price available in {}", currency)))
+}
+
+// Current XMR price in the given fiat currency (ISO code such as "usd").
+// Prices are cached on disk so CoinGecko is asked at most once per
+// `cache_secs` per currency; a stale price beats none when the API is down.
+pub fn xmr_price(config: &MarketConfig, currency: &str) -> Result<f64, XmrError> {
+    let currency = currency.to_lowercase();
+    let now = Utc::now().timestamp();
+    let mut cache = load_cache();
+
+    if let Some(cached) = cache.get(&currency)
+        && now - cached.fetched_at < config.cache_secs as i64
+    {
+        debug!("Using cached XMR price for {}", currency);
+        return Ok(cached.price);
+    }
+
+    match fetch_price(&currency) {
+        Ok(price) => {
+            cache.insert(currency, CachedPrice { price, fetched_at: now });
+            if let Err(e) = save_cache(&cache) {
+                warn!("Could not save price cache: {}", e);
+            }
+            Ok(price)
+        },
+        Err(e) => match cache.get(&currency) {
+            Some(cached) => {
+                warn!("Price API unavailable ({}), using price from {} seconds ago", e, now - cached.fetched_at);
+                Ok(cached.price)
+            },
+            None => Err(e),
+        },
+    }
+}
+
+// Alert thresholds crossed between two consecutive price readings
+fn crossed(previous: f64, current: f64, above: &[f64], below: &[f64]) -> Vec<(f64, &'static str)> {
+    let rising = above
+        .iter()
+        .filter(|&&t| previous < t && current >= t)
+        .map(|&t| (t, "above"));
+    let falling = below
+        .iter()
+        .filter(|&&t| previous > t && current <= t)
+        .map(|&t| (t, "below"));
+    rising.chain(falling).collect()
+}
+
+// Background price poller for the supervised run modes: raises alerts when
+// a configured threshold is crossed and enforces the optional price floor
+pub fn start_price_watch(config: &Config, running: Arc<AtomicBool>) {
+    let market = config.market.clone();
+    let watching = !market.alert_above.is_empty() || !market.alert_below.is_empty() || market.min_price.is_some();
+    if !watching {
+        return;
+    }
+
+    let currency = market.fiat.clone().unwrap_or_else(|| "usd".to_string());
+    let interval = Duration::from_secs(market.poll_mins.max(1) * 60);
+    info!("Watching the XMR price in {} every {} minutes", currency.to_uppercase(), market.poll_mins.max(1));
+
+    thread::spawn(move || {
+        let mut previous: Option<f64> = None;
+        while running.load(Ordering::SeqCst) {
+            match xmr_price(&market, &currency) {
+                Ok(price) => {
+                    if let Some(previous) = previous {
+                        for (threshold, direction) in crossed(previous, price, &market.alert_above, &market.alert_below) {
+                            events::emit(Event::PriceAlert {
+                                price,
+                                currency: currency.clone(),
+                                threshold,
+                                direction: direction.to_string(),
+                            });
+                        }
+                    }
+                    if let Some(floor) = market.min_price {
+                        pause::set("price floor", price < floor);
+                    }
+                    previous = Some(price);
+                },
+                Err(e) => warn!("Could not check the XMR price: {}", e),
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < interval && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_crossing() {
+        assert_eq!(crossed(150.0, 210.0, &[200.0], &[]), vec![(200.0, "above")]);
+        assert_eq!(crossed(210.0, 190.0, &[200.0], &[]), vec![]);
+        assert_eq!(crossed(130.0, 110.0, &[], &[120.0]), vec![(120.0, "below")]);
+        assert_eq!(crossed(110.0, 115.0, &[], &[120.0]), vec![]);
+    }
+}
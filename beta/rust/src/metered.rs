@@ -0,0 +1,99 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info};
+
+use crate::config::{Config, Metered, NetworkConfig};
+use crate::pause;
+use crate::run::XmrError;
+
+const PAUSE_REASON: &str = "metered connection";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// NetworkManager's global Metered property as printed by busctl ("u 1"):
+// 1 = yes, 3 = guessed yes, 2 / 4 = (guessed) no, 0 = unknown
+fn parse_metered(output: &str) -> Option<bool> {
+    match output.trim().strip_prefix("u ")?.trim() {
+        "1" | "3" => Some(true),
+        "2" | "4" => Some(false),
+        _ => None,
+    }
+}
+
+fn network_manager_metered() -> Option<bool> {
+    let output = Command::new("busctl")
+        .args([
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        debug!("NetworkManager metered state unavailable");
+        return None;
+    }
+    parse_metered(&String::from_utf8_lossy(&output.stdout))
+}
+
+// Unknown counts as unmetered so machines without NetworkManager behave as before
+pub fn is_metered(network: &NetworkConfig) -> bool {
+    match network.metered {
+        Metered::Yes => true,
+        Metered::No => false,
+        Metered::Auto => network_manager_metered().unwrap_or(false),
+    }
+}
+
+// Refuse large downloads on a metered connection unless the user insists
+pub fn check_download(network: &NetworkConfig, allow: bool) -> Result<(), XmrError> {
+    if allow || !network.metered_defer_downloads || !is_metered(network) {
+        return Ok(());
+    }
+    Err(XmrError::NetworkError(
+        "On a metered connection; run again on another network or pass --allow-metered".to_string(),
+    ))
+}
+
+// Supervised modes: keep mining paused while the connection is metered
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    if !config.network.metered_pause {
+        return;
+    }
+
+    let network = config.network.clone();
+    info!("Mining pauses while on a metered connection");
+    pause::set(PAUSE_REASON, is_metered(&network));
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            let mut waited = Duration::ZERO;
+            while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+            if running.load(Ordering::SeqCst) {
+                pause::set(PAUSE_REASON, is_metered(&network));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metered() {
+        assert_eq!(parse_metered("u 1\n"), Some(true));
+        assert_eq!(parse_metered("u 3"), Some(true));
+        assert_eq!(parse_metered("u 4"), Some(false));
+        assert_eq!(parse_metered("u 0"), None);
+        assert_eq!(parse_metered(""), None);
+    }
+}
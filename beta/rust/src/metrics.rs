@@ -0,0 +1,132 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::bandwidth;
+use crate::config::Config;
+use crate::events::{self, Event, Sink};
+use crate::pause;
+use crate::selfmetrics;
+use crate::status;
+use crate::telemetry;
+
+// Metric names shared by the exporter and the Grafana dashboard
+pub const HASHRATE: &str = "minning_hashrate";
+pub const SHARES_ACCEPTED: &str = "minning_shares_accepted_total";
+pub const SHARES_REJECTED: &str = "minning_shares_rejected_total";
+pub const TEMPERATURE: &str = "minning_cpu_temperature_celsius";
+pub const RESTARTS: &str = "minning_miner_restarts_total";
+pub const PAUSED: &str = "minning_paused";
+pub const TRAFFIC_SENT: &str = "minning_traffic_sent_bytes_total";
+pub const TRAFFIC_RECEIVED: &str = "minning_traffic_received_bytes_total";
+pub const LAUNCHER_CPU: &str = "minning_launcher_cpu_seconds_total";
+pub const LAUNCHER_RSS: &str = "minning_launcher_resident_bytes";
+
+// Miner processes started by this supervisor; all but the first are restarts
+static STARTS: AtomicU64 = AtomicU64::new(0);
+
+struct RestartCounter;
+
+impl Sink for RestartCounter {
+    fn handle(&self, event: &Event) {
+        if let Event::ChildStarted { .. } = event {
+            STARTS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn sample(out: &mut String, name: &str, help: &str, kind: &str, labels: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+}
+
+// Prometheus text exposition of the current supervisor state
+fn render(config: &Config, labels: &str) -> String {
+    let mut out = String::new();
+    if let Ok(miner) = status::query(&config.miner.api_url) {
+        if let Some(hashrate) = miner.hashrate[0].or(miner.hashrate[1]) {
+            sample(&mut out, HASHRATE, "Miner hashrate in H/s (10s average)", "gauge", labels, hashrate);
+        }
+        sample(&mut out, SHARES_ACCEPTED, "Shares accepted by the pool", "counter", labels, miner.accepted as f64);
+        sample(&mut out, SHARES_REJECTED, "Shares rejected by the pool", "counter", labels, miner.rejected as f64);
+    }
+    if let Some(temperature) = telemetry::cpu_temperature() {
+        sample(&mut out, TEMPERATURE, "CPU package temperature", "gauge", labels, temperature);
+    }
+    let restarts = STARTS.load(Ordering::Relaxed).saturating_sub(1);
+    sample(&mut out, RESTARTS, "Miner restarts by the watchdog", "counter", labels, restarts as f64);
+    let paused = if pause::is_paused() { 1.0 } else { 0.0 };
+    sample(&mut out, PAUSED, "1 while mining is paused", "gauge", labels, paused);
+    let traffic = bandwidth::load();
+    sample(&mut out, TRAFFIC_SENT, "Bytes the miner sent", "counter", labels, traffic.sent as f64);
+    sample(&mut out, TRAFFIC_RECEIVED, "Bytes the miner received", "counter", labels, traffic.received as f64);
+    let launcher = selfmetrics::current();
+    sample(&mut out, LAUNCHER_CPU, "CPU time used by the launcher itself", "counter", labels, launcher.cpu_secs);
+    sample(&mut out, LAUNCHER_RSS, "Resident memory of the launcher itself", "gauge", labels, launcher.rss_bytes as f64);
+    out
+}
+
+fn respond(mut stream: TcpStream, config: &Config, labels: &str) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, body) = if path == "/metrics" {
+        ("200 OK", render(config, labels))
+    } else {
+        ("404 Not Found", "Metrics are served at /metrics\n".to_string())
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+// Supervised modes: serve Prometheus metrics on [metrics] listen
+pub fn start(config: &Config) {
+    if !config.metrics.enabled {
+        return;
+    }
+
+    let listener = match TcpListener::bind(&config.metrics.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Could not serve metrics on {}: {}", config.metrics.listen, e);
+            return;
+        },
+    };
+    info!("Serving Prometheus metrics on http://{}/metrics", config.metrics.listen);
+    events::subscribe(Box::new(RestartCounter));
+
+    let config = config.clone();
+    let labels = format!("instance_name=\"{}\",host=\"{}\"", config.logging.instance, telemetry::hostname());
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            if let Err(e) = respond(stream, &config, &labels) {
+                debug!("Metrics request failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_format() {
+        let mut out = String::new();
+        sample(&mut out, PAUSED, "1 while mining is paused", "gauge", "host=\"rig\"", 0.0);
+        assert_eq!(out, "# HELP minning_paused 1 while mining is paused\n# TYPE minning_paused gauge\nminning_paused{host=\"rig\"} 0\n");
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::config::{self, Config};
+use crate::cpu;
+use crate::run::XmrError;
+use crate::split::Target;
+
+// Written next to the other state; it holds the pool logins
+pub const CONFIG_FILE: &str = "miner-config.json";
+
+// Host and port of the miner's HTTP API from [miner] api_url
+pub fn api_address(api_url: &str) -> Option<(String, u16)> {
+    let address = api_url.split_once("://").map_or(api_url, |(_, rest)| rest);
+    let address = address.split('/').next()?;
+    let (host, port) = address.rsplit_once(':')?;
+    Some((host.trim_matches(['[', ']']).to_string(), port.parse().ok()?))
+}
+
+fn pools(target: &Target) -> Vec<Value> {
+    std::iter::once(&target.pool)
+        .chain(&target.backups)
+        .enumerate()
+        .map(|(index, url)| {
+            let (user, password, tls) = target.login(index);
+            json!({
+                "url": url,
+                "user": user,
+                "pass": password,
+                "tls": tls,
+                "daemon": target.daemon,
+                "socks5": target.proxy,
+                "keepalive": true,
+                "enabled": true,
+            })
+        })
+        .collect()
+}
+
+// The miner's own config.json for `target`, with everything [miner] tunes.
+// Autosave stays off so the miner never rewrites it with its own defaults.
+pub fn render(config: &Config, target: &Target) -> Value {
+    let mut cpu = json!({
+        "enabled": true,
+        "huge-pages": config.miner.huge_pages,
+    });
+    if let Some(threads) = cpu::threads(&config.miner) {
+        // One entry per thread, -1 leaving its placement to the OS
+        cpu["rx"] = json!(vec![-1; threads]);
+    }
+
+    let http = match api_address(&config.miner.api_url) {
+        Some((host, port)) => json!({ "enabled": true, "host": host, "port": port, "restricted": true }),
+        None => {
+            warn!("Cannot tell host and port from [miner] api_url {}; the miner API stays off", config.miner.api_url);
+            json!({ "enabled": false })
+        },
+    };
+
+    json!({
+        "autosave": false,
+        "background": false,
+        "colors": false,
+        "http": http,
+        "cpu": cpu,
+        "randomx": { "1gb-pages": false },
+        "pools": pools(target),
+    })
+}
+
+// miner-config.json, or miner-config-node<N>.json for a NUMA node's miner
+pub fn path(config: &Config) -> Result<PathBuf, XmrError> {
+    let name = match config.miner.numa_node {
+        Some(node) => CONFIG_FILE.replace(".json", &format!("-node{}.json", node)),
+        None => CONFIG_FILE.to_string(),
+    };
+    Ok(config::state_dir()?.join(name))
+}
+
+// Render the config for `target` to the state directory, readable only by
+// this user since it carries the pool passwords
+pub fn write(config: &Config, target: &Target) -> Result<PathBuf, XmrError> {
+    let path = path(config)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    #[cfg(unix)]
+    if !path.exists() {
+        use std::os::unix::fs::OpenOptionsExt;
+        fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+    }
+    let contents = serde_json::to_string_pretty(&render(config, target)).map_err(std::io::Error::other)?;
+    config::write_atomic(&path, &contents)?;
+    debug!("Wrote the miner config to {}", path.display());
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_address() {
+        assert_eq!(api_address("http://127.0.0.1:18088"), Some(("127.0.0.1".to_string(), 18088)));
+        assert_eq!(api_address("http://[::1]:8080/"), Some(("::1".to_string(), 8080)));
+        assert_eq!(api_address("http://localhost"), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let config: Config = toml::from_str(
+            "[wallet]\naddress = \"4abc\"\n\n[pool]\nurl = \"a:3333\"\nfailover = [\"b:443\"]\n\n[miner]\nthreads = 2\nhuge_pages = false\n",
+        )
+        .unwrap();
+        let target = crate::split::primary_target(&config).unwrap();
+        let rendered = render(&config, &target);
+        assert_eq!(rendered["pools"][0]["url"], "a:3333");
+        assert_eq!(rendered["pools"][1]["user"], "4abc");
+        assert_eq!(rendered["cpu"]["rx"], json!([-1, -1]));
+        assert_eq!(rendered["cpu"]["huge-pages"], false);
+        assert_eq!(rendered["http"]["port"], 18088);
+        assert_eq!(rendered["autosave"], false);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use serde_json::json;
+use tracing::warn;
+
+use crate::config::{self, LoggingConfig};
+use crate::output;
+use crate::run::XmrError;
+
+// Each run's miner output goes to its own file in this state directory
+// folder, named after the local time the run started, e.g.
+// miner-logs/miner-20261016-113922.log
+pub const MINER_LOG_DIR: &str = "miner-logs";
+const PREFIX: &str = "miner-";
+const SUFFIX: &str = ".log";
+// How often `logs --follow` looks for new output
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Capture {
+    file: Option<File>,
+    // Echo to stderr as "[xmr] <line>"
+    echo: bool,
+}
+
+static CAPTURE: Mutex<Capture> = Mutex::new(Capture { file: None, echo: false });
+
+fn dir() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(MINER_LOG_DIR))
+}
+
+// Run files, oldest first; the timestamped names sort by start time
+fn runs(dir: &Path) -> Vec<PathBuf> {
+    let mut runs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(PREFIX) && name.ends_with(SUFFIX))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    runs.sort();
+    runs
+}
+
+// Output file of the current or last run
+pub fn latest() -> Option<PathBuf> {
+    runs(&dir().ok()?).pop()
+}
+
+fn open_run(keep: usize) -> Result<File, XmrError> {
+    let dir = dir()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}{}{}", PREFIX, Local::now().format("%Y%m%d-%H%M%S"), SUFFIX));
+    let file = File::options().create(true).append(true).open(path)?;
+
+    // The new file counts towards `keep`
+    let runs = runs(&dir);
+    for old in &runs[..runs.len().saturating_sub(keep.max(1))] {
+        let _ = fs::remove_file(old);
+    }
+    Ok(file)
+}
+
+// Start capturing the miner's output for a new run
+pub fn start_run(config: &LoggingConfig) {
+    let file = match open_run(config.miner_runs) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            warn!("Could not capture miner output: {}", e);
+            None
+        },
+    };
+    if let Ok(mut capture) = CAPTURE.lock() {
+        // Not into --json output
+        *capture = Capture { file, echo: config.echo_miner && !output::json() };
+    }
+}
+
+// One line of miner output, from any watchdog's child
+pub fn record(line: &str) {
+    let Ok(mut capture) = CAPTURE.lock() else {
+        return;
+    };
+    if let Some(file) = capture.file.as_mut() {
+        let _ = writeln!(file, "{}", line);
+    }
+    if capture.echo {
+        eprintln!("[xmr] {}", line);
+    }
+}
+
+// The last `count` lines, without holding the whole file in memory
+fn last_lines(reader: impl BufRead, count: usize) -> Vec<String> {
+    let mut lines = VecDeque::with_capacity(count);
+    for line in reader.lines().map_while(Result::ok) {
+        if lines.len() == count {
+            lines.pop_front();
+        }
+        if count > 0 {
+            lines.push_back(line);
+        }
+    }
+    lines.into()
+}
+
+// The end of the current or last run's output, e.g. for an error message
+pub fn tail(count: usize) -> Vec<String> {
+    latest()
+        .and_then(|path| File::open(path).ok())
+        .map(|file| last_lines(BufReader::new(file), count))
+        .unwrap_or_default()
+}
+
+// `logs`: print the end of the captured output and, with `follow`, keep
+// printing what the miner adds, moving on to the next run's file when a new
+// run starts
+pub fn show(count: usize, follow: bool) -> Result<(), XmrError> {
+    let Some(mut path) = latest() else {
+        return Err(XmrError::EnvError(format!(
+            "No miner output captured yet in {}; start a run first",
+            dir()?.display()
+        )));
+    };
+    let mut file = File::open(&path)?;
+    let lines = last_lines(BufReader::new(&file), count);
+    if output::json() && !follow {
+        output::ok(json!({ "file": path, "lines": lines }));
+        return Ok(());
+    }
+    for line in &lines {
+        println!("{}", line);
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut position = file.stream_position()?;
+    let mut buf = Vec::new();
+    loop {
+        thread::sleep(FOLLOW_INTERVAL);
+        // Once a new run has started, finish this run's file and carry on
+        // with the next one
+        let next = latest().filter(|next| *next != path);
+        file.seek(SeekFrom::Start(position))?;
+        buf.clear();
+        position += file.read_to_end(&mut buf)? as u64;
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&buf)?;
+        stdout.flush()?;
+
+        if let Some(next) = next {
+            file = File::open(&next)?;
+            path = next;
+            position = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_lines() {
+        let text = "one\ntwo\nthree\n";
+        assert_eq!(last_lines(text.as_bytes(), 2), vec!["two", "three"]);
+        assert_eq!(last_lines(text.as_bytes(), 10), vec!["one", "two", "three"]);
+        assert!(last_lines(text.as_bytes(), 0).is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use rumqttc::{Client, LastWill, MqttOptions, QoS, TlsConfiguration, Transport};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ClientConfig, RootCertStore};
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use crate::bandwidth;
+use crate::config::{Config, MqttConfig};
+use crate::events::{self, Event, Sink};
+use crate::pause;
+use crate::run::XmrError;
+use crate::selfmetrics;
+use crate::stats;
+use crate::status;
+use crate::telemetry;
+
+// Last state published, repeated in the JSON telemetry
+static STATE: Mutex<&str> = Mutex::new("starting");
+
+// Publishes supervisor state as soon as it changes
+struct MqttSink {
+    client: Client,
+    prefix: String,
+}
+
+impl Sink for MqttSink {
+    fn handle(&self, event: &Event) {
+        let state = match event {
+            Event::ChildStarted { pid } => {
+                publish(&self.client, &format!("{}/pid", self.prefix), &pid.to_string());
+                "running"
+            },
+            Event::ChildExited { .. } | Event::Restarting { .. } | Event::PoolFailover { .. } => "restarting",
+            Event::Paused { .. } => "paused",
+            Event::Resumed { .. } => "resuming",
+            Event::Shutdown => "stopped",
+            Event::HealthCheckFailed { .. }
+            | Event::Alert { .. }
+            | Event::PayoutReceived { .. }
+            | Event::PriceAlert { .. }
+            | Event::InstallCompleted { .. }
+            | Event::InstallFailed { .. } => return,
+        };
+        if let Ok(mut current) = STATE.lock() {
+            *current = state;
+        }
+        publish(&self.client, &format!("{}/state", self.prefix), state);
+    }
+}
+
+fn publish(client: &Client, topic: &str, payload: &str) {
+    if let Err(e) = client.publish(topic, QoS::AtLeastOnce, true, payload) {
+        warn!("Failed to queue MQTT message for {}: {}", topic, e);
+    }
+}
+
+fn topic_prefix(config: &MqttConfig, host: &str) -> String {
+    match &config.topic_prefix {
+        Some(prefix) => prefix.trim_end_matches('/').to_string(),
+        None => format!("minning/{}", host),
+    }
+}
+
+// Home Assistant MQTT discovery: one retained config message per sensor
+fn publish_discovery(client: &Client, config: &MqttConfig, host: &str, prefix: &str) {
+    let device = json!({
+        "identifiers": [format!("minning_{}", host)],
+        "name": format!("Minning {}", host),
+        "manufacturer": "Minning",
+    });
+    let availability_topic = format!("{}/availability", prefix);
+
+    let sensors = [
+        ("state", "Miner state", None, None),
+        ("hashrate", "Hashrate", Some("H/s"), None),
+        ("temperature", "CPU temperature", Some("°C"), Some("temperature")),
+        ("upload", "Miner upload", Some("B"), Some("data_size")),
+        ("download", "Miner download", Some("B"), Some("data_size")),
+    ];
+
+    for (object, name, unit, device_class) in sensors {
+        let mut payload = json!({
+            "name": name,
+            "unique_id": format!("minning_{}_{}", host, object),
+            "state_topic": format!("{}/{}", prefix, object),
+            "availability_topic": availability_topic,
+            "device": device,
+        });
+        if let Some(unit) = unit {
+            payload["unit_of_measurement"] = json!(unit);
+            payload["state_class"] = json!("measurement");
+        }
+        if let Some(device_class) = device_class {
+            payload["device_class"] = json!(device_class);
+        }
+
+        let topic = format!("{}/sensor/minning_{}/{}/config", config.discovery_prefix, host, object);
+        publish(client, &topic, &payload.to_string());
+    }
+}
+
+// rustls settings for [mqtt] tls: the configured CA bundle or the built-in
+// roots, plus a client certificate when one is configured
+fn tls_config(config: &MqttConfig) -> Result<ClientConfig, XmrError> {
+    let pem_error = |path: &str, e: &dyn std::fmt::Display| XmrError::ConfigError(format!("[mqtt] {}: {}", path, e));
+    let mut roots = RootCertStore::empty();
+    match &config.ca_file {
+        Some(path) => {
+            let contents = fs::read(path).map_err(|e| pem_error(path, &e))?;
+            for cert in CertificateDer::pem_slice_iter(&contents) {
+                roots.add(cert.map_err(|e| pem_error(path, &e))?).map_err(|e| pem_error(path, &e))?;
+            }
+        },
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| XmrError::ConfigError(format!("[mqtt] TLS: {}", e)))?
+        .with_root_certificates(roots);
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert), Some(key)) => {
+            let contents = fs::read(cert).map_err(|e| pem_error(cert, &e))?;
+            let chain = CertificateDer::pem_slice_iter(&contents)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| pem_error(cert, &e))?;
+            let key_der = PrivateKeyDer::from_pem_file(key).map_err(|e| pem_error(key, &e))?;
+            builder
+                .with_client_auth_cert(chain, key_der)
+                .map_err(|e| XmrError::ConfigError(format!("[mqtt] client certificate: {}", e)))
+        },
+        (None, None) => Ok(builder.with_no_client_auth()),
+        _ => Err(XmrError::ConfigError("[mqtt] client_cert and client_key go together".to_string())),
+    }
+}
+
+// Everything the supervisor knows right now, as one JSON document
+fn telemetry(api_url: &str, host: &str) -> serde_json::Value {
+    let miner = status::query(api_url).ok();
+    let traffic = bandwidth::load();
+    json!({
+        "timestamp": Utc::now().timestamp(),
+        "host": host,
+        "state": STATE.lock().map(|state| *state).unwrap_or("unknown"),
+        "paused": pause::reasons(),
+        "hashrate": miner.as_ref().and_then(|m| m.hashrate[0].or(m.hashrate[1])),
+        "pool": miner.as_ref().and_then(|m| m.pool.clone()),
+        "accepted": miner.as_ref().map(|m| m.accepted),
+        "rejected": miner.as_ref().map(|m| m.rejected),
+        "temperature": telemetry::cpu_temperature(),
+        "restarts": stats::session_restarts(),
+        "upload": traffic.sent,
+        "download": traffic.received,
+    })
+}
+
+// Connect to the broker and keep publishing telemetry until `running` clears
+pub fn start(config: &Config, running: Arc<AtomicBool>) {
+    let mqtt = &config.mqtt;
+    if !mqtt.enabled {
+        return;
+    }
+
+    let host = telemetry::hostname();
+    let prefix = topic_prefix(mqtt, &host);
+    let availability_topic = format!("{}/availability", prefix);
+
+    let mut options = MqttOptions::new(format!("minning-{}", host), mqtt.host.clone(), mqtt.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(&availability_topic, "offline", QoS::AtLeastOnce, true));
+    if let Some(username) = &mqtt.username {
+        options.set_credentials(username.clone(), mqtt.password.clone().unwrap_or_default());
+    }
+    if mqtt.tls {
+        match tls_config(mqtt) {
+            Ok(tls) => options.set_transport(Transport::tls_with_config(TlsConfiguration::Rustls(Arc::new(tls)))),
+            // Never fall back to sending credentials in the clear
+            Err(e) => {
+                warn!("Not publishing MQTT telemetry: {}", e);
+                return;
+            },
+        };
+    }
+
+    let (client, mut connection) = Client::new(options, 32);
+    let scheme = if mqtt.tls { "mqtts" } else { "mqtt" };
+    info!("Publishing MQTT telemetry to {}://{}:{} under {}", scheme, mqtt.host, mqtt.port, prefix);
+
+    // The connection has to be polled for anything to go out on the wire;
+    // the iterator reconnects on its own after errors
+    thread::spawn(move || {
+        for notification in connection.iter() {
+            if let Err(e) = notification {
+                debug!("MQTT connection error: {}", e);
+                thread::sleep(Duration::from_secs(5));
+            }
+        }
+    });
+
+    if mqtt.discovery {
+        publish_discovery(&client, mqtt, &host, &prefix);
+    }
+    publish(&client, &availability_topic, "online");
+
+    events::subscribe(Box::new(MqttSink { client: client.clone(), prefix: prefix.clone() }));
+
+    let interval = Duration::from_secs(mqtt.interval_secs.max(1));
+    let api_url = config.miner.api_url.clone();
+    let telemetry_topic = mqtt.telemetry_topic.clone().unwrap_or_else(|| format!("{}/telemetry", prefix));
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            // The JSON document for aggregators, and its values one per
+            // topic for Home Assistant
+            let telemetry = telemetry(&api_url, &host);
+            publish(&client, &telemetry_topic, &telemetry.to_string());
+            if let Some(hashrate) = telemetry["hashrate"].as_f64() {
+                publish(&client, &format!("{}/hashrate", prefix), &format!("{:.1}", hashrate));
+            }
+            if let Some(temperature) = telemetry["temperature"].as_f64() {
+                publish(&client, &format!("{}/temperature", prefix), &format!("{:.1}", temperature));
+            }
+            publish(&client, &format!("{}/upload", prefix), &telemetry["upload"].to_string());
+            publish(&client, &format!("{}/download", prefix), &telemetry["download"].to_string());
+            let launcher = selfmetrics::current();
+            let launcher = json!({ "cpu_secs": launcher.cpu_secs, "rss_bytes": launcher.rss_bytes, "threads": launcher.threads });
+            publish(&client, &format!("{}/launcher", prefix), &launcher.to_string());
+            thread::sleep(interval);
+        }
+
+        publish(&client, &availability_topic, "offline");
+    });
+}
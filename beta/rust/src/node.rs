@@ -0,0 +1,258 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+
+use crate::config::{self, Config};
+use crate::download;
+use crate::http;
+use crate::run::{self, XmrError};
+use crate::sidecar::Sidecar;
+
+// Official release listing; every archive name is followed by its SHA-256
+const HASHES_URL: &str = "https://www.getmonero.org/downloads/hashes.txt";
+const DOWNLOAD_BASE_URL: &str = "https://downloads.getmonero.org/cli";
+const VERSION_FILE: &str = "version";
+
+// The subset of monerod's /get_info response the launcher cares about
+#[derive(Debug, Clone)]
+pub struct NodeInfo {
+    pub height: u64,
+    // Height the node is syncing towards; 0 once caught up
+    pub target_height: u64,
+    pub synchronized: bool,
+    pub offline: bool,
+    pub outgoing_connections: u64,
+    pub incoming_connections: u64,
+    pub nettype: String,
+}
+
+impl NodeInfo {
+    // Ready to hand out block templates
+    pub fn is_ready(&self) -> bool {
+        self.synchronized && !self.offline
+    }
+
+    pub fn sync_percent(&self) -> f64 {
+        if self.target_height <= self.height {
+            100.0
+        } else {
+            self.height as f64 / self.target_height as f64 * 100.0
+        }
+    }
+}
+
+// Base URL for a node given as "host:port" or a full URL
+pub fn rpc_url(node: &str) -> String {
+    if node.starts_with("http://") || node.starts_with("https://") {
+        node.trim_end_matches('/').to_string()
+    } else {
+        format!("http://{}", node.trim_end_matches('/'))
+    }
+}
+
+pub fn get_info(node: &str) -> Result<NodeInfo, XmrError> {
+    let url = format!("{}/get_info", rpc_url(node));
+    let info = http::get_json_with_timeout(&url, Duration::from_secs(5))?;
+
+    if let Some(status) = info["status"].as_str()
+        && status != "OK"
+    {
+        return Err(XmrError::NetworkError(format!("Node at {} answered with status {}", node, status)));
+    }
+
+    Ok(NodeInfo {
+        height: info["height"].as_u64().unwrap_or(0),
+        target_height: info["target_height"].as_u64().unwrap_or(0),
+        synchronized: info["synchronized"].as_bool().unwrap_or(false),
+        offline: info["offline"].as_bool().unwrap_or(false),
+        outgoing_connections: info["outgoing_connections_count"].as_u64().unwrap_or(0),
+        incoming_connections: info["incoming_connections_count"].as_u64().unwrap_or(0),
+        nettype: info["nettype"].as_str().unwrap_or("mainnet").to_string(),
+    })
+}
+
+// Human-readable node state for status output
+pub fn print_info(node: &str, info: &Result<NodeInfo, XmrError>) {
+    println!("Node:          {}", node);
+    match info {
+        Ok(info) => {
+            let state = if info.offline {
+                "offline".to_string()
+            } else if info.synchronized {
+                "synchronized".to_string()
+            } else {
+                format!("syncing ({:.1}%)", info.sync_percent())
+            };
+            println!("State:         {}", state);
+            println!("Network:       {}", info.nettype);
+            println!("Height:        {}", info.height);
+            if info.target_height > info.height {
+                println!("Target height: {}", info.target_height);
+            }
+            println!("Connections:   {} out, {} in", info.outgoing_connections, info.incoming_connections);
+        },
+        Err(e) => println!("State:         unreachable ({})", e),
+    }
+}
+
+fn install_dir() -> Result<PathBuf, XmrError> {
+    Ok(config::data_dir()?.join("node"))
+}
+
+fn release_dir() -> Result<PathBuf, XmrError> {
+    Ok(install_dir()?.join("release"))
+}
+
+fn blockchain_dir(config: &Config) -> Result<PathBuf, XmrError> {
+    match &config.node.data_dir {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => Ok(install_dir()?.join("blockchain")),
+    }
+}
+
+fn binary_path() -> Option<PathBuf> {
+    download::find_binary(&release_dir().ok()?, "monerod")
+}
+
+fn installed_version() -> Option<String> {
+    let contents = fs::read_to_string(install_dir().ok()?.join(VERSION_FILE)).ok()?;
+    Some(contents.trim().to_string())
+}
+
+// Platform part of the official archive names, e.g. monero-linux-x64-v0.18.3.4.tar.bz2
+fn platform() -> Result<&'static str, XmrError> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("linux-x64"),
+        ("linux", "aarch64") => Ok("linux-armv8"),
+        ("macos", "x86_64") => Ok("mac-x64"),
+        ("macos", "aarch64") => Ok("mac-armv8"),
+        (os, arch) => Err(XmrError::EnvError(format!("No monerod release for {}-{}", os, arch))),
+    }
+}
+
+// CLI archive for `platform` listed in the hashes file
+fn archive_name(listing: &str, platform: &str) -> Option<String> {
+    let prefix = format!("monero-{}-v", platform);
+    listing
+        .split_whitespace()
+        .map(|token| token.trim_end_matches(','))
+        .find(|token| token.starts_with(&prefix) && token.ends_with(".tar.bz2"))
+        .map(str::to_string)
+}
+
+// Download the current monerod release and check it against the published
+// hashes before unpacking it into the data directory
+pub fn init() -> Result<(), XmrError> {
+    let platform = platform()?;
+    let listing = download::fetch_text(HASHES_URL)?;
+    let archive_name = archive_name(&listing, platform)
+        .ok_or_else(|| XmrError::NetworkError(format!("No {} archive listed in {}", platform, HASHES_URL)))?;
+    if installed_version().as_deref() == Some(archive_name.as_str()) && binary_path().is_some() {
+        println!("{} is already installed", archive_name);
+        return Ok(());
+    }
+    let expected = download::checksum_for(&listing, &archive_name)
+        .ok_or_else(|| XmrError::NetworkError(format!("No checksum published for {}", archive_name)))?;
+
+    let dir = install_dir()?;
+    fs::create_dir_all(&dir)?;
+    let archive = dir.join(&archive_name);
+    download::fetch(&format!("{}/{}", DOWNLOAD_BASE_URL, archive_name), &archive)?;
+    download::verify(&archive, &expected)?;
+
+    let release_dir = release_dir()?;
+    if release_dir.exists() {
+        fs::remove_dir_all(&release_dir)?;
+    }
+    download::unpack(&archive, &release_dir)?;
+    if let Err(e) = fs::remove_file(&archive) {
+        warn!("Could not remove {}: {}", archive.display(), e);
+    }
+
+    let binary = binary_path()
+        .ok_or_else(|| XmrError::ExecutionError("monerod missing from the release archive".to_string()))?;
+    fs::write(dir.join(VERSION_FILE), &archive_name)?;
+
+    info!("Installed {} at {}", archive_name, binary.display());
+    println!("monerod installed at {}", binary.display());
+    Ok(())
+}
+
+// The managed node as a supervised helper process, when `[node] managed`
+pub fn sidecar(config: &Config) -> Result<Option<Sidecar>, XmrError> {
+    if !config.node.managed {
+        return Ok(None);
+    }
+    monerod(config).map(Some)
+}
+
+fn monerod(config: &Config) -> Result<Sidecar, XmrError> {
+    let program = binary_path()
+        .ok_or_else(|| XmrError::EnvError("monerod is not installed, run `node init` first".to_string()))?;
+    let data_dir = blockchain_dir(config)?;
+    fs::create_dir_all(&data_dir)?;
+
+    let mut args = vec![
+        "--non-interactive".to_string(),
+        "--no-igd".to_string(),
+        "--data-dir".to_string(),
+        data_dir.to_string_lossy().to_string(),
+        "--rpc-bind-ip".to_string(),
+        "127.0.0.1".to_string(),
+        "--rpc-bind-port".to_string(),
+        config.node.rpc_port.to_string(),
+        "--zmq-pub".to_string(),
+        format!("tcp://127.0.0.1:{}", config.node.zmq_port),
+    ];
+    if config.node.prune {
+        args.push("--prune-blockchain".to_string());
+    }
+
+    Ok(Sidecar { name: "monerod".to_string(), program, args })
+}
+
+fn local_node(config: &Config) -> String {
+    format!("127.0.0.1:{}", config.node.rpc_port)
+}
+
+// `node run`: supervise only the node, e.g. while it syncs for the first time
+pub fn run(config: &Config) -> Result<(), XmrError> {
+    let sidecar = monerod(config)?;
+
+    let running = run::setup_ctrlc_handler();
+    let handle = sidecar.supervise(running.clone());
+    info!("monerod is running, RPC on {}. Press Ctrl+C to stop it.", local_node(config));
+
+    while running.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_secs(1));
+    }
+    if let Err(e) = handle.join() {
+        error!("Error joining monerod supervisor: {:?}", e);
+    }
+    Ok(())
+}
+
+pub fn status(config: &Config) -> Result<(), XmrError> {
+    println!("monerod:       {}", installed_version().as_deref().unwrap_or("not installed"));
+    println!("Data dir:      {}", blockchain_dir(config)?.display());
+    println!("Pruning:       {}", if config.node.prune { "enabled" } else { "disabled" });
+    let node = local_node(config);
+    print_info(&node, &get_info(&node));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_name() {
+        let listing = "## CLI\nmonero-linux-armv8-v0.18.3.4.tar.bz2, aaaa\nmonero-linux-x64-v0.18.3.4.tar.bz2, bbbb\n";
+        assert_eq!(archive_name(listing, "linux-x64").as_deref(), Some("monero-linux-x64-v0.18.3.4.tar.bz2"));
+        assert_eq!(archive_name(listing, "mac-x64"), None);
+    }
+}
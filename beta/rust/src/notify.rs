@@ -0,0 +1,443 @@
+use std::env;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use notify_rust::Notification;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::config::{Config, EmailConfig, SmtpSecurity, WebhookConfig};
+use crate::events::{self, Event, Sink};
+use crate::http;
+use crate::run::XmrError;
+use crate::telemetry;
+
+// For each message to Telegram or the SMTP server
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Title and text for the events worth telling the user about
+fn message(event: &Event, payouts: bool) -> Option<(&'static str, String)> {
+    let message = match event {
+        Event::ChildStarted { pid } => ("XMR miner started", format!("The miner is running as process {}.", pid)),
+        Event::ChildExited { pid, code, .. } if event.is_failure() => (
+            "XMR miner crashed",
+            format!(
+                "Process {} exited with code {}; the watchdog is restarting it.",
+                pid,
+                code.map_or("unknown".to_string(), |c| c.to_string())
+            ),
+        ),
+        Event::Alert { message } => ("XMR miner failing", format!("{}.", message)),
+        Event::Shutdown => ("Supervisor stopped", "The supervisor shut down and the miner is stopped.".to_string()),
+        Event::PriceAlert { price, currency, threshold, direction } => (
+            "XMR price alert",
+            format!(
+                "XMR is now {:.2} {}, {} your {:.2} threshold.",
+                price,
+                currency.to_uppercase(),
+                direction,
+                threshold
+            ),
+        ),
+        Event::PoolFailover { from, to, reason } => (
+            "Mining pool switched",
+            format!("{} failed ({}); now mining on {}.", from, reason, to),
+        ),
+        Event::Paused { reason } => ("Mining paused", format!("Mining was paused: {}.", reason)),
+        Event::Resumed { reason } => ("Mining resumed", format!("Mining resumed ({} cleared).", reason)),
+        Event::InstallCompleted { command, hook_exit_code: Some(code), .. } if *code != 0 => (
+            "XMR miner install failed",
+            format!("The post-install hook of {} exited with code {}.", command, code),
+        ),
+        Event::InstallCompleted { command, tag, changed, .. } => (
+            if *changed { "XMR miner installed" } else { "XMR miner up to date" },
+            format!(
+                "{} {} release {}.",
+                command,
+                if *changed { "installed" } else { "found the latest already installed:" },
+                tag.as_deref().unwrap_or("unknown")
+            ),
+        ),
+        Event::InstallFailed { command, error } => ("XMR miner install failed", format!("{} failed: {}.", command, error)),
+        Event::PayoutReceived { amount, .. } if payouts => (
+            "XMR payout received",
+            format!("The pool sent {:.6} XMR to your wallet.", amount),
+        ),
+        _ => return None,
+    };
+    Some(message)
+}
+
+// The process exits right after these, so they go out at once rather than
+// through the queue
+fn is_last(event: &Event) -> bool {
+    matches!(event, Event::Shutdown | Event::InstallCompleted { .. } | Event::InstallFailed { .. })
+}
+
+// Desktop toast notifications (libnotify on Linux, Notification Center on
+// macOS, toast on Windows) for the "mine while I'm away" workflow.
+struct DesktopSink {
+    payouts: bool,
+}
+
+impl Sink for DesktopSink {
+    fn handle(&self, event: &Event) {
+        // Someone at the desktop sees the miner come and go already
+        if matches!(event, Event::ChildStarted { .. } | Event::Shutdown) {
+            return;
+        }
+        let Some((summary, body)) = message(event, self.payouts) else { return };
+
+        if let Err(e) = Notification::new()
+            .appname("minning")
+            .summary(summary)
+            .body(&body)
+            .show()
+        {
+            warn!("Failed to show desktop notification: {}", e);
+        }
+    }
+}
+
+// Sends one message (a title and its text) to a backend
+type Deliver = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+// Hands messages to a background thread, so the watchdog loop that raised
+// the event never waits on the network
+struct Outbox {
+    queue: Mutex<Sender<(&'static str, String)>>,
+    deliver: Deliver,
+}
+
+impl Outbox {
+    fn new(deliver: impl Fn(&str, &str) + Send + Sync + 'static) -> Outbox {
+        let deliver: Deliver = Arc::new(deliver);
+        let (sender, receiver) = mpsc::channel::<(&'static str, String)>();
+        let worker = deliver.clone();
+        thread::spawn(move || {
+            for (summary, body) in receiver {
+                worker(summary, &body);
+            }
+        });
+        Outbox { queue: Mutex::new(sender), deliver }
+    }
+
+    fn post(&self, summary: &'static str, body: String) {
+        if let Ok(queue) = self.queue.lock() {
+            let _ = queue.send((summary, body));
+        }
+    }
+
+    // For the last message before the process exits
+    fn post_now(&self, summary: &str, body: &str) {
+        (self.deliver)(summary, body);
+    }
+}
+
+// Messages through a Telegram bot, for rigs nobody sits in front of
+struct TelegramSink {
+    outbox: Outbox,
+    payouts: bool,
+}
+
+impl TelegramSink {
+    fn new(bot_token: &str, chat_id: &str, payouts: bool) -> TelegramSink {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
+        let chat_id = chat_id.to_string();
+        let host = telemetry::hostname();
+        let outbox = Outbox::new(move |summary, body| {
+            let text = format!("{} on {}\n{}", summary, host, body);
+            let result = http::agent(SEND_TIMEOUT)
+                .post(&url)
+                .send_json(json!({ "chat_id": chat_id, "text": text, "disable_web_page_preview": true }));
+            // The URL holds the bot token, so it stays out of the log
+            if let Err(e) = result {
+                warn!("Failed to send Telegram notification: {}", e.to_string().replace(&url, "api.telegram.org"));
+            }
+        });
+        TelegramSink { outbox, payouts }
+    }
+}
+
+impl Sink for TelegramSink {
+    fn handle(&self, event: &Event) {
+        let Some((summary, body)) = message(event, self.payouts) else { return };
+        if is_last(event) {
+            self.outbox.post_now(summary, &body);
+        } else {
+            self.outbox.post(summary, body);
+        }
+    }
+}
+
+// Mail through an SMTP server when the miner keeps failing
+struct EmailSink {
+    outbox: Outbox,
+    failure_threshold: usize,
+}
+
+impl EmailSink {
+    fn new(email: &EmailConfig) -> Result<EmailSink, XmrError> {
+        let invalid = |e: &dyn std::fmt::Display| XmrError::ConfigError(format!("[notifications.email] {}", e));
+        let Some(host) = &email.smtp_host else {
+            return Err(invalid(&"smtp_host is not set"));
+        };
+        let Some(from) = email.from.as_ref().or(email.username.as_ref()) else {
+            return Err(invalid(&"set from (or username) to send mail as"));
+        };
+        if email.to.is_empty() {
+            return Err(invalid(&"to lists nobody to mail"));
+        }
+        let from: Mailbox = from.parse().map_err(|e| invalid(&e))?;
+        let to = email
+            .to
+            .iter()
+            .map(|to| to.parse::<Mailbox>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| invalid(&e))?;
+
+        let mut transport = match email.security {
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(host).map_err(|e| invalid(&e))?,
+            SmtpSecurity::Tls => SmtpTransport::relay(host).map_err(|e| invalid(&e))?,
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(host),
+        }
+        .port(email.smtp_port)
+        .timeout(Some(SEND_TIMEOUT));
+        if let Some(username) = &email.username {
+            let password = email.password.clone().unwrap_or_default();
+            transport = transport.credentials(Credentials::new(username.clone(), password));
+        }
+        let transport = transport.build();
+
+        let host = telemetry::hostname();
+        let outbox = Outbox::new(move |summary, body| {
+            let mut builder = Message::builder().from(from.clone()).subject(format!("[minning] {} on {}", summary, host));
+            for to in &to {
+                builder = builder.to(to.clone());
+            }
+            let sent = builder
+                .header(ContentType::TEXT_PLAIN)
+                .body(format!("{}\n\nHost: {}\n", body, host))
+                .map_err(|e| e.to_string())
+                .and_then(|message| transport.send(&message).map_err(|e| e.to_string()));
+            if let Err(e) = sent {
+                warn!("Failed to send email alert: {}", e);
+            }
+        });
+        Ok(EmailSink { outbox, failure_threshold: email.failure_threshold.max(1) })
+    }
+}
+
+impl Sink for EmailSink {
+    fn handle(&self, event: &Event) {
+        match event {
+            // Once per run of failures, when it reaches the threshold
+            Event::Restarting { consecutive_failures, delay_secs } if *consecutive_failures == self.failure_threshold => {
+                self.outbox.post(
+                    "XMR miner failing",
+                    format!(
+                        "The miner failed {} times in a row; the next attempt is in {} seconds.",
+                        consecutive_failures, delay_secs
+                    ),
+                )
+            },
+            // The watchdog backing off after too many failures
+            Event::Alert { .. } => {
+                if let Some((summary, body)) = message(event, false) {
+                    self.outbox.post(summary, body);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+// Fill the {{field}} placeholders of a webhook template. Strings go in
+// JSON-escaped but without their quotes, so a placeholder works both as a
+// whole value and inside a quoted one.
+fn render(template: &str, fields: &Value) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else { break };
+        rendered.push_str(&rest[..start]);
+        match &fields[rest[start + 2..end].trim()] {
+            Value::String(text) => {
+                let quoted = Value::String(text.clone()).to_string();
+                rendered.push_str(&quoted[1..quoted.len() - 1]);
+            },
+            value => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+// Arbitrary HTTP requests for lifecycle events, for anything without a
+// backend of its own
+struct WebhookSink {
+    outbox: Outbox,
+    events: Vec<String>,
+    template: Option<String>,
+    host: String,
+}
+
+impl WebhookSink {
+    fn new(webhook: &WebhookConfig) -> WebhookSink {
+        let (url, method, headers) = (webhook.url.clone(), webhook.method.to_uppercase(), webhook.headers.clone());
+        let outbox = Outbox::new(move |event, body| {
+            let agent = http::agent(SEND_TIMEOUT);
+            let result = match method.as_str() {
+                "GET" | "DELETE" | "HEAD" => {
+                    let mut request = match method.as_str() {
+                        "GET" => agent.get(&url),
+                        "DELETE" => agent.delete(&url),
+                        _ => agent.head(&url),
+                    };
+                    for (name, value) in &headers {
+                        request = request.header(name, value);
+                    }
+                    request.call()
+                },
+                _ => {
+                    let mut request = match method.as_str() {
+                        "PUT" => agent.put(&url),
+                        "PATCH" => agent.patch(&url),
+                        _ => agent.post(&url),
+                    };
+                    if !headers.keys().any(|name| name.eq_ignore_ascii_case("content-type")) {
+                        request = request.content_type("application/json");
+                    }
+                    for (name, value) in &headers {
+                        request = request.header(name, value);
+                    }
+                    request.send(body)
+                },
+            };
+            if let Err(e) = result {
+                warn!("Webhook for {} to {} failed: {}", event, url, e);
+            }
+        });
+        WebhookSink { outbox, events: webhook.events.clone(), template: webhook.template.clone(), host: telemetry::hostname() }
+    }
+}
+
+impl Sink for WebhookSink {
+    fn handle(&self, event: &Event) {
+        if !self.events.is_empty() && !self.events.iter().any(|name| name == event.name()) {
+            return;
+        }
+        let mut fields = events::record(event);
+        fields["host"] = json!(self.host);
+        fields["message"] = json!(message(event, true).map_or_else(|| event.name().to_string(), |(_, body)| body));
+        let body = match &self.template {
+            Some(template) => render(template, &fields),
+            None => fields.to_string(),
+        };
+        if is_last(event) {
+            self.outbox.post_now(event.name(), &body);
+        } else {
+            self.outbox.post(event.name(), body);
+        }
+    }
+}
+
+// Graphical sessions only; headless rigs have nobody to show toasts to
+fn has_desktop_session() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return true;
+    }
+
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| env::var(var).is_ok_and(|v| !v.is_empty()))
+}
+
+// Register every notification backend enabled in the config
+pub fn install(config: &Config) {
+    if config.notifications.desktop && has_desktop_session() {
+        debug!("Desktop notifications enabled");
+        events::subscribe(Box::new(DesktopSink { payouts: config.notifications.payouts }));
+    }
+
+    let telegram = &config.notifications.telegram;
+    if telegram.enabled {
+        match (&telegram.bot_token, &telegram.chat_id) {
+            (Some(bot_token), Some(chat_id)) => {
+                debug!("Telegram notifications enabled");
+                events::subscribe(Box::new(TelegramSink::new(bot_token, chat_id, config.notifications.payouts)));
+            },
+            _ => warn!("Telegram notifications need bot_token and chat_id in [notifications.telegram]"),
+        }
+    }
+
+    if config.notifications.email.enabled {
+        match EmailSink::new(&config.notifications.email) {
+            Ok(sink) => {
+                debug!("Email alerts enabled");
+                events::subscribe(Box::new(sink));
+            },
+            Err(e) => warn!("Not sending email alerts: {}", e),
+        }
+    }
+
+    for webhook in &config.notifications.webhooks {
+        if webhook.url.is_empty() {
+            warn!("Ignoring a [[notifications.webhooks]] entry without a url");
+            continue;
+        }
+        debug!("Webhook {} {} enabled", webhook.method, webhook.url);
+        events::subscribe(Box::new(WebhookSink::new(webhook)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let fields = json!({ "event": "child_exited", "pid": 42, "code": null, "message": "said \"bye\"" });
+        assert_eq!(
+            render(r#"{"text": "{{event}} {{pid}}: {{message}}", "code": {{code}}}"#, &fields),
+            r#"{"text": "child_exited 42: said \"bye\"", "code": null}"#
+        );
+        assert_eq!(render("{{ pid }} {{unclosed", &fields), "42 {{unclosed");
+    }
+
+    #[test]
+    fn test_child_exit_messages() {
+        assert!(message(&Event::ChildExited { pid: 42, code: Some(1), stopped: false }, false).is_some());
+        // Stopped by the supervisor on purpose, not a crash
+        assert!(message(&Event::ChildExited { pid: 42, code: None, stopped: true }, false).is_none());
+    }
+
+    #[test]
+    fn test_install_messages() {
+        let completed = |changed, hook_exit_code| Event::InstallCompleted {
+            command: "update".to_string(),
+            tag: Some("v6.21.0".to_string()),
+            xmr_dir: "/opt/xmr".to_string(),
+            binary: "/opt/xmr/xmr".to_string(),
+            archive_sha256: None,
+            binary_sha256: None,
+            changed,
+            hook_exit_code,
+        };
+        assert_eq!(
+            message(&completed(true, Some(0)), false),
+            Some(("XMR miner installed", "update installed release v6.21.0.".to_string()))
+        );
+        assert_eq!(message(&completed(false, None), false).unwrap().0, "XMR miner up to date");
+        assert_eq!(message(&completed(true, Some(2)), false).unwrap().0, "XMR miner install failed");
+        let failed = Event::InstallFailed { command: "init".to_string(), error: "no network".to_string() };
+        assert_eq!(message(&failed, false), Some(("XMR miner install failed", "init failed: no network.".to_string())));
+    }
+}
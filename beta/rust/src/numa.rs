@@ -0,0 +1,157 @@
+use std::process::Command;
+
+use tracing::info;
+
+use crate::config::{Config, CpuList};
+use crate::cpu;
+use crate::miner_config;
+
+// A NUMA node with CPUs; memory-only nodes run no miner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: usize,
+    pub cpus: CpuList,
+}
+
+#[cfg(target_os = "linux")]
+pub fn nodes() -> Vec<Node> {
+    let mut nodes: Vec<Node> = std::fs::read_dir("/sys/devices/system/node")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let cpus = std::fs::read_to_string(entry.path().join("cpulist")).ok()?.trim().parse().ok()?;
+            Some(Node { id, cpus })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn nodes() -> Vec<Node> {
+    Vec::new()
+}
+
+// [miner] api_url with its port moved up by `offset`
+fn api_url(api_url: &str, offset: usize) -> String {
+    match miner_config::api_address(api_url) {
+        Some((host, port)) => {
+            let scheme = api_url.split_once("://").map_or("http", |(scheme, _)| scheme);
+            let host = if host.contains(':') { format!("[{}]", host) } else { host };
+            format!("{}://{}:{}", scheme, host, port as usize + offset)
+        },
+        None => api_url.to_string(),
+    }
+}
+
+// The config for the miner on `node`, the `index`th node with CPUs. A CPU
+// set in [miner] cpus still applies, narrowed to the node.
+pub fn node_config(config: &Config, node: &Node, index: usize) -> Config {
+    let mut config = config.clone();
+    let cpus: Vec<String> = node
+        .cpus
+        .cpus()
+        .iter()
+        .filter(|cpu| config.miner.cpus.as_ref().is_none_or(|allowed| allowed.cpus().contains(cpu)))
+        .map(ToString::to_string)
+        .collect();
+    config.miner.cpus = cpus.join(",").parse().ok();
+    config.miner.api_url = api_url(&config.miner.api_url, index);
+    config.miner.numa_node = Some(node.id);
+    config
+}
+
+// `config` reloaded by `restart`, put back on `node`
+pub fn reapply(config: &Config, node: usize) -> Config {
+    match nodes().iter().enumerate().find(|(_, n)| n.id == node) {
+        Some((index, n)) => node_config(config, n, index),
+        None => config.clone(),
+    }
+}
+
+// One config per node when [miner] numa is on and there is more than one
+// node to split across; otherwise just `config`
+pub fn split(config: &Config) -> Vec<Config> {
+    if !config.miner.numa {
+        return vec![config.clone()];
+    }
+    let nodes = nodes();
+    if nodes.len() < 2 {
+        info!("[miner] numa is on, but this machine has a single NUMA node; starting one miner");
+        return vec![config.clone()];
+    }
+
+    let configs: Vec<Config> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| node_config(config, node, index))
+        .filter(|config| config.miner.cpus.is_some())
+        .collect();
+    for config in &configs {
+        let threads = cpu::threads(&config.miner).map_or("the miner's choice of".to_string(), |t| t.to_string());
+        info!(
+            "NUMA node {}: CPUs {}, {} threads, API at {}",
+            config.miner.numa_node.unwrap_or_default(),
+            config.miner.cpus.as_ref().map(ToString::to_string).unwrap_or_default(),
+            threads,
+            config.miner.api_url
+        );
+    }
+    configs
+}
+
+// Have the miner allocate from `node` first, falling back to other nodes
+// when it is full, as `numactl --preferred` does. Set between fork and exec
+// since a memory policy cannot be changed from outside the process.
+#[cfg(target_os = "linux")]
+pub fn prefer_memory(command: &mut Command, node: usize) {
+    use std::os::unix::process::CommandExt;
+
+    const MPOL_PREFERRED: libc::c_long = 1;
+    let bits = libc::c_ulong::BITS as usize;
+    let mut mask: Vec<libc::c_ulong> = vec![0; node / bits + 1];
+    mask[node / bits] |= 1 << (node % bits);
+    let max_node = (mask.len() * bits + 1) as libc::c_ulong;
+
+    // SAFETY: the closure only makes one syscall on a mask allocated before
+    // the fork. A failure is ignored so the miner still starts, unbound.
+    unsafe {
+        command.pre_exec(move || {
+            libc::syscall(libc::SYS_set_mempolicy, MPOL_PREFERRED, mask.as_ptr(), max_node);
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn prefer_memory(_command: &mut Command, _node: usize) {
+    tracing::warn!("NUMA memory placement is only supported on Linux");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_url() {
+        assert_eq!(api_url("http://127.0.0.1:18088", 0), "http://127.0.0.1:18088");
+        assert_eq!(api_url("http://127.0.0.1:18088", 2), "http://127.0.0.1:18090");
+        assert_eq!(api_url("http://[::1]:8080/", 1), "http://[::1]:8081");
+    }
+
+    #[test]
+    fn test_node_config() {
+        let config: Config = toml::from_str("[miner]\nnuma = true\ncpus = \"0-3,16-19\"\n").unwrap();
+        let node = Node { id: 1, cpus: "16-31".parse().unwrap() };
+        let node_config = node_config(&config, &node, 1);
+        assert_eq!(node_config.miner.cpus.unwrap().to_string(), "16-19");
+        assert_eq!(node_config.miner.numa_node, Some(1));
+        assert_eq!(node_config.miner.api_url, "http://127.0.0.1:18089");
+
+        // No allowed CPU on the node: no miner there
+        let node = Node { id: 2, cpus: "32-47".parse().unwrap() };
+        assert_eq!(super::node_config(&config, &node, 2).miner.cpus, None);
+    }
+}
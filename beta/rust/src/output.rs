@@ -0,0 +1,61 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::run::XmrError;
+
+// `--json`: every command ends with one result object on stdout, e.g.
+// {"timestamp":1700000000,"command":"status","status":"ok","launcher_version":"0.1.0",...}
+// so automation can act on it instead of parsing text
+static JSON: AtomicBool = AtomicBool::new(false);
+static COMMAND: OnceLock<String> = OnceLock::new();
+// Whether this command already printed its result
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json(command: &str, enabled: bool) {
+    let _ = COMMAND.set(command.to_string());
+    JSON.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+fn print(status: &str, fields: Value) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let mut result = json!({
+        "timestamp": timestamp,
+        "command": COMMAND.get().map_or("", String::as_str),
+        "status": status,
+        "launcher_version": env!("CARGO_PKG_VERSION"),
+    });
+    if let (Some(result), Value::Object(fields)) = (result.as_object_mut(), fields) {
+        result.extend(fields);
+    }
+    REPORTED.store(true, Ordering::Relaxed);
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", result);
+    let _ = stdout.flush();
+}
+
+// The command succeeded; `fields` (a JSON object) carries what it found
+pub fn ok(fields: Value) {
+    print("ok", fields);
+}
+
+pub fn error(error: &XmrError) {
+    print("error", json!({ "category": error.category(), "error": error.to_string() }));
+}
+
+// End of a successful command that had nothing more specific to report
+pub fn finish() {
+    if json() && !REPORTED.load(Ordering::Relaxed) {
+        ok(json!({}));
+    }
+}
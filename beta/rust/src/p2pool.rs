@@ -0,0 +1,233 @@
+use std::fs;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::config::{self, Config};
+use crate::download;
+use crate::run::{self, XmrError};
+use crate::sidecar::Sidecar;
+use crate::telemetry;
+
+const P2POOL_REPO: &str = "SChernykh/p2pool";
+const CHECKSUMS_ASSET: &str = "sha256sums.txt.asc";
+const VERSION_FILE: &str = "version";
+// p2pool rewrites its data API files every few seconds while running
+const API_STALE_SECS: u64 = 120;
+
+fn install_dir() -> Result<PathBuf, XmrError> {
+    Ok(config::data_dir()?.join("p2pool"))
+}
+
+fn release_dir() -> Result<PathBuf, XmrError> {
+    Ok(install_dir()?.join("release"))
+}
+
+fn api_dir() -> Result<PathBuf, XmrError> {
+    Ok(install_dir()?.join("api"))
+}
+
+// Platform suffix used in p2pool release archive names
+fn platform() -> Result<&'static str, XmrError> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("linux-x64"),
+        ("linux", "aarch64") => Ok("linux-aarch64"),
+        ("macos", "x86_64") => Ok("macos-x64"),
+        ("macos", "aarch64") => Ok("macos-aarch64"),
+        (os, arch) => Err(XmrError::EnvError(format!("No p2pool release for {}-{}", os, arch))),
+    }
+}
+
+// The p2pool executable inside the unpacked release, if installed
+fn binary_path() -> Option<PathBuf> {
+    download::find_binary(&release_dir().ok()?, "p2pool")
+}
+
+fn installed_version() -> Option<String> {
+    let contents = fs::read_to_string(install_dir().ok()?.join(VERSION_FILE)).ok()?;
+    Some(contents.trim().to_string())
+}
+
+// Download the latest p2pool release, check it against the published
+// SHA-256 list and unpack it into the data directory
+pub fn init() -> Result<(), XmrError> {
+    let release = download::latest_github_release(P2POOL_REPO)?;
+    if installed_version().as_deref() == Some(release.tag.as_str()) && binary_path().is_some() {
+        println!("p2pool {} is already installed", release.tag);
+        return Ok(());
+    }
+
+    let archive_name = format!("p2pool-{}-{}.tar.gz", release.tag, platform()?);
+    let archive_url = release
+        .asset(&archive_name)
+        .ok_or_else(|| XmrError::NetworkError(format!("Release {} has no {}", release.tag, archive_name)))?;
+    let checksums_url = release
+        .asset(CHECKSUMS_ASSET)
+        .ok_or_else(|| XmrError::NetworkError(format!("Release {} has no {}", release.tag, CHECKSUMS_ASSET)))?;
+
+    let dir = install_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let listing = download::fetch_text(checksums_url)?;
+    let expected = download::checksum_for(&listing, &archive_name)
+        .ok_or_else(|| XmrError::NetworkError(format!("No checksum published for {}", archive_name)))?;
+
+    let archive = dir.join(&archive_name);
+    download::fetch(archive_url, &archive)?;
+    download::verify(&archive, &expected)?;
+
+    let release_dir = release_dir()?;
+    if release_dir.exists() {
+        fs::remove_dir_all(&release_dir)?;
+    }
+    download::unpack(&archive, &release_dir)?;
+    if let Err(e) = fs::remove_file(&archive) {
+        warn!("Could not remove {}: {}", archive.display(), e);
+    }
+
+    let binary = binary_path()
+        .ok_or_else(|| XmrError::ExecutionError("p2pool binary missing from the release archive".to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::write(dir.join(VERSION_FILE), &release.tag)?;
+
+    info!("Installed p2pool {} at {}", release.tag, binary.display());
+    println!("p2pool {} installed at {}", release.tag, binary.display());
+    Ok(())
+}
+
+fn sidecar(config: &Config, wallet: &str) -> Result<Sidecar, XmrError> {
+    let program = binary_path()
+        .ok_or_else(|| XmrError::EnvError("p2pool is not installed, run `p2pool init` first".to_string()))?;
+    let p2pool = &config.p2pool;
+    let data_dir = install_dir()?.join("data");
+    let api_dir = api_dir()?;
+    fs::create_dir_all(&data_dir)?;
+    fs::create_dir_all(&api_dir)?;
+
+    let mut args = vec![
+        "--host".to_string(),
+        p2pool.node.clone(),
+        "--rpc-port".to_string(),
+        p2pool.rpc_port.to_string(),
+        "--zmq-port".to_string(),
+        p2pool.zmq_port.to_string(),
+        "--wallet".to_string(),
+        wallet.to_string(),
+        "--stratum".to_string(),
+        format!("127.0.0.1:{}", p2pool.stratum_port),
+        "--data-dir".to_string(),
+        data_dir.to_string_lossy().to_string(),
+        "--data-api".to_string(),
+        api_dir.to_string_lossy().to_string(),
+        "--local-api".to_string(),
+    ];
+    if p2pool.mini {
+        args.push("--mini".to_string());
+    }
+
+    Ok(Sidecar { name: "p2pool".to_string(), program, args })
+}
+
+// The miner mines against the local p2pool stratum; payouts go to the wallet
+// p2pool was started with, so pool APIs and wallet splits do not apply
+fn miner_config(config: &Config) -> Config {
+    let mut miner = config.clone();
+    miner.pool.url = Some(format!("127.0.0.1:{}", config.p2pool.stratum_port));
+    miner.pool.tls = false;
+    miner.pool.password = "x".to_string();
+    miner.pool.failover.clear();
+    miner.pool.auto = false;
+    miner.split.targets.clear();
+    miner.solo.enabled = false;
+    miner.earnings.enabled = false;
+    miner
+}
+
+// Supervise p2pool and the miner together in resilient mode
+pub fn run(config: &Config) -> Result<(), XmrError> {
+    let wallet = config
+        .wallet
+        .address
+        .clone()
+        .ok_or_else(|| XmrError::ConfigError("p2pool needs a [wallet] address".to_string()))?;
+    let sidecar = sidecar(config, &wallet)?;
+
+    info!("Mining through local p2pool on port {}", config.p2pool.stratum_port);
+    run::run_xmr_resilient_with(&miner_config(config), vec![sidecar])
+}
+
+#[derive(Debug, Default)]
+pub struct Health {
+    pub installed: Option<String>,
+    pub stratum_up: bool,
+    // Seconds since p2pool last refreshed its data API
+    pub api_age_secs: Option<u64>,
+    pub hashrate: Option<f64>,
+    pub miners: Option<u64>,
+    pub peers: Option<u64>,
+    pub shares_found: Option<u64>,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        self.stratum_up && self.api_age_secs.is_some_and(|age| age <= API_STALE_SECS)
+    }
+}
+
+fn read_api(path: &Path) -> Option<(Value, u64)> {
+    let age = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|d| d.as_secs())?;
+    let value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    Some((value, age))
+}
+
+// Local p2pool state from its stratum port and data API files
+pub fn health(config: &Config) -> Result<Health, XmrError> {
+    let api = api_dir()?.join("local");
+    let address = SocketAddr::from(([127, 0, 0, 1], config.p2pool.stratum_port));
+
+    let mut health = Health {
+        installed: installed_version(),
+        stratum_up: TcpStream::connect_timeout(&address, Duration::from_secs(2)).is_ok(),
+        ..Health::default()
+    };
+    if let Some((stratum, age)) = read_api(&api.join("stratum")) {
+        health.api_age_secs = Some(age);
+        health.hashrate = stratum["hashrate_15m"].as_f64();
+        health.miners = stratum["connections"].as_u64();
+        health.shares_found = stratum["shares_found"].as_u64();
+    }
+    if let Some((p2p, _)) = read_api(&api.join("p2p")) {
+        health.peers = p2p["connections"].as_u64();
+    }
+    Ok(health)
+}
+
+pub fn status(config: &Config) -> Result<(), XmrError> {
+    let health = health(config)?;
+    let unknown = || "-".to_string();
+
+    println!("p2pool:        {}", health.installed.as_deref().unwrap_or("not installed"));
+    println!("Health:        {}", if health.is_healthy() { "ok" } else { "down" });
+    println!(
+        "Stratum:       127.0.0.1:{} ({})",
+        config.p2pool.stratum_port,
+        if health.stratum_up { "listening" } else { "closed" }
+    );
+    println!("Sidechain:     {}", if config.p2pool.mini { "mini" } else { "main" });
+    println!("Hashrate:      {}", health.hashrate.map(telemetry::format_hashrate).unwrap_or_else(unknown));
+    println!("Miners:        {}", health.miners.map(|n| n.to_string()).unwrap_or_else(unknown));
+    println!("Peers:         {}", health.peers.map(|n| n.to_string()).unwrap_or_else(unknown));
+    println!("Shares found:  {}", health.shares_found.map(|n| n.to_string()).unwrap_or_else(unknown));
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::debug;
+
+use crate::config;
+use crate::events::{self, Event};
+use crate::run::XmrError;
+
+// Why mining is currently paused. Each subsystem (price floor, schedules,
+// the user, ...) owns one reason; the miner only runs while the set is empty.
+static REASONS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join("paused.json"))
+}
+
+// Keep the reasons in the state directory for `status`, which runs in
+// another process
+fn save(reasons: &BTreeSet<String>) {
+    let result = path().and_then(|path| {
+        let contents = serde_json::to_string(reasons)
+            .map_err(|e| XmrError::ConfigError(format!("Could not encode pause reasons: {}", e)))?;
+        config::write_atomic(&path, &contents)
+    });
+    if let Err(e) = result {
+        debug!("Could not save the pause reasons: {}", e);
+    }
+}
+
+// Supervisor start: forget the reasons a previous supervisor left behind
+pub fn clear_saved() {
+    if let Ok(path) = path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// The running supervisor's reasons, as last saved
+pub fn saved() -> Vec<String> {
+    path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+pub fn pause(reason: &str) {
+    let Ok(mut reasons) = REASONS.lock() else {
+        return;
+    };
+    if reasons.insert(reason.to_string()) {
+        save(&reasons);
+        drop(reasons);
+        events::emit(Event::Paused { reason: reason.to_string() });
+    }
+}
+
+pub fn resume(reason: &str) {
+    let Ok(mut reasons) = REASONS.lock() else {
+        return;
+    };
+    if reasons.remove(reason) {
+        save(&reasons);
+        let resumed = reasons.is_empty();
+        drop(reasons);
+        if resumed {
+            events::emit(Event::Resumed { reason: reason.to_string() });
+        }
+    }
+}
+
+pub fn set(reason: &str, paused: bool) {
+    if paused { pause(reason) } else { resume(reason) }
+}
+
+pub fn is_paused() -> bool {
+    REASONS.lock().map(|r| !r.is_empty()).unwrap_or(false)
+}
+
+pub fn reasons() -> Vec<String> {
+    REASONS.lock().map(|r| r.iter().cloned().collect()).unwrap_or_default()
+}
@@ -0,0 +1,201 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::{self, Config};
+use crate::earnings::{self, Sample};
+use crate::events::{self, Event};
+use crate::pool_api::{self, PoolPayout};
+use crate::run::XmrError;
+
+pub const PAYOUTS_FILE: &str = "payouts.jsonl";
+
+// Ignore balance wobble below this when inferring payouts from drops
+const BALANCE_DROP_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub timestamp: i64,
+    pub amount: f64,
+    pub tx_hash: Option<String>,
+    // "api" when reported by the pool, "balance" when inferred from a drop
+    pub source: String,
+}
+
+fn payouts_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(PAYOUTS_FILE))
+}
+
+pub fn load() -> Result<Vec<Payout>, XmrError> {
+    let path = payouts_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append(payouts: &[Payout]) -> Result<(), XmrError> {
+    let path = payouts_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for payout in payouts {
+        let line = serde_json::to_string(payout)
+            .map_err(|e| XmrError::ConfigError(format!("Could not encode payout: {}", e)))?;
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn is_known(known: &[Payout], payout: &PoolPayout) -> bool {
+    known.iter().any(|k| match (&k.tx_hash, &payout.tx_hash) {
+        (Some(a), Some(b)) => a == b,
+        _ => k.timestamp == payout.timestamp && (k.amount - payout.amount).abs() < BALANCE_DROP_EPSILON,
+    })
+}
+
+fn announce(new: &[Payout]) {
+    for payout in new {
+        info!("Payout received: {:.12} XMR", payout.amount);
+        events::emit(Event::PayoutReceived { amount: payout.amount, tx_hash: payout.tx_hash.clone() });
+    }
+}
+
+// Pull the pool's payout list into the local history. Returns None when the
+// pool has no payout endpoint. Payouts older than the earnings history are
+// imported silently so they don't all show up as notifications at once.
+pub fn sync_from_api(config: &Config, notify: bool) -> Result<Option<Vec<Payout>>, XmrError> {
+    let Some(wallet) = config.wallet.address.as_deref() else {
+        return Err(XmrError::ConfigError(
+            "No wallet configured; set [wallet] address in the config file".to_string(),
+        ));
+    };
+    let Some(mut reported) = pool_api::adapter(&config.pool.api)?.payouts(wallet)? else {
+        return Ok(None);
+    };
+
+    let known = load()?;
+    reported.sort_by_key(|p| p.timestamp);
+    let new: Vec<Payout> = reported
+        .iter()
+        .filter(|p| !is_known(&known, p))
+        .map(|p| Payout {
+            timestamp: p.timestamp,
+            amount: p.amount,
+            tx_hash: p.tx_hash.clone(),
+            source: "api".to_string(),
+        })
+        .collect();
+
+    append(&new)?;
+    if notify {
+        // On the first import, only payouts made since tracking began are news
+        let tracking_since = match known.is_empty() {
+            true => earnings::load_history()?.first().map_or(i64::MAX, |s| s.timestamp),
+            false => i64::MIN,
+        };
+        let fresh: Vec<Payout> = new.iter().filter(|p| p.timestamp >= tracking_since).cloned().collect();
+        announce(&fresh);
+    }
+    Ok(Some(new))
+}
+
+// A drop in the unpaid balance between two samples means the pool paid out
+// roughly what was owed before
+fn inferred(prev: Option<&Sample>, next: &Sample) -> Option<Payout> {
+    let prev = prev?;
+    (next.unpaid + BALANCE_DROP_EPSILON < prev.unpaid).then(|| Payout {
+        timestamp: next.timestamp,
+        amount: prev.unpaid,
+        tx_hash: None,
+        source: "balance".to_string(),
+    })
+}
+
+// Called by the earnings recorder after every sample
+pub fn check(config: &Config, prev: Option<&Sample>, next: &Sample) {
+    match sync_from_api(config, true) {
+        Ok(Some(_)) => return,
+        Ok(None) => {},
+        Err(e) => warn!("Could not fetch payouts from the pool: {}", e),
+    }
+
+    // No payout endpoint (or it failed): infer payouts from the balance
+    if let Some(payout) = inferred(prev, next) {
+        match append(std::slice::from_ref(&payout)) {
+            Ok(()) => announce(&[payout]),
+            Err(e) => warn!("Could not record payout: {}", e),
+        }
+    }
+}
+
+// `payouts`: list recorded payouts, newest first
+pub fn list(config: &Config) -> Result<(), XmrError> {
+    if let Err(e) = sync_from_api(config, false) {
+        eprintln!("Warning: could not refresh payouts from the pool: {}", e);
+    }
+
+    let mut payouts = load()?;
+    if payouts.is_empty() {
+        println!("No payouts recorded yet.");
+        return Ok(());
+    }
+
+    payouts.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+    println!("{:<19}  {:>16}  Transaction", "Date", "Amount (XMR)");
+    for payout in &payouts {
+        let date = Local
+            .timestamp_opt(payout.timestamp, 0)
+            .single()
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| payout.timestamp.to_string());
+        let tx = payout.tx_hash.as_deref().unwrap_or("(inferred from balance)");
+        println!("{:<19}  {:>16.12}  {}", date, payout.amount, tx);
+    }
+    let total: f64 = payouts.iter().map(|p| p.amount).sum();
+    println!("{} payouts, {:.12} XMR total", payouts.len(), total);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: i64, unpaid: f64) -> Sample {
+        Sample { timestamp, unpaid, total_paid: None }
+    }
+
+    #[test]
+    fn test_drop_is_payout() {
+        let payout = inferred(Some(&sample(100, 0.0042)), &sample(200, 0.0001)).unwrap();
+        assert_eq!(payout.timestamp, 200);
+        assert_eq!(payout.amount, 0.0042);
+        assert_eq!(payout.tx_hash, None);
+        assert_eq!(payout.source, "balance");
+    }
+
+    #[test]
+    fn test_no_drop() {
+        assert!(inferred(Some(&sample(100, 0.0042)), &sample(200, 0.0043)).is_none());
+        assert!(inferred(Some(&sample(100, 0.0042)), &sample(200, 0.0042)).is_none());
+        // Rounding wobble in the pool's figures is not a payout
+        assert!(inferred(Some(&sample(100, 0.0042)), &sample(200, 0.0042 - 1e-12)).is_none());
+    }
+
+    #[test]
+    fn test_first_sample() {
+        assert!(inferred(None, &sample(100, 0.0)).is_none());
+        assert!(inferred(None, &sample(100, 0.0042)).is_none());
+    }
+}
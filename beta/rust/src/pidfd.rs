@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::process::Command;
+
+#[cfg(target_os = "linux")]
+use std::os::fd::{FromRawFd, OwnedFd};
+
+// Signals the launcher sends to the processes it manages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    // Probe only: delivers nothing, fails once the process is gone
+    Check,
+    Interrupt,
+    Terminate,
+    Kill,
+    // Freeze and thaw without ending the process
+    Stop,
+    Continue,
+}
+
+#[cfg(unix)]
+impl Signal {
+    pub fn number(self) -> i32 {
+        match self {
+            Signal::Check => 0,
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Continue => libc::SIGCONT,
+        }
+    }
+}
+
+// Kernel start time of `pid` in clock ticks since boot, from field 22 of
+// /proc/<pid>/stat. Together with the PID it names one process for good.
+#[cfg(not(target_os = "macos"))]
+pub fn start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces and parentheses; fields resume after the last ')'
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+// macOS has no /proc; its process info gives the start time in microseconds
+#[cfg(target_os = "macos")]
+pub fn start_time(pid: u32) -> Option<u64> {
+    let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+    // SAFETY: proc_pidinfo writes at most `size` bytes into `info`
+    let written = unsafe {
+        libc::proc_pidinfo(pid as libc::c_int, libc::PROC_PIDTBSDINFO, 0, (&mut info as *mut libc::proc_bsdinfo).cast(), size)
+    };
+    (written == size).then(|| info.pbi_start_tvsec * 1_000_000 + info.pbi_start_tvusec)
+}
+
+// A process held by handle instead of by number. On Linux this is a pidfd,
+// which keeps referring to the same process after it exits, so signals and
+// checks never reach an unrelated process that was given the recycled PID.
+// Elsewhere it falls back to the plain PID.
+#[derive(Debug)]
+pub struct ProcessHandle {
+    pid: u32,
+    #[cfg(target_os = "linux")]
+    fd: Option<OwnedFd>,
+}
+
+impl ProcessHandle {
+    // Take hold of `pid`. Only sound while the PID cannot have been reused
+    // yet, e.g. for a child that has not been waited for; use
+    // `open_verified` for PIDs read from elsewhere.
+    pub fn open(pid: u32) -> ProcessHandle {
+        #[cfg(target_os = "linux")]
+        {
+            // SAFETY: pidfd_open takes a PID and flags and returns a new file
+            // descriptor, which OwnedFd takes ownership of
+            let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+            let fd = (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd as i32) });
+            ProcessHandle { pid, fd }
+        }
+        #[cfg(not(target_os = "linux"))]
+        ProcessHandle { pid }
+    }
+
+    // Take hold of `pid` only if it is still the process that started at
+    // `started` (see `start_time`), not a later one reusing its number
+    pub fn open_verified(pid: u32, started: u64) -> Option<ProcessHandle> {
+        let handle = ProcessHandle::open(pid);
+        // Still alive after the lookup means the lookup saw the same process
+        (start_time(pid) == Some(started) && handle.alive()).then_some(handle)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn signal(&self, signal: Signal) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if let Some(fd) = &self.fd {
+            use std::os::fd::AsRawFd;
+            // SAFETY: pidfd_send_signal with a valid pidfd, no siginfo and no flags
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    fd.as_raw_fd(),
+                    signal.number(),
+                    std::ptr::null::<libc::siginfo_t>(),
+                    0,
+                )
+            };
+            return if result == 0 { Ok(()) } else { Err(io::Error::last_os_error()) };
+        }
+        #[cfg(unix)]
+        {
+            // SAFETY: kill only takes a PID and a signal number
+            if unsafe { libc::kill(self.pid as libc::pid_t, signal.number()) } == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        }
+        // Windows has no signals: probe with tasklist and kill with taskkill.
+        // Graceful shutdown goes through the control channel instead.
+        #[cfg(not(unix))]
+        {
+            let pid = self.pid.to_string();
+            match signal {
+                Signal::Check => {
+                    let output = Command::new("tasklist").args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"]).output()?;
+                    if String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)) {
+                        Ok(())
+                    } else {
+                        Err(io::Error::new(io::ErrorKind::NotFound, "no such process"))
+                    }
+                },
+                Signal::Kill => {
+                    let status = Command::new("taskkill").args(["/F", "/PID", &pid]).status()?;
+                    if status.success() { Ok(()) } else { Err(io::Error::other(format!("taskkill failed: {}", status))) }
+                },
+                _ => Err(io::Error::new(io::ErrorKind::Unsupported, "signals are not supported on this platform")),
+            }
+        }
+    }
+
+    // Not yet reaped; a zombie still counts
+    pub fn alive(&self) -> bool {
+        self.signal(Signal::Check).is_ok()
+    }
+
+    // Scheduler state letter (R, S, T, Z, ...) while the process exists
+    pub fn state(&self) -> Option<char> {
+        let state = match fs::read_to_string(format!("/proc/{}/stat", self.pid)) {
+            Ok(stat) => stat.rsplit_once(')')?.1.split_whitespace().next()?.chars().next()?,
+            Err(_) => {
+                let output = Command::new("ps").args(["-p", &self.pid.to_string(), "-o", "state="]).output().ok()?;
+                String::from_utf8_lossy(&output.stdout).trim().chars().next()?
+            },
+        };
+        // Read after the process exited, the PID may already be another's
+        self.alive().then_some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_handle_outlives_child() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let handle = ProcessHandle::open(child.id());
+        let started = start_time(child.id()).unwrap();
+        assert!(handle.alive());
+        assert!(handle.state().is_some_and(|state| state != 'Z'));
+        assert!(ProcessHandle::open_verified(child.id(), started).is_some());
+        assert!(ProcessHandle::open_verified(child.id(), started + 1).is_none());
+
+        handle.signal(Signal::Kill).unwrap();
+        child.wait().unwrap();
+        assert!(!handle.alive());
+        assert_eq!(handle.state(), None);
+    }
+}
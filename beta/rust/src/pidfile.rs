@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::config;
+#[cfg(not(unix))]
+use crate::control;
+use crate::pidfd::{self, ProcessHandle};
+#[cfg(unix)]
+use crate::pidfd::Signal;
+use crate::run::XmrError;
+
+pub const PID_FILE: &str = "supervisor.pid";
+
+fn path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(PID_FILE))
+}
+
+// "PID START_TIME"; files from older versions hold only the PID
+fn parse(contents: &str) -> Option<(u32, Option<u64>)> {
+    let mut fields = contents.split_whitespace();
+    let pid = fields.next()?.parse().ok()?;
+    Some((pid, fields.next().and_then(|t| t.parse().ok())))
+}
+
+// The supervisor running from a state directory, if any. The recorded start
+// time tells it apart from an unrelated process that was given its PID after
+// it died without removing the file.
+pub fn supervisor_in(state_dir: &Path) -> Option<ProcessHandle> {
+    let (pid, started) = parse(&fs::read_to_string(state_dir.join(PID_FILE)).ok()?)?;
+    match started {
+        Some(started) => ProcessHandle::open_verified(pid, started),
+        None => Some(ProcessHandle::open(pid)).filter(|process| process.alive()),
+    }
+}
+
+// PID of the supervisor running from a state directory, if any
+pub fn running_in(state_dir: &Path) -> Option<u32> {
+    supervisor_in(state_dir).map(|process| process.pid())
+}
+
+pub fn running() -> Option<u32> {
+    running_in(&config::state_dir().ok()?)
+}
+
+// Marks this process as the supervisor for as long as it is held
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Record this process as the running supervisor. A second supervisor on the
+// same state directory would fight the first over the miner, so refuse.
+pub fn acquire() -> Result<PidFile, XmrError> {
+    if let Some(pid) = running() {
+        return Err(XmrError::ExecutionError(format!(
+            "A supervisor is already running (PID {}); stop it with `stop` first, or start with --replace",
+            pid
+        )));
+    }
+
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let pid = process::id();
+    let started = pidfd::start_time(pid).map(|t| format!(" {}", t)).unwrap_or_default();
+    fs::write(&path, format!("{}{}\n", pid, started))?;
+    Ok(PidFile { path })
+}
+
+// `stop`: ask the supervisor to shut down the way Ctrl+C does and wait for
+// it, its watchdogs and the miner to exit
+pub fn stop(timeout: Duration) -> Result<(), XmrError> {
+    let Some(supervisor) = config::state_dir().ok().and_then(|dir| supervisor_in(&dir)) else {
+        println!("No supervised miner is running.");
+        return Ok(());
+    };
+    let pid = supervisor.pid();
+
+    info!("Sending shutdown request to supervisor {}", pid);
+    #[cfg(unix)]
+    if let Err(e) = supervisor.signal(Signal::Interrupt) {
+        return Err(XmrError::PermissionError(format!("Could not signal supervisor {}: {}", pid, e)));
+    }
+    // Without signals, through the control channel
+    #[cfg(not(unix))]
+    control::send(control::Request::Stop)?;
+
+    println!("Stopping supervisor (PID {})...", pid);
+    let started = Instant::now();
+    while supervisor.alive() {
+        if started.elapsed() >= timeout {
+            warn!("Supervisor {} still running after {} seconds", pid, timeout.as_secs());
+            return Err(XmrError::ExecutionError(format!(
+                "Supervisor {} did not exit within {} seconds",
+                pid,
+                timeout.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    println!("Stopped.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("4242 987654\n"), Some((4242, Some(987654))));
+        assert_eq!(parse("4242\n"), Some((4242, None)));
+        assert_eq!(parse(""), None);
+    }
+}
@@ -0,0 +1,624 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use toml_edit::{value, ArrayOfTables, DocumentMut, Item, Table};
+use tracing::{info, warn};
+
+use crate::config::{self, Config, PoolEntry};
+use crate::events::{self, Event};
+use crate::output;
+use crate::pause;
+use crate::region;
+use crate::retry;
+use crate::run::XmrError;
+use crate::split;
+use crate::stratum::{self, Probe};
+use crate::telemetry;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECTION_POLL: Duration = Duration::from_secs(30);
+
+// Well-known public endpoints by region: (pool, region, stratum address)
+pub const REGIONAL_ENDPOINTS: &[(&str, &str, &str)] = &[
+    ("supportxmr", "global", "pool.supportxmr.com:3333"),
+    ("moneroocean", "global", "gulf.moneroocean.stream:10128"),
+    ("hashvault", "global", "pool.hashvault.pro:3333"),
+    ("nanopool", "eu", "xmr-eu1.nanopool.org:14444"),
+    ("nanopool", "eu", "xmr-eu2.nanopool.org:14444"),
+    ("nanopool", "us-east", "xmr-us-east1.nanopool.org:14444"),
+    ("nanopool", "us-west", "xmr-us-west1.nanopool.org:14444"),
+    ("nanopool", "asia", "xmr-asia1.nanopool.org:14444"),
+    ("nanopool", "jp", "xmr-jp1.nanopool.org:14444"),
+    ("nanopool", "au", "xmr-au1.nanopool.org:14444"),
+];
+
+// Latency-ranked endpoint order from the last `auto` probe round
+static AUTO_ORDER: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+// How many endpoints supervisor failover has skipped past so far
+static FAILOVER_OFFSET: AtomicUsize = AtomicUsize::new(0);
+
+// The [[pools]] entry [pool] default names
+pub fn default_entry(config: &Config) -> Option<&PoolEntry> {
+    let name = config.pool.default.as_ref()?;
+    config.pools.iter().find(|entry| &entry.name == name)
+}
+
+// The saved pool an endpoint belongs to, preferring the default one
+pub fn saved<'a>(config: &'a Config, endpoint: &str) -> Option<&'a PoolEntry> {
+    default_entry(config)
+        .filter(|entry| entry.address() == endpoint)
+        .or_else(|| config.pools.iter().find(|entry| entry.address() == endpoint))
+}
+
+// Login, password and TLS flag for one endpoint: the saved pool's where it
+// has them, [wallet] and [pool] otherwise
+pub fn login(config: &Config, endpoint: &str) -> (Option<String>, String, bool) {
+    let entry = saved(config, endpoint);
+    (
+        entry.and_then(|entry| entry.user.clone()).or_else(|| config.wallet.address.clone()),
+        entry.and_then(|entry| entry.password.clone()).unwrap_or_else(|| config.pool.password.clone()),
+        entry.map_or(config.pool.tls, |entry| entry.tls),
+    )
+}
+
+// [pool] url (or the default entry) followed by the failover endpoints,
+// without duplicates. Failover entries may name saved pools.
+pub fn endpoints(config: &Config) -> Vec<String> {
+    let primary = default_entry(config).map(PoolEntry::address).or_else(|| config.pool.url.clone());
+    let failover = config.pool.failover.iter().map(|endpoint| {
+        match config.pools.iter().find(|entry| &entry.name == endpoint) {
+            Some(entry) => entry.address(),
+            None => endpoint.clone(),
+        }
+    });
+    let mut endpoints: Vec<String> = Vec::new();
+    for endpoint in primary.into_iter().chain(failover) {
+        if !endpoints.contains(&endpoint) {
+            endpoints.push(endpoint);
+        }
+    }
+    endpoints
+}
+
+// Where the miner is connected in the configured order, e.g. "sx, backup 1
+// of 2"; None for pools that are not configured
+pub fn describe(config: &Config, endpoint: &str) -> Option<String> {
+    let endpoints = endpoints(config);
+    let index = endpoints.iter().position(|e| e == endpoint)?;
+    let position = match index {
+        0 => "primary".to_string(),
+        _ => format!("backup {} of {}", index, endpoints.len() - 1),
+    };
+    Some(match saved(config, endpoint) {
+        Some(entry) => format!("{}, {}", entry.name, position),
+        None => position,
+    })
+}
+
+// Endpoints in the order the miner should try them, starting from the one
+// supervisor failover has moved on to
+pub fn ordered_endpoints(config: &Config) -> Vec<String> {
+    let configured = endpoints(config);
+    let auto_order = if config.pool.auto { AUTO_ORDER.lock().ok().and_then(|order| order.clone()) } else { None };
+
+    let mut ordered = match auto_order {
+        // Only trust the ranking while it still covers the same endpoints
+        Some(order) if order.len() == configured.len() && order.iter().all(|e| configured.contains(e)) => order,
+        _ => configured,
+    };
+    if !ordered.is_empty() {
+        let offset = FAILOVER_OFFSET.load(Ordering::SeqCst) % ordered.len();
+        ordered.rotate_left(offset);
+    }
+    ordered
+}
+
+// Move the miner to the next endpoint; the watchdog notices the changed
+// target and restarts the miner against it
+fn fail_over(config: &Config, reason: &str) {
+    let from = ordered_endpoints(config).first().cloned().unwrap_or_default();
+    FAILOVER_OFFSET.fetch_add(1, Ordering::SeqCst);
+    let to = ordered_endpoints(config).first().cloned().unwrap_or_default();
+
+    warn!("Failing over from pool {} to {}: {}", from, to, reason);
+    events::emit(Event::PoolFailover { from, to, reason: reason.to_string() });
+}
+
+// Watch the miner's pool connection through its HTTP API and fail over when
+// it stays disconnected for `failover_after_mins`. The miner's own failover
+// only retries its list; this also covers pools that accept the connection
+// but never hand out work.
+pub fn start_failover_watch(config: &Config, running: Arc<AtomicBool>) {
+    let limit = Duration::from_secs(config.pool.failover_after_mins * 60);
+    if limit.is_zero() || config.solo.enabled || endpoints(config).len() < 2 {
+        return;
+    }
+
+    let config = config.clone();
+    thread::spawn(move || {
+        let mut down_since: Option<Instant> = None;
+        while running.load(Ordering::SeqCst) {
+            if pause::is_paused() {
+                down_since = None;
+            } else {
+                match telemetry::miner_connection(&config.miner.api_url) {
+                    Some((_, uptime)) if uptime > 0 => down_since = None,
+                    Some(_) => {
+                        down_since.get_or_insert_with(Instant::now);
+                    },
+                    // API unreachable: the watchdog deals with a dead miner
+                    None => {},
+                }
+            }
+
+            if down_since.is_some_and(|since| since.elapsed() >= limit) {
+                fail_over(&config, &format!("no pool connection for {} minutes", config.pool.failover_after_mins));
+                down_since = None;
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < CONNECTION_POLL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+// Total time from TCP connect to login answer, if the login went through
+fn latency(probe: &Probe) -> Option<f64> {
+    probe
+        .accepted
+        .then(|| probe.connect_ms + probe.tls_ms.unwrap_or(0.0) + probe.login_ms)
+}
+
+// Fastest first; unreachable or rejecting endpoints keep their relative order at the end
+fn rank(results: &[(String, Option<f64>)]) -> Vec<String> {
+    let mut ranked: Vec<&(String, Option<f64>)> = results.iter().collect();
+    ranked.sort_by(|a, b| match (a.1, b.1) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    ranked.into_iter().map(|(endpoint, _)| endpoint.clone()).collect()
+}
+
+fn probe_all(config: &Config, endpoints: &[String]) -> Vec<(String, Result<Probe, XmrError>)> {
+    let handles: Vec<_> = endpoints
+        .iter()
+        .map(|endpoint| {
+            let endpoint = endpoint.clone();
+            let (user, password, tls) = login(config, &endpoint);
+            thread::spawn(move || {
+                let probe = match user {
+                    Some(user) => retry::run(&endpoint, || stratum::probe(&endpoint, tls, &user, &password, PROBE_TIMEOUT)),
+                    None => Err(no_wallet()),
+                };
+                (endpoint, probe)
+            })
+        })
+        .collect();
+
+    handles.into_iter().filter_map(|handle| handle.join().ok()).collect()
+}
+
+fn reorder(config: &Config) {
+    let endpoints = endpoints(config);
+    let results: Vec<(String, Option<f64>)> = probe_all(config, &endpoints)
+        .into_iter()
+        .map(|(endpoint, probe)| {
+            let latency = probe.as_ref().ok().and_then(latency);
+            (endpoint, latency)
+        })
+        .collect();
+
+    let order = rank(&results);
+    info!("Pool order by latency: {}", order.join(", "));
+    if let Ok(mut current) = AUTO_ORDER.lock() {
+        *current = Some(order);
+    }
+}
+
+// `auto` mode: rank the endpoints before the first miner start and again
+// every `auto_recheck_mins`; the watchdog follows when the fastest changes
+pub fn start_auto_select(config: &Config, running: Arc<AtomicBool>) {
+    if !config.pool.auto || endpoints(config).len() < 2 {
+        return;
+    }
+
+    reorder(config);
+
+    let config = config.clone();
+    let interval = Duration::from_secs(config.pool.auto_recheck_mins.max(1) * 60);
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            let mut waited = Duration::ZERO;
+            while waited < interval && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+            if running.load(Ordering::SeqCst) {
+                reorder(&config);
+            }
+        }
+    });
+}
+
+fn no_wallet() -> XmrError {
+    XmrError::ConfigError("No [wallet] address configured".to_string())
+}
+
+// `pool test`: log in to the pool the miner would use right now (or the one
+// given with --pool) and report latency and whether the login was accepted
+pub fn test(config: &Config, pool: Option<&str>, tls: bool) -> Result<(), XmrError> {
+    let target = split::current_target(config);
+    let (url, wallet, password, tls) = match (pool, &target) {
+        (Some(url), target) => {
+            let wallet = target.as_ref().map(|target| target.wallet.clone()).or_else(|| config.wallet.address.clone());
+            (url.to_string(), wallet.ok_or_else(no_wallet)?, config.pool.password.clone(), tls)
+        },
+        (None, Some(target)) if target.daemon => {
+            return Err(XmrError::ConfigError("Solo mining talks to a node, not a pool; try `solo`".to_string()));
+        },
+        (None, Some(target)) => {
+            let (wallet, password, target_tls) = target.login(0);
+            (target.pool.clone(), wallet, password, target_tls || tls)
+        },
+        (None, None) => return Err(XmrError::ConfigError("No [pool] url configured".to_string())),
+    };
+
+    println!("Testing {}{} ...", url, if tls { " (TLS)" } else { "" });
+    let probe = retry::run(&url, || stratum::probe(&url, tls, &wallet, &password, PROBE_TIMEOUT))?;
+
+    println!("TCP connect:   {:.0} ms", probe.connect_ms);
+    if let Some(tls_ms) = probe.tls_ms {
+        println!("TLS handshake: {:.0} ms", tls_ms);
+    }
+    println!("Login:         {:.0} ms", probe.login_ms);
+
+    if probe.accepted {
+        println!("Result:        login accepted for {}", wallet);
+        Ok(())
+    } else {
+        let reason = probe.error.unwrap_or_else(|| "no job in the response".to_string());
+        Err(XmrError::NetworkError(format!("Pool rejected the login: {}", reason)))
+    }
+}
+
+// `pool ping`: resolve every configured endpoint (plus the built-in
+// regional list with --regional), connect over TCP and TLS where the pool
+// uses it, and log in, which on Monero pools is also the job subscription.
+// The probes run in parallel and print fastest first.
+pub fn ping(config: &Config, regional: bool) -> Result<(), XmrError> {
+    let mut endpoints = endpoints(config);
+    if regional {
+        for (_, _, endpoint) in REGIONAL_ENDPOINTS {
+            if !endpoints.iter().any(|e| e == endpoint) {
+                endpoints.push(endpoint.to_string());
+            }
+        }
+    }
+    if endpoints.is_empty() {
+        return Err(XmrError::ConfigError("No pools configured; set [pool] url or use --regional".to_string()));
+    }
+
+    let results = probe_all(config, &endpoints);
+    let order = rank(
+        &results
+            .iter()
+            .map(|(endpoint, probe)| (endpoint.clone(), probe.as_ref().ok().and_then(latency)))
+            .collect::<Vec<_>>(),
+    );
+
+    let outcome = |probe: &Result<Probe, XmrError>| match probe {
+        Ok(probe) => match (&probe.error, probe.accepted) {
+            (_, true) => "ok".to_string(),
+            (Some(error), false) => format!("rejected: {}", error),
+            (None, false) => "rejected".to_string(),
+        },
+        Err(e) => e.to_string(),
+    };
+    let sorted = order.iter().filter_map(|endpoint| results.iter().find(|(e, _)| e == endpoint));
+
+    if output::json() {
+        let pools: Vec<_> = sorted
+            .map(|(endpoint, probe)| {
+                let probe_ok = probe.as_ref().ok();
+                json!({
+                    "endpoint": endpoint,
+                    "address": probe_ok.and_then(|p| p.peer).map(|peer| peer.to_string()),
+                    "resolve_ms": probe_ok.and_then(|p| p.resolve_ms),
+                    "connect_ms": probe_ok.map(|p| p.connect_ms),
+                    "tls_ms": probe_ok.and_then(|p| p.tls_ms),
+                    "login_ms": probe_ok.map(|p| p.login_ms),
+                    "total_ms": probe_ok.and_then(latency),
+                    "ok": probe_ok.is_some_and(|p| p.accepted),
+                    "result": outcome(probe),
+                })
+            })
+            .collect();
+        output::ok(json!({ "pools": pools }));
+        return Ok(());
+    }
+
+    let ms = |v: f64| format!("{:.0} ms", v);
+    let dash = || "-".to_string();
+    println!(
+        "{:<40}  {:<22}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  Result",
+        "Endpoint", "Address", "DNS", "Connect", "TLS", "Login", "Total"
+    );
+    for (endpoint, probe) in sorted {
+        let probe_ok = probe.as_ref().ok();
+        println!(
+            "{:<40}  {:<22}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}  {}",
+            endpoint,
+            probe_ok.and_then(|p| p.peer).map(|peer| peer.to_string()).unwrap_or_else(dash),
+            probe_ok.and_then(|p| p.resolve_ms).map(ms).unwrap_or_else(dash),
+            probe_ok.map(|p| ms(p.connect_ms)).unwrap_or_else(dash),
+            probe_ok.and_then(|p| p.tls_ms).map(ms).unwrap_or_else(dash),
+            probe_ok.map(|p| ms(p.login_ms)).unwrap_or_else(dash),
+            probe_ok.and_then(latency).map(ms).unwrap_or_else(dash),
+            outcome(probe)
+        );
+    }
+
+    // Every reachable pool refusing the login usually means a bad wallet address
+    let answered: Vec<&Probe> = results.iter().filter_map(|(_, probe)| probe.as_ref().ok()).collect();
+    if !answered.is_empty() && answered.iter().all(|probe| !probe.accepted) {
+        warn!("Every reachable pool rejected the login; check the wallet address");
+    }
+    Ok(())
+}
+
+// `pool suggest`: regional endpoints of well-known pools closest to the
+// user, followed by the pools that route globally
+pub fn suggest(geoip: bool) -> Result<(), XmrError> {
+    let region = region::detect(geoip);
+    match region {
+        Some(region) => println!("Detected region: {}", region),
+        None if geoip => println!("Could not detect your region; showing global endpoints."),
+        None => println!("Could not detect your region; try --geoip to look it up by IP address."),
+    }
+
+    println!("Suggested pool endpoints:");
+    let nearby = REGIONAL_ENDPOINTS.iter().filter(|(_, r, _)| Some(*r) == region);
+    let global = REGIONAL_ENDPOINTS.iter().filter(|(_, r, _)| *r == "global");
+    for (pool, region, endpoint) in nearby.chain(global) {
+        println!("  {:<12}  {:<8}  {}", pool, region, endpoint);
+    }
+    println!("Save one with `pool add <name> <endpoint> --default`, or set it as [pool] url (and others as");
+    println!("[pool] failover) in {}", config::config_path()?.display());
+    Ok(())
+}
+
+// "stratum+ssl://host:port", "host:port" or "host": the host, the port
+// if given, and whether the scheme asks for TLS
+fn parse_address(url: &str) -> Result<(String, Option<u16>, bool), XmrError> {
+    let invalid = || XmrError::ConfigError(format!("Invalid pool address {:?}", url));
+    let (rest, tls) = match url.split_once("://") {
+        Some((scheme, rest)) => match scheme {
+            "stratum+ssl" | "stratum+tls" | "ssl" | "tls" => (rest, true),
+            "stratum+tcp" | "stratum" | "tcp" => (rest, false),
+            _ => return Err(invalid()),
+        },
+        None => (url, false),
+    };
+    let rest = rest.trim_end_matches('/');
+    match rest.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() => Ok((host.to_string(), Some(port.parse().map_err(|_| invalid())?), tls)),
+        None if !rest.is_empty() => Ok((rest.to_string(), None, tls)),
+        _ => Err(invalid()),
+    }
+}
+
+fn pools_mut(document: &mut DocumentMut) -> Result<&mut ArrayOfTables, XmrError> {
+    if !document.contains_key("pools") {
+        document.insert("pools", Item::ArrayOfTables(ArrayOfTables::new()));
+    }
+    document["pools"]
+        .as_array_of_tables_mut()
+        .ok_or_else(|| XmrError::ConfigError("pools in the config file is not a list of [[pools]] tables".to_string()))
+}
+
+fn position(pools: &ArrayOfTables, name: &str) -> Option<usize> {
+    pools.iter().position(|pool| pool.get("name").and_then(|n| n.as_str()) == Some(name))
+}
+
+fn no_such_pool(name: &str) -> XmrError {
+    XmrError::ConfigError(format!("No saved pool named {}; see `pool list`", name))
+}
+
+// `pool add`: save a pool as a [[pools]] entry
+pub fn add(
+    name: &str,
+    url: &str,
+    port: Option<u16>,
+    tls: bool,
+    user: Option<String>,
+    password: Option<String>,
+    make_default: bool,
+) -> Result<(), XmrError> {
+    let (host, url_port, url_tls) = parse_address(url)?;
+    let Some(port) = port.or(url_port) else {
+        return Err(XmrError::ConfigError(format!("{} has no port; add one or pass --port", url)));
+    };
+    let config = config::edit(|document| {
+        let pools = pools_mut(document)?;
+        if position(pools, name).is_some() {
+            return Err(XmrError::ConfigError(format!("A pool named {} is already saved; remove it first", name)));
+        }
+        let mut entry = Table::new();
+        entry["name"] = value(name);
+        entry["host"] = value(host.as_str());
+        entry["port"] = value(i64::from(port));
+        entry["tls"] = value(tls || url_tls);
+        if let Some(user) = &user {
+            entry["user"] = value(user.as_str());
+        }
+        if let Some(password) = &password {
+            entry["password"] = value(password.as_str());
+        }
+        pools.push(entry);
+        if make_default {
+            config::section(document, "pool")?["default"] = value(name);
+        }
+        Ok(())
+    })?;
+
+    if output::json() {
+        output::ok(json!({ "pool": name, "default": config.pool.default.as_deref() == Some(name) }));
+    } else if make_default {
+        println!("Saved pool {} ({}:{}) and mining on it from now on.", name, host, port);
+    } else {
+        println!("Saved pool {} ({}:{}); `pool set-default {}` mines on it.", name, host, port, name);
+    }
+    Ok(())
+}
+
+// `pool list`
+pub fn list(config: &Config) -> Result<(), XmrError> {
+    let default = default_entry(config).map(|entry| entry.name.as_str());
+    if output::json() {
+        let pools: Vec<_> = config
+            .pools
+            .iter()
+            .map(|entry| {
+                json!({
+                    "name": entry.name,
+                    "host": entry.host,
+                    "port": entry.port,
+                    "tls": entry.tls,
+                    "user": entry.user,
+                    "default": Some(entry.name.as_str()) == default,
+                })
+            })
+            .collect();
+        output::ok(json!({ "pools": pools }));
+        return Ok(());
+    }
+
+    if config.pools.is_empty() {
+        println!("No saved pools; add one with `pool add <name> <host:port>`.");
+        return Ok(());
+    }
+    println!("  {:<16}  {:<40}  {:<3}  User", "Name", "Address", "TLS");
+    for entry in &config.pools {
+        println!(
+            "{} {:<16}  {:<40}  {:<3}  {}",
+            if Some(entry.name.as_str()) == default { "*" } else { " " },
+            entry.name,
+            entry.address(),
+            if entry.tls { "yes" } else { "no" },
+            entry.user.as_deref().unwrap_or("(wallet)")
+        );
+    }
+    if default.is_none()
+        && let Some(url) = &config.pool.url
+    {
+        println!("Mining on [pool] url {}.", url);
+    }
+    Ok(())
+}
+
+// `pool remove`
+pub fn remove(name: &str) -> Result<(), XmrError> {
+    let mut was_default = false;
+    config::edit(|document| {
+        let pools = pools_mut(document)?;
+        let index = position(pools, name).ok_or_else(|| no_such_pool(name))?;
+        pools.remove(index);
+        if let Some(pool) = document.get_mut("pool").and_then(Item::as_table_like_mut)
+            && pool.get("default").and_then(Item::as_str) == Some(name)
+        {
+            pool.remove("default");
+            was_default = true;
+        }
+        Ok(())
+    })?;
+
+    if output::json() {
+        output::ok(json!({ "pool": name, "was_default": was_default }));
+    } else if was_default {
+        println!("Removed pool {}; it was the default, so mining falls back to [pool] url.", name);
+    } else {
+        println!("Removed pool {}.", name);
+    }
+    Ok(())
+}
+
+// `pool set-default`
+pub fn set_default(name: &str) -> Result<(), XmrError> {
+    config::edit(|document| {
+        let pools = pools_mut(document)?;
+        position(pools, name).ok_or_else(|| no_such_pool(name))?;
+        config::section(document, "pool")?["default"] = value(name);
+        Ok(())
+    })?;
+
+    if output::json() {
+        output::ok(json!({ "pool": name }));
+    } else {
+        println!("Mining on pool {} from now on; `restart` switches a running supervisor over.", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_by_latency() {
+        let results = vec![
+            ("slow".to_string(), Some(250.0)),
+            ("dead".to_string(), None),
+            ("fast".to_string(), Some(40.0)),
+            ("refused".to_string(), None),
+        ];
+        assert_eq!(rank(&results), vec!["fast", "slow", "dead", "refused"]);
+    }
+
+    #[test]
+    fn test_endpoints_dedup() {
+        let config: Config = toml::from_str(
+            "[pool]\nurl = \"a:3333\"\nfailover = [\"b:3333\", \"a:3333\", \"c:3333\"]\n",
+        )
+        .unwrap();
+        assert_eq!(endpoints(&config), vec!["a:3333", "b:3333", "c:3333"]);
+    }
+
+    #[test]
+    fn test_default_entry_replaces_url() {
+        let config: Config = toml::from_str(
+            "[pool]\nurl = \"a:3333\"\ndefault = \"b\"\n\n[[pools]]\nname = \"b\"\nhost = \"b.example\"\nport = 443\ntls = true\n",
+        )
+        .unwrap();
+        assert_eq!(endpoints(&config), vec!["b.example:443"]);
+    }
+
+    #[test]
+    fn test_failover_names_saved_pools() {
+        let config: Config = toml::from_str(
+            "[pool]\nurl = \"a:3333\"\nfailover = [\"b\", \"c:3333\"]\n\n[[pools]]\nname = \"b\"\nhost = \"b.example\"\nport = 443\ntls = true\n",
+        )
+        .unwrap();
+        assert_eq!(endpoints(&config), vec!["a:3333", "b.example:443", "c:3333"]);
+        assert_eq!(describe(&config, "b.example:443").as_deref(), Some("b, backup 1 of 2"));
+        assert_eq!(describe(&config, "a:3333").as_deref(), Some("primary"));
+        assert_eq!(describe(&config, "elsewhere:1"), None);
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(parse_address("stratum+ssl://pool.example:443").unwrap(), ("pool.example".to_string(), Some(443), true));
+        assert_eq!(parse_address("pool.example:3333").unwrap(), ("pool.example".to_string(), Some(3333), false));
+        assert_eq!(parse_address("pool.example").unwrap(), ("pool.example".to_string(), None, false));
+        assert!(parse_address("http://pool.example:80").is_err());
+        assert!(parse_address("pool.example:http").is_err());
+    }
+}
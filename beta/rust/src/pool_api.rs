@@ -0,0 +1,141 @@
+use serde_json::Value;
+
+use crate::http;
+use crate::run::XmrError;
+
+// Monero amounts on nodejs-pool APIs are in atomic units (piconero)
+const ATOMIC_UNITS_PER_XMR: f64 = 1e12;
+
+// Per-wallet figures as reported by the pool
+#[derive(Debug, Clone, Default)]
+pub struct WalletStats {
+    pub unpaid: f64,
+    pub pending: Option<f64>,
+    pub total_paid: Option<f64>,
+    pub hashrate: Option<f64>,
+}
+
+// A payment the pool has sent to the wallet
+#[derive(Debug, Clone)]
+pub struct PoolPayout {
+    pub timestamp: i64,
+    pub amount: f64,
+    pub tx_hash: Option<String>,
+}
+
+// One adapter per pool family; the pool's public API needs no credentials
+pub trait PoolApi {
+    fn name(&self) -> &'static str;
+    fn wallet_stats(&self, wallet: &str) -> Result<WalletStats, XmrError>;
+
+    // Recent payouts, newest first; None when the pool has no payout endpoint
+    fn payouts(&self, _wallet: &str) -> Result<Option<Vec<PoolPayout>>, XmrError> {
+        Ok(None)
+    }
+}
+
+// Some pools report milliseconds, others seconds
+fn unix_seconds(ts: f64) -> i64 {
+    if ts > 1e12 { (ts / 1000.0) as i64 } else { ts as i64 }
+}
+
+// nodejs-pool based pools (supportxmr, moneroocean, ...)
+struct NodejsPool {
+    name: &'static str,
+    base_url: &'static str,
+}
+
+impl PoolApi for NodejsPool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn wallet_stats(&self, wallet: &str) -> Result<WalletStats, XmrError> {
+        let stats = http::get_json(&format!("{}/miner/{}/stats", self.base_url, wallet))?;
+        let atomic = |v: &Value| v.as_f64().map(|n| n / ATOMIC_UNITS_PER_XMR);
+
+        Ok(WalletStats {
+            unpaid: atomic(&stats["amtDue"]).unwrap_or_default(),
+            pending: None,
+            total_paid: atomic(&stats["amtPaid"]),
+            hashrate: stats["hash"].as_f64(),
+        })
+    }
+
+    fn payouts(&self, wallet: &str) -> Result<Option<Vec<PoolPayout>>, XmrError> {
+        let payments = http::get_json(&format!("{}/miner/{}/payments", self.base_url, wallet))?;
+        let payouts = payments
+            .as_array()
+            .map(|list| {
+                list.iter()
+                    .map(|p| PoolPayout {
+                        timestamp: unix_seconds(p["ts"].as_f64().unwrap_or_default()),
+                        amount: p["amount"].as_f64().unwrap_or_default() / ATOMIC_UNITS_PER_XMR,
+                        tx_hash: p["txnHash"].as_str().map(str::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Some(payouts))
+    }
+}
+
+struct Nanopool;
+
+impl PoolApi for Nanopool {
+    fn name(&self) -> &'static str {
+        "nanopool"
+    }
+
+    fn wallet_stats(&self, wallet: &str) -> Result<WalletStats, XmrError> {
+        let response = http::get_json(&format!("https://api.nanopool.org/v1/xmr/user/{}", wallet))?;
+        if response["status"].as_bool() != Some(true) {
+            let error = response["error"].as_str().unwrap_or("unknown error");
+            return Err(XmrError::NetworkError(format!("nanopool: {}", error)));
+        }
+
+        // nanopool reports amounts as decimal XMR, sometimes quoted
+        let number = |v: &Value| v.as_f64().or_else(|| v.as_str().and_then(|s| s.parse().ok()));
+        let data = &response["data"];
+
+        Ok(WalletStats {
+            unpaid: number(&data["balance"]).unwrap_or_default(),
+            pending: number(&data["unconfirmed_balance"]),
+            total_paid: None,
+            hashrate: number(&data["hashrate"]),
+        })
+    }
+
+    fn payouts(&self, wallet: &str) -> Result<Option<Vec<PoolPayout>>, XmrError> {
+        let response = http::get_json(&format!("https://api.nanopool.org/v1/xmr/payments/{}", wallet))?;
+        let payouts = response["data"]
+            .as_array()
+            .map(|list| {
+                list.iter()
+                    .map(|p| PoolPayout {
+                        timestamp: unix_seconds(p["date"].as_f64().unwrap_or_default()),
+                        amount: p["amount"].as_f64().unwrap_or_default(),
+                        tx_hash: p["txHash"].as_str().map(str::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Some(payouts))
+    }
+}
+
+pub const SUPPORTED_POOLS: [&str; 3] = ["supportxmr", "moneroocean", "nanopool"];
+
+// Look up the adapter for a pool name from the config
+pub fn adapter(name: &str) -> Result<Box<dyn PoolApi>, XmrError> {
+    match name.to_lowercase().as_str() {
+        "supportxmr" => Ok(Box::new(NodejsPool { name: "supportxmr", base_url: "https://supportxmr.com/api" })),
+        "moneroocean" => Ok(Box::new(NodejsPool { name: "moneroocean", base_url: "https://api.moneroocean.stream" })),
+        "nanopool" => Ok(Box::new(Nanopool)),
+        other => Err(XmrError::ConfigError(format!(
+            "Unsupported pool API '{}' (supported: {})",
+            other,
+            SUPPORTED_POOLS.join(", ")
+        ))),
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::config::{BatteryAction, Config, PowerConfig};
+use crate::control;
+use crate::cpu;
+use crate::pause;
+
+const PAUSE_REASON: &str = "on battery";
+// How often the power supply is checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// One entry of /sys/class/power_supply
+#[derive(Debug, Clone, PartialEq)]
+struct Supply {
+    kind: String,
+    online: bool,
+    status: String,
+    capacity: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    Ac,
+    // With the lowest charge of the batteries, where known
+    Battery(Option<u8>),
+}
+
+// On AC while any charger is plugged in; on battery while one discharges.
+// A machine without batteries is always on AC.
+fn power(supplies: &[Supply]) -> Power {
+    if supplies.iter().any(|supply| supply.kind != "Battery" && supply.online) {
+        return Power::Ac;
+    }
+    let batteries: Vec<&Supply> = supplies.iter().filter(|supply| supply.kind == "Battery").collect();
+    if !batteries.iter().any(|battery| battery.status == "Discharging") {
+        return Power::Ac;
+    }
+    Power::Battery(batteries.iter().filter_map(|battery| battery.capacity).min())
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).map(|s| s.trim().to_string()).unwrap_or_default()
+}
+
+fn supplies() -> Vec<Supply> {
+    fs::read_dir("/sys/class/power_supply")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let dir = entry.path();
+            Supply {
+                kind: read(&dir.join("type")),
+                online: read(&dir.join("online")) == "1",
+                status: read(&dir.join("status")),
+                capacity: read(&dir.join("capacity")).parse().ok(),
+            }
+        })
+        .collect()
+}
+
+pub fn current() -> Power {
+    power(&supplies())
+}
+
+// Whether to pause, and the CPU cap to mine with otherwise, on `power`
+fn decide(config: &PowerConfig, power: Power) -> (bool, Option<u8>) {
+    let Power::Battery(charge) = power else {
+        return (false, None);
+    };
+    if config.min_charge.is_some_and(|min| charge.is_some_and(|charge| charge < min)) {
+        return (true, None);
+    }
+    match config.on_battery {
+        BatteryAction::Mine => (false, None),
+        BatteryAction::Throttle => (false, Some(config.battery_cpu_cap.clamp(1, 100))),
+        BatteryAction::Pause => (true, None),
+    }
+}
+
+fn describe(power: Power) -> String {
+    match power {
+        Power::Ac => "on AC power".to_string(),
+        Power::Battery(Some(charge)) => format!("on battery at {}%", charge),
+        Power::Battery(None) => "on battery".to_string(),
+    }
+}
+
+// Apply [power] for the current power supply; the miner is restarted when
+// its CPU cap changes
+fn check(config: &PowerConfig, last: &mut Option<Power>) {
+    let power = current();
+    let (paused, throttle) = decide(config, power);
+    if last.is_none_or(|last| decide(config, last) != (paused, throttle)) {
+        info!(
+            "Running {}: {}",
+            describe(power),
+            match (paused, throttle) {
+                (true, _) => "pausing mining".to_string(),
+                (false, Some(cap)) => format!("mining on {}% of the CPUs", cap),
+                (false, None) => "mining at full speed".to_string(),
+            }
+        );
+    }
+    *last = Some(power);
+    pause::set(PAUSE_REASON, paused);
+    if cpu::set_cap(PAUSE_REASON, throttle) {
+        control::restart_miner("the CPU cap changed with the power supply");
+    }
+}
+
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    let power = config.power.clone();
+    if power.on_battery == BatteryAction::Mine && power.min_charge.is_none() {
+        return;
+    }
+    let mut last = None;
+    // Before the first miner start, so it starts throttled if it must
+    cpu::set_cap(PAUSE_REASON, decide(&power, current()).1);
+    check(&power, &mut last);
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            let mut waited = Duration::ZERO;
+            while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+            if running.load(Ordering::SeqCst) {
+                check(&power, &mut last);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(kind: &str, online: bool, status: &str, capacity: Option<u8>) -> Supply {
+        Supply { kind: kind.to_string(), online, status: status.to_string(), capacity }
+    }
+
+    #[test]
+    fn test_power() {
+        let charger = supply("Mains", true, "", None);
+        let unplugged = supply("Mains", false, "", None);
+        let battery = supply("Battery", false, "Discharging", Some(64));
+        assert_eq!(power(&[charger, battery.clone()]), Power::Ac);
+        assert_eq!(power(&[unplugged.clone(), battery.clone()]), Power::Battery(Some(64)));
+        assert_eq!(power(&[unplugged, supply("Battery", false, "Full", Some(100))]), Power::Ac);
+        assert_eq!(power(&[battery, supply("Battery", false, "Discharging", Some(20))]), Power::Battery(Some(20)));
+        // Desktops
+        assert_eq!(power(&[]), Power::Ac);
+    }
+
+    #[test]
+    fn test_decide() {
+        let mut config = PowerConfig { on_battery: BatteryAction::Throttle, battery_cpu_cap: 30, min_charge: Some(25) };
+        assert_eq!(decide(&config, Power::Ac), (false, None));
+        assert_eq!(decide(&config, Power::Battery(Some(80))), (false, Some(30)));
+        assert_eq!(decide(&config, Power::Battery(Some(10))), (true, None));
+        assert_eq!(decide(&config, Power::Battery(None)), (false, Some(30)));
+        config.on_battery = BatteryAction::Pause;
+        assert_eq!(decide(&config, Power::Battery(Some(80))), (true, None));
+        config.on_battery = BatteryAction::Mine;
+        assert_eq!(decide(&config, Power::Battery(Some(80))), (false, None));
+    }
+}
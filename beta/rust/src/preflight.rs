@@ -0,0 +1,191 @@
+use std::fs;
+
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::config::Config;
+use crate::cpu;
+use crate::output;
+use crate::run::XmrError;
+use crate::tune;
+
+// RandomX wants 2 MiB of L3 cache per mining thread
+const SCRATCHPAD_KIB: u64 = 2 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_aes() -> Option<bool> {
+    Some(std::arch::is_x86_feature_detected!("aes"))
+}
+
+#[cfg(target_arch = "aarch64")]
+fn has_aes() -> Option<bool> {
+    Some(std::arch::is_aarch64_feature_detected!("aes"))
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn has_aes() -> Option<bool> {
+    None
+}
+
+fn aes_check() -> Check {
+    let (ok, detail) = match has_aes() {
+        Some(true) => (true, "hardware AES".to_string()),
+        Some(false) => (false, "no hardware AES; RandomX falls back to software AES at a fraction of the hashrate".to_string()),
+        None => (true, "unknown on this architecture".to_string()),
+    };
+    Check { name: "AES", ok, detail }
+}
+
+// Whether the kernel has 2 MiB pages at all; reserving them is `tune hugepages`
+fn huge_page_check(meminfo: Option<&str>) -> Check {
+    let name = "2 MiB pages";
+    let Some(meminfo) = meminfo else {
+        return Check { name, ok: true, detail: "unknown on this system".to_string() };
+    };
+    match tune::meminfo_field(meminfo, "Hugepagesize:") {
+        Some(2048) => {
+            let reserved = tune::meminfo_field(meminfo, "HugePages_Total:").unwrap_or(0);
+            let detail = if reserved == 0 {
+                "supported, none reserved; `tune hugepages` reserves them".to_string()
+            } else {
+                format!("supported, {} reserved", reserved)
+            };
+            Check { name, ok: true, detail }
+        },
+        Some(kib) => Check { name, ok: false, detail: format!("the kernel uses {} KiB huge pages, not 2 MiB", kib) },
+        None => Check { name, ok: false, detail: "the kernel has no huge page support".to_string() },
+    }
+}
+
+fn l3_check(l3_bytes: Option<u64>, threads: u64) -> Check {
+    let name = "L3 cache";
+    let Some(bytes) = l3_bytes else {
+        return Check { name, ok: true, detail: "size unknown".to_string() };
+    };
+    let kib = bytes / 1024;
+    let needed = threads * SCRATCHPAD_KIB;
+    if kib >= needed {
+        Check { name, ok: true, detail: format!("{} MiB for {} threads", kib / 1024, threads) }
+    } else {
+        Check {
+            name,
+            ok: false,
+            detail: format!("{} MiB, less than the {} MiB {} threads need; use fewer threads", kib / 1024, needed / 1024, threads),
+        }
+    }
+}
+
+// Free memory, counting huge pages already reserved, against what fast mode
+// needs for its datasets
+fn memory_check(meminfo: Option<&str>, needed_mib: u64) -> Check {
+    let name = "RAM";
+    let Some(meminfo) = meminfo else {
+        return Check { name, ok: true, detail: "unknown on this system".to_string() };
+    };
+    let available = tune::meminfo_field(meminfo, "MemAvailable:").unwrap_or(0);
+    let huge_free = tune::meminfo_field(meminfo, "HugePages_Free:").unwrap_or(0)
+        * tune::meminfo_field(meminfo, "Hugepagesize:").unwrap_or(0);
+    let available_mib = (available + huge_free) / 1024;
+    if available_mib >= needed_mib {
+        Check { name, ok: true, detail: format!("{} MiB available, fast mode needs {} MiB", available_mib, needed_mib) }
+    } else {
+        Check {
+            name,
+            ok: false,
+            detail: format!("{} MiB available, fast mode needs {} MiB; the miner can only run in light mode", available_mib, needed_mib),
+        }
+    }
+}
+
+// Everything RandomX fast mode depends on, for the miners `config` starts
+pub fn checks(config: &Config) -> Vec<Check> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok();
+    let (miners, threads) = tune::workload(config);
+    vec![
+        aes_check(),
+        huge_page_check(meminfo.as_deref()),
+        l3_check(cpu::detect(config.miner.cpus.as_ref()).l3_bytes, threads),
+        memory_check(meminfo.as_deref(), tune::fast_mode_mib(miners, threads)),
+    ]
+}
+
+// Before starting the miner: warn about what will make it fail or crawl,
+// or refuse to start with --strict
+pub fn ensure_capable(config: &Config, strict: bool) -> Result<(), XmrError> {
+    let problems: Vec<Check> = checks(config).into_iter().filter(|check| !check.ok).collect();
+    if problems.is_empty() {
+        debug!("Preflight: this machine can run RandomX in fast mode");
+        return Ok(());
+    }
+    for check in &problems {
+        warn!("Preflight: {}: {}", check.name, check.detail);
+    }
+    if strict {
+        let names: Vec<&str> = problems.iter().map(|check| check.name).collect();
+        return Err(XmrError::ExecutionError(format!(
+            "This machine cannot run RandomX in fast mode ({}); start without --strict to mine anyway",
+            names.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+// `preflight`: print every check
+pub fn report(config: &Config, strict: bool) -> Result<(), XmrError> {
+    let checks = checks(config);
+    let ready = checks.iter().all(|check| check.ok);
+    if output::json() {
+        let list: Vec<_> = checks
+            .iter()
+            .map(|check| json!({ "name": check.name, "ok": check.ok, "detail": check.detail }))
+            .collect();
+        output::ok(json!({ "ready": ready, "checks": list }));
+    } else {
+        for check in &checks {
+            println!("{:<4}  {:<12} {}", if check.ok { "ok" } else { "warn" }, check.name, check.detail);
+        }
+        if ready {
+            println!("Ready for RandomX in fast mode.");
+        }
+    }
+    if strict && !ready {
+        return Err(XmrError::ExecutionError("This machine cannot run RandomX in fast mode".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "MemTotal: 8000000 kB\nMemAvailable: 2000000 kB\nHugePages_Total: 600\nHugePages_Free: 600\nHugepagesize: 2048 kB\n";
+
+    #[test]
+    fn test_memory_check() {
+        // 1953 MiB free plus 1200 MiB of reserved huge pages
+        assert!(memory_check(Some(MEMINFO), 2338).ok);
+        assert!(!memory_check(Some(MEMINFO), 4000).ok);
+        assert!(memory_check(None, 4000).ok);
+    }
+
+    #[test]
+    fn test_huge_page_check() {
+        assert!(huge_page_check(Some(MEMINFO)).ok);
+        assert!(!huge_page_check(Some("MemTotal: 1 kB\n")).ok);
+        assert!(!huge_page_check(Some("Hugepagesize: 16384 kB\n")).ok);
+    }
+
+    #[test]
+    fn test_l3_check() {
+        assert!(l3_check(Some(16 * 1024 * 1024), 8).ok);
+        assert!(!l3_check(Some(8 * 1024 * 1024), 8).ok);
+        assert!(l3_check(None, 8).ok);
+    }
+}
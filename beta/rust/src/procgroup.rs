@@ -0,0 +1,76 @@
+use std::io;
+use std::process::{Child, Command};
+
+#[cfg(unix)]
+use crate::pidfd::Signal;
+
+// Start the process in a process group of its own (with its PID as the
+// group ID), so it and everything it forks can be signalled together and
+// a Ctrl+C meant for the supervisor does not reach it directly
+pub fn isolate(command: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command
+}
+
+// Send `signal` to the child's whole process group. Only while the child
+// has not been reaped: until then its PID, and so the group ID, cannot have
+// been handed to another process.
+#[cfg(unix)]
+fn signal_group(child: &mut Child, signal: Signal) -> io::Result<()> {
+    if child.try_wait()?.is_some() {
+        return Err(io::Error::other(format!("process {} already exited", child.id())));
+    }
+    // SAFETY: killpg only takes a process group ID and a signal number
+    if unsafe { libc::killpg(child.id() as libc::pid_t, signal.number()) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// Ask the child and its helpers to exit
+pub fn terminate(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    return signal_group(child, Signal::Terminate);
+    #[cfg(not(unix))]
+    {
+        let _ = child;
+        Ok(())
+    }
+}
+
+// Kill the child together with any processes it forked. Falls back to the
+// child alone when it was not started with `isolate` or the group is gone.
+pub fn kill(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    if signal_group(child, Signal::Kill).is_ok() {
+        return Ok(());
+    }
+    child.kill()
+}
+
+// Stop the child and its helpers where they are, keeping their memory, until
+// `thaw`
+pub fn freeze(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    return signal_group(child, Signal::Stop);
+    #[cfg(not(unix))]
+    {
+        let _ = child;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "freezing processes is not supported on this platform"))
+    }
+}
+
+pub fn thaw(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    return signal_group(child, Signal::Continue);
+    #[cfg(not(unix))]
+    {
+        let _ = child;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "freezing processes is not supported on this platform"))
+    }
+}
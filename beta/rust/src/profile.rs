@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use serde_json::json;
+use toml_edit::{value, Item, Table};
+
+use crate::config::{self, Config, Profile, Threads};
+use crate::hours::Hours;
+use crate::output;
+use crate::run::XmrError;
+
+// Shipped profiles; a [profiles.<name>] table of the same name replaces one
+fn builtin() -> BTreeMap<String, Profile> {
+    BTreeMap::from([
+        ("eco".to_string(), Profile { nice: Some(19), cpu_cap: Some(25), ..Profile::default() }),
+        ("balanced".to_string(), Profile { nice: Some(10), cpu_cap: Some(50), ..Profile::default() }),
+        ("max".to_string(), Profile { threads: Some(Threads::Auto), ..Profile::default() }),
+    ])
+}
+
+// Built-in profiles with the config file's on top
+pub fn all(config: &Config) -> BTreeMap<String, Profile> {
+    let mut profiles = builtin();
+    profiles.extend(config.profiles.clone());
+    profiles
+}
+
+fn validate(profile: &Profile) -> Result<(), XmrError> {
+    if let Some(nice) = profile.nice
+        && !(-20..=19).contains(&nice)
+    {
+        return Err(XmrError::ConfigError(format!("nice must be between -20 and 19, got {}", nice)));
+    }
+    if let Some(cap) = profile.cpu_cap
+        && !(1..=100).contains(&cap)
+    {
+        return Err(XmrError::ConfigError(format!("cpu_cap must be a percentage from 1 to 100, got {}", cap)));
+    }
+    if let Some(hours) = &profile.hours {
+        Hours::parse(hours, None)?;
+    }
+    Ok(())
+}
+
+fn no_such_profile(config: &Config, name: &str) -> XmrError {
+    let names: Vec<String> = all(config).into_keys().collect();
+    XmrError::ConfigError(format!("No profile named {}; there are {}", name, names.join(", ")))
+}
+
+// Put profile `name` over [miner]
+pub fn apply(config: &mut Config, name: &str) -> Result<(), XmrError> {
+    let profile = all(config).remove(name).ok_or_else(|| no_such_profile(config, name))?;
+    validate(&profile)?;
+    let miner = &mut config.miner;
+    if profile.threads.is_some() {
+        miner.threads = profile.threads;
+    }
+    if profile.nice.is_some() {
+        miner.nice = profile.nice;
+    }
+    if profile.cpu_cap.is_some() {
+        miner.cpu_cap = profile.cpu_cap;
+    }
+    if profile.hours.is_some() {
+        miner.hours = profile.hours;
+    }
+    miner.profile = Some(name.to_string());
+    Ok(())
+}
+
+fn describe(profile: &Profile) -> String {
+    let mut parts = Vec::new();
+    match profile.threads {
+        Some(Threads::Auto) => parts.push("threads auto".to_string()),
+        Some(Threads::Count(count)) => parts.push(format!("{} threads", count)),
+        None => {},
+    }
+    if let Some(nice) = profile.nice {
+        parts.push(format!("nice {}", nice));
+    }
+    if let Some(cap) = profile.cpu_cap {
+        parts.push(format!("{}% of CPUs", cap));
+    }
+    if let Some(hours) = &profile.hours {
+        parts.push(format!("hours {}", hours));
+    }
+    if parts.is_empty() { "[miner] settings".to_string() } else { parts.join(", ") }
+}
+
+// `profile list`
+pub fn list(config: &Config) -> Result<(), XmrError> {
+    let profiles = all(config);
+    let current = config.miner.profile.as_deref();
+    if output::json() {
+        let list: Vec<_> = profiles
+            .iter()
+            .map(|(name, profile)| {
+                json!({
+                    "name": name,
+                    "threads": profile.threads.map(|threads| match threads {
+                        Threads::Auto => json!("auto"),
+                        Threads::Count(count) => json!(count),
+                    }),
+                    "nice": profile.nice,
+                    "cpu_cap": profile.cpu_cap,
+                    "hours": profile.hours,
+                    "builtin": !config.profiles.contains_key(name),
+                    "default": Some(name.as_str()) == current,
+                })
+            })
+            .collect();
+        output::ok(json!({ "profiles": list }));
+        return Ok(());
+    }
+
+    println!("  {:<12}  Settings", "Name");
+    for (name, profile) in &profiles {
+        println!(
+            "{} {:<12}  {}{}",
+            if Some(name.as_str()) == current { "*" } else { " " },
+            name,
+            describe(profile),
+            if config.profiles.contains_key(name) { "" } else { " (built in)" }
+        );
+    }
+    Ok(())
+}
+
+// `profile set`: create or change [profiles.<name>]; a built-in profile is
+// copied into the file first so the fields not given keep their values
+pub fn set(config: &Config, name: &str, changes: Profile) -> Result<(), XmrError> {
+    let mut profile = all(config).remove(name).unwrap_or_default();
+    if changes.threads.is_some() {
+        profile.threads = changes.threads;
+    }
+    if changes.nice.is_some() {
+        profile.nice = changes.nice;
+    }
+    if changes.cpu_cap.is_some() {
+        profile.cpu_cap = changes.cpu_cap;
+    }
+    if changes.hours.is_some() {
+        profile.hours = changes.hours;
+    }
+    validate(&profile)?;
+
+    config::edit(|document| {
+        let profiles = config::section(document, "profiles")?;
+        profiles.set_implicit(true);
+        if !profiles.contains_key(name) {
+            profiles.insert(name, Item::Table(Table::new()));
+        }
+        let table = profiles[name]
+            .as_table_mut()
+            .ok_or_else(|| XmrError::ConfigError(format!("profiles.{} in the config file is not a table", name)))?;
+        match profile.threads {
+            Some(Threads::Auto) => table["threads"] = value("auto"),
+            Some(Threads::Count(count)) => table["threads"] = value(count as i64),
+            None => {},
+        }
+        if let Some(nice) = profile.nice {
+            table["nice"] = value(nice as i64);
+        }
+        if let Some(cap) = profile.cpu_cap {
+            table["cpu_cap"] = value(cap as i64);
+        }
+        if let Some(hours) = &profile.hours {
+            table["hours"] = value(hours.as_str());
+        }
+        Ok(())
+    })?;
+
+    if output::json() {
+        output::ok(json!({ "profile": name, "settings": describe(&profile) }));
+    } else {
+        println!("Saved profile {}: {}.", name, describe(&profile));
+        println!("Use it with `run --profile {}` or [miner] profile = \"{}\".", name, name);
+    }
+    Ok(())
+}
+
+// `profile remove`: drop [profiles.<name>]; a built-in profile of that name
+// comes back as shipped
+pub fn remove(name: &str) -> Result<(), XmrError> {
+    let mut removed = false;
+    config::edit(|document| {
+        if let Some(profiles) = document.get_mut("profiles").and_then(Item::as_table_like_mut) {
+            removed = profiles.remove(name).is_some();
+        }
+        Ok(())
+    })?;
+    if !removed {
+        return Err(XmrError::ConfigError(format!("No [profiles.{}] in the config file", name)));
+    }
+
+    let restored = builtin().contains_key(name);
+    if output::json() {
+        output::ok(json!({ "profile": name, "builtin_restored": restored }));
+    } else if restored {
+        println!("Removed your {} profile; the built-in one applies again.", name);
+    } else {
+        println!("Removed profile {}.", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut config: Config =
+            toml::from_str("[miner]\nthreads = 8\n\n[profiles.eco]\ncpu_cap = 10\nhours = \"01:00-05:00\"\n").unwrap();
+        apply(&mut config, "eco").unwrap();
+        // The file's eco replaces the built-in one entirely
+        assert_eq!(config.miner.nice, None);
+        assert_eq!(config.miner.cpu_cap, Some(10));
+        assert_eq!(config.miner.threads, Some(Threads::Count(8)));
+        assert_eq!(config.miner.profile.as_deref(), Some("eco"));
+
+        apply(&mut config, "balanced").unwrap();
+        assert_eq!(config.miner.nice, Some(10));
+        assert!(apply(&mut config, "turbo").is_err());
+    }
+}
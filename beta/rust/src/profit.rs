@@ -0,0 +1,126 @@
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::http;
+use crate::market;
+use crate::run::XmrError;
+use crate::telemetry;
+
+// Monero targets one block every two minutes
+const BLOCKS_PER_DAY: f64 = 720.0;
+const NETWORK_INFO_URL: &str = "https://xmrchain.net/api/networkinfo";
+const RAPL_ENERGY: &str = "/sys/class/powercap/intel-rapl:0/energy_uj";
+
+// Inputs to the estimate, each tagged with where it came from
+pub struct Inputs {
+    pub hashrate: (f64, &'static str),
+    pub difficulty: (f64, &'static str),
+    pub block_reward: f64,
+    pub watts: Option<(f64, &'static str)>,
+}
+
+fn network_difficulty() -> Result<f64, XmrError> {
+    let info = http::get_json(NETWORK_INFO_URL)?;
+    info["data"]["difficulty"]
+        .as_f64()
+        .ok_or_else(|| XmrError::NetworkError("Network info did not include the difficulty".to_string()))
+}
+
+// Package power from RAPL, sampled over one second. Only Intel and recent
+// AMD CPUs expose it, and reading energy_uj usually needs root.
+fn rapl_watts() -> Option<f64> {
+    let read = || fs::read_to_string(RAPL_ENERGY).ok()?.trim().parse::<f64>().ok();
+
+    let start = Instant::now();
+    let before = read()?;
+    thread::sleep(Duration::from_secs(1));
+    let after = read()?;
+
+    // The counter wraps around; skip the sample rather than report nonsense
+    (after >= before).then(|| (after - before) / 1e6 / start.elapsed().as_secs_f64())
+}
+
+pub fn gather(config: &Config, hashrate: Option<f64>, watts: Option<f64>) -> Result<Inputs, XmrError> {
+    let hashrate = match hashrate {
+        Some(h) => (h, "given"),
+        None => match telemetry::miner_hashrate(&config.miner.api_url) {
+            Some(h) => (h, "measured"),
+            None => {
+                return Err(XmrError::ExecutionError(
+                    "Could not read the miner's hashrate; is it running? Pass --hashrate <H/s> to estimate anyway"
+                        .to_string(),
+                ))
+            }
+        },
+    };
+
+    let difficulty = match config.profit.difficulty {
+        Some(d) => (d, "configured"),
+        None => (network_difficulty()?, "network"),
+    };
+
+    let watts = match (watts, config.profit.watts) {
+        (Some(w), _) => Some((w, "given")),
+        (None, Some(w)) => Some((w, "configured")),
+        (None, None) => rapl_watts().map(|w| (w, "RAPL")),
+    };
+
+    Ok(Inputs { hashrate, difficulty, block_reward: config.profit.block_reward, watts })
+}
+
+// Expected XMR per day: our share of the network's hashes times daily emission
+pub fn daily_xmr(hashrate: f64, difficulty: f64, block_reward: f64) -> f64 {
+    if difficulty <= 0.0 {
+        return 0.0;
+    }
+    hashrate * 86_400.0 / difficulty * block_reward
+}
+
+// `profit`: estimate daily revenue, electricity cost and profit for this rig
+pub fn report(config: &Config, hashrate: Option<f64>, watts: Option<f64>) -> Result<(), XmrError> {
+    let inputs = gather(config, hashrate, watts)?;
+    let xmr_per_day = daily_xmr(inputs.hashrate.0, inputs.difficulty.0, inputs.block_reward);
+
+    let currency = config.market.fiat.clone().unwrap_or_else(|| "usd".to_string());
+    let price = market::xmr_price(&config.market, &currency);
+    let unit = currency.to_uppercase();
+
+    println!("Hashrate:            {} ({})", telemetry::format_hashrate(inputs.hashrate.0), inputs.hashrate.1);
+    println!("Network difficulty:  {:.0} ({})", inputs.difficulty.0, inputs.difficulty.1);
+    println!("Block reward:        {} XMR (~{} blocks/day)", inputs.block_reward, BLOCKS_PER_DAY);
+    match &price {
+        Ok(price) => println!("Expected revenue:    {:.8} XMR/day ({:.4} {})", xmr_per_day, xmr_per_day * price, unit),
+        Err(e) => println!("Expected revenue:    {:.8} XMR/day (no price: {})", xmr_per_day, e),
+    }
+
+    let Some((watts, source)) = inputs.watts else {
+        println!("Power draw:          unknown (set [profit] watts or pass --watts)");
+        return Ok(());
+    };
+    let kwh_per_day = watts * 24.0 / 1000.0;
+    let cost = kwh_per_day * config.profit.kwh_price;
+    println!("Power draw:          {:.1} W ({}), {:.2} kWh/day", watts, source, kwh_per_day);
+    println!("Electricity cost:    {:.4} {}/day at {} {}/kWh", cost, unit, config.profit.kwh_price, unit);
+
+    if let Ok(price) = price {
+        let profit = xmr_per_day * price - cost;
+        let verdict = if profit >= 0.0 { "profit" } else { "loss" };
+        println!("Estimated {}:    {:.4} {}/day ({:.2} {}/month)", verdict, profit, unit, profit * 30.0, unit);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_xmr() {
+        // 1 kH/s against a difficulty of 86.4M hashes/block finds 1 block/day
+        assert!((daily_xmr(1000.0, 86_400_000.0, 0.6) - 0.6).abs() < 1e-9);
+        assert_eq!(daily_xmr(1000.0, 0.0, 0.6), 0.0);
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::Path;
+use std::process::Command;
+
+use tracing::{info, warn};
+
+use crate::config::{self, MinerConfig};
+use crate::events::{self, Event};
+use crate::init::Installation;
+use crate::run::XmrError;
+
+// Run [miner] post_install_hook with the installed release in the
+// environment, returning its exit code (-1 when it could not be run)
+fn run_hook(hook: &str, command: &str, installation: &Installation, binary: &Path) -> i32 {
+    let release = installation.release.as_ref();
+    info!("Running post-install hook {}", hook);
+    let status = Command::new(hook)
+        .env("MINNING_COMMAND", command)
+        .env("MINNING_TAG", release.map_or("", |r| r.tag.as_str()))
+        .env("MINNING_XMR_DIR", &installation.xmr_dir)
+        .env("MINNING_BINARY", binary)
+        .env("MINNING_ARCHIVE_SHA256", release.map_or("", |r| r.sha256.as_str()))
+        .env("MINNING_BINARY_SHA256", release.and_then(|r| r.binary_sha256.as_deref()).unwrap_or_default())
+        .status();
+    match status {
+        Ok(status) if status.success() => 0,
+        Ok(status) => {
+            warn!("Post-install hook {} failed: {}", hook, status);
+            status.code().unwrap_or(-1)
+        },
+        Err(e) => {
+            warn!("Could not run post-install hook {}: {}", hook, e);
+            -1
+        },
+    }
+}
+
+fn completed(command: &str, miner: &MinerConfig, installation: &Installation) -> Event {
+    let binary = installation.xmr_dir.join("xmr");
+    // Hooks react to a new install; a no-op run has nothing to hand them
+    let hook_exit_code = match &miner.post_install_hook {
+        Some(hook) if installation.changed => Some(run_hook(hook, command, installation, &binary)),
+        _ => None,
+    };
+    let release = installation.release.as_ref();
+    Event::InstallCompleted {
+        command: command.to_string(),
+        tag: release.map(|r| r.tag.clone()),
+        xmr_dir: installation.xmr_dir.display().to_string(),
+        binary: binary.display().to_string(),
+        archive_sha256: release.map(|r| r.sha256.clone()),
+        binary_sha256: release.and_then(|r| r.binary_sha256.clone()),
+        changed: installation.changed,
+        hook_exit_code,
+    }
+}
+
+// Final step of `init` and `update`: run the post-install hook, then publish
+// the outcome as an install_completed / install_failed event (a JSON line
+// with --events-json) and to `result_file` when given, so provisioning tools
+// can chain on it. Fails when the install or its hook failed.
+pub fn finish(
+    command: &str,
+    miner: &MinerConfig,
+    outcome: Result<Installation, XmrError>,
+    result_file: Option<&str>,
+) -> Result<(), XmrError> {
+    let (event, result) = match outcome {
+        Ok(installation) => {
+            let event = completed(command, miner, &installation);
+            let result = match &event {
+                Event::InstallCompleted { hook_exit_code: Some(code), .. } if *code != 0 => {
+                    Err(XmrError::ExecutionError(format!("post-install hook failed with exit code {}", code)))
+                },
+                _ => Ok(()),
+            };
+            (event, result)
+        },
+        Err(e) => (Event::InstallFailed { command: command.to_string(), error: e.to_string() }, Err(e)),
+    };
+
+    if let Some(path) = result_file {
+        let contents = format!("{:#}\n", events::record(&event));
+        if let Err(e) = config::write_atomic(Path::new(path), &contents) {
+            warn!("Could not write the {} result to {}: {}", command, path, e);
+        }
+    }
+    events::emit(event);
+    result
+}
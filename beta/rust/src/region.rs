@@ -0,0 +1,114 @@
+use std::env;
+use std::fs;
+
+use tracing::debug;
+
+use crate::http;
+
+// Opt-in lookup; only ever used when the user passes --geoip
+const GEOIP_URL: &str = "https://ipapi.co/json/";
+
+const EU_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "CH", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GB", "GR", "HR", "HU", "IE", "IS",
+    "IT", "LT", "LU", "LV", "MT", "NL", "NO", "PL", "PT", "RO", "SE", "SI", "SK", "UA",
+];
+
+// Regions used by the pool endpoint table: eu, us-east, us-west, asia, jp, au
+pub fn region_for_timezone(timezone: &str) -> Option<&'static str> {
+    let (continent, city) = timezone.split_once('/')?;
+    match continent {
+        "Europe" | "Africa" => Some("eu"),
+        "America" | "US" | "Canada" => match city {
+            "Los_Angeles" | "Vancouver" | "Denver" | "Phoenix" | "Edmonton" | "Boise" | "Tijuana" | "Anchorage"
+            | "Pacific" | "Mountain" => Some("us-west"),
+            _ => Some("us-east"),
+        },
+        "Asia" if city == "Tokyo" => Some("jp"),
+        "Asia" => Some("asia"),
+        "Australia" => Some("au"),
+        "Pacific" if city == "Auckland" => Some("au"),
+        _ => None,
+    }
+}
+
+pub fn region_for_country(country: &str) -> Option<&'static str> {
+    let country = country.to_uppercase();
+    match country.as_str() {
+        "US" | "CA" | "MX" | "BR" | "AR" => Some("us-east"),
+        "JP" => Some("jp"),
+        "AU" | "NZ" => Some("au"),
+        "CN" | "HK" | "SG" | "KR" | "TW" | "IN" | "TH" | "VN" | "MY" | "ID" | "PH" => Some("asia"),
+        c if EU_COUNTRIES.contains(&c) => Some("eu"),
+        _ => None,
+    }
+}
+
+// IANA name of the local timezone from TZ, /etc/timezone or /etc/localtime
+fn local_timezone() -> Option<String> {
+    if let Ok(tz) = env::var("TZ")
+        && tz.contains('/')
+    {
+        return Some(tz.trim_start_matches(':').to_string());
+    }
+    if let Ok(tz) = fs::read_to_string("/etc/timezone") {
+        return Some(tz.trim().to_string());
+    }
+
+    let target = fs::read_link("/etc/localtime").ok()?;
+    let target = target.to_string_lossy();
+    target.split_once("zoneinfo/").map(|(_, tz)| tz.to_string())
+}
+
+// Country from a locale such as "de_DE.UTF-8"
+fn locale_country() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find_map(|locale| {
+            let (_, country) = locale.split('.').next()?.split_once('_')?;
+            Some(country.to_string())
+        })
+}
+
+fn geoip_region() -> Option<&'static str> {
+    let response = http::get_json(GEOIP_URL).ok()?;
+    response["timezone"]
+        .as_str()
+        .and_then(region_for_timezone)
+        .or_else(|| response["country_code"].as_str().and_then(region_for_country))
+}
+
+// Best guess at the user's region: the timezone first, then the locale,
+// then (only when allowed) an IP geolocation lookup
+pub fn detect(geoip: bool) -> Option<&'static str> {
+    let timezone = local_timezone();
+    debug!("Local timezone: {:?}", timezone);
+
+    timezone
+        .as_deref()
+        .and_then(region_for_timezone)
+        .or_else(|| locale_country().as_deref().and_then(region_for_country))
+        .or_else(|| if geoip { geoip_region() } else { None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_for_timezone() {
+        assert_eq!(region_for_timezone("Europe/Berlin"), Some("eu"));
+        assert_eq!(region_for_timezone("America/New_York"), Some("us-east"));
+        assert_eq!(region_for_timezone("America/Los_Angeles"), Some("us-west"));
+        assert_eq!(region_for_timezone("Asia/Tokyo"), Some("jp"));
+        assert_eq!(region_for_timezone("Asia/Singapore"), Some("asia"));
+        assert_eq!(region_for_timezone("Etc/UTC"), None);
+    }
+
+    #[test]
+    fn test_region_for_country() {
+        assert_eq!(region_for_country("de"), Some("eu"));
+        assert_eq!(region_for_country("NZ"), Some("au"));
+        assert_eq!(region_for_country("ZZ"), None);
+    }
+}
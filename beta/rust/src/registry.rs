@@ -0,0 +1,280 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::config;
+use crate::control;
+use crate::events::{self, Event, Sink};
+use crate::pause;
+use crate::pidfd::{self, ProcessHandle, Signal};
+use crate::pidfile;
+use crate::run::XmrError;
+
+// Every miner the supervisors of this instance have started and not yet
+// seen exit, so one left behind by a crashed supervisor is not mined
+// alongside
+const REGISTRY_FILE: &str = "miners.json";
+// How long a replaced miner or supervisor gets to exit on its own
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Serializes updates to the file between the watchdogs of one supervisor
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    pid: u32,
+    // Kernel start times, which tell the processes apart from later ones
+    // given the same PID
+    started: Option<u64>,
+    supervisor: u32,
+    supervisor_started: Option<u64>,
+}
+
+fn verified(pid: u32, started: Option<u64>) -> Option<ProcessHandle> {
+    match started {
+        Some(started) => ProcessHandle::open_verified(pid, started),
+        None => Some(ProcessHandle::open(pid)).filter(|process| process.alive()),
+    }
+}
+
+impl Entry {
+    fn ours(pid: u32) -> Entry {
+        let supervisor = process::id();
+        Entry { pid, started: pidfd::start_time(pid), supervisor, supervisor_started: pidfd::start_time(supervisor) }
+    }
+
+    // The miner, unless it has exited; a zombie waiting to be reaped counts
+    // as exited
+    fn miner(&self) -> Option<ProcessHandle> {
+        verified(self.pid, self.started).filter(|miner| miner.state() != Some('Z'))
+    }
+
+    fn owner(&self) -> Option<ProcessHandle> {
+        verified(self.supervisor, self.supervisor_started)
+    }
+}
+
+fn path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(REGISTRY_FILE))
+}
+
+fn load() -> Vec<Entry> {
+    path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(entries: &[Entry]) -> Result<(), XmrError> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(entries)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode the miner registry: {}", e)))?;
+    config::write_atomic(&path, &contents)
+}
+
+// Apply `change` to the registry, dropping miners that are gone
+fn update(change: impl FnOnce(&mut Vec<Entry>)) {
+    let _guard = LOCK.lock();
+    let mut entries = load();
+    change(&mut entries);
+    entries.retain(|entry| entry.miner().is_some());
+    if let Err(e) = save(&entries) {
+        debug!("Could not update the miner registry: {}", e);
+    }
+}
+
+struct RegistrySink;
+
+impl Sink for RegistrySink {
+    fn handle(&self, event: &Event) {
+        match event {
+            Event::ChildStarted { pid } => update(|entries| entries.push(Entry::ours(*pid))),
+            Event::ChildExited { pid, .. } => update(|entries| entries.retain(|entry| entry.pid != *pid)),
+            _ => {},
+        }
+    }
+}
+
+// What to do about a miner this instance already has running
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Existing {
+    #[default]
+    Refuse,
+    // Keep it mining and supervise it until it exits
+    Adopt,
+    // Stop it and start afresh
+    Replace,
+}
+
+impl Existing {
+    pub fn from_flags(adopt: bool, replace: bool) -> Existing {
+        match (adopt, replace) {
+            (true, _) => Existing::Adopt,
+            (_, true) => Existing::Replace,
+            _ => Existing::Refuse,
+        }
+    }
+}
+
+// Ask `process` to exit and kill it when it does not in time
+fn stop(process: &ProcessHandle, signal: Signal, what: &str) -> Result<(), XmrError> {
+    info!("Stopping {} (PID {})", what, process.pid());
+    process
+        .signal(signal)
+        .map_err(|e| XmrError::PermissionError(format!("Could not signal {} (PID {}): {}", what, process.pid(), e)))?;
+    let started = Instant::now();
+    while process.alive() && process.state() != Some('Z') {
+        if started.elapsed() >= STOP_TIMEOUT {
+            warn!("{} (PID {}) did not exit within {} seconds, killing it", what, process.pid(), STOP_TIMEOUT.as_secs());
+            let _ = process.signal(Signal::Kill);
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    Ok(())
+}
+
+// --replace: stop the supervisor holding the PID file of a state directory,
+// miner and all, so this one can take it over
+fn replace_supervisor(state_dir: &Path) -> Result<(), XmrError> {
+    match pidfile::supervisor_in(state_dir) {
+        Some(supervisor) if supervisor.pid() != process::id() => {
+            stop(&supervisor, Signal::Interrupt, "the running supervisor")
+        },
+        _ => Ok(()),
+    }
+}
+
+// Before the PID file and the first miner start: check for miners of this
+// instance that are still running. One whose supervisor is alive (a
+// foreground `run`, say) is never mined alongside; one left by a crashed
+// supervisor is adopted or replaced as asked. Returns the miners adopted.
+// From here on, started miners are recorded.
+pub fn claim(existing: Existing) -> Result<Vec<ProcessHandle>, XmrError> {
+    if existing == Existing::Replace {
+        replace_supervisor(&config::state_dir()?)?;
+    }
+    let mut adopted = Vec::new();
+    {
+        let _guard = LOCK.lock();
+        let mut entries = load();
+        entries.retain(|entry| entry.supervisor != process::id() && entry.miner().is_some());
+        for entry in &mut entries {
+            let Some(miner) = entry.miner() else { continue };
+            match (entry.owner(), existing) {
+                (Some(_), Existing::Refuse | Existing::Adopt) => {
+                    return Err(XmrError::ExecutionError(format!(
+                        "Miner PID {} is already supervised by PID {}; stop that first, or start with --replace",
+                        entry.pid, entry.supervisor
+                    )));
+                },
+                (Some(owner), Existing::Replace) => {
+                    stop(&owner, Signal::Interrupt, "the supervisor of the running miner")?;
+                    if entry.miner().is_some() {
+                        stop(&miner, Signal::Terminate, "the miner")?;
+                    }
+                },
+                (None, Existing::Refuse) => {
+                    return Err(XmrError::ExecutionError(format!(
+                        "Miner PID {} is still running from a supervisor that exited (PID {}); start with --adopt to keep it or --replace to stop it",
+                        entry.pid, entry.supervisor
+                    )));
+                },
+                (None, Existing::Adopt) => {
+                    info!("Adopting miner PID {} left by supervisor PID {}", entry.pid, entry.supervisor);
+                    *entry = Entry::ours(entry.pid);
+                    adopted.push(miner);
+                },
+                (None, Existing::Replace) => stop(&miner, Signal::Terminate, "the miner left by an earlier supervisor")?,
+            }
+        }
+        entries.retain(|entry| entry.miner().is_some());
+        save(&entries)?;
+    }
+    events::subscribe(Box::new(RegistrySink));
+    Ok(adopted)
+}
+
+// Mine on with adopted miners until they exit. They are stopped on
+// shutdown, on a pause and on a restart request, which need a miner the
+// supervisor started itself.
+pub fn supervise_adopted(adopted: Vec<ProcessHandle>, running: &AtomicBool) {
+    if adopted.is_empty() {
+        return;
+    }
+    let restarts_seen = control::restarts();
+    let alive = |adopted: &[ProcessHandle]| adopted.iter().any(|miner| miner.alive() && miner.state() != Some('Z'));
+    while alive(&adopted) {
+        let reason = if !running.load(Ordering::SeqCst) {
+            Some("shutting down")
+        } else if pause::is_paused() {
+            Some("mining paused")
+        } else if control::restarts() != restarts_seen {
+            Some("restart requested")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            info!("Stopping the adopted miner: {}", reason);
+            for miner in &adopted {
+                if let Err(e) = stop(miner, Signal::Terminate, "the adopted miner") {
+                    warn!("{}", e);
+                }
+            }
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    update(|entries| entries.retain(|entry| !adopted.iter().any(|miner| miner.pid() == entry.pid)));
+    if running.load(Ordering::SeqCst) {
+        info!("The adopted miner is gone; starting one of our own");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries() {
+        let ours = Entry::ours(process::id());
+        assert!(ours.miner().is_some());
+        assert!(ours.owner().is_some());
+        // A PID reused by another process does not count as the miner
+        let reused = Entry { started: ours.started.map(|started| started + 1), ..ours.clone() };
+        assert_eq!(reused.miner().is_some(), ours.started.is_none());
+
+        let entries = vec![ours];
+        let json = serde_json::to_string(&entries).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<Entry>>(&json).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_replace_supervisor() {
+        let dir = std::env::temp_dir().join(format!("minning-registry-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // A live supervisor holding the PID file
+        let mut supervisor = process::Command::new("sleep").arg("30").spawn().unwrap();
+        let started = pidfd::start_time(supervisor.id()).map(|t| format!(" {}", t)).unwrap_or_default();
+        fs::write(dir.join(pidfile::PID_FILE), format!("{}{}\n", supervisor.id(), started)).unwrap();
+        assert_eq!(pidfile::running_in(&dir), Some(supervisor.id()));
+
+        replace_supervisor(&dir).unwrap();
+        assert!(!supervisor.wait().unwrap().success());
+        assert_eq!(pidfile::running_in(&dir), None);
+        // Nothing left to replace
+        replace_supervisor(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
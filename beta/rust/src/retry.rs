@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, warn};
+
+use crate::config::RetryConfig;
+use crate::run::XmrError;
+
+// [retry] settings, installed once at startup like http::configure
+static SETTINGS: OnceLock<RetryConfig> = OnceLock::new();
+
+pub fn configure(retry: &RetryConfig) {
+    let _ = SETTINGS.set(retry.clone());
+}
+
+fn settings() -> RetryConfig {
+    SETTINGS.get().cloned().unwrap_or_default()
+}
+
+// Failures in a row per host, and until when calls to it fail fast
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+static BREAKERS: Mutex<BTreeMap<String, Breaker>> = Mutex::new(BTreeMap::new());
+
+// The host (and port) part of a URL or stratum endpoint; breakers are per host
+fn breaker_key(target: &str) -> &str {
+    let rest = target.split_once("://").map_or(target, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+fn is_open(key: &str) -> Option<Duration> {
+    let breakers = BREAKERS.lock().ok()?;
+    let until = breakers.get(key)?.open_until?;
+    until.checked_duration_since(Instant::now())
+}
+
+fn record(key: &str, ok: bool, settings: &RetryConfig) {
+    let Ok(mut breakers) = BREAKERS.lock() else {
+        return;
+    };
+    let breaker = breakers.entry(key.to_string()).or_default();
+    if ok {
+        *breaker = Breaker::default();
+        return;
+    }
+    breaker.failures += 1;
+    if settings.breaker_failures > 0 && breaker.failures >= settings.breaker_failures {
+        warn!(
+            "{} failed {} times in a row; leaving it alone for {} seconds",
+            key, breaker.failures, settings.breaker_cooldown_secs
+        );
+        breaker.failures = 0;
+        breaker.open_until = Some(Instant::now() + Duration::from_secs(settings.breaker_cooldown_secs));
+    }
+}
+
+// A random duration up to `max`; good enough to keep rigs out of lockstep
+pub fn jitter(max: Duration) -> Duration {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos() as u64);
+    Duration::from_millis(nanos % (max.as_millis() as u64 + 1))
+}
+
+// Exponential backoff for the given retry (0-based), capped, with the
+// upper half randomised so many rigs do not retry in lockstep
+fn backoff(retry: u32, settings: &RetryConfig) -> Duration {
+    let full = settings.base_delay_ms.saturating_mul(1 << retry.min(16)).min(settings.max_delay_ms);
+    Duration::from_millis(full / 2) + jitter(Duration::from_millis(full / 2))
+}
+
+// Run a network operation against `target` with the configured retries,
+// deadline and per-host circuit breaker. Only network errors are retried;
+// anything else (a full disk, a bad config) is returned right away.
+pub fn run<T>(target: &str, mut op: impl FnMut() -> Result<T, XmrError>) -> Result<T, XmrError> {
+    let settings = settings();
+    let key = breaker_key(target);
+    if let Some(remaining) = is_open(key) {
+        return Err(XmrError::NetworkError(format!(
+            "{} is failing; not retrying for another {} seconds",
+            key,
+            remaining.as_secs()
+        )));
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(settings.deadline_secs);
+    let mut retry = 0;
+    loop {
+        match op() {
+            Ok(value) => {
+                record(key, true, &settings);
+                return Ok(value);
+            },
+            Err(XmrError::NetworkError(e)) => {
+                record(key, false, &settings);
+                let delay = backoff(retry, &settings);
+                retry += 1;
+                if retry >= settings.attempts.max(1) || Instant::now() + delay >= deadline || is_open(key).is_some() {
+                    return Err(XmrError::NetworkError(e));
+                }
+                debug!("{} (attempt {}/{}); retrying in {} ms", e, retry, settings.attempts, delay.as_millis());
+                thread::sleep(delay);
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_key() {
+        assert_eq!(breaker_key("https://api.nanopool.org/v1/xmr/user/4abc"), "api.nanopool.org");
+        assert_eq!(breaker_key("stratum+ssl://pool.example.com:443"), "pool.example.com:443");
+        assert_eq!(breaker_key("pool.example.com:3333"), "pool.example.com:3333");
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        let settings = RetryConfig { base_delay_ms: 1000, max_delay_ms: 5000, ..RetryConfig::default() };
+        for retry in 0..6 {
+            let full = (1000u64 << retry).min(5000);
+            let delay = backoff(retry, &settings).as_millis() as u64;
+            assert!(delay >= full / 2 && delay <= full, "retry {}: {} ms", retry, delay);
+        }
+    }
+}
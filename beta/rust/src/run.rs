@@ -1,21 +1,75 @@
-use std::process::{Command, Stdio, Child};
+use std::process::{Command, Stdio, Child, ExitStatus};
 use std::env;
-use std::io;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::fs;
 use std::path::Path;
 use std::fmt;
 
+use tracing::{debug, error, info, warn};
+
+use crate::api;
+use crate::bandwidth;
+use crate::binwatch;
+use crate::cgroup;
+use crate::config::{BatteryAction, Config, CpuList, PauseMode, Threads};
+use crate::cpu;
+use crate::connectivity;
+use crate::control;
+use crate::earnings;
+use crate::health;
+use crate::hooks;
+use crate::hours;
+use crate::logging;
+use crate::events::{self, Event};
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::load;
+use crate::market;
+use crate::metered;
+use crate::metrics;
+use crate::miner_config;
+use crate::minerlog;
+use crate::mqtt;
+use crate::node;
+use crate::notify;
+use crate::numa;
+use crate::pause;
+use crate::pidfile;
+use crate::pool;
+use crate::power;
+use crate::preflight;
+use crate::profile;
+use crate::procgroup;
+use crate::proxy;
+use crate::registry::{self, Existing};
+use crate::scheduler;
+use crate::selfmetrics;
+use crate::sidecar::Sidecar;
+use crate::solo;
+use crate::split::{self, Target};
+use crate::stats;
+use crate::suspend;
+use crate::thermal;
+use crate::wallet;
+
 // For error handling
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum XmrError {
     IoError(io::Error),
     EnvError(String),
     ExecutionError(String),
     PermissionError(String),
+    ConfigError(String),
+    NetworkError(String),
+    // A download did not match its published checksum
+    ChecksumError(String),
+    // A release signature is missing or does not verify
+    SignatureError(String),
 }
 
 // Implement Display for XmrError
@@ -26,6 +80,26 @@ impl fmt::Display for XmrError {
             XmrError::EnvError(s) => write!(f, "Environment error: {}", s),
             XmrError::ExecutionError(s) => write!(f, "Execution error: {}", s),
             XmrError::PermissionError(s) => write!(f, "Permission error: {}", s),
+            XmrError::ConfigError(s) => write!(f, "Config error: {}", s),
+            XmrError::NetworkError(s) => write!(f, "Network error: {}", s),
+            XmrError::ChecksumError(s) => write!(f, "Checksum error: {}", s),
+            XmrError::SignatureError(s) => write!(f, "Signature error: {}", s),
+        }
+    }
+}
+
+impl XmrError {
+    // Short machine-readable kind, reported with --json
+    pub fn category(&self) -> &'static str {
+        match self {
+            XmrError::IoError(_) => "io",
+            XmrError::EnvError(_) => "environment",
+            XmrError::ExecutionError(_) => "execution",
+            XmrError::PermissionError(_) => "permission",
+            XmrError::ConfigError(_) => "config",
+            XmrError::NetworkError(_) => "network",
+            XmrError::ChecksumError(_) => "checksum",
+            XmrError::SignatureError(_) => "signature",
         }
     }
 }
@@ -37,25 +111,12 @@ impl From<io::Error> for XmrError {
     }
 }
 
-// Simplified logging functions
-fn log_info(msg: &str) {
-    println!("[INFO] {}", msg);
-}
-
-fn log_warn(msg: &str) {
-    println!("[WARN] {}", msg);
-}
-
-fn log_error(msg: &str) {
-    eprintln!("[ERROR] {}", msg);
-}
-
-fn log_debug(msg: &str) {
-    println!("[DEBUG] {}", msg);
+pub fn get_xmr_path() -> Result<String, XmrError> {
+    selfmetrics::time("resolve path", find_xmr_path)
 }
 
 // Helper function to get XMR path with better error handling
-fn get_xmr_path() -> Result<String, XmrError> {
+fn find_xmr_path() -> Result<String, XmrError> {
     // Try HOME first
     if let Ok(home_dir) = env::var("HOME") {
         let path = format!("{}/xmr/xmr", home_dir);
@@ -78,15 +139,20 @@ fn get_xmr_path() -> Result<String, XmrError> {
         return Ok(path.to_string());
     }
     
+    // Homebrew on Apple silicon installs outside /usr/local
+    #[cfg(target_os = "macos")]
+    if Path::new("/opt/homebrew/bin/xmr").exists() {
+        return Ok("/opt/homebrew/bin/xmr".to_string());
+    }
+    
     // Last attempt - try to find xmr in PATH
-    if let Ok(output) = Command::new("which").arg("xmr").output() {
-        if output.status.success() {
-            if let Ok(path) = String::from_utf8(output.stdout) {
-                let path = path.trim();
-                if !path.is_empty() {
-                    return Ok(path.to_string());
-                }
-            }
+    if let Ok(output) = Command::new("which").arg("xmr").output()
+        && output.status.success()
+        && let Ok(path) = String::from_utf8(output.stdout)
+    {
+        let path = path.trim();
+        if !path.is_empty() {
+            return Ok(path.to_string());
         }
     }
     
@@ -94,8 +160,8 @@ fn get_xmr_path() -> Result<String, XmrError> {
 }
 
 // Set executable permissions with better error handling
-fn set_executable_permissions(path: &str) -> Result<(), XmrError> {
-    log_debug(&format!("Setting executable permissions for {}", path));
+pub fn set_executable_permissions(path: &str) -> Result<(), XmrError> {
+    debug!("Setting executable permissions for {}", path);
     
     // Try chmod first (Unix systems)
     let chmod_result = Command::new("chmod")
@@ -105,14 +171,14 @@ fn set_executable_permissions(path: &str) -> Result<(), XmrError> {
         
     match chmod_result {
         Ok(status) if status.success() => {
-            log_debug("chmod successful");
+            debug!("chmod successful");
             return Ok(());
         },
         Ok(_) => {
-            log_warn("chmod command failed, trying alternative method");
+            warn!("chmod command failed, trying alternative method");
         },
         Err(e) => {
-            log_warn(&format!("chmod command error: {}, trying alternative method", e));
+            warn!("chmod command error: {}, trying alternative method", e);
         }
     }
     
@@ -126,16 +192,16 @@ fn set_executable_permissions(path: &str) -> Result<(), XmrError> {
                 perms.set_mode(0o755); // rwxr-xr-x
                 match fs::set_permissions(path, perms) {
                     Ok(_) => {
-                        log_debug("Successfully set permissions using fs::set_permissions");
-                        return Ok(());
+                        debug!("Successfully set permissions using fs::set_permissions");
+                        Ok(())
                     },
                     Err(e) => {
-                        return Err(XmrError::PermissionError(format!("Failed to set permissions: {}", e)));
+                        Err(XmrError::PermissionError(format!("Failed to set permissions: {}", e)))
                     }
                 }
             },
             Err(e) => {
-                return Err(XmrError::PermissionError(format!("Failed to get file metadata: {}", e)));
+                Err(XmrError::PermissionError(format!("Failed to get file metadata: {}", e)))
             }
         }
     }
@@ -143,223 +209,561 @@ fn set_executable_permissions(path: &str) -> Result<(), XmrError> {
     // If we get here on non-unix systems, assume it's already executable
     #[cfg(not(unix))]
     {
-        log_debug("Non-Unix system detected, assuming executable permissions are already set");
+        debug!("Non-Unix system detected, assuming executable permissions are already set");
         Ok(())
     }
 }
 
 // The original function - kept for backward compatibility but improved
-pub fn run_xmr() -> Result<(), XmrError> {
-    log_info("Starting run_xmr function");
+pub fn run_xmr(config: &Config) -> Result<(), XmrError> {
+    info!("Starting run_xmr function");
     
     // Get XMR path with better error handling
     let xmr_path = get_xmr_path()?;
-    log_info(&format!("Found XMR at: {}", xmr_path));
+    info!("Found XMR at: {}", xmr_path);
     
     // Set executable permissions
     set_executable_permissions(&xmr_path)?;
+    wallet::ensure_confirmed(config)?;
+    preflight::ensure_capable(config, flags().strict)?;
+    cgroup::prepare(config)?;
+    solo::ensure_ready(config)?;
+    if config.miner.numa {
+        warn!("[miner] numa only starts one miner per NUMA node in run-resilient; starting one");
+    }
+    if config.miner.hours.is_some() {
+        warn!("[miner] hours only pause mining in run-resilient and run-super-resilient; mining now");
+    }
+    if config.power.on_battery != BatteryAction::Mine || config.power.min_charge.is_some() {
+        warn!("[power] only applies in run-resilient and run-super-resilient; mining whatever the power supply");
+    }
+    if config.thermal.max_temp.is_some() {
+        warn!("[thermal] only applies in run-resilient and run-super-resilient; mining however hot the CPU runs");
+    }
+    if config.load.max_other_percent.is_some() {
+        warn!("[load] only applies in run-resilient and run-super-resilient; mining however busy the machine is");
+    }
+    hooks::configure(&config.hooks);
+    registry::supervise_adopted(registry::claim(flags().existing)?, &AtomicBool::new(true));
     
     // Execute with improved error handling and output capture
-    log_info("Executing XMR");
-    let output = Command::new(&xmr_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()?;
-    
-    if output.status.success() {
-        log_info("XMR execution completed successfully");
-        
-        // Log stdout for debugging if needed
-        if let Ok(stdout) = String::from_utf8(output.stdout) {
-            if !stdout.trim().is_empty() {
-                log_debug(&format!("XMR stdout: {}", stdout));
-            }
-        }
-        
+    info!("Executing XMR");
+    hooks::pre_start();
+    // In the foreground the miner stays in our process group, so Ctrl+C
+    // reaches it and everything it started
+    let mut command = miner_command(&xmr_path, config, split::current_target(config).as_ref())?;
+    let mut child = selfmetrics::time("spawn miner", || command.spawn())?;
+    let pid = child.id();
+    cpu::pin_miner(pid, &config.miner);
+    cgroup::enter(pid);
+    events::emit(Event::ChildStarted { pid });
+    minerlog::start_run(&config.logging);
+    // No health checks in the foreground, so nobody reads the output clock
+    let readers = drain_output(&mut child, health::OutputClock::default());
+    
+    let status = child.wait()?;
+    // The last lines are only in the log once the readers are through
+    for reader in readers {
+        let _ = reader.join();
+    }
+    reaped(pid, Some(status), false);
+    
+    if status.success() {
+        info!("XMR execution completed successfully");
         Ok(())
     } else {
-        // Capture error details
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let exit_code = output.status.code().unwrap_or(-1);
+        // Capture error details from the end of the miner's output
+        let output = minerlog::tail(5).join("\n");
+        let exit_code = status.code().unwrap_or(-1);
         
-        log_error(&format!("XMR execution failed with exit code {}: {}", exit_code, stderr));
+        error!("XMR execution failed with exit code {}: {}", exit_code, output);
         
         Err(XmrError::ExecutionError(format!(
             "XMR execution failed with exit code {}: {}", 
             exit_code,
-            stderr
+            output
         )))
     }
 }
 
 // Function to capture and handle CTRL+C with improved handling
-fn setup_ctrlc_handler() -> Arc<AtomicBool> {
+pub fn setup_ctrlc_handler() -> Arc<AtomicBool> {
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
     
+    // Also SIGTERM and SIGHUP, which service managers such as launchd stop with
     match ctrlc::set_handler(move || {
-        log_info("Received CTRL+C, preparing for graceful shutdown...");
+        info!("Received CTRL+C, preparing for graceful shutdown...");
         r.store(false, Ordering::SeqCst);
     }) {
         Ok(_) => {},
         Err(e) => {
-            log_warn(&format!("Failed to set Ctrl-C handler: {}", e));
-            log_warn("Process will continue without Ctrl-C handling");
+            warn!("Failed to set Ctrl-C handler: {}", e);
+            warn!("Process will continue without Ctrl-C handling");
         }
     }
-    
     running
 }
 
 // Set process priority to be resistant to OOM killer
 fn set_process_priority() -> Result<(), XmrError> {
-    log_info("Setting process priority");
+    info!("Setting process priority");
     
     #[cfg(target_os = "linux")]
     {
         // Set process nice value to -20 (highest priority)
-        log_debug("Setting process nice value to -20");
+        debug!("Setting process nice value to -20");
         match Command::new("renice")
             .args(["-n", "-20", "-p", &format!("{}", std::process::id())])
             .output() {
                 Ok(output) => {
                     if !output.status.success() {
                         let stderr = String::from_utf8_lossy(&output.stderr);
-                        log_warn(&format!("Failed to set nice value: {}", stderr));
+                        warn!("Failed to set nice value: {}", stderr);
                     } else {
-                        log_debug("Successfully set nice value");
+                        debug!("Successfully set nice value");
                     }
                 },
                 Err(e) => {
-                    log_warn(&format!("Failed to execute renice command: {}", e));
+                    warn!("Failed to execute renice command: {}", e);
                 }
             }
         
         // Try with alternative methods if renice fails
         if let Err(e) = Command::new("nice")
             .args(["-n", "-20", "echo", "Setting priority"])
+            .stdout(Stdio::null())
             .status() {
-                log_warn(&format!("Nice command also failed: {}", e));
+                warn!("Nice command also failed: {}", e);
             }
         
         // Write -1000 to /proc/self/oom_score_adj
-        log_debug("Setting OOM score to minimum");
+        debug!("Setting OOM score to minimum");
         if fs::write("/proc/self/oom_score_adj", "-1000").is_ok() {
-            log_debug("Successfully set OOM score to minimum");
+            debug!("Successfully set OOM score to minimum");
         } else {
-            log_warn("Failed to set OOM score");
+            warn!("Failed to set OOM score");
             
             // Try alternative method
             if Command::new("echo")
                 .args(["-1000", ">", "/proc/self/oom_score_adj"])
+                .stdout(Stdio::null())
                 .status()
                 .is_ok() {
-                    log_debug("Set OOM score using echo command");
+                    debug!("Set OOM score using echo command");
                 }
         }
     }
     
+    // No OOM killer to hide from; only raise the priority, which the miner
+    // inherits
+    #[cfg(target_os = "macos")]
+    {
+        debug!("Setting process nice value to -20");
+        // SAFETY: setpriority only takes plain integers
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, -20) } == 0 {
+            debug!("Successfully set nice value");
+        } else {
+            warn!("Failed to set nice value: {}", io::Error::last_os_error());
+        }
+    }
+    
     Ok(())
 }
 
-// Function to retry execution with improved error handling and recovery strategies
-fn execute_with_retry(
-    cmd: &str,
-    max_retries: usize,
-    delay_ms: u64
-) -> Result<(), XmrError> {
-    let mut attempt = 0;
+// How long a watchdog waits between rounds while mining is paused
+const PAUSE_POLL: Duration = Duration::from_millis(500);
+
+// While mining is paused, stop the child and keep it stopped. Returns true
+// when the watchdog should skip this round instead of (re)starting XMR, and
+// wait PAUSE_POLL before the next.
+fn hold_while_paused(
+    current_process: &mut Option<Child>,
+    frozen: &mut bool,
+    monitor: &mut health::Monitor,
+    mode: PauseMode,
+    label: &str,
+) -> bool {
+    if !pause::is_paused() {
+        if *frozen && let Some(child) = current_process {
+            info!("{}Mining resumed, thawing XMR process {}", label, child.id());
+            if let Err(e) = procgroup::thaw(child) {
+                error!("{}Failed to thaw XMR process: {}", label, e);
+            }
+            // The time spent frozen is not held against it
+            monitor.resumed();
+        }
+        *frozen = false;
+        return false;
+    }
     
-    while attempt < max_retries {
-        match Command::new(cmd).status() {
-            Ok(status) if status.success() => {
-                return Ok(());
-            },
-            Ok(status) => {
-                let code = status.code().unwrap_or(-1);
-                log_warn(&format!("Command '{}' failed with exit code {}. Retry {}/{}", 
-                     cmd, code, attempt + 1, max_retries));
-            },
-            Err(e) => {
-                log_warn(&format!("Command '{}' failed with error: {}. Retry {}/{}", 
-                     cmd, e, attempt + 1, max_retries));
+    if mode == PauseMode::Freeze && !*frozen && let Some(child) = current_process {
+        info!("{}Mining paused ({}), freezing XMR process {}", label, pause::reasons().join(", "), child.id());
+        match procgroup::freeze(child) {
+            Ok(()) => *frozen = true,
+            Err(e) => error!("{}Failed to freeze XMR process, stopping it instead: {}", label, e),
+        }
+    }
+    if !*frozen && let Some(child) = current_process.take() {
+        info!("{}Mining paused ({}), stopping XMR process {}", label, pause::reasons().join(", "), child.id());
+        kill_child(child, label);
+    }
+    true
+}
+
+// Run-mode flags from the command line. They hold for the life of the
+// supervisor, whatever `restart` reloads.
+#[derive(Debug, Clone, Default)]
+pub struct RunFlags {
+    // Given after `--`, appended to every miner start
+    pub miner_args: Vec<String>,
+    pub threads: Option<Threads>,
+    pub cpus: Option<CpuList>,
+    // Refuse to start when the preflight check finds a problem
+    pub strict: bool,
+    // Profile to use instead of [miner] profile
+    pub profile: Option<String>,
+    // What to do about a miner already running for this instance
+    pub existing: Existing,
+}
+
+impl RunFlags {
+    // Put the profile, then the flags, over the config file's settings
+    fn apply(&self, config: &mut Config) -> Result<(), XmrError> {
+        if let Some(name) = self.profile.clone().or_else(|| config.miner.profile.clone()) {
+            profile::apply(config, &name)?;
+        }
+        if let Some(threads) = self.threads {
+            config.miner.threads = Some(threads);
+        }
+        if let Some(cpus) = &self.cpus {
+            config.miner.cpus = Some(cpus.clone());
+        }
+        Ok(())
+    }
+}
+
+static FLAGS: Mutex<RunFlags> =
+    Mutex::new(RunFlags { miner_args: Vec::new(), threads: None, cpus: None, strict: false, profile: None, existing: Existing::Refuse });
+
+pub fn set_flags(flags: RunFlags, config: &mut Config) -> Result<(), XmrError> {
+    if !flags.miner_args.is_empty() {
+        info!("Passing {} to the miner", flags.miner_args.join(" "));
+    }
+    flags.apply(config)?;
+    hours::validate(config)?;
+    cgroup::validate(config)?;
+    if let Some(name) = &config.miner.profile {
+        info!("Mining with the {} profile", name);
+    }
+    if let Ok(mut current) = FLAGS.lock() {
+        *current = flags;
+    }
+    Ok(())
+}
+
+fn flags() -> RunFlags {
+    FLAGS.lock().map(|flags| flags.clone()).unwrap_or_default()
+}
+
+// Build the XMR command line, pointing it at a pool/wallet when configured
+fn miner_command(xmr_path: &str, config: &Config, target: Option<&Target>) -> io::Result<Command> {
+    let mut command = Command::new(xmr_path);
+    if let Some(target) = target {
+        let target = proxy::route_target(target);
+        if config.miner.generate_config {
+            let path = miner_config::write(config, &target).map_err(|e| io::Error::other(e.to_string()))?;
+            command.arg(format!("--config={}", path.display()));
+        } else {
+            command.args(target.miner_args());
+        }
+    }
+    if let Some(threads) = cpu::threads(&config.miner)
+        && !config.miner.generate_config
+    {
+        command.arg(format!("--threads={}", threads));
+    }
+    if let Some(nice) = config.miner.nice {
+        cpu::nice(&mut command, nice);
+    }
+    if let Some(node) = config.miner.numa_node {
+        numa::prefer_memory(&mut command, node);
+        // Miners next to each other cannot share the API port
+        if let Some((host, port)) = miner_config::api_address(&config.miner.api_url)
+            && !config.miner.generate_config
+        {
+            command.args([format!("--http-host={}", host), format!("--http-port={}", port)]);
+        }
+    }
+    // Last, so they win over what minning sets
+    command.args(flags().miner_args);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    Ok(command)
+}
+
+// Supervised modes start the miner in its own process group, so stopping
+// it also stops any helpers it forked
+fn spawn_miner(xmr_path: &str, config: &Config, target: Option<&Target>) -> io::Result<Child> {
+    hooks::pre_start();
+    let mut command = miner_command(xmr_path, config, target)?;
+    let child = selfmetrics::time("spawn miner", || procgroup::isolate(&mut command).spawn())?;
+    cpu::pin_miner(child.id(), &config.miner);
+    cgroup::enter(child.id());
+    Ok(child)
+}
+
+// Read the miner's output so its pipes never fill up, feeding the log,
+// the output-based health check and `logs`. The readers finish once the
+// miner has exited and its output is recorded.
+fn drain_output(child: &mut Child, output: health::OutputClock) -> Vec<thread::JoinHandle<()>> {
+    fn forward(pipe: impl Read + Send + 'static, output: health::OutputClock) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                output.note();
+                minerlog::record(&line);
+                debug!("XMR: {}", line);
             }
+        })
+    }
+    let stdout = child.stdout.take().map(|pipe| forward(pipe, output.clone()));
+    let stderr = child.stderr.take().map(|pipe| forward(pipe, output));
+    stdout.into_iter().chain(stderr).collect()
+}
+
+// Kill the child when the configured health check finds it unhealthy; the
+// watchdog starts a new one on its next round
+fn enforce_health(current_process: &mut Option<Child>, monitor: &mut health::Monitor, label: &str) {
+    let Some(pid) = current_process.as_ref().map(Child::id) else {
+        return;
+    };
+    let Some(reason) = monitor.poll() else {
+        return;
+    };
+    warn!("{}XMR process is unhealthy ({}), restarting...", label, reason);
+    events::emit(Event::HealthCheckFailed { pid, detail: reason });
+    if let Some(child) = current_process.take() {
+        kill_child(child, label);
+    }
+}
+
+// Once a new miner binary is installed, stop the child (politely first) so
+// the watchdog starts the new one on its next round
+fn restart_on_new_binary(current_process: &mut Option<Child>, seen: &mut u64, label: &str) {
+    let replacements = binwatch::replacements();
+    if replacements == *seen {
+        return;
+    }
+    *seen = replacements;
+    if let Some(child) = current_process.take() {
+        info!("{}Stopping XMR process {} to switch to the new binary", label, child.id());
+        stop_politely(child, label);
+    }
+}
+
+// After `restart`, take over the reloaded config and stop the child so the
+// watchdog starts a fresh one with it on its next round. The restart does
+// not count as a failure.
+fn restart_on_request(
+    current_process: &mut Option<Child>,
+    config: &mut Config,
+    monitor: &mut health::Monitor,
+    seen: &mut u64,
+    label: &str,
+) {
+    let restarts = control::restarts();
+    if restarts == *seen {
+        return;
+    }
+    *seen = restarts;
+    let started_node = config.miner.numa_node;
+    if let Some(reloaded) = control::reloaded_config() {
+        *config = reloaded;
+        if let Err(e) = flags().apply(config) {
+            warn!("{}", e);
         }
-        
-        attempt += 1;
-        thread::sleep(Duration::from_millis(delay_ms));
+        if let Some(node) = started_node {
+            *config = numa::reapply(config, node);
+        }
+        *monitor = health::Monitor::new(config);
+        hooks::configure(&config.hooks);
+    }
+    if let Some(child) = current_process.take() {
+        info!("{}Stopping XMR process {} on request", label, child.id());
+        stop_politely(child, label);
+    }
+}
+
+// A child has exited and been reaped: tell the sinks and run the post-stop
+// hook
+fn reaped(pid: u32, status: Option<ExitStatus>, stopped: bool) {
+    events::emit(Event::ChildExited { pid, code: status.and_then(|status| status.code()), stopped });
+    hooks::post_stop(pid, status);
+}
+
+// Kill the child with its process group and reap it
+fn kill_child(mut child: Child, label: &str) {
+    if let Err(e) = procgroup::kill(&mut child) {
+        error!("{}Failed to kill XMR process: {}", label, e);
+    }
+    reaped(child.id(), child.wait().ok(), true);
+}
+
+// Give the child ten seconds to exit on SIGTERM before killing it
+fn stop_politely(mut child: Child, label: &str) {
+    let _ = procgroup::terminate(&mut child);
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(10) {
+        if let Ok(Some(status)) = child.try_wait() {
+            return reaped(child.id(), Some(status), true);
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    kill_child(child, label);
+}
+
+// Hold a restart back by the configured per-host stagger plus random
+// jitter, so a fleet does not reconnect to a recovering pool all at once.
+// Returns false when shutdown was requested meanwhile.
+fn stagger_restart(config: &Config, running: &AtomicBool, label: &str) -> bool {
+    let delay = health::restart_delay(&config.health);
+    if delay.is_zero() {
+        return true;
+    }
+    info!("{}Restarting XMR in {} seconds", label, delay.as_secs());
+    let started = Instant::now();
+    while started.elapsed() < delay && running.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(200));
+    }
+    running.load(Ordering::SeqCst)
+}
+
+// Credit mining time to the target the child was started with, and stop the
+// child when the wallet split (or pool order) has moved on so the watchdog
+// restarts it against the new target
+fn follow_split(
+    current_process: &mut Option<Child>,
+    started_target: &Option<Target>,
+    wanted_target: &Option<Target>,
+    last_tick: &mut Instant,
+    label: &str,
+) {
+    let elapsed = last_tick.elapsed();
+    *last_tick = Instant::now();
+    
+    if current_process.is_none() {
+        return;
+    }
+    if let Some(target) = started_target {
+        split::credit(&target.name, elapsed.as_secs_f64());
     }
     
-    Err(XmrError::ExecutionError(format!(
-        "Command '{}' failed after {} retries", cmd, max_retries
-    )))
+    if started_target != wanted_target && let Some(child) = current_process.take() {
+        let wanted = wanted_target.as_ref().map_or("(none)".to_string(), |t| format!("{} ({})", t.name, t.pool));
+        info!("{}Switching mining target to {}", label, wanted);
+        kill_child(child, label);
+    }
 }
 
 // Function to create a watchdog that restarts the process if it's killed
-fn create_watchdog(xmr_path: String, running: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+fn create_watchdog(xmr_path: String, mut config: Config, running: Arc<AtomicBool>, id: usize) -> thread::JoinHandle<()> {
     thread::spawn(move || {
+        logging::set_watchdog(id);
+        let label = match config.miner.numa_node {
+            Some(node) => format!("NUMA node {}: ", node),
+            None => String::new(),
+        };
         let mut current_process: Option<Child> = None;
-        let mut consecutive_failures = 0;
+        let mut started_target: Option<Target> = None;
+        let mut last_tick = Instant::now();
+        let mut failures = health::Failures::default();
+        let mut monitor = health::Monitor::new(&config);
+        let mut started_once = false;
+        let mut binaries_seen = binwatch::replacements();
+        let mut restarts_seen = control::restarts();
+        let mut frozen = false;
         const MAX_FAILURES: usize = 5;
         
         while running.load(Ordering::SeqCst) {
+            if hold_while_paused(&mut current_process, &mut frozen, &mut monitor, config.miner.pause_mode, &label) {
+                thread::sleep(PAUSE_POLL);
+                continue;
+            }
+            restart_on_new_binary(&mut current_process, &mut binaries_seen, &label);
+            restart_on_request(&mut current_process, &mut config, &mut monitor, &mut restarts_seen, &label);
+            
+            let wanted_target = split::current_target(&config);
+            follow_split(&mut current_process, &started_target, &wanted_target, &mut last_tick, &label);
+            
             // Check if we need to start/restart the process
             let need_restart = match &mut current_process {
                 None => true,
                 Some(child) => match child.try_wait() {
                     Ok(Some(status)) => {
                         // Process has exited
+                        reaped(child.id(), Some(status), false);
+                        failures.exited(status.success(), Instant::now());
                         if !status.success() {
                             let code = status.code().unwrap_or(-1);
-                            log_warn(&format!("XMR process exited with code {}. Restarting...", code));
-                            consecutive_failures += 1;
+                            warn!("XMR process exited with code {}. Restarting...", code);
                         } else {
-                            log_info("XMR process exited normally. Restarting...");
-                            consecutive_failures = 0;
+                            info!("XMR process exited normally. Restarting...");
                         }
                         true
                     },
                     Ok(None) => false, // Process still running
                     Err(e) => {
-                        log_error(&format!("Error checking XMR process status: {}", e));
-                        consecutive_failures += 1;
+                        error!("Error checking XMR process status: {}", e);
+                        failures.exited(false, Instant::now());
                         true
                     }
                 }
             };
             
             if need_restart {
+                let consecutive_failures = failures.count();
                 // If too many consecutive failures, wait longer before retrying
                 if consecutive_failures >= MAX_FAILURES {
-                    log_warn(&format!("Too many consecutive failures ({}). Waiting longer before restart...", 
-                         consecutive_failures));
+                    warn!("Too many consecutive failures ({}). Waiting longer before restart...", 
+                         consecutive_failures);
+                    events::emit(Event::Alert {
+                        message: format!("XMR failed {} times in a row", consecutive_failures),
+                    });
+                    events::emit(Event::Restarting { consecutive_failures, delay_secs: 30 });
                     thread::sleep(Duration::from_secs(30));
+                } else if current_process.is_some() {
+                    events::emit(Event::Restarting { consecutive_failures, delay_secs: 0 });
+                }
+                
+                if started_once && !stagger_restart(&config, &running, &label) {
+                    continue;
                 }
                 
                 // Previous process ended or doesn't exist, start a new one
-                match Command::new(&xmr_path)
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .spawn() {
-                        Ok(child) => {
-                            log_info(&format!("Started XMR process with PID: {}", child.id()));
+                match spawn_miner(&xmr_path, &config, wanted_target.as_ref()) {
+                        Ok(mut child) => {
+                            started_once = true;
+                            info!("{}Started XMR process with PID: {}", label, child.id());
+                            events::emit(Event::ChildStarted { pid: child.id() });
+                            drain_output(&mut child, monitor.output());
+                            monitor.child_started(child.id());
                             current_process = Some(child);
-                            
-                            // Reset consecutive failures if successful
-                            if consecutive_failures > 0 {
-                                consecutive_failures = 0;
-                            }
+                            started_target = wanted_target;
+                            // Only a miner that stays up clears the failures
+                            failures.started(Instant::now());
                         },
                         Err(e) => {
-                            log_error(&format!("Failed to start XMR process: {}", e));
-                            consecutive_failures += 1;
+                            error!("Failed to start XMR process: {}", e);
+                            let consecutive_failures = failures.start_failed();
                             
                             // Exponential backoff for retries
                             let backoff = 5 * (1 << consecutive_failures.min(10));
-                            log_warn(&format!("Retrying in {} seconds...", backoff));
+                            warn!("Retrying in {} seconds...", backoff);
+                            events::emit(Event::Restarting { consecutive_failures, delay_secs: backoff });
                             thread::sleep(Duration::from_secs(backoff));
                         }
                     }
+            } else {
+                enforce_health(&mut current_process, &mut monitor, &label);
             }
             
             // Small sleep to prevent CPU thrashing
@@ -367,177 +771,460 @@ fn create_watchdog(xmr_path: String, running: Arc<AtomicBool>) -> thread::JoinHa
         }
         
         // When ctrl+c is received, terminate the child process
-        if let Some(mut child) = current_process {
-            log_info("Terminating XMR process...");
-            if let Err(e) = child.kill() {
-                log_error(&format!("Failed to kill XMR process: {}", e));
-            }
+        if let Some(child) = current_process {
+            info!("Terminating XMR process...");
+            kill_child(child, "");
         }
     })
 }
 
-pub fn run_xmr_resilient() -> Result<(), XmrError> {
-    log_info("Starting run_xmr_resilient function");
+// What both resilient modes run beside their watchdogs. The PID file stays
+// held until it is dropped.
+struct Services {
+    running: Arc<AtomicBool>,
+    sidecars: Vec<thread::JoinHandle<()>>,
+    _pid_file: pidfile::PidFile,
+}
+
+impl Services {
+    fn join_sidecars(&mut self) {
+        for handle in self.sidecars.drain(..) {
+            if let Err(e) = handle.join() {
+                error!("Error joining sidecar thread: {:?}", e);
+            }
+        }
+    }
+}
+
+// Take over as the supervisor and start the control channel, exporters,
+// recorders, watches and `sidecars`
+fn start_services(config: &Config, xmr_path: &str, sidecars: Vec<Sidecar>) -> Result<Services, XmrError> {
+    // Set process priority to be resistant to system killing
+    set_process_priority()?;
+    
+    // Hook up notification sinks and hook scripts before the first child is spawned
+    notify::install(config);
+    hooks::configure(&config.hooks);
+    
+    // Setup CTRL+C handler; `stop` finds this process through the PID file
+    // --replace first stops a supervisor holding the PID file
+    let adopted = registry::claim(flags().existing)?;
+    let pid_file = pidfile::acquire()?;
+    minerlog::start_run(&config.logging);
+    let running = setup_ctrlc_handler();
+    // `restart`, `stop` and friends arrive through the control channel as well
+    control::start_watch(config, running.clone());
+    #[cfg(feature = "grpc")]
+    grpc::start(config, running.clone());
+    #[cfg(not(feature = "grpc"))]
+    if config.grpc.enabled {
+        warn!("[grpc] is enabled, but this build has no gRPC support; rebuild with --features grpc");
+    }
+    mqtt::start(config, running.clone());
+    metrics::start(config);
+    api::start(config);
+    earnings::start_recorder(config, running.clone());
+    bandwidth::start_recorder(running.clone());
+    stats::start_recorder(config, running.clone());
+    selfmetrics::start_recorder(running.clone());
+    market::start_price_watch(config, running.clone());
+    solo::start_sync_watch(config, running.clone());
+    pool::start_auto_select(config, running.clone());
+    pool::start_failover_watch(config, running.clone());
+    connectivity::start_watch(config, running.clone());
+    metered::start_watch(config, running.clone());
+    suspend::start_watch(running.clone());
+    binwatch::start_watch(xmr_path, running.clone());
+    scheduler::start(config, running.clone());
+    hours::start_watch(config, running.clone());
+    power::start_watch(config, running.clone());
+    thermal::start_watch(config, running.clone());
+    load::start_watch(config, running.clone());
+    registry::supervise_adopted(adopted, &running);
+    let sidecars = sidecars.into_iter().map(|s| s.supervise(running.clone())).collect();
+    Ok(Services { running, sidecars, _pid_file: pid_file })
+}
+
+pub fn run_xmr_resilient(config: &Config) -> Result<(), XmrError> {
+    run_xmr_resilient_with(config, Vec::new())
+}
+
+// Resilient mode plus helper processes (p2pool, a node) that share the
+// miner's lifetime and are restarted independently of it
+pub fn run_xmr_resilient_with(config: &Config, sidecars: Vec<Sidecar>) -> Result<(), XmrError> {
+    info!("Starting run_xmr_resilient function");
+    
+    // The managed node comes first so p2pool / solo mining have something to talk to
+    let sidecars: Vec<Sidecar> = node::sidecar(config)?.into_iter().chain(sidecars).collect();
     
     // Get XMR path with better error handling
     let xmr_path = get_xmr_path()?;
-    log_info(&format!("Found XMR at: {}", xmr_path));
+    info!("Found XMR at: {}", xmr_path);
     
     // Set executable permissions
     set_executable_permissions(&xmr_path)?;
+    wallet::ensure_confirmed(config)?;
+    preflight::ensure_capable(config, flags().strict)?;
+    cgroup::prepare(config)?;
     
-    // Set process priority to be resistant to system killing
-    set_process_priority()?;
-    
-    // Setup CTRL+C handler
-    let running = setup_ctrlc_handler();
+    let mut services = start_services(config, &xmr_path, sidecars)?;
     
-    // Create and start the watchdog
-    let watchdog_handle = create_watchdog(xmr_path, running.clone());
+    // Create and start the watchdog, one per NUMA node with [miner] numa
+    let watchdog_handles: Vec<_> = numa::split(config)
+        .into_iter()
+        .enumerate()
+        .map(|(index, config)| create_watchdog(xmr_path.clone(), config, services.running.clone(), index + 1))
+        .collect();
     
-    log_info("XMR process is now running and protected. Press Ctrl+C to terminate when needed.");
+    info!("XMR process is now running and protected. Press Ctrl+C to terminate when needed.");
     
     // Wait for Ctrl+C
-    while running.load(Ordering::SeqCst) {
+    while services.running.load(Ordering::SeqCst) {
         thread::sleep(Duration::from_secs(1));
     }
     
-    // Wait for watchdog to complete
-    if let Err(e) = watchdog_handle.join() {
-        log_error(&format!("Error joining watchdog thread: {:?}", e));
+    // Wait for the watchdogs to complete
+    for handle in watchdog_handles {
+        if let Err(e) = handle.join() {
+            error!("Error joining watchdog thread: {:?}", e);
+        }
     }
+    services.join_sidecars();
     
-    log_info("XMR process has been terminated. Exiting...");
+    split::flush();
+    events::emit(Event::Shutdown);
+    info!("XMR process has been terminated. Exiting...");
     Ok(())
 }
 
-// New function: run_xmr_super_resilient for the most aggressive approach
-pub fn run_xmr_super_resilient() -> Result<(), XmrError> {
-    log_info("Starting run_xmr_super_resilient function");
+// Watchdogs in super-resilient mode
+const WATCHDOGS: usize = 3;
+// How long the leading watchdog may go without finishing a round before
+// another one takes over
+const LEADER_TIMEOUT: Duration = Duration::from_secs(5);
+// Between rounds of the leading watchdog
+const ROUND_INTERVAL: Duration = Duration::from_millis(100);
+// Between checks of a watchdog standing by
+const STANDBY_POLL: Duration = Duration::from_millis(500);
+
+// The miner the super-resilient watchdogs share and everything known about it
+struct Supervision {
+    config: Config,
+    current_process: Option<Child>,
+    started_target: Option<Target>,
+    last_tick: Instant,
+    failures: health::Failures,
+    // Seconds to wait after a failed start, doubling up to five minutes
+    backoff_time: u64,
+    monitor: health::Monitor,
+    started_once: bool,
+    binaries_seen: u64,
+    restarts_seen: u64,
+    frozen: bool,
+    // No new miner before this: the restart stagger or the backoff after a
+    // failed start
+    next_start: Option<Instant>,
+    // The watchdog looking after the miner
+    leader: usize,
+}
+
+impl Supervision {
+    fn new(config: Config) -> Supervision {
+        Supervision {
+            monitor: health::Monitor::new(&config),
+            config,
+            current_process: None,
+            started_target: None,
+            last_tick: Instant::now(),
+            failures: health::Failures::default(),
+            backoff_time: 1,
+            started_once: false,
+            binaries_seen: binwatch::replacements(),
+            restarts_seen: control::restarts(),
+            frozen: false,
+            next_start: None,
+            leader: 0,
+        }
+    }
+}
+
+// When the leading watchdog last finished a round. It is kept apart from the
+// shared state, so reporting in never waits for the lock.
+struct Heartbeat {
+    epoch: Instant,
+    millis: AtomicU64,
+}
+
+impl Heartbeat {
+    fn new() -> Heartbeat {
+        Heartbeat { epoch: Instant::now(), millis: AtomicU64::new(0) }
+    }
+
+    fn beat(&self) {
+        self.millis.store(self.epoch.elapsed().as_millis() as u64, Ordering::SeqCst);
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.epoch.elapsed().saturating_sub(Duration::from_millis(self.millis.load(Ordering::SeqCst)))
+    }
+}
+
+// A turn of watchdog `i`: run `round` on the shared miner when it leads, or
+// take the lead once the leader has not reported in for LEADER_TIMEOUT.
+// Returns how long to wait before the next turn, with the state released.
+fn watchdog_turn(
+    shared: &Mutex<Supervision>,
+    heartbeat: &Heartbeat,
+    i: usize,
+    label: &str,
+    round: impl FnOnce(&mut Supervision) -> Duration,
+) -> Duration {
+    // A leader that is busy holds the lock; one that panicked leaves it
+    // poisoned with the miner still in it
+    let mut state = match shared.try_lock() {
+        Ok(state) => state,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return STANDBY_POLL,
+    };
+    if state.leader != i {
+        if heartbeat.elapsed() < LEADER_TIMEOUT {
+            return STANDBY_POLL;
+        }
+        warn!("{}Watchdog #{} stopped reporting in, taking over the miner", label, state.leader + 1);
+        state.leader = i;
+    }
+    let wait = round(&mut state);
+    drop(state);
+    heartbeat.beat();
+    wait
+}
+
+// One round of the leading watchdog: start, restart or health-check the miner.
+// Never sleeps, so the shared state is free between rounds; returns how
+// long to wait before the next one.
+fn supervise(state: &mut Supervision, xmr_path: &str, label: &str) -> Duration {
+    let Supervision {
+        config,
+        current_process,
+        started_target,
+        last_tick,
+        failures,
+        backoff_time,
+        monitor,
+        started_once,
+        binaries_seen,
+        restarts_seen,
+        frozen,
+        next_start,
+        ..
+    } = state;
+    if hold_while_paused(current_process, frozen, monitor, config.miner.pause_mode, label) {
+        return PAUSE_POLL;
+    }
+    restart_on_new_binary(current_process, binaries_seen, label);
+    restart_on_request(current_process, config, monitor, restarts_seen, label);
+    
+    let wanted_target = split::current_target(config);
+    follow_split(current_process, started_target, &wanted_target, last_tick, label);
+    
+    // Check if we need to start/restart the process
+    let need_restart = match current_process {
+        None => true,
+        Some(child) => match child.try_wait() {
+            Ok(Some(status)) => {
+                // Process has exited
+                reaped(child.id(), Some(status), false);
+                let consecutive_failures = failures.exited(status.success(), Instant::now());
+                if !status.success() {
+                    warn!("{}XMR process exited with code {}. Restarting...", label, status.code().unwrap_or(-1));
+                }
+                events::emit(Event::Restarting { consecutive_failures, delay_secs: 0 });
+                true
+            },
+            Ok(None) => false,    // Process still running
+            Err(_) => {
+                // Error checking status
+                failures.exited(false, Instant::now());
+                true
+            }
+        }
+    };
+    
+    if !need_restart {
+        // Process is running, check its health
+        enforce_health(current_process, monitor, label);
+        return ROUND_INTERVAL;
+    }
+    let now = Instant::now();
+    let start_at = *next_start.get_or_insert_with(|| {
+        let delay = if *started_once { health::restart_delay(&config.health) } else { Duration::ZERO };
+        if !delay.is_zero() {
+            info!("{}Restarting XMR in {} seconds", label, delay.as_secs());
+        }
+        now + delay
+    });
+    if now < start_at {
+        return ROUND_INTERVAL;
+    }
+    *next_start = None;
+    
+    // Previous process ended or doesn't exist, start a new one
+    match spawn_miner(xmr_path, config, wanted_target.as_ref()) {
+        Ok(mut child) => {
+            *started_once = true;
+            info!("{}Started XMR process with PID: {}", label, child.id());
+            events::emit(Event::ChildStarted { pid: child.id() });
+            drain_output(&mut child, monitor.output());
+            monitor.child_started(child.id());
+            *current_process = Some(child);
+            *started_target = wanted_target;
+            // Only a miner that stays up clears the failures
+            failures.started(Instant::now());
+            *backoff_time = 1;
+        },
+        Err(e) => {
+            let consecutive_failures = failures.start_failed();
+            error!("{}Failed to start XMR process (failure #{}): {}", label, consecutive_failures, e);
+            
+            // Exponential backoff with maximum cap
+            *backoff_time = (*backoff_time * 2).min(300); // Max 5 minutes
+            events::emit(Event::Restarting { consecutive_failures, delay_secs: *backoff_time });
+            *next_start = Some(Instant::now() + Duration::from_secs(*backoff_time));
+        }
+    }
+    ROUND_INTERVAL
+}
+
+pub fn run_xmr_super_resilient(config: &Config) -> Result<(), XmrError> {
+    info!("Starting run_xmr_super_resilient function");
+    
+    // The managed node comes first so solo mining has something to talk to
+    let sidecars: Vec<Sidecar> = node::sidecar(config)?.into_iter().collect();
     
     // Get XMR path with better error handling
     let xmr_path = get_xmr_path()?;
-    log_info(&format!("Found XMR at: {}", xmr_path));
+    info!("Found XMR at: {}", xmr_path);
     
     // Set executable permissions
     set_executable_permissions(&xmr_path)?;
+    wallet::ensure_confirmed(config)?;
+    preflight::ensure_capable(config, flags().strict)?;
+    cgroup::prepare(config)?;
+    if config.miner.numa {
+        warn!("[miner] numa only starts one miner per NUMA node in run-resilient; starting one");
+    }
     
-    // Set process priority to be resistant to system killing
-    set_process_priority()?;
-    
-    // Setup CTRL+C handler
-    let running = setup_ctrlc_handler();
+    let mut services = start_services(config, &xmr_path, sidecars)?;
     
-    // Create multiple watchdogs for redundancy (3 independent watchdogs)
-    log_info("Starting multiple watchdog threads for redundancy");
-    let watchdog_handles = (0..3).map(|i| {
+    // Three watchdogs share one miner: one leads, the others stand by to
+    // take over should it stop reporting in
+    info!("Starting multiple watchdog threads for redundancy");
+    let shared = Arc::new(Mutex::new(Supervision::new(config.clone())));
+    let heartbeat = Arc::new(Heartbeat::new());
+    let watchdog_handles = (0..WATCHDOGS).map(|i| {
         let xmr_path_clone = xmr_path.clone();
-        let running_clone = running.clone();
+        let shared = shared.clone();
+        let heartbeat = heartbeat.clone();
+        let running_clone = services.running.clone();
         
         thread::spawn(move || {
-            log_info(&format!("Watchdog #{} started", i+1));
-            let mut current_process: Option<Child> = None;
-            let mut consecutive_failures = 0;
-            let mut backoff_time = 1; // Initial backoff in seconds
+            logging::set_watchdog(i + 1);
+            info!("Watchdog #{} started", i+1);
+            let label = format!("Watchdog #{}: ", i+1);
             
             while running_clone.load(Ordering::SeqCst) {
-                // Check if we need to start/restart the process
-                let need_restart = match &mut current_process {
-                    None => true,
-                    Some(child) => match child.try_wait() {
-                        Ok(Some(_)) => true,  // Process has exited
-                        Ok(None) => false,    // Process still running
-                        Err(_) => true        // Error checking status
-                    }
-                };
-                
-                if need_restart {
-                    // Try to kill any existing processes first to ensure clean start
-                    #[cfg(unix)]
-                    {
-                        let _ = Command::new("pkill")
-                            .arg("-f")
-                            .arg(&xmr_path_clone)
-                            .status();
-                    }
-                    
-                    // Previous process ended or doesn't exist, start a new one
-                    match Command::new(&xmr_path_clone)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn() {
-                            Ok(child) => {
-                                log_info(&format!("Watchdog #{}: Started XMR process with PID: {}", i+1, child.id()));
-                                current_process = Some(child);
-                                consecutive_failures = 0;
-                                backoff_time = 1;
-                            },
-                            Err(e) => {
-                                log_error(&format!("Watchdog #{}: Failed to start XMR process: {}", i+1, e));
-                                consecutive_failures += 1;
-                                
-                                // Exponential backoff with maximum cap
-                                backoff_time = (backoff_time * 2).min(300); // Max 5 minutes
-                                thread::sleep(Duration::from_secs(backoff_time));
-                            }
-                        }
-                } else {
-                    // Process is running, check its health
-                    if let Some(child) = &mut current_process {
-                        // Try to get some output to verify it's still responsive
-                        #[cfg(unix)]
-                        {
-                            match Command::new("ps")
-                                .args(["-p", &child.id().to_string(), "-o", "state"])
-                                .output() {
-                                    Ok(output) => {
-                                        let ps_output = String::from_utf8_lossy(&output.stdout);
-                                        if !ps_output.contains('R') && !ps_output.contains('S') {
-                                            log_warn(&format!("Watchdog #{}: XMR process may be in a bad state ({}), restarting...", 
-                                                 i+1, ps_output.trim()));
-                                            let _ = child.kill();
-                                            current_process = None;
-                                        }
-                                    },
-                                    Err(_) => {
-                                        // Can't check process state, assume it's ok
-                                    }
-                                }
-                        }
-                    }
-                }
-                
-                // Small sleep to prevent CPU thrashing - different for each watchdog
-                // to avoid synchronization
-                thread::sleep(Duration::from_millis(100 + (i as u64 * 50)));
-            }
-            
-            // When ctrl+c is received, terminate the child process
-            if let Some(mut child) = current_process {
-                log_info(&format!("Watchdog #{}: Terminating XMR process...", i+1));
-                if let Err(e) = child.kill() {
-                    log_error(&format!("Watchdog #{}: Failed to kill XMR process: {}", i+1, e));
-                }
+                let wait = watchdog_turn(&shared, &heartbeat, i, &label, |state| {
+                    supervise(state, &xmr_path_clone, &label)
+                });
+                // Waits, backoff included, happen with the state released
+                thread::sleep(wait);
             }
             
-            log_info(&format!("Watchdog #{} terminated", i+1));
+            info!("Watchdog #{} terminated", i+1);
         })
     }).collect::<Vec<_>>();
     
-    log_info("XMR process is now running with super-resilient protection. Press Ctrl+C to terminate.");
+    info!("XMR process is now running with super-resilient protection. Press Ctrl+C to terminate.");
     
     // Wait for Ctrl+C
-    while running.load(Ordering::SeqCst) {
+    while services.running.load(Ordering::SeqCst) {
         thread::sleep(Duration::from_secs(1));
     }
     
     // Wait for all watchdogs to complete
     for (i, handle) in watchdog_handles.into_iter().enumerate() {
         if let Err(e) = handle.join() {
-            log_error(&format!("Error joining watchdog #{} thread: {:?}", i+1, e));
+            error!("Error joining watchdog #{} thread: {:?}", i+1, e);
         }
     }
     
-    log_info("All XMR processes have been terminated. Exiting...");
+    // When ctrl+c is received, terminate the child process
+    let mut state = shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(child) = state.current_process.take() {
+        info!("Terminating XMR process...");
+        kill_child(child, "");
+    }
+    drop(state);
+    services.join_sidecars();
+    
+    split::flush();
+    events::emit(Event::Shutdown);
+    info!("All XMR processes have been terminated. Exiting...");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A heartbeat whose leader last reported in `ago`
+    fn heartbeat(ago: Duration) -> Heartbeat {
+        let epoch = Instant::now().checked_sub(ago).unwrap();
+        Heartbeat { epoch, millis: AtomicU64::new(0) }
+    }
+
+    #[test]
+    fn test_stale_leader_taken_over() {
+        let shared = Mutex::new(Supervision::new(Config::default()));
+        let heartbeat = heartbeat(LEADER_TIMEOUT * 2);
+        let mut rounds = 0;
+        let wait = watchdog_turn(&shared, &heartbeat, 1, "", |_| {
+            rounds += 1;
+            ROUND_INTERVAL
+        });
+        assert_eq!((wait, rounds), (ROUND_INTERVAL, 1));
+        assert_eq!(shared.lock().unwrap().leader, 1);
+        // The new leader's round counts as a beat
+        assert!(heartbeat.elapsed() < LEADER_TIMEOUT);
+    }
+
+    #[test]
+    fn test_beating_leader_kept() {
+        let shared = Mutex::new(Supervision::new(Config::default()));
+        let heartbeat = heartbeat(Duration::ZERO);
+        for _ in 0..3 {
+            for i in 1..WATCHDOGS {
+                let wait = watchdog_turn(&shared, &heartbeat, i, "", |_| panic!("watchdog #{} took over", i + 1));
+                assert_eq!(wait, STANDBY_POLL);
+            }
+            assert_eq!(watchdog_turn(&shared, &heartbeat, 0, "", |_| ROUND_INTERVAL), ROUND_INTERVAL);
+        }
+        assert_eq!(shared.lock().unwrap().leader, 0);
+    }
+
+    #[test]
+    fn test_lock_released_for_waits() {
+        let shared = Mutex::new(Supervision::new(Config::default()));
+        let heartbeat = heartbeat(Duration::ZERO);
+        // A failed start backs off for minutes; the state is free meanwhile
+        let backoff = Duration::from_secs(300);
+        assert_eq!(watchdog_turn(&shared, &heartbeat, 0, "", |_| backoff), backoff);
+        assert!(shared.try_lock().is_ok());
+
+        // While a round holds the state the others stand by without waiting on it
+        let state = shared.lock().unwrap();
+        let wait = watchdog_turn(&shared, &heartbeat, 0, "", |_| panic!("ran a round without the lock"));
+        assert_eq!(wait, STANDBY_POLL);
+        drop(state);
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Timelike, Utc, Weekday};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, Config, ScheduledJob};
+use crate::pidfile;
+use crate::run::XmrError;
+
+pub const SCHEDULER_FILE: &str = "scheduler.json";
+// How often due jobs are looked for
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// When a job runs, in local time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Hourly { minute: u32 },
+    Daily { time: NaiveTime },
+    Weekly { day: Weekday, time: NaiveTime },
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| format!("expected a time like 03:30, got \"{}\"", s))
+}
+
+// "hourly", "hourly :15", "daily 03:30" or "weekly sun 04:00"
+pub fn parse(schedule: &str) -> Result<Schedule, String> {
+    let words: Vec<&str> = schedule.split_whitespace().collect();
+    match words.as_slice() {
+        ["hourly"] => Ok(Schedule::Hourly { minute: 0 }),
+        ["hourly", minute] => minute
+            .trim_start_matches(':')
+            .parse()
+            .ok()
+            .filter(|m| *m < 60)
+            .map(|minute| Schedule::Hourly { minute })
+            .ok_or_else(|| format!("expected a minute like :15, got \"{}\"", minute)),
+        ["daily", time] => Ok(Schedule::Daily { time: parse_time(time)? }),
+        ["weekly", day, time] => Ok(Schedule::Weekly {
+            day: day.parse().map_err(|_| format!("unknown day \"{}\"", day))?,
+            time: parse_time(time)?,
+        }),
+        _ => Err(format!("unknown schedule \"{}\"; use \"hourly :MM\", \"daily HH:MM\" or \"weekly DAY HH:MM\"", schedule)),
+    }
+}
+
+impl Schedule {
+    fn period(&self) -> TimeDelta {
+        match self {
+            Schedule::Hourly { .. } => TimeDelta::hours(1),
+            Schedule::Daily { .. } => TimeDelta::days(1),
+            Schedule::Weekly { .. } => TimeDelta::weeks(1),
+        }
+    }
+
+    // The most recent slot at or before `now`
+    pub fn previous(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let slot = match *self {
+            Schedule::Hourly { minute } => now.date().and_hms_opt(now.hour(), minute, 0).unwrap_or(now),
+            Schedule::Daily { time } => now.date().and_time(time),
+            Schedule::Weekly { day, time } => {
+                let back = (7 + now.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+                (now.date() - TimeDelta::days(back as i64)).and_time(time)
+            },
+        };
+        if slot > now { slot - self.period() } else { slot }
+    }
+
+    pub fn next(&self, now: NaiveDateTime) -> NaiveDateTime {
+        self.previous(now) + self.period()
+    }
+}
+
+fn state_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(SCHEDULER_FILE))
+}
+
+// Unix time each job last ran, by job name
+fn load() -> BTreeMap<String, i64> {
+    state_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(last_runs: &BTreeMap<String, i64>) -> Result<(), XmrError> {
+    let path = state_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(last_runs)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode scheduler state: {}", e)))?;
+    config::write_atomic(&path, &contents)
+}
+
+fn unix_time(local: NaiveDateTime) -> i64 {
+    Local.from_local_datetime(&local).earliest().map_or_else(|| local.and_utc().timestamp(), |t| t.timestamp())
+}
+
+// Run a job as this launcher with the job's arguments, for the same instance
+fn run_job(job: &ScheduledJob) {
+    let Ok(exe) = env::current_exe() else {
+        warn!("Cannot run job {}: launcher path unknown", job.name);
+        return;
+    };
+    let mut command = Command::new(exe);
+    if config::instance() != config::DEFAULT_INSTANCE {
+        command.args(["--name", config::instance()]);
+    }
+    if let Some(path) = config::config_file() {
+        command.arg("--config").arg(path);
+    }
+    info!("Running scheduled job {}: {}", job.name, job.args.join(" "));
+    match command.args(&job.args).output() {
+        Ok(output) => {
+            for line in String::from_utf8_lossy(&output.stdout).lines().chain(String::from_utf8_lossy(&output.stderr).lines()) {
+                debug!("{}: {}", job.name, line);
+            }
+            if output.status.success() {
+                info!("Scheduled job {} finished", job.name);
+            } else {
+                warn!("Scheduled job {} failed: {}", job.name, output.status);
+            }
+        },
+        Err(e) => warn!("Could not run scheduled job {}: {}", job.name, e),
+    }
+}
+
+// Jobs with a valid schedule; the others are reported and left out
+fn jobs(config: &Config) -> Vec<(ScheduledJob, Schedule)> {
+    config
+        .scheduler
+        .jobs
+        .iter()
+        .filter_map(|job| match parse(&job.schedule) {
+            Ok(schedule) if !job.args.is_empty() => Some((job.clone(), schedule)),
+            Ok(_) => {
+                warn!("Scheduled job {} has no args; skipping it", job.name);
+                None
+            },
+            Err(e) => {
+                warn!("Scheduled job {}: {}; skipping it", job.name, e);
+                None
+            },
+        })
+        .collect()
+}
+
+// Run each job whose latest slot has passed since it last ran. A job seen
+// for the first time waits for its next slot; slots missed while the
+// machine was off are caught up once.
+fn run_due(jobs: &[(ScheduledJob, Schedule)], running: &AtomicBool) {
+    let mut last_runs = load();
+    let now = Local::now().naive_local();
+    for (job, schedule) in jobs {
+        if !running.load(Ordering::SeqCst) {
+            return;
+        }
+        let Some(&last) = last_runs.get(&job.name) else {
+            last_runs.insert(job.name.clone(), Utc::now().timestamp());
+            let _ = save(&last_runs);
+            continue;
+        };
+        if last >= unix_time(schedule.previous(now)) {
+            continue;
+        }
+        run_job(job);
+        // Jobs may take a while; reload so a concurrent scheduler's runs are kept
+        let mut latest = load();
+        latest.insert(job.name.clone(), Utc::now().timestamp());
+        if let Err(e) = save(&latest) {
+            warn!("Could not save scheduler state: {}", e);
+        }
+        last_runs = latest;
+    }
+}
+
+fn schedule_loop(jobs: Vec<(ScheduledJob, Schedule)>, running: Arc<AtomicBool>) {
+    let now = Local::now().naive_local();
+    for (job, schedule) in &jobs {
+        info!("Scheduled job {} ({}), next run {}", job.name, job.schedule, schedule.next(now).format("%Y-%m-%d %H:%M"));
+    }
+    while running.load(Ordering::SeqCst) {
+        run_due(&jobs, &running);
+        let mut waited = Duration::ZERO;
+        while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+            waited += Duration::from_secs(1);
+        }
+    }
+}
+
+// Supervised modes: run [scheduler] jobs alongside the miner
+pub fn start(config: &Config, running: Arc<AtomicBool>) {
+    if !config.scheduler.supervised {
+        return;
+    }
+    let jobs = jobs(config);
+    if jobs.is_empty() {
+        return;
+    }
+    thread::spawn(move || schedule_loop(jobs, running));
+}
+
+// `scheduler`: run [scheduler] jobs in the foreground, for machines that do
+// not keep a supervisor running
+pub fn run(config: &Config) -> Result<(), XmrError> {
+    let jobs = jobs(config);
+    if jobs.is_empty() {
+        return Err(XmrError::ConfigError("No [[scheduler.jobs]] configured".to_string()));
+    }
+    if config.scheduler.supervised && let Some(pid) = pidfile::running() {
+        warn!("Supervisor {} already runs these jobs; set [scheduler] supervised = false to run them here only", pid);
+    }
+    schedule_loop(jobs, Arc::new(AtomicBool::new(true)));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-06-03 is a Monday
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("hourly :15"), Ok(Schedule::Hourly { minute: 15 }));
+        assert_eq!(parse("daily 03:30"), Ok(Schedule::Daily { time: NaiveTime::from_hms_opt(3, 30, 0).unwrap() }));
+        assert_eq!(
+            parse("weekly sun 04:00"),
+            Ok(Schedule::Weekly { day: Weekday::Sun, time: NaiveTime::from_hms_opt(4, 0, 0).unwrap() })
+        );
+        assert!(parse("daily").is_err());
+        assert!(parse("hourly :75").is_err());
+    }
+
+    #[test]
+    fn test_previous_slot() {
+        assert_eq!(parse("hourly :15").unwrap().previous(at(5, 10, 10)), at(5, 9, 15));
+        assert_eq!(parse("daily 03:30").unwrap().previous(at(5, 3, 30)), at(5, 3, 30));
+        assert_eq!(parse("daily 03:30").unwrap().previous(at(5, 2, 0)), at(4, 3, 30));
+        // Wednesday the 5th back to Sunday the 2nd
+        assert_eq!(parse("weekly sun 04:00").unwrap().previous(at(5, 12, 0)), at(2, 4, 0));
+        assert_eq!(parse("weekly wed 13:00").unwrap().previous(at(5, 12, 0)), at(5, 13, 0) - TimeDelta::weeks(1));
+        assert_eq!(parse("weekly wed 13:00").unwrap().next(at(5, 12, 0)), at(5, 13, 0));
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config;
+use crate::run::XmrError;
+use crate::telemetry;
+
+pub const METRICS_FILE: &str = "selfmetrics.json";
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+// /proc reports CPU time in clock ticks; USER_HZ is 100 on every mainstream
+// Linux build
+const CLOCK_TICKS: f64 = 100.0;
+
+// How long one kind of launcher operation has taken
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timing {
+    pub count: u64,
+    pub total_ms: f64,
+    pub last_ms: f64,
+    pub max_ms: f64,
+}
+
+// The launcher's own footprint during the last run of a command
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandMetrics {
+    pub updated: i64,
+    pub pid: u32,
+    pub elapsed_secs: f64,
+    pub cpu_secs: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    pub timings: BTreeMap<String, Timing>,
+}
+
+static TIMINGS: Mutex<BTreeMap<String, Timing>> = Mutex::new(BTreeMap::new());
+// The command this process was started with, and when
+static COMMAND: OnceLock<(String, Instant)> = OnceLock::new();
+
+pub fn begin(command: &str) {
+    let _ = COMMAND.set((command.to_string(), Instant::now()));
+}
+
+fn command() -> (&'static str, Instant) {
+    COMMAND.get().map_or(("?", Instant::now()), |(command, started)| (command.as_str(), *started))
+}
+
+pub fn record(operation: &str, elapsed: Duration) {
+    let ms = elapsed.as_secs_f64() * 1000.0;
+    if let Ok(mut timings) = TIMINGS.lock() {
+        let timing = timings.entry(operation.to_string()).or_default();
+        timing.count += 1;
+        timing.total_ms += ms;
+        timing.last_ms = ms;
+        timing.max_ms = timing.max_ms.max(ms);
+    }
+}
+
+// Run `f`, charging its wall time to `operation`
+pub fn time<T>(operation: &str, f: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = f();
+    record(operation, started.elapsed());
+    result
+}
+
+// (CPU seconds, resident bytes, threads) of this process from its /proc
+// stat and status files
+fn parse_usage(stat: &str, status: &str) -> Option<(f64, u64, u64)> {
+    // The command name may contain spaces; fields are counted after it
+    let fields: Vec<&str> = stat.rsplit_once(") ")?.1.split_whitespace().collect();
+    let ticks: u64 = fields.get(11)?.parse::<u64>().ok()? + fields.get(12)?.parse::<u64>().ok()?;
+    let threads = fields.get(17)?.parse().ok()?;
+    let rss_kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0);
+    Some((ticks as f64 / CLOCK_TICKS, rss_kb * 1024, threads))
+}
+
+// The launcher's footprint right now
+pub fn current() -> CommandMetrics {
+    let started = command().1;
+    let stat = fs::read_to_string("/proc/self/stat").unwrap_or_default();
+    let status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let (cpu_secs, rss_bytes, threads) = parse_usage(&stat, &status).unwrap_or_default();
+    CommandMetrics {
+        updated: Utc::now().timestamp(),
+        pid: std::process::id(),
+        elapsed_secs: started.elapsed().as_secs_f64(),
+        cpu_secs,
+        rss_bytes,
+        threads,
+        timings: TIMINGS.lock().map(|t| t.clone()).unwrap_or_default(),
+    }
+}
+
+fn metrics_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(METRICS_FILE))
+}
+
+pub fn load() -> BTreeMap<String, CommandMetrics> {
+    metrics_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(command: &str, metrics: CommandMetrics) -> Result<(), XmrError> {
+    let path = metrics_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut all = load();
+    all.insert(command.to_string(), metrics);
+    let contents = serde_json::to_string_pretty(&all)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode launcher metrics: {}", e)))?;
+    config::write_atomic(&path, &contents)
+}
+
+// Record what a finished command cost; only runs that did real work
+// (timed operations or supervision) are worth keeping
+pub fn finish() {
+    let metrics = current();
+    if metrics.timings.is_empty() {
+        return;
+    }
+    if let Err(e) = save(command().0, metrics) {
+        warn!("Could not save launcher metrics: {}", e);
+    }
+}
+
+// Supervised modes: keep the supervisor's entry current while it runs
+pub fn start_recorder(running: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            if let Err(e) = save(command().0, current()) {
+                warn!("Could not save launcher metrics: {}", e);
+            }
+            let mut waited = Duration::ZERO;
+            while waited < SAVE_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+// `status --internal`: how the launcher itself (not the miner) is doing
+pub fn show() -> Result<(), XmrError> {
+    let all = load();
+    if all.is_empty() {
+        println!("No launcher metrics recorded yet.");
+        return Ok(());
+    }
+
+    for (command, metrics) in &all {
+        let when = Local
+            .timestamp_opt(metrics.updated, 0)
+            .single()
+            .map_or_else(|| "?".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+        println!("{} (PID {}, {}):", command, metrics.pid, when);
+        println!(
+            "  Ran {:.1}s, CPU {:.2}s, RSS {}, {} threads",
+            metrics.elapsed_secs,
+            metrics.cpu_secs,
+            telemetry::format_bytes(metrics.rss_bytes),
+            metrics.threads
+        );
+        for (operation, timing) in &metrics.timings {
+            println!(
+                "  {:<16} {:>5}x  avg {:>9.1} ms  last {:>9.1} ms  max {:>9.1} ms",
+                operation,
+                timing.count,
+                timing.total_ms / timing.count.max(1) as f64,
+                timing.last_ms,
+                timing.max_ms
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_usage() {
+        let stat = "4242 (xmr (launcher)) S 1 4242 4242 0 -1 4194560 900 0 0 0 250 50 0 0 20 0 7 0 12345 1000 300";
+        let status = "Name:\txmr\nVmPeak:\t  20000 kB\nVmRSS:\t   8192 kB\nThreads:\t7\n";
+        assert_eq!(parse_usage(stat, status), Some((3.0, 8192 * 1024, 7)));
+    }
+}
@@ -0,0 +1,566 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use clap::ValueEnum;
+
+use crate::config;
+use crate::daemon;
+use crate::run::XmrError;
+
+// Same values the supervisor tries to give itself at startup
+const NICE: i32 = -20;
+const OOM_SCORE_ADJUST: i32 = -1000;
+const RESTART_SECS: u64 = 5;
+// Time the supervisor gets to stop the miner after its Ctrl+C
+const STOP_TIMEOUT_SECS: u64 = 60;
+
+// Init system the service is installed into
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Manager {
+    Systemd,
+    Openrc,
+    Runit,
+    Launchd,
+    // Windows Task Scheduler
+    TaskScheduler,
+}
+
+impl Manager {
+    // The init system this machine booted with
+    pub fn detect() -> Option<Manager> {
+        if cfg!(windows) {
+            Some(Manager::TaskScheduler)
+        } else if cfg!(target_os = "macos") {
+            Some(Manager::Launchd)
+        } else if Path::new("/run/systemd/system").is_dir() {
+            Some(Manager::Systemd)
+        } else if Path::new("/run/openrc").is_dir() {
+            Some(Manager::Openrc)
+        } else if Path::new("/run/runit").is_dir() || Path::new("/etc/runit").is_dir() {
+            Some(Manager::Runit)
+        } else {
+            None
+        }
+    }
+}
+
+// Where and for whom the service is installed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    // Runs at boot as root
+    System,
+    // Runs while the user is logged in, or always with lingering enabled
+    User,
+}
+
+impl Scope {
+    // System when run as root, the user's own service manager otherwise
+    pub fn detect() -> Scope {
+        #[cfg(unix)]
+        // SAFETY: geteuid cannot fail
+        if unsafe { libc::geteuid() } == 0 {
+            return Scope::System;
+        }
+        Scope::User
+    }
+}
+
+// What the service runs
+pub struct Service {
+    pub scope: Scope,
+    // run-resilient or run-super-resilient
+    pub mode: &'static str,
+}
+
+// "minning", or "minning-<instance>" for a named instance
+pub fn name() -> String {
+    if config::instance() == config::DEFAULT_INSTANCE {
+        "minning".to_string()
+    } else {
+        format!("minning-{}", config::instance())
+    }
+}
+
+// This launcher invoked for `mode` on the current instance and config file,
+// with absolute paths since services do not start in the current directory
+pub fn command(mode: &str) -> Vec<String> {
+    let exe = env::current_exe().map_or_else(|_| "minning".to_string(), |p| p.display().to_string());
+    let mut command = vec![exe];
+    if config::instance() != config::DEFAULT_INSTANCE {
+        command.extend(["--name".to_string(), config::instance().to_string()]);
+    }
+    if let Some(path) = config::config_file() {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        command.extend(["--config".to_string(), path.display().to_string()]);
+    }
+    command.push(mode.to_string());
+    command
+}
+
+// systemd unit that keeps the supervisor running, restarting it whenever it
+// exits. `extra` lines go into [Service].
+pub fn systemd_unit(service: &Service, extra: &[String]) -> String {
+    let mut unit = String::new();
+    unit.push_str("[Unit]\n");
+    unit.push_str("Description=Minning XMR miner supervisor\n");
+    unit.push_str("Wants=network-online.target\n");
+    unit.push_str("After=network-online.target\n");
+    // Never stop restarting, however often it fails
+    unit.push_str("StartLimitIntervalSec=0\n\n");
+    unit.push_str("[Service]\n");
+    unit.push_str(&format!("ExecStart={}\n", command(service.mode).join(" ")));
+    // ~/xmr and the config are found through HOME, which system units lack
+    if let Ok(home) = env::var("HOME") {
+        unit.push_str(&format!("Environment=HOME={}\n", home));
+    }
+    unit.push_str("Restart=always\n");
+    unit.push_str(&format!("RestartSec={}\n", RESTART_SECS));
+    // The supervisor shuts the miner down cleanly on SIGINT, like `stop`
+    unit.push_str("KillSignal=SIGINT\n");
+    unit.push_str("KillMode=mixed\n");
+    // Lets [cgroup] give the miner a cgroup of its own below the service's
+    unit.push_str("Delegate=yes\n");
+    unit.push_str(&format!("TimeoutStopSec={}\n", STOP_TIMEOUT_SECS));
+    // A user manager may not raise priority or OOM protection
+    if service.scope == Scope::System {
+        unit.push_str(&format!("Nice={}\n", NICE));
+        unit.push_str(&format!("OOMScoreAdjust={}\n", OOM_SCORE_ADJUST));
+    }
+    for line in extra {
+        unit.push_str(line);
+        unit.push('\n');
+    }
+    unit.push_str("\n[Install]\n");
+    let target = match service.scope {
+        Scope::System => "multi-user.target",
+        Scope::User => "default.target",
+    };
+    unit.push_str(&format!("WantedBy={}\n", target));
+    unit
+}
+
+fn systemd_path(scope: Scope) -> Result<PathBuf, XmrError> {
+    let file = format!("{}.service", name());
+    match scope {
+        Scope::System => Ok(PathBuf::from("/etc/systemd/system").join(file)),
+        Scope::User => {
+            let home = env::var("HOME").map_err(|_| XmrError::EnvError("Could not determine home directory".to_string()))?;
+            Ok(PathBuf::from(home).join(".config/systemd/user").join(file))
+        },
+    }
+}
+
+fn systemctl(scope: Scope, args: &[&str]) -> Result<(), XmrError> {
+    let mut command = Command::new("systemctl");
+    if scope == Scope::User {
+        command.arg("--user");
+    }
+    let status = command
+        .args(args)
+        .status()
+        .map_err(|e| XmrError::ExecutionError(format!("Could not run systemctl: {}", e)))?;
+    if !status.success() {
+        return Err(XmrError::ExecutionError(format!("systemctl {} failed: {}", args.join(" "), status)));
+    }
+    Ok(())
+}
+
+// Quote `arg` for /bin/sh
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@".contains(c)) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn shell_command(mode: &str) -> String {
+    command(mode).iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>().join(" ")
+}
+
+// OpenRC service script, run under supervise-daemon so the supervisor is
+// respawned whenever it exits
+pub fn openrc_script(service: &Service) -> String {
+    let mut command = command(service.mode).into_iter();
+    let exe = command.next().unwrap_or_default();
+    let args: Vec<String> = command.map(|arg| shell_quote(&arg)).collect();
+    let mut script = String::new();
+    script.push_str("#!/sbin/openrc-run\n\n");
+    script.push_str("description=\"Minning XMR miner supervisor\"\n");
+    script.push_str("supervisor=supervise-daemon\n");
+    script.push_str(&format!("command={}\n", shell_quote(&exe)));
+    script.push_str(&format!("command_args=\"{}\"\n", args.join(" ")));
+    script.push_str(&format!("supervise_daemon_args=\"--nicelevel {}\"\n", NICE));
+    script.push_str(&format!("respawn_delay={}\n", RESTART_SECS));
+    script.push_str("respawn_max=0\n");
+    // The supervisor shuts the miner down cleanly on SIGINT, like `stop`
+    script.push_str(&format!("retry=\"SIGINT/{}/SIGKILL/5\"\n", STOP_TIMEOUT_SECS));
+    if let Ok(home) = env::var("HOME") {
+        script.push_str(&format!("export HOME={}\n", shell_quote(&home)));
+    }
+    script.push_str("\ndepend() {\n\tneed net\n}\n\n");
+    // Inherited by the supervisor and the miner it starts
+    script.push_str(&format!("start_pre() {{\n\techo {} > /proc/self/oom_score_adj\n}}\n", OOM_SCORE_ADJUST));
+    script
+}
+
+// runit service directory files: runsv reruns `run` whenever the supervisor
+// exits, and `control/t` turns `sv down` into the SIGINT the supervisor
+// shuts down cleanly on
+pub fn runit_files(service: &Service) -> Vec<(&'static str, String)> {
+    let mut run = String::from("#!/bin/sh\nexec 2>&1\n");
+    if let Ok(home) = env::var("HOME") {
+        run.push_str(&format!("export HOME={}\n", shell_quote(&home)));
+    }
+    if service.scope == Scope::System {
+        run.push_str(&format!("echo {} > /proc/self/oom_score_adj\n", OOM_SCORE_ADJUST));
+        run.push_str(&format!("exec nice -n {} {}\n", NICE, shell_command(service.mode)));
+    } else {
+        run.push_str(&format!("exec {}\n", shell_command(service.mode)));
+    }
+    let control = "#!/bin/sh\nkill -INT \"$(cat supervise/pid)\"\n".to_string();
+    vec![("run", run), ("control/t", control)]
+}
+
+// Escape text for a plist <string>
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// launchd job label, e.g. "io.github.minning" or "io.github.minning-rig2"
+fn launchd_label() -> String {
+    format!("io.github.{}", name())
+}
+
+// launchd property list: KeepAlive restarts the supervisor whenever it
+// exits, and launchd's SIGTERM on unload is handled like `stop`
+pub fn launchd_plist(service: &Service) -> String {
+    let mut plist = String::new();
+    plist.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    plist.push_str("<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n");
+    plist.push_str("<plist version=\"1.0\">\n<dict>\n");
+    plist.push_str(&format!("\t<key>Label</key>\n\t<string>{}</string>\n", launchd_label()));
+    plist.push_str("\t<key>ProgramArguments</key>\n\t<array>\n");
+    for arg in command(service.mode) {
+        plist.push_str(&format!("\t\t<string>{}</string>\n", xml_escape(&arg)));
+    }
+    plist.push_str("\t</array>\n");
+    if let Ok(home) = env::var("HOME") {
+        plist.push_str("\t<key>EnvironmentVariables</key>\n\t<dict>\n");
+        plist.push_str(&format!("\t\t<key>HOME</key>\n\t\t<string>{}</string>\n", xml_escape(&home)));
+        plist.push_str("\t</dict>\n");
+    }
+    plist.push_str("\t<key>RunAtLoad</key>\n\t<true/>\n");
+    plist.push_str("\t<key>KeepAlive</key>\n\t<true/>\n");
+    plist.push_str(&format!("\t<key>ThrottleInterval</key>\n\t<integer>{}</integer>\n", RESTART_SECS));
+    plist.push_str(&format!("\t<key>ExitTimeOut</key>\n\t<integer>{}</integer>\n", STOP_TIMEOUT_SECS));
+    if service.scope == Scope::System {
+        plist.push_str(&format!("\t<key>Nice</key>\n\t<integer>{}</integer>\n", NICE));
+    }
+    if let Ok(dir) = config::state_dir() {
+        let output = xml_escape(&dir.join(daemon::OUTPUT_FILE).display().to_string());
+        plist.push_str(&format!("\t<key>StandardOutPath</key>\n\t<string>{}</string>\n", output));
+        plist.push_str(&format!("\t<key>StandardErrorPath</key>\n\t<string>{}</string>\n", output));
+    }
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+// Task Scheduler definition. Both scopes run without anyone logged in: as
+// SYSTEM from boot, or as the user from boot without their password (S4U
+// logon), so mining survives logoff. A supervisor that exits is started
+// again every minute, up to 999 times.
+pub fn scheduled_task(service: &Service) -> String {
+    let (trigger, principal) = match service.scope {
+        Scope::System => ("<BootTrigger/>".to_string(), "<UserId>S-1-5-18</UserId>\n      <RunLevel>HighestAvailable</RunLevel>".to_string()),
+        Scope::User => {
+            let user = env::var("USERDOMAIN")
+                .map(|domain| format!("{}\\", domain))
+                .unwrap_or_default()
+                + &env::var("USERNAME").unwrap_or_default();
+            (
+                format!("<BootTrigger/>\n    <LogonTrigger>\n      <UserId>{}</UserId>\n    </LogonTrigger>", xml_escape(&user)),
+                format!("<UserId>{}</UserId>\n      <LogonType>S4U</LogonType>\n      <RunLevel>LeastPrivilege</RunLevel>", xml_escape(&user)),
+            )
+        },
+    };
+    let mut args = command(service.mode);
+    let exe = args.remove(0);
+    let args = args.iter().map(|arg| if arg.contains(' ') { format!("\"{}\"", arg) } else { arg.clone() }).collect::<Vec<_>>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo>
+    <Description>Minning supervisor ({mode})</Description>
+  </RegistrationInfo>
+  <Triggers>
+    {trigger}
+  </Triggers>
+  <Principals>
+    <Principal id="Author">
+      {principal}
+    </Principal>
+  </Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <StartWhenAvailable>true</StartWhenAvailable>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+    <Priority>4</Priority>
+    <RestartOnFailure>
+      <Interval>PT1M</Interval>
+      <Count>999</Count>
+    </RestartOnFailure>
+  </Settings>
+  <Actions Context="Author">
+    <Exec>
+      <Command>{exe}</Command>
+      <Arguments>{args}</Arguments>
+    </Exec>
+  </Actions>
+</Task>
+"#,
+        mode = service.mode,
+        trigger = trigger,
+        principal = principal,
+        exe = xml_escape(&exe),
+        args = xml_escape(&args.join(" ")),
+    )
+}
+
+// The service definition as `--print` shows it
+pub fn definition(manager: Manager, service: &Service) -> String {
+    match manager {
+        Manager::Systemd => systemd_unit(service, &[]),
+        Manager::Openrc => openrc_script(service),
+        Manager::Launchd => launchd_plist(service),
+        Manager::TaskScheduler => scheduled_task(service),
+        Manager::Runit => runit_files(service)
+            .into_iter()
+            .map(|(file, contents)| format!("# {}\n{}", file, contents))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn run_tool(program: &str, args: &[&str]) -> Result<(), XmrError> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| XmrError::ExecutionError(format!("Could not run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(XmrError::ExecutionError(format!("{} {} failed: {}", program, args.join(" "), status)));
+    }
+    Ok(())
+}
+
+fn write_executable(path: &Path, contents: &str) -> Result<(), XmrError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+fn install_systemd(service: &Service, start: bool) -> Result<(), XmrError> {
+    let path = systemd_path(service.scope)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, systemd_unit(service, &[]))?;
+    println!("Installed {}", path.display());
+
+    systemctl(service.scope, &["daemon-reload"])?;
+    let name = name();
+    if start {
+        systemctl(service.scope, &["enable", "--now", &name])?;
+        println!("Enabled and started {}.", name);
+    } else {
+        systemctl(service.scope, &["enable", &name])?;
+        println!("Enabled {}; it starts on the next boot.", name);
+    }
+    if service.scope == Scope::User {
+        println!("To keep it running while logged out: loginctl enable-linger");
+    }
+    Ok(())
+}
+
+fn install_openrc(service: &Service, start: bool) -> Result<(), XmrError> {
+    if service.scope == Scope::User {
+        return Err(XmrError::PermissionError("OpenRC services are system-wide; run install-service as root".to_string()));
+    }
+    let name = name();
+    let path = Path::new("/etc/init.d").join(&name);
+    write_executable(&path, &openrc_script(service))?;
+    println!("Installed {}", path.display());
+
+    run_tool("rc-update", &["add", &name, "default"])?;
+    if start {
+        run_tool("rc-service", &[&name, "start"])?;
+        println!("Enabled and started {}.", name);
+    } else {
+        println!("Enabled {}; it starts on the next boot.", name);
+    }
+    Ok(())
+}
+
+// Service definitions and the directory runsvdir watches: Artix keeps them
+// under /etc/runit, Void and most others in /etc/sv and /var/service
+fn runit_dirs(scope: Scope) -> Result<(PathBuf, PathBuf), XmrError> {
+    match scope {
+        Scope::System if Path::new("/etc/runit/sv").is_dir() => {
+            Ok((PathBuf::from("/etc/runit/sv"), PathBuf::from("/run/runit/service")))
+        },
+        Scope::System => Ok((PathBuf::from("/etc/sv"), PathBuf::from("/var/service"))),
+        // A per-user runsvdir, as the Void handbook sets up
+        Scope::User => {
+            let home = env::var("HOME").map_err(|_| XmrError::EnvError("Could not determine home directory".to_string()))?;
+            let home = PathBuf::from(home);
+            Ok((home.join(".local/sv"), home.join("service")))
+        },
+    }
+}
+
+fn install_runit(service: &Service, start: bool) -> Result<(), XmrError> {
+    let name = name();
+    let (sv_dir, service_dir) = runit_dirs(service.scope)?;
+    let dir = sv_dir.join(&name);
+    for (file, contents) in runit_files(service) {
+        write_executable(&dir.join(file), &contents)?;
+    }
+    // With `down` present runsv leaves the service stopped until `sv up`
+    let down = dir.join("down");
+    if start {
+        let _ = fs::remove_file(&down);
+    } else {
+        fs::write(&down, "")?;
+    }
+    println!("Installed {}", dir.display());
+
+    fs::create_dir_all(&service_dir)?;
+    let link = service_dir.join(&name);
+    if fs::symlink_metadata(&link).is_err() {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, &link)?;
+    }
+    if start {
+        println!("Enabled {}; runsvdir starts it within a few seconds.", name);
+    } else {
+        println!("Enabled {}; start it with `sv up {}`.", name, link.display());
+    }
+    if service.scope == Scope::User {
+        println!("{} needs a runsvdir of your own running, e.g. from your session startup", service_dir.display());
+    }
+    Ok(())
+}
+
+fn install_launchd(service: &Service, start: bool) -> Result<(), XmrError> {
+    let label = launchd_label();
+    let (path, domain) = match service.scope {
+        Scope::System => (Path::new("/Library/LaunchDaemons").join(format!("{}.plist", label)), "system".to_string()),
+        Scope::User => {
+            let home = env::var("HOME").map_err(|_| XmrError::EnvError("Could not determine home directory".to_string()))?;
+            #[cfg(unix)]
+            // SAFETY: getuid cannot fail
+            let uid = unsafe { libc::getuid() };
+            #[cfg(not(unix))]
+            let uid = 0;
+            (PathBuf::from(home).join("Library/LaunchAgents").join(format!("{}.plist", label)), format!("gui/{}", uid))
+        },
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    if let Ok(dir) = config::state_dir() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, launchd_plist(service))?;
+    println!("Installed {}", path.display());
+
+    if start {
+        // Replace a job loaded from an earlier install
+        let _ = Command::new("launchctl").args(["bootout", &format!("{}/{}", domain, label)]).status();
+        run_tool("launchctl", &["bootstrap", &domain, &path.display().to_string()])?;
+        println!("Loaded and started {}.", label);
+    } else {
+        println!("{} starts at the next {}.", label, if service.scope == Scope::System { "boot" } else { "login" });
+    }
+    Ok(())
+}
+
+// Registered under the task name, e.g. \\Minning\\minning
+fn install_task(service: &Service, start: bool) -> Result<(), XmrError> {
+    let task = format!("\\Minning\\{}", name());
+    let dir = config::state_dir()?;
+    fs::create_dir_all(&dir)?;
+    // schtasks reads the definition as UTF-16, as the XML declaration says
+    let path = dir.join(format!("{}.task.xml", name()));
+    let mut contents = vec![0xFF, 0xFE];
+    contents.extend(scheduled_task(service).encode_utf16().flat_map(u16::to_le_bytes));
+    fs::write(&path, contents)?;
+
+    run_tool("schtasks", &["/Create", "/TN", &task, "/XML", &path.display().to_string(), "/F"])?;
+    println!("Registered scheduled task {}", task);
+    if start {
+        run_tool("schtasks", &["/Run", "/TN", &task])?;
+        println!("Started {}; stop it with `stop`.", task);
+    } else {
+        println!("{} starts at the next boot.", task);
+    }
+    Ok(())
+}
+
+// `install-service`: install the supervisor into the init system and enable
+// it, so it comes back after reboots and crashes of its own
+pub fn install(manager: Manager, service: &Service, start: bool) -> Result<(), XmrError> {
+    match manager {
+        Manager::Systemd => install_systemd(service, start),
+        Manager::Openrc => install_openrc(service, start),
+        Manager::Runit => install_runit(service, start),
+        Manager::Launchd => install_launchd(service, start),
+        Manager::TaskScheduler => install_task(service, start),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_systemd_unit() {
+        let system = systemd_unit(&Service { scope: Scope::System, mode: "run-resilient" }, &[]);
+        assert!(system.contains("run-resilient\n"));
+        assert!(system.contains("Restart=always\n"));
+        assert!(system.contains("OOMScoreAdjust=-1000\n"));
+        assert!(system.contains("WantedBy=multi-user.target\n"));
+
+        let user = systemd_unit(&Service { scope: Scope::User, mode: "run-super-resilient" }, &["ImportCredential=minning.*".to_string()]);
+        assert!(!user.contains("Nice="));
+        assert!(user.contains("ImportCredential=minning.*\n"));
+        assert!(user.contains("WantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn test_scheduled_task() {
+        let task = scheduled_task(&Service { scope: Scope::System, mode: "run-super-resilient" });
+        assert!(task.contains("<UserId>S-1-5-18</UserId>"));
+        assert!(task.contains("<BootTrigger/>"));
+        assert!(task.contains("<Count>999</Count>"));
+        assert!(task.contains("run-super-resilient</Arguments>"));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/bin/minning"), "/usr/bin/minning");
+        assert_eq!(shell_quote("/home/a b/it's"), "'/home/a b/it'\\''s'");
+    }
+}
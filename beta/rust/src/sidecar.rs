@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{error, info, warn};
+
+use crate::events::{self, Event};
+use crate::procgroup;
+use crate::selfmetrics;
+
+// A run of this length counts as healthy and resets the restart backoff
+const STABLE_RUN: Duration = Duration::from_secs(60);
+const MAX_BACKOFF_SECS: u64 = 60;
+
+// A helper process (p2pool, a node) supervised next to the miner. It keeps
+// running while mining is paused and is only stopped at shutdown.
+#[derive(Debug, Clone)]
+pub struct Sidecar {
+    pub name: String,
+    pub program: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl Sidecar {
+    fn spawn_child(&self) -> std::io::Result<Child> {
+        selfmetrics::time("spawn sidecar", || {
+            procgroup::isolate(&mut Command::new(&self.program))
+                .args(&self.args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        })
+    }
+
+    // Keep the process alive until `running` clears, restarting it with
+    // exponential backoff whenever it exits
+    pub fn supervise(self, running: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let mut current: Option<(Child, Instant)> = None;
+            let mut backoff_secs = 1;
+
+            while running.load(Ordering::SeqCst) {
+                if let Some((child, started)) = &mut current {
+                    match child.try_wait() {
+                        Ok(None) => {
+                            thread::sleep(Duration::from_millis(500));
+                            continue;
+                        },
+                        Ok(Some(status)) => {
+                            warn!("{} process {} exited with {}", self.name, child.id(), status);
+                            events::emit(Event::Alert { message: format!("{} exited ({})", self.name, status) });
+                        },
+                        Err(e) => error!("Error checking {} process status: {}", self.name, e),
+                    }
+                    if started.elapsed() >= STABLE_RUN {
+                        backoff_secs = 1;
+                    }
+                    current = None;
+
+                    info!("Restarting {} in {} seconds", self.name, backoff_secs);
+                    for _ in 0..backoff_secs {
+                        if !running.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        thread::sleep(Duration::from_secs(1));
+                    }
+                    backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF_SECS);
+                    continue;
+                }
+
+                match self.spawn_child() {
+                    Ok(child) => {
+                        info!("Started {} with PID: {}", self.name, child.id());
+                        current = Some((child, Instant::now()));
+                    },
+                    Err(e) => {
+                        error!("Failed to start {} ({}): {}", self.name, self.program.display(), e);
+                        thread::sleep(Duration::from_secs(backoff_secs));
+                        backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF_SECS);
+                    },
+                }
+            }
+
+            if let Some((mut child, _)) = current {
+                info!("Terminating {} process...", self.name);
+                if let Err(e) = procgroup::kill(&mut child) {
+                    error!("Failed to kill {} process: {}", self.name, e);
+                }
+                let _ = child.wait();
+            }
+        })
+    }
+}
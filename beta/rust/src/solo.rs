@@ -0,0 +1,97 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::node::{self, NodeInfo};
+use crate::pause;
+use crate::run::XmrError;
+use crate::split::Target;
+
+const PAUSE_REASON: &str = "node not synced";
+
+// Solo target: the miner fetches block templates from the node and the
+// whole block reward goes to the configured wallet
+pub fn target(config: &Config) -> Option<Target> {
+    Some(Target {
+        name: "solo".to_string(),
+        pool: config.solo.node.clone(),
+        wallet: config.wallet.address.clone()?,
+        password: "x".to_string(),
+        tls: false,
+        daemon: true,
+        backups: Vec::new(),
+        proxy: None,
+        saved: Vec::new(),
+    })
+}
+
+// Pause mining unless the node is reachable and fully synced
+fn check(node: &str) -> Option<NodeInfo> {
+    match node::get_info(node) {
+        Ok(info) => {
+            if !info.is_ready() {
+                info!("Node {} is not ready yet (height {} of {})", node, info.height, info.target_height);
+            }
+            pause::set(PAUSE_REASON, !info.is_ready());
+            Some(info)
+        },
+        Err(e) => {
+            warn!("Could not reach node {}: {}", node, e);
+            pause::pause(PAUSE_REASON);
+            None
+        },
+    }
+}
+
+// Supervised modes: check the node once before the first miner start, then
+// keep re-checking so a node that falls behind pauses mining again
+pub fn start_sync_watch(config: &Config, running: Arc<AtomicBool>) {
+    if !config.solo.enabled {
+        return;
+    }
+
+    let node = config.solo.node.clone();
+    let interval = Duration::from_secs(config.solo.check_secs.max(5));
+    info!("Solo mining against {}", node);
+    check(&node);
+
+    thread::spawn(move || {
+        while running.load(Ordering::SeqCst) {
+            let mut waited = Duration::ZERO;
+            while waited < interval && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+            if running.load(Ordering::SeqCst) {
+                check(&node);
+            }
+        }
+    });
+}
+
+// One-shot `run`: refuse to start against a node that cannot serve templates
+pub fn ensure_ready(config: &Config) -> Result<(), XmrError> {
+    if !config.solo.enabled {
+        return Ok(());
+    }
+
+    let info = node::get_info(&config.solo.node)?;
+    if !info.is_ready() {
+        return Err(XmrError::NetworkError(format!(
+            "Node {} is not synced (height {} of {})",
+            config.solo.node, info.height, info.target_height
+        )));
+    }
+    Ok(())
+}
+
+// `solo`: node height and connection state for the configured node
+pub fn status(config: &Config) -> Result<(), XmrError> {
+    println!("Solo mining:   {}", if config.solo.enabled { "enabled" } else { "disabled" });
+    node::print_info(&config.solo.node, &node::get_info(&config.solo.node));
+    Ok(())
+}
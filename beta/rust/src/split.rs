@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::warn;
+
+use crate::config::{self, Config, PoolEntry};
+use crate::pool;
+use crate::run::XmrError;
+use crate::solo;
+
+pub const SPLIT_STATS_FILE: &str = "split.json";
+const FLUSH_INTERVAL_SECS: u64 = 60;
+
+// Where the miner sends its shares: one pool + wallet pair
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub name: String,
+    pub pool: String,
+    pub wallet: String,
+    pub password: String,
+    pub tls: bool,
+    // `pool` is a monerod RPC endpoint mined through getblocktemplate
+    pub daemon: bool,
+    // Further endpoints the miner fails over to, same wallet and password
+    pub backups: Vec<String>,
+    // SOCKS5 proxy the miner connects through
+    pub proxy: Option<String>,
+    // Saved pools behind `pool` and each backup, in that order; their TLS
+    // flag and login take the place of the target's for that endpoint
+    pub saved: Vec<Option<PoolEntry>>,
+}
+
+impl Target {
+    // xmrig-style command line arguments for this target; every `-o` starts
+    // a new pool entry that the following options apply to
+    pub fn miner_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (index, pool) in std::iter::once(&self.pool).chain(&self.backups).enumerate() {
+            let (user, password, tls) = self.login(index);
+            args.extend(["-o".to_string(), pool.clone(), "-u".to_string(), user, "-p".to_string(), password]);
+            if tls {
+                args.push("--tls".to_string());
+            }
+            if self.daemon {
+                args.push("--daemon".to_string());
+            }
+            if let Some(proxy) = &self.proxy {
+                args.extend(["-x".to_string(), proxy.clone()]);
+            }
+        }
+        args
+    }
+
+    // Login, password and TLS flag for `pool` (0) or a backup (1..)
+    pub fn login(&self, index: usize) -> (String, String, bool) {
+        match self.saved.get(index).and_then(Option::as_ref) {
+            Some(entry) => (
+                entry.user.clone().unwrap_or_else(|| self.wallet.clone()),
+                entry.password.clone().unwrap_or_else(|| self.password.clone()),
+                entry.tls,
+            ),
+            None => (self.wallet.clone(), self.password.clone(), self.tls),
+        }
+    }
+}
+
+// The [pool] endpoints + [wallet] address pair, when both are configured
+pub fn primary_target(config: &Config) -> Option<Target> {
+    let endpoints = pool::ordered_endpoints(config);
+    let saved: Vec<Option<PoolEntry>> = endpoints.iter().map(|e| pool::saved(config, e).cloned()).collect();
+    // Saved pools with their own login do without a [wallet] address
+    let wallet = config.wallet.address.clone().or_else(|| saved.first()?.as_ref()?.user.clone());
+    let mut endpoints = endpoints.into_iter();
+    Some(Target {
+        name: "primary".to_string(),
+        pool: endpoints.next()?,
+        wallet: wallet?,
+        password: config.pool.password.clone(),
+        tls: config.pool.tls,
+        daemon: false,
+        backups: endpoints.collect(),
+        proxy: None,
+        saved,
+    })
+}
+
+// Configured split targets with their normalised share of mining time
+fn split_targets(config: &Config) -> Vec<(Target, f64)> {
+    let total: f64 = config.split.targets.iter().map(|t| t.percent.max(0.0)).sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+
+    config
+        .split
+        .targets
+        .iter()
+        .filter_map(|t| {
+            // Targets without their own pool share the [pool] endpoints
+            let mut endpoints = match &t.pool {
+                Some(pool) => vec![pool.clone()],
+                None => pool::ordered_endpoints(config),
+            }
+            .into_iter();
+            let target = Target {
+                name: t.name.clone(),
+                pool: endpoints.next()?,
+                wallet: t.wallet.clone(),
+                password: config.pool.password.clone(),
+                tls: t.tls.unwrap_or(config.pool.tls),
+                daemon: false,
+                backups: endpoints.collect(),
+                proxy: None,
+                // The split wallet is the login, whatever the saved pool says
+                saved: Vec::new(),
+            };
+            Some((target, t.percent.max(0.0) / total))
+        })
+        .collect()
+}
+
+// Target that should be mined at `now` (unix seconds). Each cycle is cut
+// into consecutive slices proportional to the configured percentages, so
+// over many cycles the time split approximates the ratio. Solo mining
+// replaces pools and splits altogether.
+pub fn target_at(config: &Config, now: u64) -> Option<Target> {
+    if config.solo.enabled {
+        return solo::target(config);
+    }
+
+    let targets = split_targets(config);
+    if targets.is_empty() {
+        return primary_target(config);
+    }
+
+    let cycle = (config.split.cycle_mins.max(1) * 60) as f64;
+    let position = (now as f64 % cycle) / cycle;
+    let mut cumulative = 0.0;
+    for (target, share) in &targets {
+        cumulative += share;
+        if position < cumulative {
+            return Some(target.clone());
+        }
+    }
+    targets.last().map(|(target, _)| target.clone())
+}
+
+pub fn current_target(config: &Config) -> Option<Target> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    target_at(config, now)
+}
+
+struct Tally {
+    seconds: BTreeMap<String, f64>,
+    last_flush: Option<Instant>,
+}
+
+// Mining seconds per target not yet written to disk
+static TALLY: Mutex<Tally> = Mutex::new(Tally { seconds: BTreeMap::new(), last_flush: None });
+
+fn stats_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(SPLIT_STATS_FILE))
+}
+
+pub fn load_stats() -> BTreeMap<String, f64> {
+    stats_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_stats(pending: &mut BTreeMap<String, f64>) -> Result<(), XmrError> {
+    let mut totals = load_stats();
+    for (name, secs) in pending.iter() {
+        *totals.entry(name.clone()).or_insert(0.0) += secs;
+    }
+
+    let path = stats_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(&totals)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode split stats: {}", e)))?;
+    config::write_atomic(&path, &contents)?;
+    pending.clear();
+    Ok(())
+}
+
+// Credit time mined for a target; totals are persisted about once a minute
+pub fn credit(name: &str, seconds: f64) {
+    let Ok(mut tally) = TALLY.lock() else {
+        return;
+    };
+    *tally.seconds.entry(name.to_string()).or_insert(0.0) += seconds;
+
+    let due = tally.last_flush.is_none_or(|t| t.elapsed().as_secs() >= FLUSH_INTERVAL_SECS);
+    if due {
+        tally.last_flush = Some(Instant::now());
+        if let Err(e) = write_stats(&mut tally.seconds) {
+            warn!("Could not save split statistics: {}", e);
+        }
+    }
+}
+
+pub fn flush() {
+    if let Ok(mut tally) = TALLY.lock()
+        && let Err(e) = write_stats(&mut tally.seconds)
+    {
+        warn!("Could not save split statistics: {}", e);
+    }
+}
+
+// `split`: configured shares against the mining time actually recorded
+pub fn status(config: &Config) -> Result<(), XmrError> {
+    let targets = split_targets(config);
+    if targets.is_empty() {
+        println!("No wallet split configured; all mining goes to the primary wallet.");
+        return Ok(());
+    }
+
+    let mined = load_stats();
+    let total_mined: f64 = targets.iter().map(|(t, _)| mined.get(&t.name).copied().unwrap_or(0.0)).sum();
+    let active = current_target(config).map(|t| t.name);
+
+    println!("Cycle length: {} minutes", config.split.cycle_mins.max(1));
+    println!("{:<16}  {:>9}  {:>9}  {:>10}  Wallet", "Target", "Configured", "Actual", "Mined");
+    for (target, share) in &targets {
+        let secs = mined.get(&target.name).copied().unwrap_or(0.0);
+        let actual = if total_mined > 0.0 { secs / total_mined * 100.0 } else { 0.0 };
+        let marker = if active.as_deref() == Some(target.name.as_str()) { " (active)" } else { "" };
+        println!(
+            "{:<16}  {:>8.1}%  {:>8.1}%  {:>9.1}h  {}{}",
+            target.name,
+            share * 100.0,
+            actual,
+            secs / 3600.0,
+            target.wallet,
+            marker
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_slices() {
+        let config: Config = toml::from_str(
+            r#"
+            [pool]
+            url = "pool.example.com:3333"
+
+            [split]
+            cycle_mins = 100
+
+            [[split.targets]]
+            name = "me"
+            wallet = "4me"
+            percent = 90
+
+            [[split.targets]]
+            name = "friend"
+            wallet = "4friend"
+            percent = 10
+            "#,
+        )
+        .unwrap();
+
+        // 100 minute cycle: first 90 minutes for "me", last 10 for "friend"
+        assert_eq!(target_at(&config, 0).unwrap().name, "me");
+        assert_eq!(target_at(&config, 89 * 60).unwrap().name, "me");
+        assert_eq!(target_at(&config, 95 * 60).unwrap().name, "friend");
+        assert_eq!(target_at(&config, 100 * 60).unwrap().name, "me");
+    }
+
+    #[test]
+    fn test_failover_args_use_saved_logins() {
+        let config: Config = toml::from_str(
+            r#"
+            [wallet]
+            address = "4me"
+
+            [pool]
+            default = "main"
+            failover = ["backup", "other.example.com:3333"]
+
+            [[pools]]
+            name = "main"
+            host = "main.example.com"
+            port = 443
+            tls = true
+
+            [[pools]]
+            name = "backup"
+            host = "backup.example.com"
+            port = 3333
+            user = "rig1"
+            password = "secret"
+            "#,
+        )
+        .unwrap();
+
+        let args = primary_target(&config).unwrap().miner_args();
+        assert_eq!(
+            args.join(" "),
+            "-o main.example.com:443 -u 4me -p x --tls \
+             -o backup.example.com:3333 -u rig1 -p secret \
+             -o other.example.com:3333 -u 4me -p x"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::bandwidth::{self, Totals};
+use crate::config;
+use crate::earnings::{self, Sample};
+use crate::market;
+use crate::payouts::{self, Payout};
+use crate::pidfile;
+use crate::run::XmrError;
+use crate::scheduler;
+use crate::split;
+use crate::stratum_proxy::{self, ProxyStats};
+
+const QUARANTINE_DIR: &str = "quarantine";
+
+enum Kind {
+    // One JSON document of the given shape
+    Json(fn(&str) -> bool),
+    // Append-only records, one JSON document per line
+    JsonLines(fn(&str) -> bool),
+    Pid,
+}
+
+fn parses<T: DeserializeOwned>(text: &str) -> bool {
+    serde_json::from_str::<T>(text).is_ok()
+}
+
+const FILES: &[(&str, Kind)] = &[
+    (split::SPLIT_STATS_FILE, Kind::Json(parses::<BTreeMap<String, f64>>)),
+    (bandwidth::BANDWIDTH_FILE, Kind::Json(parses::<Totals>)),
+    (stratum_proxy::STATS_FILE, Kind::Json(parses::<ProxyStats>)),
+    (scheduler::SCHEDULER_FILE, Kind::Json(parses::<BTreeMap<String, i64>>)),
+    (market::PRICE_CACHE_FILE, Kind::Json(parses::<HashMap<String, Value>>)),
+    (earnings::HISTORY_FILE, Kind::JsonLines(parses::<Sample>)),
+    (payouts::PAYOUTS_FILE, Kind::JsonLines(parses::<Payout>)),
+    (pidfile::PID_FILE, Kind::Pid),
+];
+
+// Move a damaged file out of the way, keeping it for inspection
+fn quarantine(dir: &Path, name: &str) -> Result<(), XmrError> {
+    let quarantine = dir.join(QUARANTINE_DIR);
+    fs::create_dir_all(&quarantine)?;
+    fs::rename(dir.join(name), quarantine.join(format!("{}.{}", name, Utc::now().timestamp())))?;
+    Ok(())
+}
+
+// Keep the records that still parse; torn lines go to the quarantine
+fn repair_lines(dir: &Path, name: &str, bytes: &[u8], valid: fn(&str) -> bool) -> Result<Option<String>, XmrError> {
+    let text = String::from_utf8_lossy(bytes);
+    let (good, bad): (Vec<&str>, Vec<&str>) =
+        text.lines().filter(|line| !line.trim().is_empty()).partition(|line| valid(line));
+    if bad.is_empty() && text.ends_with('\n') && !text.contains('\u{FFFD}') {
+        return Ok(None);
+    }
+
+    if !bad.is_empty() {
+        let quarantine = dir.join(QUARANTINE_DIR);
+        fs::create_dir_all(&quarantine)?;
+        let kept = format!("{}\n", bad.join("\n"));
+        fs::write(quarantine.join(format!("{}.{}", name, Utc::now().timestamp())), kept)?;
+    }
+    let mut repaired = good.join("\n");
+    if !repaired.is_empty() {
+        repaired.push('\n');
+    }
+    config::write_atomic(&dir.join(name), &repaired)?;
+    Ok(Some(format!("dropped {} damaged line(s)", bad.len())))
+}
+
+fn check_file(dir: &Path, name: &str, kind: &Kind) -> Result<Option<String>, XmrError> {
+    let path = dir.join(name);
+    let Ok(bytes) = fs::read(&path) else {
+        return Ok(None);
+    };
+
+    match kind {
+        Kind::Pid => {
+            if pidfile::running().is_some() {
+                return Ok(None);
+            }
+            fs::remove_file(&path)?;
+            Ok(Some("removed stale PID file".to_string()))
+        },
+        _ if bytes.iter().all(u8::is_ascii_whitespace) => {
+            fs::remove_file(&path)?;
+            Ok(Some("removed empty file".to_string()))
+        },
+        Kind::Json(valid) => {
+            if std::str::from_utf8(&bytes).is_ok_and(valid) {
+                return Ok(None);
+            }
+            quarantine(dir, name)?;
+            Ok(Some(format!("unreadable, moved to {}/", QUARANTINE_DIR)))
+        },
+        Kind::JsonLines(valid) => repair_lines(dir, name, &bytes, *valid),
+    }
+}
+
+// Every command starts here: repair what a crash or power loss may have
+// left half-written in the state directory, so later reads never trip
+// over it. Skipped while a supervisor is running, as it owns the files and
+// checked them itself when it started.
+pub fn run() {
+    let Ok(dir) = config::state_dir() else {
+        return;
+    };
+    if !dir.is_dir() || pidfile::running().is_some() {
+        return;
+    }
+
+    // Leftovers from writes that never got renamed into place
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            if entry.path().extension().is_some_and(|ext| ext == "tmp") {
+                debug!("Removing leftover {}", entry.path().display());
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    for (name, kind) in FILES {
+        match check_file(&dir, name, kind) {
+            Ok(Some(action)) => warn!("State file {}: {}", name, action),
+            Ok(None) => {},
+            Err(e) => warn!("Could not check state file {}: {}", name, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_lines() {
+        let dir = std::env::temp_dir().join(format!("minning-statecheck-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let name = "earnings.jsonl";
+        let torn = "{\"timestamp\":1,\"unpaid\":0.5,\"total_paid\":null}\n{\"timestamp\":2,\"unp";
+        fs::write(dir.join(name), torn).unwrap();
+
+        let action = repair_lines(&dir, name, torn.as_bytes(), parses::<Sample>).unwrap();
+        assert_eq!(action.as_deref(), Some("dropped 1 damaged line(s)"));
+        let repaired = fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(repaired, "{\"timestamp\":1,\"unpaid\":0.5,\"total_paid\":null}\n");
+        assert_eq!(repair_lines(&dir, name, repaired.as_bytes(), parses::<Sample>).unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
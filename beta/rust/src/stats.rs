@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::config::{self, Config};
+use crate::events::{self, Event, Sink};
+use crate::output;
+use crate::pidfile;
+use crate::run::XmrError;
+use crate::status;
+
+pub const STATS_FILE: &str = "stats.json";
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(30);
+
+// Counters for one supervisor session, or summed over all of them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Totals {
+    // Unix seconds the session (or the first one) started
+    pub since: i64,
+    pub sessions: u64,
+    pub restarts: u64,
+    // Time the supervisor was up, and the part of it the miner was hashing
+    pub uptime_secs: u64,
+    pub mining_secs: u64,
+    // Hashrate integrated over mining time, for the average
+    pub hashes: f64,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+impl Totals {
+    pub fn average_hashrate(&self) -> Option<f64> {
+        (self.mining_secs > 0).then(|| self.hashes / self.mining_secs as f64)
+    }
+
+    fn add(&mut self, other: &Totals) {
+        self.restarts += other.restarts;
+        self.uptime_secs += other.uptime_secs;
+        self.mining_secs += other.mining_secs;
+        self.hashes += other.hashes;
+        self.accepted += other.accepted;
+        self.rejected += other.rejected;
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    // Supervisor the session belongs to
+    pub pid: u32,
+    pub session: Totals,
+    // All sessions before the current one
+    pub previous: Totals,
+}
+
+impl Stats {
+    pub fn lifetime(&self) -> Totals {
+        let mut lifetime = self.previous;
+        lifetime.add(&self.session);
+        lifetime.sessions += 1;
+        lifetime
+    }
+}
+
+// Miner processes started by this supervisor; all but the first are restarts
+static STARTS: AtomicU64 = AtomicU64::new(0);
+
+struct StartCounter;
+
+impl Sink for StartCounter {
+    fn handle(&self, event: &Event) {
+        if let Event::ChildStarted { .. } = event {
+            STARTS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// Restarts so far in this process's session, ahead of the next sample
+pub fn session_restarts() -> u64 {
+    STARTS.load(Ordering::Relaxed).saturating_sub(1)
+}
+
+fn stats_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(STATS_FILE))
+}
+
+pub fn load() -> Option<Stats> {
+    let contents = fs::read_to_string(stats_path().ok()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save(stats: &Stats) -> Result<(), XmrError> {
+    let path = stats_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(stats)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode mining statistics: {}", e)))?;
+    config::write_atomic(&path, &contents)
+}
+
+// Shares counted so far by the running miner process. Its counters start
+// over when it is restarted, so only growth is added.
+#[derive(Default)]
+struct Shares {
+    accepted: u64,
+    rejected: u64,
+}
+
+impl Shares {
+    fn advance(&mut self, accepted: u64, rejected: u64) -> (u64, u64) {
+        let grown = |now: u64, was: u64| if now >= was { now - was } else { now };
+        let (new_accepted, new_rejected) = (grown(accepted, self.accepted), grown(rejected, self.rejected));
+        *self = Shares { accepted, rejected };
+        (new_accepted, new_rejected)
+    }
+}
+
+// Supervised modes: start a new session on top of the saved totals and
+// keep it up to date from the miner's API
+pub fn start_recorder(config: &Config, running: Arc<AtomicBool>) {
+    events::subscribe(Box::new(StartCounter));
+    let mut stats = match load() {
+        // The previous session is over; fold it into the totals
+        Some(saved) => Stats { previous: saved.lifetime(), ..Stats::default() },
+        None => Stats::default(),
+    };
+    stats.pid = process::id();
+    stats.session.since = Utc::now().timestamp();
+    if stats.previous.since == 0 {
+        stats.previous.since = stats.session.since;
+    }
+
+    let api_url = config.miner.api_url.clone();
+    thread::spawn(move || {
+        if let Err(e) = save(&stats) {
+            warn!("Could not save mining statistics: {}", e);
+        }
+        let mut shares = Shares::default();
+        let mut last = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            let mut waited = Duration::ZERO;
+            while waited < SAMPLE_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+
+            let elapsed = last.elapsed().as_secs();
+            last += Duration::from_secs(elapsed);
+            let session = &mut stats.session;
+            session.uptime_secs += elapsed;
+            session.restarts = session_restarts();
+            match status::query(&api_url) {
+                Ok(miner) => {
+                    if let Some(hashrate) = miner.hashrate[1].or(miner.hashrate[0]).filter(|&h| h > 0.0) {
+                        session.mining_secs += elapsed;
+                        session.hashes += hashrate * elapsed as f64;
+                    }
+                    let (accepted, rejected) = shares.advance(miner.accepted, miner.rejected);
+                    session.accepted += accepted;
+                    session.rejected += rejected;
+                },
+                Err(e) => debug!("No miner statistics: {}", e),
+            }
+            if let Err(e) = save(&stats) {
+                warn!("Could not save mining statistics: {}", e);
+            }
+        }
+    });
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map_or_else(|| "?".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string())
+}
+
+fn print_totals(title: &str, totals: &Totals) {
+    println!("{} (since {}):", title, format_time(totals.since));
+    if totals.sessions > 0 {
+        println!("  Sessions:       {}", totals.sessions);
+    }
+    println!("  Uptime:         {}", status::format_duration(totals.uptime_secs));
+    println!("  Mining time:    {}", status::format_duration(totals.mining_secs));
+    println!("  Restarts:       {}", totals.restarts);
+    match totals.average_hashrate() {
+        Some(hashrate) => println!("  Avg hashrate:   {:.1} H/s", hashrate),
+        None => println!("  Avg hashrate:   -"),
+    }
+    println!("  Shares:         {} accepted, {} rejected", totals.accepted, totals.rejected);
+}
+
+// `stats`: the current (or last) session and the totals over all sessions
+pub fn show() -> Result<(), XmrError> {
+    let Some(stats) = load() else {
+        return Err(XmrError::EnvError("No mining statistics recorded yet; start a supervised run first".to_string()));
+    };
+    let active = pidfile::running() == Some(stats.pid);
+    let lifetime = stats.lifetime();
+    if output::json() {
+        let with_average = |totals: &Totals| {
+            let mut value = json!(totals);
+            value["average_hashrate"] = json!(totals.average_hashrate());
+            value
+        };
+        output::ok(json!({
+            "session_active": active,
+            "session": with_average(&stats.session),
+            "lifetime": with_average(&lifetime),
+        }));
+        return Ok(());
+    }
+
+    print_totals(if active { "Current session" } else { "Last session" }, &stats.session);
+    println!();
+    print_totals("Lifetime", &lifetime);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shares_advance() {
+        let mut shares = Shares::default();
+        assert_eq!(shares.advance(10, 1), (10, 1));
+        assert_eq!(shares.advance(15, 1), (5, 0));
+        // Restarted miner counts from zero again
+        assert_eq!(shares.advance(3, 0), (3, 0));
+    }
+
+    #[test]
+    fn test_lifetime() {
+        let stats = Stats {
+            pid: 1,
+            session: Totals { restarts: 2, mining_secs: 100, hashes: 50_000.0, ..Totals::default() },
+            previous: Totals { sessions: 3, restarts: 1, mining_secs: 100, hashes: 150_000.0, ..Totals::default() },
+        };
+        let lifetime = stats.lifetime();
+        assert_eq!(lifetime.sessions, 4);
+        assert_eq!(lifetime.restarts, 3);
+        assert_eq!(lifetime.average_hashrate(), Some(1000.0));
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::bandwidth;
+use crate::config::{self, Config};
+use crate::http;
+use crate::init;
+use crate::pause;
+use crate::pidfile;
+use crate::pool;
+use crate::run::XmrError;
+use crate::stratum_proxy::{self, ProxyStats};
+use crate::telemetry;
+
+// Snapshot of the miner from its HTTP API summary (/2/summary)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinerStatus {
+    pub version: String,
+    pub uptime_secs: u64,
+    // 10s, 60s and 15m averages in H/s; null while not yet measured
+    pub hashrate: [Option<f64>; 3],
+    pub pool: Option<String>,
+    // Seconds on the current pool connection, 0 while disconnected
+    pub connected_secs: u64,
+    pub ping_ms: Option<u64>,
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+impl MinerStatus {
+    fn from_summary(summary: &Value) -> MinerStatus {
+        let connection = &summary["connection"];
+        let total = &summary["hashrate"]["total"];
+        MinerStatus {
+            version: summary["version"].as_str().unwrap_or("?").to_string(),
+            uptime_secs: summary["uptime"].as_u64().unwrap_or(0),
+            hashrate: [total[0].as_f64(), total[1].as_f64(), total[2].as_f64()],
+            pool: connection["pool"].as_str().filter(|p| !p.is_empty()).map(str::to_string),
+            connected_secs: connection["uptime"].as_u64().unwrap_or(0),
+            ping_ms: connection["ping"].as_u64().filter(|&p| p > 0),
+            accepted: connection["accepted"].as_u64().unwrap_or(0),
+            rejected: connection["rejected"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+pub fn query(api_url: &str) -> Result<MinerStatus, XmrError> {
+    let url = format!("{}/2/summary", api_url.trim_end_matches('/'));
+    let summary = http::get_json_with_timeout(&url, Duration::from_secs(5)).map_err(|e| {
+        debug!("{}", e);
+        XmrError::NetworkError(format!("no miner API at {}; is the miner running with its HTTP API enabled?", api_url))
+    })?;
+    Ok(MinerStatus::from_summary(&summary))
+}
+
+// "3d 4h 12m" style uptime
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, mins) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m {}s", mins, secs % 60),
+        (0, _) => format!("{}h {}m", hours, mins),
+        _ => format!("{}d {}h {}m", days, hours, mins),
+    }
+}
+
+fn print_miner(status: &MinerStatus, config: &Config) {
+    let hashrate = |h: Option<f64>| h.map_or("n/a".to_string(), telemetry::format_hashrate);
+
+    println!("Miner:       {} (up {})", status.version, format_duration(status.uptime_secs));
+    println!(
+        "Hashrate:    {} (10s)  {} (60s)  {} (15m)",
+        hashrate(status.hashrate[0]),
+        hashrate(status.hashrate[1]),
+        hashrate(status.hashrate[2])
+    );
+    // Which of the configured pools it is, e.g. [sx, backup 1 of 2]
+    let role = |pool: &str| pool::describe(config, pool).map_or(String::new(), |role| format!(" [{}]", role));
+    match &status.pool {
+        Some(pool) if status.connected_secs > 0 => {
+            let ping = status.ping_ms.map_or(String::new(), |p| format!(", ping {} ms", p));
+            println!("Pool:        {}{} (connected {}{})", pool, role(pool), format_duration(status.connected_secs), ping);
+        },
+        Some(pool) => println!("Pool:        {}{} (not connected)", pool, role(pool)),
+        None => println!("Pool:        not connected"),
+    }
+    println!("Shares:      {} accepted, {} rejected", status.accepted, status.rejected);
+}
+
+fn print_proxy(stats: &ProxyStats) {
+    println!();
+    println!("Stratum proxy upstream: {}", stats.pool.as_deref().unwrap_or("not connected"));
+    if stats.workers.is_empty() {
+        println!("No rigs connected.");
+        return;
+    }
+    println!("{:<20}  {:<21}  {:>12}  {:>8}  {:>8}  Connected", "Rig", "Address", "Hashrate", "Accepted", "Rejected");
+    for worker in &stats.workers {
+        println!(
+            "{:<20}  {:<21}  {:>12}  {:>8}  {:>8}  {}",
+            worker.name,
+            worker.address,
+            telemetry::format_hashrate(worker.hashrate),
+            worker.accepted,
+            worker.rejected,
+            format_duration(worker.connected_secs)
+        );
+    }
+}
+
+// One line per instance on this host, when there is more than one
+fn print_instances() {
+    let instances = config::instances();
+    if instances.len() < 2 {
+        return;
+    }
+
+    println!("Instances on this host:");
+    for name in instances {
+        let pid = config::state_dir_for(&name).ok().and_then(|dir| pidfile::running_in(&dir));
+        let hashrate = pid
+            .and_then(|_| config::load_instance(&name).ok())
+            .and_then(|config| telemetry::miner_hashrate(&config.miner.api_url));
+        let marker = if name == config::instance() { " *" } else { "" };
+        println!(
+            "  {:<18}  {:<16}  {}",
+            format!("{}{}", name, marker),
+            pid.map_or("stopped".to_string(), |pid| format!("running ({})", pid)),
+            hashrate.map_or(String::new(), telemetry::format_hashrate)
+        );
+    }
+    println!();
+}
+
+// `status`: every instance on this host, then whether this instance's miner
+// is hashing and where to, plus the rigs behind a local stratum proxy
+pub fn show(config: &Config) -> Result<(), XmrError> {
+    print_instances();
+    let proxy = stratum_proxy::load_stats();
+    let supervisor = pidfile::running();
+    if let Some(pid) = supervisor {
+        println!("Supervisor:  running (PID {})", pid);
+        let paused = pause::saved();
+        if !paused.is_empty() {
+            println!("Mining:      paused ({})", paused.join(", "));
+        }
+    }
+    match query(&config.miner.api_url) {
+        Ok(status) => print_miner(&status, config),
+        Err(e) if proxy.is_none() && supervisor.is_none() => return Err(e),
+        Err(_) if supervisor.is_some() => println!("Miner:       API not answering at {}", config.miner.api_url),
+        Err(_) => println!("Miner:       not running on this host"),
+    }
+
+    let traffic = bandwidth::load();
+    println!(
+        "Traffic:     {} up, {} down",
+        telemetry::format_bytes(traffic.sent),
+        telemetry::format_bytes(traffic.received)
+    );
+    if let Some(proxy) = &proxy {
+        print_proxy(proxy);
+    }
+    Ok(())
+}
+
+// `status --json`: what `show` prints, plus the installed miner release
+pub fn snapshot(config: &Config) -> Result<Value, XmrError> {
+    let proxy = stratum_proxy::load_stats();
+    let supervisor = pidfile::running();
+    let miner = match query(&config.miner.api_url) {
+        Ok(status) => Some(status),
+        Err(e) if proxy.is_none() && supervisor.is_none() => return Err(e),
+        Err(_) => None,
+    };
+    let instances: Vec<Value> = config::instances()
+        .into_iter()
+        .map(|name| {
+            let pid = config::state_dir_for(&name).ok().and_then(|dir| pidfile::running_in(&dir));
+            json!({ "name": name, "supervisor_pid": pid })
+        })
+        .collect();
+    let xmr_dir = init::xmr_dir().ok();
+    let release = xmr_dir.as_deref().and_then(init::installed_release);
+    let pool_role = miner.as_ref().and_then(|m| m.pool.as_deref()).and_then(|p| pool::describe(config, p));
+    Ok(json!({
+        "instance": config::instance(),
+        "instances": instances,
+        "supervisor_pid": supervisor,
+        "paused": supervisor.map(|_| pause::saved()),
+        "xmr_dir": xmr_dir,
+        "release": release.map(|r| r.tag),
+        "miner": miner,
+        "pool_role": pool_role,
+        "traffic": bandwidth::load(),
+        "proxy": proxy,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_summary() {
+        let summary = json!({
+            "version": "6.22.2",
+            "uptime": 3720,
+            "hashrate": { "total": [5012.3, 4990.1, null] },
+            "connection": { "pool": "pool.example.com:3333", "uptime": 3600, "ping": 42, "accepted": 17, "rejected": 1 },
+        });
+        let status = MinerStatus::from_summary(&summary);
+        assert_eq!(status.hashrate, [Some(5012.3), Some(4990.1), None]);
+        assert_eq!(status.pool.as_deref(), Some("pool.example.com:3333"));
+        assert_eq!((status.accepted, status.rejected, status.ping_ms), (17, 1, Some(42)));
+        assert_eq!(format_duration(status.uptime_secs), "1h 2m");
+    }
+}
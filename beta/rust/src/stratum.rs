@@ -0,0 +1,220 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme, StreamOwned};
+use serde_json::{json, Value};
+
+use crate::dns;
+use crate::proxy;
+use crate::run::XmrError;
+
+// Outcome of connecting to a pool and logging in. Timings are in milliseconds.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    // Name lookup; None when a proxy resolves the name instead
+    pub resolve_ms: Option<f64>,
+    // Address the connection went to, as resolved; None through a proxy
+    pub peer: Option<SocketAddr>,
+    pub connect_ms: f64,
+    pub tls_ms: Option<f64>,
+    pub login_ms: f64,
+    pub accepted: bool,
+    // Pool's reason when the login was rejected
+    pub error: Option<String>,
+}
+
+// Split "stratum+ssl://host:port" style URLs into address and TLS flag
+pub fn parse_url(url: &str) -> (String, bool) {
+    match url.split_once("://") {
+        Some((scheme, address)) => {
+            let tls = scheme.ends_with("ssl") || scheme.ends_with("tls");
+            (address.trim_end_matches('/').to_string(), tls)
+        },
+        None => (url.to_string(), false),
+    }
+}
+
+// Pools mostly present self-signed certificates and the miner does not
+// check them either, so the test only proves a TLS session can be set up
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn network_error(address: &str, e: impl std::fmt::Display) -> XmrError {
+    XmrError::NetworkError(format!("{}: {}", address, e))
+}
+
+fn open(address: &str, addrs: Vec<SocketAddr>, timeout: Duration) -> Result<TcpStream, XmrError> {
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))?;
+                return Ok(stream);
+            },
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => network_error(address, e),
+        None => network_error(address, "no addresses resolved"),
+    })
+}
+
+fn through_proxy(address: &str, timeout: Duration) -> Option<Result<TcpStream, XmrError>> {
+    let proxy = proxy::for_address(address)?;
+    Some(proxy::connect(&proxy, address, timeout).map_err(|e| network_error(address, e)).and_then(|stream| {
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(stream)
+    }))
+}
+
+// TCP connection to "host:port", through the proxy when one applies
+pub fn connect(address: &str, timeout: Duration) -> Result<TcpStream, XmrError> {
+    match through_proxy(address, timeout) {
+        Some(stream) => stream,
+        None => open(address, dns::socket_addrs(address)?, timeout),
+    }
+}
+
+pub fn tls_session(address: &str, tcp: TcpStream) -> Result<StreamOwned<ClientConnection, TcpStream>, XmrError> {
+    let provider = Arc::new(ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| network_error(address, e))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+        .with_no_client_auth();
+
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let name = ServerName::try_from(host.trim_matches(['[', ']']).to_string()).map_err(|e| network_error(address, e))?;
+    let connection = ClientConnection::new(Arc::new(config), name).map_err(|e| network_error(address, e))?;
+
+    let mut stream = StreamOwned::new(connection, tcp);
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock).map_err(|e| network_error(address, format!("TLS handshake failed: {}", e)))?;
+    }
+    Ok(stream)
+}
+
+// Send the stratum login request and wait for the pool's answer
+fn login<S: Read + Write>(stream: S, wallet: &str, password: &str) -> Result<Value, std::io::Error> {
+    let request = json!({
+        "id": 1,
+        "jsonrpc": "2.0",
+        "method": "login",
+        "params": {
+            "login": wallet,
+            "pass": password,
+            "agent": concat!("minning/", env!("CARGO_PKG_VERSION")),
+            "algo": ["rx/0"],
+        },
+    });
+
+    let mut reader = BufReader::new(stream);
+    reader.get_mut().write_all(format!("{}\n", request).as_bytes())?;
+    reader.get_mut().flush()?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(std::io::Error::other)
+}
+
+// Whether a TCP connection to the pool (through the proxy, if any) succeeds
+pub fn reachable(url: &str, timeout: Duration) -> bool {
+    connect(&parse_url(url).0, timeout).is_ok()
+}
+
+// Connect to `url`, optionally over TLS, and log in with the given wallet
+pub fn probe(url: &str, tls: bool, wallet: &str, password: &str, timeout: Duration) -> Result<Probe, XmrError> {
+    let (address, url_tls) = parse_url(url);
+    let tls = tls || url_tls;
+
+    // Timed step by step, so a slow resolver does not pass for a slow pool
+    let started = Instant::now();
+    let (tcp, resolve_ms) = match through_proxy(&address, timeout) {
+        Some(stream) => (stream?, None),
+        None => {
+            let addrs = dns::socket_addrs(&address)?;
+            let resolve_ms = started.elapsed().as_secs_f64() * 1000.0;
+            (open(&address, addrs, timeout)?, Some(resolve_ms))
+        },
+    };
+    let connect_ms = started.elapsed().as_secs_f64() * 1000.0 - resolve_ms.unwrap_or(0.0);
+    // Through a proxy this is the proxy's address
+    let peer = tcp.peer_addr().ok().filter(|_| resolve_ms.is_some());
+
+    let (response, tls_ms) = if tls {
+        let handshake = Instant::now();
+        let stream = tls_session(&address, tcp)?;
+        let tls_ms = handshake.elapsed().as_secs_f64() * 1000.0;
+        let sent = Instant::now();
+        (login(stream, wallet, password).map(|r| (r, sent)), Some(tls_ms))
+    } else {
+        let sent = Instant::now();
+        (login(tcp, wallet, password).map(|r| (r, sent)), None)
+    };
+    let (response, sent) = response.map_err(|e| network_error(&address, format!("login failed: {}", e)))?;
+    let login_ms = sent.elapsed().as_secs_f64() * 1000.0;
+
+    let error = match &response["error"] {
+        Value::Null => None,
+        error => Some(error["message"].as_str().map(str::to_string).unwrap_or_else(|| error.to_string())),
+    };
+    let accepted = error.is_none() && response["result"]["id"].is_string();
+
+    Ok(Probe { resolve_ms, peer, connect_ms, tls_ms, login_ms, accepted, error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(parse_url("pool.example.com:3333"), ("pool.example.com:3333".to_string(), false));
+        assert_eq!(parse_url("stratum+ssl://pool.example.com:443"), ("pool.example.com:443".to_string(), true));
+        assert_eq!(parse_url("stratum+tcp://pool.example.com:3333/"), ("pool.example.com:3333".to_string(), false));
+    }
+}
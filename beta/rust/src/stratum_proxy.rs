@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, Config};
+use crate::pool;
+use crate::run::{self, XmrError};
+use crate::stratum;
+
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+// How long an upstream read waits before queued submits get written
+const POLL: Duration = Duration::from_millis(250);
+const KEEPALIVE: Duration = Duration::from_secs(60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const RIG_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const STATS_INTERVAL: Duration = Duration::from_secs(30);
+pub const STATS_FILE: &str = "proxy.json";
+// Stats older than this were left behind by a proxy that is gone
+const STATS_FRESH_SECS: i64 = 120;
+// Offset of the nonce's most significant byte in a Monero hashing blob.
+// Every rig gets its own value there (xmrig's "nicehash" mode keeps it
+// fixed), so rigs sharing one job never search the same nonces.
+const NICEHASH_BYTE: usize = 42;
+const LOGIN_ID: u64 = 1;
+
+// Anything the upstream session runs over: plain TCP or TLS
+trait Transport: Read + Write + Send {}
+impl<T: Read + Write + Send> Transport for T {}
+
+struct Worker {
+    name: String,
+    address: String,
+    byte: u8,
+    stream: TcpStream,
+    connected: Instant,
+    accepted: u64,
+    rejected: u64,
+    // Sum of the difficulty of accepted shares, i.e. hashes done
+    hashes: f64,
+}
+
+#[derive(Default)]
+struct State {
+    workers: HashMap<u64, Worker>,
+    next_worker: u64,
+    // Upstream login id, current job and the pool they came from
+    session: Option<String>,
+    job: Option<Value>,
+    pool: Option<String>,
+    upstream: Option<Sender<String>>,
+    next_request: u64,
+    // Upstream request id -> (worker, the rig's own request id, share difficulty)
+    pending: HashMap<u64, (u64, Value, f64)>,
+}
+
+type Shared = Arc<Mutex<State>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerStats {
+    pub name: String,
+    pub address: String,
+    pub accepted: u64,
+    pub rejected: u64,
+    // Average H/s since the rig connected, from accepted share difficulty
+    pub hashrate: f64,
+    pub connected_secs: u64,
+}
+
+// What a running proxy last wrote to the state directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyStats {
+    pub updated: i64,
+    pub pool: Option<String>,
+    pub workers: Vec<WorkerStats>,
+}
+
+// Share difficulty from a stratum target: 4 or 8 little-endian bytes in hex
+fn difficulty(target: &str) -> Option<f64> {
+    if !matches!(target.len(), 8 | 16) {
+        return None;
+    }
+    let mut bytes = [0u8; 8];
+    for (i, chunk) in target.as_bytes().chunks(2).enumerate() {
+        bytes[i] = u8::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+    let value = u64::from_le_bytes(bytes);
+    if value == 0 {
+        return None;
+    }
+    let max = if target.len() == 8 { u32::MAX as f64 } else { u64::MAX as f64 };
+    Some(max / value as f64)
+}
+
+// The upstream job as one rig sees it: its own nonce byte and worker id
+fn job_for(job: &Value, byte: u8, worker: u64) -> Option<Value> {
+    let blob = job["blob"].as_str()?;
+    let at = NICEHASH_BYTE * 2;
+    let blob = format!("{}{:02x}{}", blob.get(..at)?, byte, blob.get(at + 2..)?);
+
+    let mut job = job.clone();
+    job["blob"] = json!(blob);
+    job["id"] = json!(worker.to_string());
+    Some(job)
+}
+
+fn write_line(mut stream: impl Write, message: &Value) -> io::Result<()> {
+    stream.write_all(format!("{}\n", message).as_bytes())
+}
+
+fn reply(request: &Value, result: Value) -> Value {
+    json!({ "id": request["id"], "jsonrpc": "2.0", "error": null, "result": result })
+}
+
+fn error_reply(request: &Value, message: &str) -> Value {
+    json!({ "id": request["id"], "jsonrpc": "2.0", "error": { "code": -1, "message": message }, "result": null })
+}
+
+fn broadcast_job(state: &State) {
+    let Some(job) = &state.job else {
+        return;
+    };
+    for (id, worker) in &state.workers {
+        if let Some(job) = job_for(job, worker.byte, *id) {
+            let _ = write_line(&worker.stream, &json!({ "jsonrpc": "2.0", "method": "job", "params": job }));
+        }
+    }
+}
+
+fn login_rig(shared: &Shared, request: &Value, address: &str, stream: &TcpStream) -> Result<(u64, Value), Value> {
+    let Ok(mut state) = shared.lock() else {
+        return Err(error_reply(request, "Proxy unavailable"));
+    };
+    let Some(job) = state.job.clone() else {
+        return Err(error_reply(request, "Upstream pool not connected yet"));
+    };
+    let Some(byte) = (0..=u8::MAX).find(|b| state.workers.values().all(|w| w.byte != *b)) else {
+        return Err(error_reply(request, "Too many rigs on this proxy"));
+    };
+    let Ok(stream) = stream.try_clone() else {
+        return Err(error_reply(request, "Proxy unavailable"));
+    };
+
+    let params = &request["params"];
+    let name = [&params["rig-id"], &params["login"]]
+        .into_iter()
+        .filter_map(Value::as_str)
+        .find(|name| !name.is_empty())
+        .unwrap_or(address)
+        .to_string();
+
+    state.next_worker += 1;
+    let id = state.next_worker;
+    info!("Rig {} ({}) joined the proxy", name, address);
+    state.workers.insert(
+        id,
+        Worker {
+            name,
+            address: address.to_string(),
+            byte,
+            stream,
+            connected: Instant::now(),
+            accepted: 0,
+            rejected: 0,
+            hashes: 0.0,
+        },
+    );
+
+    let result = json!({
+        "id": id.to_string(),
+        "job": job_for(&job, byte, id),
+        "extensions": ["nicehash", "keepalive"],
+        "status": "OK",
+    });
+    Ok((id, reply(request, result)))
+}
+
+// Queue a rig's share for the pool; the answer is relayed when it arrives
+fn submit(shared: &Shared, worker: u64, request: &Value) -> Option<Value> {
+    let Ok(mut state) = shared.lock() else {
+        return Some(error_reply(request, "Proxy unavailable"));
+    };
+    let (Some(session), Some(upstream)) = (state.session.clone(), state.upstream.clone()) else {
+        return Some(error_reply(request, "Upstream pool not connected"));
+    };
+
+    let difficulty = state.job.as_ref().and_then(|job| difficulty(job["target"].as_str()?)).unwrap_or(0.0);
+    state.next_request += 1;
+    let id = LOGIN_ID + state.next_request;
+    state.pending.insert(id, (worker, request["id"].clone(), difficulty));
+
+    let mut params = request["params"].clone();
+    params["id"] = json!(session);
+    let message = json!({ "id": id, "jsonrpc": "2.0", "method": "submit", "params": params });
+    if upstream.send(format!("{}\n", message)).is_err() {
+        state.pending.remove(&id);
+        return Some(error_reply(request, "Upstream pool not connected"));
+    }
+    None
+}
+
+fn serve_rig(shared: Shared, stream: TcpStream) {
+    let address = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    let _ = stream.set_write_timeout(Some(RIG_WRITE_TIMEOUT));
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+
+    let mut worker = None;
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+
+        let answer = match (request["method"].as_str(), worker) {
+            (Some("login"), _) => match login_rig(&shared, &request, &address, &stream) {
+                Ok((id, answer)) => {
+                    worker = Some(id);
+                    Some(answer)
+                },
+                Err(answer) => Some(answer),
+            },
+            (Some("submit"), Some(id)) => submit(&shared, id, &request),
+            (Some("keepalived"), _) => Some(reply(&request, json!({ "status": "KEEPALIVED" }))),
+            (Some("submit"), None) => Some(error_reply(&request, "Unauthenticated")),
+            _ => Some(error_reply(&request, "Unsupported method")),
+        };
+        if let Some(answer) = answer
+            && write_line(&stream, &answer).is_err()
+        {
+            break;
+        }
+    }
+
+    if let Some(id) = worker
+        && let Ok(mut state) = shared.lock()
+        && let Some(worker) = state.workers.remove(&id)
+    {
+        info!("Rig {} ({}) left the proxy", worker.name, worker.address);
+    }
+}
+
+// A line from the pool: login answer, new job, or the verdict on a share
+fn handle_upstream(shared: &Shared, message: &Value) -> Result<(), XmrError> {
+    let Ok(mut state) = shared.lock() else {
+        return Ok(());
+    };
+
+    if message["method"] == "job" {
+        state.job = Some(message["params"].clone());
+        broadcast_job(&state);
+        return Ok(());
+    }
+
+    let Some(id) = message["id"].as_u64() else {
+        return Ok(());
+    };
+    if id == LOGIN_ID {
+        if !message["error"].is_null() {
+            return Err(XmrError::NetworkError(format!("pool rejected the login: {}", message["error"])));
+        }
+        state.session = message["result"]["id"].as_str().map(str::to_string);
+        state.job = Some(message["result"]["job"].clone());
+        broadcast_job(&state);
+        return Ok(());
+    }
+
+    let Some((worker, rig_request, difficulty)) = state.pending.remove(&id) else {
+        return Ok(());
+    };
+    if let Some(worker) = state.workers.get_mut(&worker) {
+        if message["error"].is_null() {
+            worker.accepted += 1;
+            worker.hashes += difficulty;
+        } else {
+            worker.rejected += 1;
+            debug!("Share from {} rejected: {}", worker.name, message["error"]);
+        }
+        let answer = json!({ "id": rig_request, "jsonrpc": "2.0", "error": message["error"], "result": message["result"] });
+        let _ = write_line(&worker.stream, &answer);
+    }
+    Ok(())
+}
+
+// One upstream connection, from login until it fails or the proxy stops
+fn upstream_session(config: &Config, endpoint: &str, shared: &Shared, running: &AtomicBool) -> Result<(), XmrError> {
+    let wallet = config.wallet.address.clone().unwrap_or_default();
+    let (address, url_tls) = stratum::parse_url(endpoint);
+    let tcp = stratum::connect(&address, UPSTREAM_TIMEOUT)?;
+    let transport: Box<dyn Transport> = if config.pool.tls || url_tls {
+        Box::new(stratum::tls_session(&address, tcp.try_clone()?)?)
+    } else {
+        Box::new(tcp.try_clone()?)
+    };
+    // Short reads so queued submits go out promptly
+    tcp.set_read_timeout(Some(POLL))?;
+
+    let mut reader = BufReader::new(transport);
+    let login = json!({
+        "id": LOGIN_ID,
+        "jsonrpc": "2.0",
+        "method": "login",
+        "params": {
+            "login": wallet,
+            "pass": config.pool.password,
+            "agent": concat!("minning-proxy/", env!("CARGO_PKG_VERSION")),
+            "algo": ["rx/0"],
+        },
+    });
+    write_line(reader.get_mut(), &login)?;
+
+    let (sender, outgoing) = mpsc::channel::<String>();
+    if let Ok(mut state) = shared.lock() {
+        state.upstream = Some(sender);
+        state.pool = Some(endpoint.to_string());
+    }
+    info!("Proxy connected upstream to {}", endpoint);
+
+    let mut line = Vec::new();
+    let mut last_write = Instant::now();
+    while running.load(Ordering::SeqCst) {
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => return Err(XmrError::NetworkError(format!("{} closed the connection", endpoint))),
+            Ok(_) if line.ends_with(b"\n") => {
+                if let Ok(message) = serde_json::from_slice::<Value>(&line) {
+                    handle_upstream(shared, &message)?;
+                }
+                line.clear();
+            },
+            Ok(_) => {},
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {},
+            Err(e) => return Err(e.into()),
+        }
+
+        while let Ok(message) = outgoing.try_recv() {
+            reader.get_mut().write_all(message.as_bytes())?;
+            last_write = Instant::now();
+        }
+        if last_write.elapsed() >= KEEPALIVE {
+            let session = shared.lock().ok().and_then(|s| s.session.clone()).unwrap_or_default();
+            let keepalive = json!({ "id": 0, "jsonrpc": "2.0", "method": "keepalived", "params": { "id": session } });
+            write_line(reader.get_mut(), &keepalive)?;
+            last_write = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+// Keep an upstream session alive, moving through the configured endpoints
+// when one fails
+fn run_upstream(config: Config, shared: Shared, running: Arc<AtomicBool>) {
+    let mut attempt = 0;
+    while running.load(Ordering::SeqCst) {
+        let endpoints = pool::ordered_endpoints(&config);
+        let endpoint = &endpoints[attempt % endpoints.len()];
+        if let Err(e) = upstream_session(&config, endpoint, &shared, &running) {
+            warn!("Proxy upstream {} failed: {}", endpoint, e);
+            attempt += 1;
+        }
+
+        // Shares in flight are lost with the session; rigs get a fresh job
+        // once the next login succeeds
+        if let Ok(mut state) = shared.lock() {
+            state.session = None;
+            state.upstream = None;
+            state.pool = None;
+            state.pending.clear();
+        }
+
+        let mut waited = Duration::ZERO;
+        while waited < RECONNECT_DELAY && running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+            waited += Duration::from_secs(1);
+        }
+    }
+}
+
+fn stats_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(STATS_FILE))
+}
+
+fn snapshot(shared: &Shared) -> Option<ProxyStats> {
+    let state = shared.lock().ok()?;
+    let mut workers: Vec<WorkerStats> = state
+        .workers
+        .values()
+        .map(|w| {
+            let secs = w.connected.elapsed().as_secs();
+            WorkerStats {
+                name: w.name.clone(),
+                address: w.address.clone(),
+                accepted: w.accepted,
+                rejected: w.rejected,
+                hashrate: if secs > 0 { w.hashes / secs as f64 } else { 0.0 },
+                connected_secs: secs,
+            }
+        })
+        .collect();
+    workers.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(ProxyStats { updated: Utc::now().timestamp(), pool: state.pool.clone(), workers })
+}
+
+fn write_stats(shared: &Shared) -> Result<(), XmrError> {
+    let Some(stats) = snapshot(shared) else {
+        return Ok(());
+    };
+    let path = stats_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = serde_json::to_string_pretty(&stats)
+        .map_err(|e| XmrError::ConfigError(format!("Could not encode proxy stats: {}", e)))?;
+    config::write_atomic(&path, &contents)?;
+    Ok(())
+}
+
+// Per-rig stats of a proxy running on this host, if there is one
+pub fn load_stats() -> Option<ProxyStats> {
+    let contents = fs::read_to_string(stats_path().ok()?).ok()?;
+    let stats: ProxyStats = serde_json::from_str(&contents).ok()?;
+    (Utc::now().timestamp() - stats.updated <= STATS_FRESH_SECS).then_some(stats)
+}
+
+// `proxy`: accept rigs on the LAN and mine for all of them over a single
+// upstream pool connection until Ctrl+C
+pub fn run(config: &Config, listen: Option<&str>) -> Result<(), XmrError> {
+    if config.wallet.address.is_none() {
+        return Err(XmrError::ConfigError("Set [wallet] address before starting the proxy".to_string()));
+    }
+    if pool::endpoints(config).is_empty() {
+        return Err(XmrError::ConfigError("Set [pool] url to the pool the proxy should mine on".to_string()));
+    }
+
+    let listen = listen.unwrap_or(&config.stratum_proxy.listen);
+    let listener = TcpListener::bind(listen)?;
+    listener.set_nonblocking(true)?;
+    info!("Stratum proxy listening on {}", listen);
+
+    let running = run::setup_ctrlc_handler();
+    let shared: Shared = Arc::new(Mutex::new(State::default()));
+    {
+        let (config, shared, running) = (config.clone(), shared.clone(), running.clone());
+        thread::spawn(move || run_upstream(config, shared, running));
+    }
+    {
+        let (shared, running) = (shared.clone(), running.clone());
+        thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                if let Err(e) = write_stats(&shared) {
+                    warn!("Could not save proxy stats: {}", e);
+                }
+                let mut waited = Duration::ZERO;
+                while waited < STATS_INTERVAL && running.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_secs(1));
+                    waited += Duration::from_secs(1);
+                }
+            }
+        });
+    }
+
+    while running.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                let shared = shared.clone();
+                thread::spawn(move || serve_rig(shared, stream));
+            },
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(200)),
+            Err(e) => warn!("Proxy accept failed: {}", e),
+        }
+    }
+
+    if let Ok(path) = stats_path() {
+        let _ = fs::remove_file(path);
+    }
+    info!("Stratum proxy stopped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(difficulty("b88d0600").map(f64::round), Some(10000.0));
+        assert_eq!(difficulty("00000000"), None);
+        assert_eq!(difficulty("xyz"), None);
+    }
+
+    #[test]
+    fn test_job_for() {
+        let blob = "0".repeat(152);
+        let job = json!({ "blob": blob, "job_id": "7", "target": "b88d0600", "id": "upstream" });
+        let rig = job_for(&job, 0x2a, 3).unwrap();
+        let rig_blob = rig["blob"].as_str().unwrap();
+        assert_eq!(&rig_blob[84..86], "2a");
+        assert_eq!(rig_blob.len(), 152);
+        assert_eq!(rig["id"], "3");
+        assert_eq!(rig["job_id"], "7");
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, info};
+
+// True between logind's PrepareForSleep(true) and PrepareForSleep(false)
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+// Bumped on every resume; health monitors compare it with what they last saw
+static RESUMES: AtomicU64 = AtomicU64::new(0);
+// Unix time of the last resume, so logind and the clock check together
+// report a wake-up only once
+static LAST_RESUME: AtomicU64 = AtomicU64::new(0);
+// Wall-clock time running ahead of the monotonic clock by this much means
+// the machine was asleep, for systems where logind cannot tell us
+const CLOCK_GAP: Duration = Duration::from_secs(30);
+
+const PREPARE_FOR_SLEEP: &str =
+    "type='signal',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+pub fn suspended() -> bool {
+    SUSPENDED.load(Ordering::SeqCst)
+}
+
+pub fn resumes() -> u64 {
+    RESUMES.load(Ordering::SeqCst)
+}
+
+fn resumed(how: &str) {
+    SUSPENDED.store(false, Ordering::SeqCst);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    if now.saturating_sub(LAST_RESUME.swap(now, Ordering::SeqCst)) < CLOCK_GAP.as_secs() {
+        return;
+    }
+    RESUMES.fetch_add(1, Ordering::SeqCst);
+    info!("System resumed from suspend ({})", how);
+}
+
+// The boolean argument of a PrepareForSleep signal as printed by dbus-monitor
+fn parse_signal(line: &str) -> Option<bool> {
+    match line.trim() {
+        "boolean true" => Some(true),
+        "boolean false" => Some(false),
+        _ => None,
+    }
+}
+
+// Follow logind's sleep signals on the system bus
+fn watch_logind() -> Option<Child> {
+    let mut child = Command::new("dbus-monitor")
+        .args(["--system", PREPARE_FOR_SLEEP])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let stdout = child.stdout.take()?;
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            match parse_signal(&line) {
+                Some(true) => {
+                    info!("System is going to sleep");
+                    SUSPENDED.store(true, Ordering::SeqCst);
+                },
+                Some(false) => resumed("logind"),
+                None => {},
+            }
+        }
+        debug!("dbus-monitor exited");
+    });
+    Some(child)
+}
+
+// Supervised modes: notice suspend/resume so health checks skip the gap and
+// the watchdogs can restart the miner on its stale pool connection
+pub fn start_watch(running: Arc<AtomicBool>) {
+    let mut monitor = watch_logind();
+    if monitor.is_none() {
+        debug!("dbus-monitor unavailable; detecting suspend from clock jumps only");
+    }
+
+    thread::spawn(move || {
+        let mut wall = SystemTime::now();
+        let mut mono = Instant::now();
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_secs(1));
+
+            // The monotonic clock stops while suspended, the wall clock does not
+            let wall_elapsed = wall.elapsed().unwrap_or_default();
+            let gap = wall_elapsed.saturating_sub(mono.elapsed());
+            (wall, mono) = (SystemTime::now(), Instant::now());
+            if gap >= CLOCK_GAP {
+                resumed(&format!("clock jumped {} seconds", gap.as_secs()));
+            }
+        }
+        if let Some(child) = monitor.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("   boolean true"), Some(true));
+        assert_eq!(parse_signal("   boolean false\n"), Some(false));
+        assert_eq!(parse_signal("signal time=1700000000.1 sender=:1.2 -> destination=(null destination)"), None);
+    }
+}
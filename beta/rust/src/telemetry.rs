@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::http;
+
+// Sensor drivers that report the CPU package temperature, in preference order
+const CPU_HWMON_DRIVERS: [&str; 5] = ["k10temp", "zenpower", "coretemp", "cpu_thermal", "acpitz"];
+
+// Short host name used to tell rigs apart in telemetry
+pub fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "rig".to_string())
+}
+
+// CPU temperature in degrees Celsius from the first recognised hwmon sensor
+pub fn cpu_temperature() -> Option<f64> {
+    let hwmon = Path::new("/sys/class/hwmon");
+    let entries: Vec<_> = fs::read_dir(hwmon).ok()?.flatten().collect();
+
+    for driver in CPU_HWMON_DRIVERS {
+        for entry in &entries {
+            let name = fs::read_to_string(entry.path().join("name")).unwrap_or_default();
+            if name.trim() != driver {
+                continue;
+            }
+
+            let raw = fs::read_to_string(entry.path().join("temp1_input")).ok()?;
+            let millidegrees: f64 = raw.trim().parse().ok()?;
+            return Some(millidegrees / 1000.0);
+        }
+    }
+
+    None
+}
+
+// Current total hashrate (H/s, 10s average) from the miner's HTTP API summary
+pub fn miner_hashrate(api_url: &str) -> Option<f64> {
+    let url = format!("{}/2/summary", api_url.trim_end_matches('/'));
+    let summary = http::get_json_with_timeout(&url, Duration::from_secs(5)).ok()?;
+
+    summary["hashrate"]["total"][0].as_f64()
+}
+
+// Pool the miner is connected to and for how long (seconds); an uptime of
+// zero means the miner is currently without a pool connection
+pub fn miner_connection(api_url: &str) -> Option<(String, u64)> {
+    let url = format!("{}/2/summary", api_url.trim_end_matches('/'));
+    let summary = http::get_json_with_timeout(&url, Duration::from_secs(5)).ok()?;
+
+    let connection = &summary["connection"];
+    Some((
+        connection["pool"].as_str().unwrap_or_default().to_string(),
+        connection["uptime"].as_u64().unwrap_or(0),
+    ))
+}
+
+// Human-readable hashrate with an SI prefix, e.g. "1.23 kH/s"
+pub fn format_hashrate(hashrate: f64) -> String {
+    match hashrate {
+        h if h >= 1e9 => format!("{:.2} GH/s", h / 1e9),
+        h if h >= 1e6 => format!("{:.2} MH/s", h / 1e6),
+        h if h >= 1e3 => format!("{:.2} kH/s", h / 1e3),
+        h => format!("{:.1} H/s", h),
+    }
+}
+
+// Human-readable byte count with a decimal prefix, e.g. "12.3 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes as f64 {
+        b if b >= 1e9 => format!("{:.2} GB", b / 1e9),
+        b if b >= 1e6 => format!("{:.1} MB", b / 1e6),
+        b if b >= 1e3 => format!("{:.1} kB", b / 1e3),
+        b => format!("{} B", b),
+    }
+}
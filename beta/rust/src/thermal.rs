@@ -0,0 +1,109 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::config::{Config, HeatAction, ThermalConfig};
+use crate::control;
+use crate::cpu;
+use crate::pause;
+use crate::telemetry;
+
+const PAUSE_REASON: &str = "CPU too hot";
+// Temperatures move fast under RandomX; check often
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Whether the CPU counts as hot: from reaching max_temp until it has cooled
+// by the hysteresis and the cooldown has passed
+#[derive(Debug)]
+struct Thermostat {
+    hot_since: Option<Instant>,
+}
+
+impl Thermostat {
+    fn update(&mut self, config: &ThermalConfig, max_temp: f64, temperature: f64, now: Instant) -> bool {
+        match self.hot_since {
+            None if temperature >= max_temp => self.hot_since = Some(now),
+            Some(since)
+                if temperature <= max_temp - config.hysteresis
+                    && now.duration_since(since) >= Duration::from_secs(config.cooldown_secs) =>
+            {
+                self.hot_since = None
+            },
+            _ => {},
+        }
+        self.hot_since.is_some()
+    }
+}
+
+// Back off while hot: pause, or hold the miner to throttle_cpu_cap
+fn apply(config: &ThermalConfig, hot: bool) {
+    let paused = hot && config.action == HeatAction::Pause;
+    let cap = (hot && config.action == HeatAction::Throttle).then(|| config.throttle_cpu_cap.clamp(1, 100));
+    pause::set(PAUSE_REASON, paused);
+    if cpu::set_cap(PAUSE_REASON, cap) {
+        control::restart_miner("the CPU cap changed with its temperature");
+    }
+}
+
+pub fn start_watch(config: &Config, running: Arc<AtomicBool>) {
+    let thermal = config.thermal.clone();
+    let Some(max_temp) = thermal.max_temp else {
+        return;
+    };
+    if telemetry::cpu_temperature().is_none() {
+        warn!("[thermal] max_temp is set, but no CPU temperature sensor was found; mining without it");
+        return;
+    }
+    info!("Backing off mining above {:.0}°C", max_temp);
+
+    thread::spawn(move || {
+        let mut thermostat = Thermostat { hot_since: None };
+        while running.load(Ordering::SeqCst) {
+            if let Some(temperature) = telemetry::cpu_temperature() {
+                let was_hot = thermostat.hot_since.is_some();
+                let hot = thermostat.update(&thermal, max_temp, temperature, Instant::now());
+                if hot != was_hot {
+                    match (hot, thermal.action) {
+                        (true, HeatAction::Pause) => info!("CPU at {:.0}°C, pausing mining to cool down", temperature),
+                        (true, HeatAction::Throttle) => info!(
+                            "CPU at {:.0}°C, mining on {}% of the CPUs to cool down",
+                            temperature, thermal.throttle_cpu_cap
+                        ),
+                        (false, _) => info!("CPU cooled to {:.0}°C, mining at full speed again", temperature),
+                    }
+                    apply(&thermal, hot);
+                }
+            }
+
+            let mut waited = Duration::ZERO;
+            while waited < CHECK_INTERVAL && running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thermostat() {
+        let config = ThermalConfig { max_temp: Some(85.0), hysteresis: 10.0, cooldown_secs: 60, ..ThermalConfig::default() };
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut thermostat = Thermostat { hot_since: None };
+        assert!(!thermostat.update(&config, 85.0, 80.0, at(0)));
+        assert!(thermostat.update(&config, 85.0, 86.0, at(5)));
+        // Cooler, but not by the hysteresis yet
+        assert!(thermostat.update(&config, 85.0, 78.0, at(70)));
+        // Cool enough, but the cooldown has not passed
+        let mut early = Thermostat { hot_since: Some(at(5)) };
+        assert!(early.update(&config, 85.0, 70.0, at(30)));
+        assert!(!thermostat.update(&config, 85.0, 75.0, at(70)));
+    }
+}
@@ -0,0 +1,478 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::{self, Config};
+use crate::cpu;
+use crate::numa;
+use crate::output;
+use crate::run::XmrError;
+
+// What RandomX keeps in huge pages: the 2080 MiB dataset and 256 MiB cache
+// of every miner, and a 2 MiB scratchpad per mining thread
+const DATASET_MIB: u64 = 2080;
+const CACHE_MIB: u64 = 256;
+const SCRATCHPAD_MIB: u64 = 2;
+
+const NR_HUGEPAGES: &str = "/proc/sys/vm/nr_hugepages";
+const SYSCTL_FILE: &str = "/etc/sysctl.d/90-minning-hugepages.conf";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HugePages {
+    total: u64,
+    free: u64,
+    page_kib: u64,
+}
+
+// A /proc/meminfo field such as "MemAvailable:", in kB or pages
+pub fn meminfo_field(meminfo: &str, name: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with(name))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+// HugePages_Total, HugePages_Free and Hugepagesize from /proc/meminfo
+fn parse_meminfo(meminfo: &str) -> Option<HugePages> {
+    Some(HugePages {
+        total: meminfo_field(meminfo, "HugePages_Total:")?,
+        free: meminfo_field(meminfo, "HugePages_Free:")?,
+        page_kib: meminfo_field(meminfo, "Hugepagesize:")?,
+    })
+}
+
+fn huge_pages() -> Result<HugePages, XmrError> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    parse_meminfo(&meminfo)
+        .ok_or_else(|| XmrError::ExecutionError("This kernel reports no huge pages in /proc/meminfo".to_string()))
+}
+
+// Memory RandomX fast mode takes for `miners` miners and `threads` threads
+pub fn fast_mode_mib(miners: u64, threads: u64) -> u64 {
+    miners * (DATASET_MIB + CACHE_MIB) + threads * SCRATCHPAD_MIB
+}
+
+fn pages_needed(miners: u64, threads: u64, page_kib: u64) -> u64 {
+    (fast_mode_mib(miners, threads) * 1024).div_ceil(page_kib)
+}
+
+fn is_root() -> bool {
+    // SAFETY: geteuid cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+// Ask before changing the system; --yes answers for unattended use
+fn confirm(question: &str, yes: bool) -> Result<bool, XmrError> {
+    if yes {
+        return Ok(true);
+    }
+    if output::json() || !io::stdin().is_terminal() {
+        return Err(XmrError::ConfigError("Not asking without a terminal; pass --yes to apply".to_string()));
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+// Write `contents` to a root-owned file, through sudo unless already root
+fn write_privileged(path: &str, contents: &str) -> Result<(), XmrError> {
+    if is_root() {
+        return Ok(fs::write(path, contents)?);
+    }
+    let mut tee = Command::new("sudo")
+        .args(["tee", path])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| XmrError::PermissionError(format!("Could not run sudo to write {}: {}", path, e)))?;
+    if let Some(mut stdin) = tee.stdin.take() {
+        stdin.write_all(contents.as_bytes())?;
+    }
+    match tee.wait()? {
+        status if status.success() => Ok(()),
+        status => Err(XmrError::PermissionError(format!("sudo tee {} failed: {}", path, status))),
+    }
+}
+
+// Miners and threads the current config starts
+pub fn workload(config: &Config) -> (u64, u64) {
+    let miners = numa::split(config);
+    let threads = miners
+        .iter()
+        .map(|config| {
+            let threads = cpu::threads(&config.miner);
+            threads.unwrap_or_else(|| cpu::auto_threads(&cpu::detect(config.miner.cpus.as_ref()))) as u64
+        })
+        .sum();
+    (miners.len() as u64, threads)
+}
+
+// `tune hugepages`: compare the reserved huge pages with what RandomX needs
+// for this config, reserve the difference and optionally keep the setting
+// across reboots
+pub fn hugepages(config: &Config, check: bool, persist: bool, yes: bool) -> Result<(), XmrError> {
+    if !cfg!(target_os = "linux") {
+        return Err(XmrError::ExecutionError("Huge page tuning is only supported on Linux".to_string()));
+    }
+    let before = huge_pages()?;
+    let (miners, threads) = workload(config);
+    let needed = pages_needed(miners, threads, before.page_kib);
+    let human = !output::json();
+
+    if human {
+        println!("Huge page size:  {} KiB", before.page_kib);
+        println!("Reserved:        {} pages ({} free)", before.total, before.free);
+        println!(
+            "RandomX needs:   {} pages for {} miner{} with {} thread{}",
+            needed,
+            miners,
+            if miners == 1 { "" } else { "s" },
+            threads,
+            if threads == 1 { "" } else { "s" }
+        );
+    }
+
+    let short = before.total < needed;
+    let mut after = before;
+    let mut persisted = false;
+    if short && !check {
+        if !confirm(&format!("Set vm.nr_hugepages to {}?", needed), yes)? {
+            return Err(XmrError::ExecutionError("Left vm.nr_hugepages unchanged".to_string()));
+        }
+        write_privileged(NR_HUGEPAGES, &needed.to_string())?;
+        after = huge_pages()?;
+        if persist {
+            write_privileged(SYSCTL_FILE, &format!("# Huge pages for RandomX, set by minning\nvm.nr_hugepages = {}\n", needed))?;
+            persisted = true;
+        }
+    }
+
+    if output::json() {
+        output::ok(json!({
+            "page_kib": before.page_kib,
+            "needed": needed,
+            "miners": miners,
+            "threads": threads,
+            "before": { "total": before.total, "free": before.free },
+            "after": { "total": after.total, "free": after.free },
+            "persisted": persisted.then_some(SYSCTL_FILE),
+        }));
+    } else if !short {
+        println!("Enough huge pages are reserved.");
+    } else if check {
+        println!("Short by {} pages; `tune hugepages` reserves them.", needed - before.total);
+    } else {
+        println!("Reserved now:    {} pages ({} free)", after.total, after.free);
+        if persisted {
+            println!("Kept across reboots in {}.", SYSCTL_FILE);
+        }
+    }
+
+    // The kernel reserves what it can find contiguous room for
+    if !check && short && after.total < needed {
+        return Err(XmrError::ExecutionError(format!(
+            "The kernel only reserved {} of {} pages because memory is fragmented; {}",
+            after.total,
+            needed,
+            if persisted { "they are reserved in full after a reboot" } else { "rerun with --persist and reboot" }
+        )));
+    }
+    Ok(())
+}
+
+// Register writes for RandomX, the values XMRig applies: on Ryzen they turn
+// off the prefetchers and tune the caches, on Intel they turn off the
+// hardware prefetchers. Each is (register, value, mask); bits outside the
+// mask keep their current value.
+type MsrWrite = (u32, u64, u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Preset {
+    name: &'static str,
+    writes: &'static [MsrWrite],
+}
+
+const RYZEN_17H: Preset = Preset {
+    name: "Ryzen (Zen, Zen+, Zen 2)",
+    writes: &[
+        (0xc001_1020, 0, u64::MAX),
+        (0xc001_1021, 0x40, !0x20),
+        (0xc001_1022, 0x0151_0000, u64::MAX),
+        (0xc001_102b, 0x2000_cc16, u64::MAX),
+    ],
+};
+const RYZEN_19H: Preset = Preset {
+    name: "Ryzen (Zen 3)",
+    writes: &[
+        (0xc001_1020, 0x0004_4800_0000_0000, u64::MAX),
+        (0xc001_1021, 0x001c_0002_0000_0040, !0x20),
+        (0xc001_1022, 0xc000_0004_0157_0000, u64::MAX),
+        (0xc001_102b, 0x2000_cc10, u64::MAX),
+    ],
+};
+const RYZEN_ZEN4: Preset = Preset {
+    name: "Ryzen (Zen 4, Zen 5)",
+    writes: &[
+        (0xc001_1020, 0x0004_4000_0000_0000, u64::MAX),
+        (0xc001_1021, 0x0004_0000_0000_0040, !0x20),
+        (0xc001_1022, 0x8680_0004_0157_0000, u64::MAX),
+        (0xc001_102b, 0x2040_cc10, u64::MAX),
+    ],
+};
+const INTEL: Preset = Preset { name: "Intel", writes: &[(0x1a4, 0xf, u64::MAX)] };
+
+// Original register values, saved before the first write so `--revert`
+// can put them back
+pub const MSR_BACKUP_FILE: &str = "msr-backup.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedMsr {
+    cpu: usize,
+    register: u32,
+    value: u64,
+}
+
+// The preset for the CPU /proc/cpuinfo describes, if it has one
+fn detect_preset(cpuinfo: &str) -> Option<Preset> {
+    let field = |name: &str| -> Option<&str> {
+        let line = cpuinfo.lines().find(|line| line.split(':').next().is_some_and(|key| key.trim() == name))?;
+        Some(line.split_once(':')?.1.trim())
+    };
+    let family: u32 = field("cpu family")?.parse().ok()?;
+    let model: u32 = field("model")?.parse().ok()?;
+    match (field("vendor_id")?, family) {
+        ("AuthenticAMD", 0x17) => Some(RYZEN_17H),
+        ("AuthenticAMD", 0x19) if matches!(model, 0x10..=0x1f | 0x60..=0x7f | 0xa0..=0xaf) => Some(RYZEN_ZEN4),
+        ("AuthenticAMD", 0x19) => Some(RYZEN_19H),
+        ("AuthenticAMD", 0x1a) => Some(RYZEN_ZEN4),
+        ("GenuineIntel", 6) => Some(INTEL),
+        _ => None,
+    }
+}
+
+fn masked(old: u64, value: u64, mask: u64) -> u64 {
+    (value & mask) | (old & !mask)
+}
+
+// CPUs with an MSR device; none until the msr module is loaded
+fn msr_cpus() -> Vec<usize> {
+    let mut cpus: Vec<usize> = fs::read_dir("/dev/cpu")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().join("msr").exists())
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect();
+    cpus.sort_unstable();
+    cpus
+}
+
+#[cfg(unix)]
+fn read_msr(cpu: usize, register: u32) -> Result<u64, XmrError> {
+    use std::os::unix::fs::FileExt;
+
+    let mut value = [0; 8];
+    fs::File::open(format!("/dev/cpu/{}/msr", cpu))?.read_exact_at(&mut value, register as u64)?;
+    Ok(u64::from_le_bytes(value))
+}
+
+#[cfg(unix)]
+fn write_msr(cpu: usize, register: u32, value: u64) -> Result<(), XmrError> {
+    use std::os::unix::fs::FileExt;
+
+    let file = fs::OpenOptions::new().write(true).open(format!("/dev/cpu/{}/msr", cpu))?;
+    file.write_all_at(&value.to_le_bytes(), register as u64)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn read_msr(_cpu: usize, _register: u32) -> Result<u64, XmrError> {
+    Err(XmrError::ExecutionError("MSR access is only supported on Linux".to_string()))
+}
+
+#[cfg(not(unix))]
+fn write_msr(_cpu: usize, _register: u32, _value: u64) -> Result<(), XmrError> {
+    Err(XmrError::ExecutionError("MSR access is only supported on Linux".to_string()))
+}
+
+fn msr_backup_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(MSR_BACKUP_FILE))
+}
+
+fn load_msr_backup() -> Result<Option<Vec<SavedMsr>>, XmrError> {
+    let path = msr_backup_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| XmrError::ConfigError(format!("Cannot read {}: {}", path.display(), e)))
+}
+
+// Load the msr module and check this process may use it
+fn msr_access() -> Result<Vec<usize>, XmrError> {
+    if !cfg!(target_os = "linux") {
+        return Err(XmrError::ExecutionError("MSR tuning is only supported on Linux".to_string()));
+    }
+    if !is_root() {
+        return Err(XmrError::PermissionError("Writing MSRs needs root; rerun with sudo".to_string()));
+    }
+    if msr_cpus().is_empty() {
+        let _ = Command::new("modprobe").arg("msr").status();
+    }
+    let cpus = msr_cpus();
+    if cpus.is_empty() {
+        return Err(XmrError::ExecutionError("No /dev/cpu/*/msr; the msr kernel module could not be loaded".to_string()));
+    }
+    Ok(cpus)
+}
+
+fn hex(value: u64) -> String {
+    format!("{:#x}", value)
+}
+
+// Put back the values saved by the last `tune msr`
+fn revert_msr(yes: bool) -> Result<(), XmrError> {
+    let Some(saved) = load_msr_backup()? else {
+        return Err(XmrError::ConfigError("No MSR changes to revert".to_string()));
+    };
+    msr_access()?;
+    if !confirm(&format!("Restore {} saved MSR values?", saved.len()), yes)? {
+        return Err(XmrError::ExecutionError("Left the MSRs unchanged".to_string()));
+    }
+    for entry in &saved {
+        write_msr(entry.cpu, entry.register, entry.value)?;
+    }
+    fs::remove_file(msr_backup_path()?)?;
+    if output::json() {
+        output::ok(json!({ "reverted": saved.len() }));
+    } else {
+        println!("Restored {} MSR values.", saved.len());
+    }
+    Ok(())
+}
+
+// `tune msr`: apply the RandomX register preset for this CPU to every core,
+// saving the original values first. Nothing is written with --dry-run, and
+// the values only last until the next reboot.
+pub fn msr(dry_run: bool, revert: bool, yes: bool) -> Result<(), XmrError> {
+    if revert {
+        return revert_msr(yes);
+    }
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let Some(preset) = detect_preset(&cpuinfo) else {
+        return Err(XmrError::ExecutionError("No RandomX MSR preset for this CPU; only Ryzen and Intel are supported".to_string()));
+    };
+
+    // A dry run shows current values where it may read them, the preset otherwise
+    let cpus = if dry_run { msr_access().ok() } else { Some(msr_access()?) };
+    let first = cpus.as_ref().and_then(|cpus| cpus.first().copied());
+    let registers: Vec<_> = preset
+        .writes
+        .iter()
+        .map(|&(register, value, mask)| {
+            let current = first.and_then(|cpu| read_msr(cpu, register).ok());
+            let new = current.map_or(value, |old| masked(old, value, mask));
+            (register, current, new)
+        })
+        .collect();
+
+    let human = !output::json();
+    if human {
+        println!("MSR preset: {}", preset.name);
+        for (register, current, new) in &registers {
+            let current = current.map_or("unreadable".to_string(), hex);
+            println!("  {:#x}: {} -> {}", register, current, hex(*new));
+        }
+    }
+
+    let mut applied = 0;
+    if !dry_run {
+        let cpus = cpus.unwrap_or_default();
+        if !confirm(&format!("Write {} MSRs on {} CPUs?", preset.writes.len(), cpus.len()), yes)? {
+            return Err(XmrError::ExecutionError("Left the MSRs unchanged".to_string()));
+        }
+        // Keep the oldest backup: after a second run the current values are
+        // already the tuned ones
+        if load_msr_backup()?.is_none() {
+            let mut saved = Vec::new();
+            for &cpu in &cpus {
+                for &(register, _, _) in preset.writes {
+                    saved.push(SavedMsr { cpu, register, value: read_msr(cpu, register)? });
+                }
+            }
+            let contents = serde_json::to_string_pretty(&saved).map_err(io::Error::other)?;
+            let path = msr_backup_path()?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            config::write_atomic(&path, &contents)?;
+        }
+        for &cpu in &cpus {
+            for &(register, value, mask) in preset.writes {
+                write_msr(cpu, register, masked(read_msr(cpu, register)?, value, mask))?;
+            }
+        }
+        applied = cpus.len();
+    }
+
+    if output::json() {
+        let registers: Vec<_> = registers
+            .iter()
+            .map(|(register, current, new)| json!({ "register": hex(*register as u64), "current": current.map(hex), "new": hex(*new) }))
+            .collect();
+        output::ok(json!({ "preset": preset.name, "dry_run": dry_run, "cpus": applied, "registers": registers }));
+    } else if dry_run {
+        println!("Dry run; nothing was written.");
+    } else {
+        println!("Applied to {} CPUs until the next reboot; `tune msr --revert` restores the old values.", applied);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal: 32000000 kB\nHugePages_Total:    1280\nHugePages_Free:     1100\nHugepagesize:       2048 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(HugePages { total: 1280, free: 1100, page_kib: 2048 }));
+        assert_eq!(parse_meminfo("MemTotal: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn test_pages_needed() {
+        // 2336 MiB per miner plus 2 MiB per thread, in 2 MiB pages
+        assert_eq!(pages_needed(1, 8, 2048), 1176);
+        assert_eq!(pages_needed(2, 32, 2048), 2368);
+        // 1 GiB pages round up
+        assert_eq!(pages_needed(1, 8, 1024 * 1024), 3);
+    }
+
+    #[test]
+    fn test_detect_preset() {
+        let cpuinfo = |vendor: &str, family: u32, model: u32| {
+            format!("processor\t: 0\nvendor_id\t: {}\ncpu family\t: {}\nmodel\t\t: {}\nmodel name\t: x\n", vendor, family, model)
+        };
+        assert_eq!(detect_preset(&cpuinfo("AuthenticAMD", 23, 113)), Some(RYZEN_17H));
+        assert_eq!(detect_preset(&cpuinfo("AuthenticAMD", 25, 33)), Some(RYZEN_19H));
+        assert_eq!(detect_preset(&cpuinfo("AuthenticAMD", 25, 97)), Some(RYZEN_ZEN4));
+        assert_eq!(detect_preset(&cpuinfo("GenuineIntel", 6, 151)), Some(INTEL));
+        assert_eq!(detect_preset(&cpuinfo("AuthenticAMD", 21, 2)), None);
+        assert_eq!(detect_preset(""), None);
+    }
+
+    #[test]
+    fn test_masked() {
+        // Bit 5 of 0xc0011021 is left as the CPU has it
+        assert_eq!(masked(0x20, 0x40, !0x20), 0x60);
+        assert_eq!(masked(0xff, 0x40, !0x20), 0x60);
+        assert_eq!(masked(0xff, 0xf, u64::MAX), 0xf);
+    }
+}
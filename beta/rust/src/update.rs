@@ -0,0 +1,136 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::download;
+use crate::init;
+use crate::pidfile;
+use crate::run::XmrError;
+
+// Whether the installed release is the newest one; rolling tags are
+// compared by the checksum configured or published on the GitHub release
+fn up_to_date(config: &Config, installed: Option<&init::InstalledRelease>, tag: &str) -> bool {
+    let Some(installed) = installed else {
+        return false;
+    };
+    if installed.tag != tag {
+        return false;
+    }
+    init::expected_checksum(&config.miner, tag).is_ok_and(|sha256| sha256 == installed.sha256)
+}
+
+// Put the freshly unpacked folder in place of the installed one, keeping
+// the old one until the new one is there
+fn swap(staged: &Path, xmr_dir: &Path) -> Result<(), XmrError> {
+    let old = xmr_dir.with_file_name(".xmr-old");
+    let _ = fs::remove_dir_all(&old);
+    if xmr_dir.exists() {
+        fs::rename(xmr_dir, &old)?;
+    }
+    if let Err(e) = fs::rename(staged, xmr_dir) {
+        let _ = fs::rename(&old, xmr_dir);
+        return Err(e.into());
+    }
+    let _ = fs::remove_dir_all(&old);
+    Ok(())
+}
+
+// Whether the download is the archive already installed, for a rolling tag
+// that publishes no checksum to tell beforehand
+fn unchanged(installed: Option<&init::InstalledRelease>, downloaded: &init::InstalledRelease) -> bool {
+    installed.is_some_and(|installed| installed.tag == downloaded.tag && installed.sha256 == downloaded.sha256)
+}
+
+// `update`: install the latest release over the current one. A running
+// supervisor notices the new binary and restarts the miner on it.
+pub fn run(config: &Config, verify: bool) -> Result<init::Installation, XmrError> {
+    let home = env::var("HOME").map_err(|_| XmrError::EnvError("Could not determine home directory".to_string()))?;
+    let xmr_dir = PathBuf::from(&home).join("xmr");
+    let installed = init::installed_release(&xmr_dir);
+
+    let asset = init::asset_name()?;
+    let latest = download::latest_github_release(init::RELEASE_REPO)?;
+    if latest.asset(asset).is_none() {
+        return Err(XmrError::EnvError(format!("Release {} has no {} build ({})", latest.tag, env::consts::ARCH, asset)));
+    }
+    let current = installed.as_ref().map_or("unknown", |r| r.tag.as_str());
+    if up_to_date(config, installed.as_ref(), &latest.tag) {
+        if !download::quiet() {
+            println!("Already up to date ({}).", current);
+        }
+        return Ok(init::Installation { xmr_dir, release: installed, changed: false });
+    }
+    if !download::quiet() {
+        println!("Updating from {} to {}...", current, latest.tag);
+    }
+
+    // Download and unpack next to the install so a failure leaves it untouched
+    let staging = PathBuf::from(&home).join(".xmr-update");
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+    let release = match init::install(&config.miner, verify, &latest.tag, &staging) {
+        Ok(release) => release,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        },
+    };
+
+    if unchanged(installed.as_ref(), &release) {
+        let _ = fs::remove_dir_all(&staging);
+        if !download::quiet() {
+            println!("Already up to date ({}).", current);
+        }
+        return Ok(init::Installation { xmr_dir, release: installed, changed: false });
+    }
+
+    let swapped = swap(&staging.join("xmr"), &xmr_dir);
+    let _ = fs::remove_dir_all(&staging);
+    swapped?;
+
+    if !download::quiet() {
+        println!("Updated to {}.", release.tag);
+        if let Some(pid) = pidfile::running() {
+            println!("Supervisor {} will restart the miner on the new release.", pid);
+        }
+    }
+    Ok(init::Installation { xmr_dir, release: Some(release), changed: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str, sha256: &str) -> init::InstalledRelease {
+        init::InstalledRelease {
+            tag: tag.to_string(),
+            url: String::new(),
+            sha256: sha256.to_string(),
+            installed: 0,
+            binary_sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_up_to_date() {
+        let installed = release("v1.0.0", "aa");
+        let mut config = Config::default();
+        config.miner.sha256 = Some("aa".to_string());
+        assert!(up_to_date(&config, Some(&installed), "v1.0.0"));
+        assert!(!up_to_date(&config, Some(&installed), "v1.1.0"));
+        assert!(!up_to_date(&config, None, "v1.0.0"));
+        config.miner.sha256 = Some("bb".to_string());
+        assert!(!up_to_date(&config, Some(&installed), "v1.0.0"));
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let installed = release("latest", "aa");
+        // The same archive downloaded again leaves the install alone
+        assert!(unchanged(Some(&installed), &release("latest", "aa")));
+        assert!(!unchanged(Some(&installed), &release("latest", "bb")));
+        assert!(!unchanged(Some(&installed), &release("v1.0.0", "aa")));
+        assert!(!unchanged(None, &release("latest", "aa")));
+    }
+}
@@ -0,0 +1,243 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use toml_edit::value;
+use tracing::{info, warn};
+
+use crate::address::{self, Network};
+use crate::config::{self, Config};
+use crate::output;
+use crate::run::XmrError;
+use crate::split;
+
+pub const CONFIRMED_FILE: &str = "confirmed_wallets";
+
+// 64 short, easily told apart words; four of them (24 bits of the address
+// hash) are read out to check an address without comparing 95 characters
+const WORDS: [&str; 64] = [
+    "acid", "amber", "anchor", "apple", "arrow", "atlas", "badge", "bamboo", "basil", "beacon", "birch", "blade",
+    "bloom", "bonus", "brick", "bridge", "cabin", "canal", "candle", "cargo", "cedar", "chalk", "cherry", "cloud",
+    "cobalt", "comet", "coral", "crane", "delta", "desert", "dolphin", "dragon", "eagle", "ember", "falcon", "fern",
+    "flint", "forest", "galaxy", "garden", "glacier", "harbor", "hazel", "honey", "island", "jasmine", "jungle",
+    "kettle", "lantern", "lemon", "maple", "marble", "meadow", "nickel", "oasis", "orbit", "pepper", "pilot",
+    "quartz", "raven", "saddle", "tiger", "velvet", "walnut",
+];
+
+// Checksum words for an address
+pub fn checksum_words(address: &str) -> Vec<&'static str> {
+    let hash = Sha256::digest(address.as_bytes());
+    let bits = u32::from_be_bytes([0, hash[0], hash[1], hash[2]]);
+    (0..4).rev().map(|i| WORDS[(bits >> (i * 6)) as usize & 63]).collect()
+}
+
+// Every address this config mines to: the primary wallet and split targets
+fn addresses(config: &Config) -> Vec<String> {
+    let mut addresses: Vec<String> = config.wallet.address.iter().cloned().collect();
+    for target in &config.split.targets {
+        if !addresses.contains(&target.wallet) {
+            addresses.push(target.wallet.clone());
+        }
+    }
+    addresses
+}
+
+fn confirmed_path() -> Result<PathBuf, XmrError> {
+    Ok(config::state_dir()?.join(CONFIRMED_FILE))
+}
+
+fn confirmed() -> Vec<String> {
+    confirmed_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn remember(address: &str) -> Result<(), XmrError> {
+    let path = confirmed_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut all = confirmed();
+    all.push(address.to_string());
+    config::write_atomic(&path, &format!("{}\n", all.join("\n")))
+}
+
+// Show an address and have the user type "yes" to it
+fn prompt(address: &str) -> Result<bool, XmrError> {
+    println!("Mining rewards will be paid to:");
+    println!();
+    println!("  {}", address);
+    println!();
+    println!("Checksum words: {}", checksum_words(address).join(" "));
+    println!("Compare the address with the one your wallet shows before continuing.");
+    print!("Type \"yes\" to mine to this address: ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim() == "yes")
+}
+
+fn confirm(address: &str) -> Result<(), XmrError> {
+    if !prompt(address)? {
+        return Err(XmrError::ConfigError(format!("Wallet {} was not confirmed", address)));
+    }
+    remember(address)?;
+    info!("Wallet {} confirmed", address);
+    Ok(())
+}
+
+fn no_wallet() -> XmrError {
+    XmrError::ConfigError("No [wallet] address configured; set one with `wallet set <address>`".to_string())
+}
+
+// Before the first mining session with a new wallet, have the user confirm
+// it. Unattended rigs cannot answer, so they refuse to start until the
+// address is confirmed with `wallet confirm`.
+pub fn ensure_confirmed(config: &Config) -> Result<(), XmrError> {
+    check(config, io::stdin().is_terminal())
+}
+
+// The same checks for a config a running supervisor reloads on `restart`,
+// where nobody is there to confirm a new address
+pub fn check_confirmed(config: &Config) -> Result<(), XmrError> {
+    check(config, false)
+}
+
+fn check(config: &Config, interactive: bool) -> Result<(), XmrError> {
+    // Without a target the miner is started bare and mines wherever its own
+    // config points, if anywhere
+    if config.wallet.address.is_none() && split::current_target(config).is_none() {
+        warn!("No [wallet] address configured, so none is passed to the miner; set one with `wallet set <address>`");
+    }
+    // A hand-edited address is checked as strictly as one set with `wallet set`
+    for address in addresses(config) {
+        address::validate(&address)?;
+    }
+    let known = confirmed();
+    for address in addresses(config).into_iter().filter(|a| !known.contains(a)) {
+        if !interactive {
+            return Err(XmrError::ConfigError(format!(
+                "Wallet {} has not been confirmed yet; run `wallet confirm` in a terminal first",
+                address
+            )));
+        }
+        confirm(&address)?;
+    }
+    Ok(())
+}
+
+// `wallet confirm`: confirm the configured addresses not confirmed yet
+pub fn confirm_all(config: &Config) -> Result<(), XmrError> {
+    let addresses = addresses(config);
+    if addresses.is_empty() {
+        return Err(no_wallet());
+    }
+    let known = confirmed();
+    for address in addresses {
+        if known.contains(&address) {
+            println!("{} ({}) is already confirmed.", address, checksum_words(&address).join(" "));
+            continue;
+        }
+        confirm(&address)?;
+    }
+    Ok(())
+}
+
+// `wallet set`: store the payout address in [wallet]. It is confirmed like
+// any new address before the first session mines to it.
+pub fn set(address: &str) -> Result<(), XmrError> {
+    let address = address.trim();
+    let (network, kind) = address::validate(address)?;
+    if network != Network::Mainnet {
+        warn!("{} is a {} address; pools only pay out on mainnet", address, network.name());
+    }
+    config::edit(|document| {
+        config::section(document, "wallet")?["address"] = value(address);
+        Ok(())
+    })?;
+
+    let confirmed = confirmed().iter().any(|known| known == address);
+    if output::json() {
+        output::ok(json!({
+            "address": address,
+            "network": network.name(),
+            "kind": kind.name(),
+            "checksum_words": checksum_words(address),
+            "confirmed": confirmed,
+        }));
+        return Ok(());
+    }
+    println!("Saved wallet {} ({}).", address, checksum_words(address).join(" "));
+    if confirmed {
+        println!("`restart` passes it to a running miner.");
+    } else {
+        // A running supervisor cannot ask, so `restart` refuses it until then
+        println!("The next start asks you to confirm it; `wallet confirm` does that now.");
+        println!("After `wallet confirm`, `restart` passes it to a running miner.");
+    }
+    Ok(())
+}
+
+// `wallet show`
+pub fn show(config: &Config) -> Result<(), XmrError> {
+    let known = confirmed();
+    if output::json() {
+        let split: Vec<_> = config
+            .split
+            .targets
+            .iter()
+            .map(|target| json!({ "name": target.name, "address": target.wallet, "percent": target.percent }))
+            .collect();
+        output::ok(json!({
+            "address": config.wallet.address,
+            "valid": config.wallet.address.as_deref().map(|address| address::validate(address).is_ok()),
+            "checksum_words": config.wallet.address.as_deref().map(checksum_words),
+            "confirmed": config.wallet.address.as_ref().is_some_and(|address| known.contains(address)),
+            "split": split,
+        }));
+        return Ok(());
+    }
+
+    let Some(address) = &config.wallet.address else {
+        return Err(no_wallet());
+    };
+    println!("Payout address: {}", address);
+    match address::validate(address) {
+        Ok((network, kind)) => println!("Type:           {} {} address", network.name(), kind.name()),
+        Err(e) => println!("Type:           {}", e),
+    }
+    println!("Checksum words: {}", checksum_words(address).join(" "));
+    println!("Confirmed:      {}", if known.contains(address) { "yes" } else { "no, see `wallet confirm`" });
+    for target in &config.split.targets {
+        println!("Split {:>5.1}%:   {} ({})", target.percent, target.wallet, target.name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_words() {
+        let address = "44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A";
+        let words = checksum_words(address);
+        assert_eq!(words.len(), 4);
+        assert_eq!(words, checksum_words(address));
+        assert_ne!(words, checksum_words(&address.replace('A', "B")));
+    }
+
+    #[test]
+    fn test_check_confirmed() {
+        let mut config = Config::default();
+        assert!(check_confirmed(&config).is_ok());
+        // A reload with a mistyped address is refused before any prompt
+        config.wallet.address = Some("44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3B".to_string());
+        assert!(check_confirmed(&config).is_err());
+    }
+}